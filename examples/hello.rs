@@ -1,4 +1,4 @@
-/// This is a simple example to demonstrate the usage of this library.
+//! This is a simple example to demonstrate the usage of this library.
 
 fn main() -> Result<(), minreq::Error> {
     let response = minreq::get("http://example.com").send()?;
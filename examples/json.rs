@@ -1,4 +1,4 @@
-/// This example demonstrates the `json-using-serde` feature.
+//! This example demonstrates the `json-using-serde` feature.
 
 #[derive(serde::Deserialize)]
 struct Response {
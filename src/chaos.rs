@@ -0,0 +1,206 @@
+use crate::random::{RandomRef, RandomSource};
+use crate::{Error, ResponseLazy};
+use std::io;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Injects faults into requests, for testing how an application
+/// handles a misbehaving network or server, eg. that its retries and
+/// error messages work.
+///
+/// Each fault is injected into a request (or the response to it) with
+/// its own probability, independently of the others:
+///
+/// - [Latency](#method.with_latency): the request waits before
+///   connecting, which counts towards its timeout.
+/// - [Dropped connections](#method.with_dropped_connections): the
+///   request fails with an IO error of kind `ConnectionReset` instead
+///   of being sent.
+/// - [Truncated bodies](#method.with_truncated_bodies): the body of
+///   the response ends early, as if the server had closed the
+///   connection.
+/// - [Error statuses](#method.with_error_status): the response is
+///   replaced with an empty one with a 5xx status code.
+///
+/// Faults are injected into each redirection and retry of a request
+/// separately. Connections of faulty responses aren't reused.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), minreq::Error> {
+/// use minreq::{Chaos, RetryPolicy};
+/// use std::time::Duration;
+///
+/// let chaos = Chaos::new()
+///     .with_latency(0.2, Duration::from_millis(500))
+///     .with_dropped_connections(0.1)
+///     .with_error_status(0.1, 503);
+/// let client = minreq::Client::new().with_chaos(chaos);
+/// let response = client
+///     .get("http://example.com")
+///     .with_retry(RetryPolicy::new(5))
+///     .send()?;
+/// # Ok(()) }
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Chaos {
+    latency: Option<(Chance, Duration)>,
+    dropped_connections: Option<Chance>,
+    truncated_bodies: Option<(Chance, usize)>,
+    error_status: Option<(Chance, i32)>,
+    random: RandomRef,
+}
+
+impl Chaos {
+    /// Creates a `Chaos` that doesn't inject any faults yet.
+    pub fn new() -> Chaos {
+        Chaos::default()
+    }
+
+    /// Delays requests by `delay` with the given `probability`, from
+    /// 0.0 (never) to 1.0 (always).
+    pub fn with_latency(mut self, probability: f64, delay: Duration) -> Chaos {
+        self.latency = Some((Chance::new(probability), delay));
+        self
+    }
+
+    /// Fails requests with an IO error with the given `probability`,
+    /// as if the connection had been dropped.
+    pub fn with_dropped_connections(mut self, probability: f64) -> Chaos {
+        self.dropped_connections = Some(Chance::new(probability));
+        self
+    }
+
+    /// Cuts off response bodies after `length` bytes (as sent by the
+    /// server, before decoding) with the given `probability`.
+    pub fn with_truncated_bodies(mut self, probability: f64, length: usize) -> Chaos {
+        self.truncated_bodies = Some((Chance::new(probability), length));
+        self
+    }
+
+    /// Replaces responses with an empty one with `status_code`, eg.
+    /// 503, with the given `probability`.
+    pub fn with_error_status(mut self, probability: f64, status_code: i32) -> Chaos {
+        self.error_status = Some((Chance::new(probability), status_code));
+        self
+    }
+
+    /// Sets the source of the random numbers that decide which faults
+    /// are injected, eg. a seeded generator for reproducible test
+    /// runs. By default, [SystemRandom](crate::SystemRandom) is used.
+    pub fn with_random_source<R: RandomSource + 'static>(mut self, source: R) -> Chaos {
+        self.random = RandomRef(Arc::new(source));
+        self
+    }
+
+    /// Injects the faults that happen before a request is sent.
+    pub(crate) fn before_send(&self) -> Result<(), Error> {
+        if let Some((chance, delay)) = &self.latency {
+            if chance.roll(&self.random) {
+                log::debug!("Chaos: delaying the request by {:?}.", delay);
+                thread::sleep(*delay);
+            }
+        }
+        if let Some(chance) = &self.dropped_connections {
+            if chance.roll(&self.random) {
+                log::debug!("Chaos: dropping the connection.");
+                return Err(Error::IoError(io::Error::new(
+                    io::ErrorKind::ConnectionReset,
+                    "the connection was dropped by Chaos",
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Injects the faults into `response`, whose headers have been
+    /// read.
+    pub(crate) fn received(&self, response: &mut ResponseLazy) {
+        if let Some((chance, status_code)) = &self.error_status {
+            if chance.roll(&self.random) {
+                log::debug!("Chaos: replacing the response with a {}.", status_code);
+                response.substitute(*status_code, reason_phrase(*status_code));
+                return;
+            }
+        }
+        if let Some((chance, length)) = &self.truncated_bodies {
+            if chance.roll(&self.random) {
+                log::debug!("Chaos: truncating the body after {} bytes.", length);
+                response.truncate(*length);
+            }
+        }
+    }
+}
+
+/// A probability, scaled to the range of u64 so that it can be
+/// compared with random numbers (and with other chances, unlike
+/// floats).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Chance(u64);
+
+impl Chance {
+    fn new(probability: f64) -> Chance {
+        // Floats saturate when cast to integers, and NaN becomes 0.
+        Chance((probability * u64::MAX as f64) as u64)
+    }
+
+    fn roll(&self, random: &RandomRef) -> bool {
+        self.0 == u64::MAX || random.next_u64() < self.0
+    }
+}
+
+fn reason_phrase(status_code: i32) -> &'static str {
+    match status_code {
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Chance, Chaos};
+    use crate::random::RandomRef;
+    use crate::{Error, RandomSource};
+    use std::io;
+    use std::sync::Arc;
+
+    struct Fixed(u64);
+
+    impl RandomSource for Fixed {
+        fn next_u64(&self) -> u64 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_chance() {
+        let low = RandomRef(Arc::new(Fixed(0)));
+        let high = RandomRef(Arc::new(Fixed(u64::MAX)));
+        let middle = RandomRef(Arc::new(Fixed(u64::MAX / 2)));
+        assert!(!Chance::new(0.0).roll(&low));
+        assert!(Chance::new(1.0).roll(&high));
+        assert!(Chance::new(2.0).roll(&high));
+        assert!(!Chance::new(-1.0).roll(&low));
+        assert!(Chance::new(0.6).roll(&middle));
+        assert!(!Chance::new(0.4).roll(&middle));
+    }
+
+    #[test]
+    fn test_dropped_connections() {
+        let chaos = Chaos::new()
+            .with_dropped_connections(0.5)
+            .with_random_source(Fixed(0));
+        match chaos.before_send() {
+            Err(Error::IoError(err)) => assert_eq!(err.kind(), io::ErrorKind::ConnectionReset),
+            result => panic!("unexpected result: {:?}", result),
+        }
+        let chaos = chaos.with_random_source(Fixed(u64::MAX));
+        assert!(chaos.before_send().is_ok());
+    }
+}
@@ -0,0 +1,207 @@
+use crate::Error;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::str;
+
+/// How many bytes of the body are searched for a `<meta charset>` tag.
+const META_SEARCH_LENGTH: usize = 1024;
+
+/// A character encoding of a response body, see
+/// [`Response::text`](struct.Response.html#method.text).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Charset {
+    /// UTF-8, the default.
+    Utf8,
+    /// UTF-16, little-endian.
+    Utf16Le,
+    /// UTF-16, big-endian.
+    Utf16Be,
+    /// ISO-8859-1, where every byte is the character with the same
+    /// code point.
+    Latin1,
+}
+
+impl Charset {
+    /// Returns the charset for the `label`, eg. "utf-8" or
+    /// "iso-8859-1", or None if it's not supported.
+    pub fn from_label(label: &str) -> Option<Charset> {
+        match label.trim().to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" | "unicode-1-1-utf-8" => Some(Charset::Utf8),
+            "utf-16le" | "utf-16" => Some(Charset::Utf16Le),
+            "utf-16be" => Some(Charset::Utf16Be),
+            "iso-8859-1" | "iso8859-1" | "latin1" | "l1" | "us-ascii" | "ascii" => {
+                Some(Charset::Latin1)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Returns the value of the `charset` parameter of a `Content-Type`
+/// header, without quotes.
+pub(crate) fn charset_label(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let mut parts = param.splitn(2, '=');
+        let name = parts.next()?.trim();
+        if name.eq_ignore_ascii_case("charset") {
+            Some(parts.next()?.trim().trim_matches('"'))
+        } else {
+            None
+        }
+    })
+}
+
+/// Returns the charset of a body, given by the `Content-Type` header,
+/// or when there is no `Content-Type` and `sniff` is true, guessed
+/// from the body. Returns None if neither applies.
+pub(crate) fn body_charset(
+    headers: &HashMap<String, String>,
+    body: &[u8],
+    sniff: bool,
+) -> Result<Option<Charset>, Error> {
+    match headers.get("content-type") {
+        Some(content_type) => match charset_label(content_type) {
+            Some(label) => match Charset::from_label(label) {
+                Some(charset) => Ok(Some(charset)),
+                None => Err(Error::UnsupportedCharset(label.to_string())),
+            },
+            None => Ok(None),
+        },
+        None if sniff => Ok(Some(sniff_charset(body))),
+        None => Ok(None),
+    }
+}
+
+/// Guesses the charset of a body without a `Content-Type`, like
+/// browsers do: a byte order mark wins, then the null byte pattern of
+/// UTF-16 JSON (RFC 4627 section 3), then an HTML `<meta charset>`
+/// tag. Otherwise, the body is UTF-8 if it's valid UTF-8, and Latin-1
+/// if it's not.
+pub(crate) fn sniff_charset(body: &[u8]) -> Charset {
+    if let Some((charset, _)) = bom(body) {
+        return charset;
+    }
+    match body {
+        [0, a, 0, b, ..] if *a != 0 && *b != 0 => return Charset::Utf16Be,
+        [a, 0, b, 0, ..] if *a != 0 && *b != 0 => return Charset::Utf16Le,
+        _ => {}
+    }
+    if let Some(charset) = meta_charset(&body[..body.len().min(META_SEARCH_LENGTH)]) {
+        return charset;
+    }
+    if str::from_utf8(body).is_ok() {
+        Charset::Utf8
+    } else {
+        Charset::Latin1
+    }
+}
+
+/// Returns the charset indicated by the byte order mark at the start
+/// of `body`, and the length of the mark.
+fn bom(body: &[u8]) -> Option<(Charset, usize)> {
+    match body {
+        [0xEF, 0xBB, 0xBF, ..] => Some((Charset::Utf8, 3)),
+        [0xFE, 0xFF, ..] => Some((Charset::Utf16Be, 2)),
+        [0xFF, 0xFE, ..] => Some((Charset::Utf16Le, 2)),
+        _ => None,
+    }
+}
+
+/// Finds a `<meta charset="...">` or `<meta http-equiv="Content-Type"
+/// content="...; charset=...">` tag in `html`.
+fn meta_charset(html: &[u8]) -> Option<Charset> {
+    let html = String::from_utf8_lossy(html).to_ascii_lowercase();
+    let mut rest = html.as_str();
+    while let Some(start) = rest.find("<meta") {
+        rest = &rest[start + 5..];
+        let tag = &rest[..rest.find('>').unwrap_or(rest.len())];
+        if let Some(position) = tag.find("charset=") {
+            let value = tag[position + 8..].trim_start_matches(|c| c == '"' || c == '\'');
+            let end = value
+                .find(|c: char| c == '"' || c == '\'' || c == ';' || c == '/' || c.is_whitespace())
+                .unwrap_or(value.len());
+            // Like browsers, a page can't declare itself UTF-16 from
+            // within, as the tag would then be unreadable.
+            return match Charset::from_label(&value[..end])? {
+                Charset::Utf16Le | Charset::Utf16Be => Some(Charset::Utf8),
+                charset => Some(charset),
+            };
+        }
+    }
+    None
+}
+
+/// Decodes `body` with `charset`, skipping its byte order mark if it
+/// has one.
+pub(crate) fn decode(body: &[u8], charset: Charset) -> Result<Cow<'_, str>, Error> {
+    let body = match bom(body) {
+        Some((bom_charset, length)) if bom_charset == charset => &body[length..],
+        _ => body,
+    };
+    match charset {
+        Charset::Utf8 => match str::from_utf8(body) {
+            Ok(s) => Ok(Cow::Borrowed(s)),
+            Err(err) => Err(Error::InvalidUtf8InBody(err)),
+        },
+        Charset::Utf16Le | Charset::Utf16Be => {
+            if body.len() % 2 != 0 {
+                return Err(Error::InvalidUtf16InBody);
+            }
+            let units = body.chunks(2).map(|pair| {
+                let pair = [pair[0], pair[1]];
+                if charset == Charset::Utf16Le {
+                    u16::from_le_bytes(pair)
+                } else {
+                    u16::from_be_bytes(pair)
+                }
+            });
+            let text: Result<String, _> = std::char::decode_utf16(units).collect();
+            text.map(Cow::Owned).map_err(|_| Error::InvalidUtf16InBody)
+        }
+        Charset::Latin1 => match str::from_utf8(body) {
+            // ASCII is the same in both, so most bodies can be borrowed.
+            Ok(s) if s.is_ascii() => Ok(Cow::Borrowed(s)),
+            _ => Ok(Cow::Owned(body.iter().map(|&byte| byte as char).collect())),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{charset_label, decode, sniff_charset, Charset};
+
+    #[test]
+    fn test_charset_label() {
+        assert_eq!(
+            charset_label("text/html; Charset=\"ISO-8859-1\""),
+            Some("ISO-8859-1")
+        );
+        assert_eq!(charset_label("text/html"), None);
+    }
+
+    #[test]
+    fn test_sniff_charset() {
+        assert_eq!(sniff_charset(b"\xEF\xBB\xBF{}"), Charset::Utf8);
+        assert_eq!(sniff_charset(b"\xFF\xFE{\0}\0"), Charset::Utf16Le);
+        assert_eq!(sniff_charset(b"\0{\0\"\0a"), Charset::Utf16Be);
+        assert_eq!(sniff_charset(b"{\0\"\0a\0"), Charset::Utf16Le);
+        let html = b"<html><head><META charset='iso-8859-1'></head><body>\xE9</body></html>";
+        assert_eq!(sniff_charset(html), Charset::Latin1);
+        let html = b"<meta http-equiv=\"Content-Type\" content=\"text/html; charset=utf-16\">";
+        assert_eq!(sniff_charset(html), Charset::Utf8);
+        assert_eq!(sniff_charset("h\u{e9}llo".as_bytes()), Charset::Utf8);
+        assert_eq!(sniff_charset(b"h\xE9llo"), Charset::Latin1);
+    }
+
+    #[test]
+    fn test_decode() {
+        let text = decode(b"\xEF\xBB\xBFh\xC3\xA9llo", Charset::Utf8).unwrap();
+        assert_eq!(text, "h\u{e9}llo");
+        let text = decode(b"\xFF\xFEh\0\xE9\0", Charset::Utf16Le).unwrap();
+        assert_eq!(text, "h\u{e9}");
+        assert_eq!(decode(b"\0h\0\xE9", Charset::Utf16Be).unwrap(), "h\u{e9}");
+        assert_eq!(decode(b"h\xE9llo", Charset::Latin1).unwrap(), "h\u{e9}llo");
+        assert!(decode(b"\xD8\0", Charset::Utf16Be).is_err());
+        assert!(decode(b"\0h\0", Charset::Utf16Be).is_err());
+    }
+}
@@ -0,0 +1,495 @@
+use crate::connection::SocketHook;
+use crate::event::EventHook;
+use crate::pool::{ConnectionPool, PoolConfig};
+use crate::random::RandomRef;
+#[cfg(feature = "rustls")]
+use crate::tls::TlsBackendConfig;
+#[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+use crate::tls::{SharedTlsConfig, TlsConfig};
+use crate::token::TokenProviderRef;
+use crate::trace::RequestIdGenerator;
+#[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+use crate::Error;
+#[cfg(feature = "har")]
+use crate::HarRecorder;
+#[cfg(feature = "metrics")]
+use crate::{metrics::SharedMetrics, MetricsRecorder};
+use crate::{
+    Chaos, CookieJar, Event, Method, RandomSource, RateLimiter, Request, TokenProvider,
+    TraceContext, URL,
+};
+use std::io;
+use std::net::TcpStream;
+use std::time::Duration;
+
+/// A reusable configuration for creating
+/// [`Request`](struct.Request.html)s.
+///
+/// Libraries embedding minreq can use a `Client` to enforce the same
+/// policies across all of their call sites, instead of repeating the
+/// configuration for every request.
+///
+/// A client also keeps the connections of its requests open after
+/// their responses have been read, and reuses them for later
+/// requests to the same scheme, host and port, which saves the
+/// latency of connecting (and the TLS handshake) for each request.
+/// Clones of a client share its connections. Idle connections that
+/// the server has closed are detected and skipped, and idempotent
+/// requests are sent again on a new connection if the server closes
+/// the connection while they're being sent.
+///
+/// # Example
+///
+/// ```
+/// let client = minreq::Client::new()
+///     .with_header("Accept", "application/json")
+///     .with_max_request_body_size(1024)
+///     .with_max_response_size(16 * 1024 * 1024);
+/// let request = client.get("http://example.com");
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Client {
+    headers: Vec<(String, String)>,
+    max_request_body_size: Option<usize>,
+    max_response_size: Option<usize>,
+    read_timeout: Option<Duration>,
+    rate_limiter: Option<RateLimiter>,
+    cookie_jar: Option<CookieJar>,
+    token_provider: Option<TokenProviderRef>,
+    request_ids: Option<RequestIdGenerator>,
+    random: RandomRef,
+    trace_context: Option<TraceContext>,
+    socket_hook: Option<SocketHook>,
+    event_hook: Option<EventHook>,
+    chaos: Option<Chaos>,
+    pool: ConnectionPool,
+    #[cfg(feature = "har")]
+    har_recorder: Option<HarRecorder>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<SharedMetrics>,
+    #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+    tls: SharedTlsConfig,
+}
+
+impl Client {
+    /// Creates a new `Client` with the default configuration.
+    pub fn new() -> Client {
+        Client::default()
+    }
+
+    /// Adds a default header, which is added to every request created
+    /// with this client, eg. `Accept: application/json`.
+    ///
+    /// Requests can override the default by setting the same header
+    /// (compared case-insensitively) with
+    /// [`Request::with_header`](struct.Request.html#method.with_header).
+    /// Setting the same default header again replaces the old value.
+    pub fn with_header<T: Into<String>, U: Into<String>>(mut self, key: T, value: U) -> Client {
+        let key = key.into();
+        self.headers
+            .retain(|(existing, _)| !existing.eq_ignore_ascii_case(&key));
+        self.headers.push((key, value.into()));
+        self
+    }
+
+    /// Sets the maximum size of the request bodies sent by requests
+    /// created with this client. See
+    /// [`Request::with_max_request_body_size`](struct.Request.html#method.with_max_request_body_size).
+    pub fn with_max_request_body_size<S: Into<Option<usize>>>(mut self, max_size: S) -> Client {
+        self.max_request_body_size = max_size.into();
+        self
+    }
+
+    /// Sets the maximum size of the response bodies accepted by
+    /// requests created with this client. See
+    /// [`Request::with_max_response_size`](struct.Request.html#method.with_max_response_size).
+    pub fn with_max_response_size<S: Into<Option<usize>>>(mut self, max_size: S) -> Client {
+        self.max_response_size = max_size.into();
+        self
+    }
+
+    /// Sets the read timeout of the requests created with this client,
+    /// which fails them when the server stops sending data for
+    /// `timeout`, however long the response takes as a whole. See
+    /// [`Request::with_read_timeout`](struct.Request.html#method.with_read_timeout).
+    pub fn with_read_timeout(mut self, timeout: Duration) -> Client {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the rate limiter shared by the requests created with this
+    /// client (and its clones). See [RateLimiter].
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Client {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Sets the cookie jar shared by the requests created with this
+    /// client, so that cookies set by one response are sent with the
+    /// later requests they apply to. See [CookieJar].
+    pub fn with_cookie_jar(mut self, jar: CookieJar) -> Client {
+        self.cookie_jar = Some(jar);
+        self
+    }
+
+    /// Authenticates the requests created with this client with
+    /// bearer tokens from `provider`. See
+    /// [`Request::with_token_provider`](struct.Request.html#method.with_token_provider).
+    pub fn with_token_provider<P: TokenProvider + 'static>(mut self, provider: P) -> Client {
+        self.token_provider = Some(TokenProviderRef::new(provider));
+        self
+    }
+
+    /// Adds a random `X-Request-Id` (a UUID) to each request created
+    /// with this client, so that the requests can be found in the
+    /// server's logs. See
+    /// [`Response::request_id`](struct.Response.html#method.request_id)
+    /// for the ID the server sends back.
+    pub fn with_request_ids(mut self) -> Client {
+        self.request_ids = Some(RequestIdGenerator::default());
+        self
+    }
+
+    /// Adds an `X-Request-Id` generated by `generator` to each request
+    /// created with this client, eg. for IDs in a format the server
+    /// expects.
+    pub fn with_request_id_generator<F>(mut self, generator: F) -> Client
+    where
+        F: Fn() -> String + Send + Sync + 'static,
+    {
+        self.request_ids = Some(RequestIdGenerator::new(generator));
+        self
+    }
+
+    /// Sets the source of the random numbers used for the generated
+    /// values of the requests created with this client, like request
+    /// IDs, trace span IDs and Digest authentication nonces. See
+    /// [RandomSource].
+    pub fn with_random_source<R: RandomSource + 'static>(mut self, source: R) -> Client {
+        self.random = RandomRef(std::sync::Arc::new(source));
+        self
+    }
+
+    /// Places each request created with this client in the trace
+    /// `context`, as a new span (see [`TraceContext::child`]).
+    pub fn with_trace_context(mut self, context: TraceContext) -> Client {
+        self.trace_context = Some(context);
+        self
+    }
+
+    /// Sets a hook that is called with each TCP connection made by
+    /// the requests created with this client. See
+    /// [`Request::with_socket_hook`](struct.Request.html#method.with_socket_hook).
+    pub fn with_socket_hook<F>(mut self, hook: F) -> Client
+    where
+        F: Fn(&TcpStream) -> io::Result<()> + Send + Sync + 'static,
+    {
+        self.socket_hook = Some(SocketHook::new(hook));
+        self
+    }
+
+    /// Sets a hook that is called with each [Event] of the requests
+    /// created with this client. See
+    /// [`Request::on_event`](struct.Request.html#method.on_event).
+    pub fn on_event<F>(mut self, hook: F) -> Client
+    where
+        F: Fn(&Event) + Send + Sync + 'static,
+    {
+        self.event_hook = Some(EventHook::new(hook));
+        self
+    }
+
+    /// Injects the faults configured in `chaos` into the requests
+    /// created with this client. See [Chaos].
+    pub fn with_chaos(mut self, chaos: Chaos) -> Client {
+        self.chaos = Some(chaos);
+        self
+    }
+
+    /// Sets how many idle connections are kept open per scheme, host
+    /// and port, 8 by default. Setting it to 0 disables keeping
+    /// connections open.
+    ///
+    /// This replaces the connections of this client, so it should be
+    /// set before the client is cloned or used.
+    pub fn with_max_idle_connections(mut self, max_idle: usize) -> Client {
+        self.pool = ConnectionPool::new(PoolConfig {
+            max_idle_per_host: max_idle,
+            ..self.pool.config()
+        });
+        self
+    }
+
+    /// Sets how long idle connections are kept open, 90 seconds by
+    /// default. Servers usually close idle connections on their own
+    /// after a while, so this should be shorter than that.
+    ///
+    /// This replaces the connections of this client, so it should be
+    /// set before the client is cloned or used.
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Client {
+        self.pool = ConnectionPool::new(PoolConfig {
+            idle_timeout: timeout,
+            ..self.pool.config()
+        });
+        self
+    }
+
+    /// Sets how many unread bytes of a response body are read when
+    /// the response is dropped, so that its connection can be reused,
+    /// 64 KiB by default. The connection is closed instead if more of
+    /// the body is left, or its length isn't known. Setting it to 0
+    /// closes the connections of all responses dropped before their
+    /// body has been read.
+    ///
+    /// Draining happens when a [`ResponseLazy`](struct.ResponseLazy.html)
+    /// is dropped, and is limited by the timeout of its request.
+    ///
+    /// This replaces the connections of this client, so it should be
+    /// set before the client is cloned or used.
+    pub fn with_max_drain_bytes(mut self, max_drain_bytes: usize) -> Client {
+        self.pool = ConnectionPool::new(PoolConfig {
+            max_drain_bytes,
+            ..self.pool.config()
+        });
+        self
+    }
+
+    /// Checks the idle connections every `interval` on a background
+    /// thread, closing the ones which have expired or been closed by
+    /// the server, instead of only when they would be reused. This
+    /// frees their sockets sooner for clients which sit idle for a
+    /// while. The thread stops once the client and its clones have
+    /// been dropped.
+    ///
+    /// Idle connections are always checked before they're reused, so
+    /// this isn't needed to avoid reusing closed connections.
+    ///
+    /// This replaces the connections of this client, so it should be
+    /// set before the client is cloned or used.
+    pub fn with_pool_reaper(mut self, interval: Duration) -> Client {
+        self.pool = ConnectionPool::new(PoolConfig {
+            reap_interval: Some(interval),
+            ..self.pool.config()
+        });
+        self
+    }
+
+    /// Limits how many connections are used at the same time per
+    /// scheme, host and port, unlimited by default. Requests that
+    /// would go over the limit wait until one of the responses has
+    /// been read, or dropped, or until their timeout (or the
+    /// [pool timeout](#method.with_pool_timeout)) passes. The
+    /// waiting requests are served by their
+    /// [`Priority`](enum.Priority.html), see
+    /// [`Request::with_priority`](struct.Request.html#method.with_priority),
+    /// and then in the order they started waiting.
+    ///
+    /// This replaces the connections of this client, so it should be
+    /// set before the client is cloned or used.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), minreq::Error> {
+    /// let client = minreq::Client::new().with_max_connections_per_host(2);
+    /// let download = client.get("http://example.com/large.iso");
+    /// let status = client
+    ///     .get("http://example.com/status")
+    ///     .with_priority(minreq::Priority::High)
+    ///     .send()?;
+    /// # Ok(()) }
+    /// ```
+    pub fn with_max_connections_per_host(mut self, max: usize) -> Client {
+        self.pool = ConnectionPool::new(PoolConfig {
+            max_per_host: Some(max),
+            ..self.pool.config()
+        });
+        self
+    }
+
+    /// Limits how long requests wait for a connection when the
+    /// [number of connections per host](#method.with_max_connections_per_host)
+    /// is limited, so that requests fail fast when the host is
+    /// overloaded instead of queueing. Requests that time out while
+    /// waiting fail with
+    /// [`PoolTimeout`](enum.Error.html#variant.PoolTimeout), as do ones
+    /// whose own timeout passes while waiting.
+    ///
+    /// This replaces the connections of this client, so it should be
+    /// set before the client is cloned or used.
+    pub fn with_pool_timeout(mut self, timeout: Duration) -> Client {
+        self.pool = ConnectionPool::new(PoolConfig {
+            acquire_timeout: Some(timeout),
+            ..self.pool.config()
+        });
+        self
+    }
+
+    /// Records the requests created with this client, and their
+    /// responses, into `recorder`. See [HarRecorder].
+    #[cfg(feature = "har")]
+    pub fn with_har_recorder(mut self, recorder: HarRecorder) -> Client {
+        self.har_recorder = Some(recorder);
+        self
+    }
+
+    /// Records a measurement of each request created with this client
+    /// into `recorder`, eg. a [PrometheusMetrics](crate::PrometheusMetrics).
+    /// See [MetricsRecorder].
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics<M: MetricsRecorder + 'static>(mut self, recorder: M) -> Client {
+        self.metrics = Some(SharedMetrics::new(recorder));
+        self
+    }
+
+    /// Sets the TLS configuration used for HTTPS requests created
+    /// with this client. See [TlsConfig](struct.TlsConfig.html).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvalidCertificate`](enum.Error.html#variant.InvalidCertificate)
+    /// if the TLS backend does not accept one of the certificates in
+    /// the configuration.
+    #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+    pub fn with_tls_config(mut self, config: TlsConfig) -> Result<Client, Error> {
+        self.tls = SharedTlsConfig::new(config.build()?);
+        Ok(self)
+    }
+
+    /// Replaces the TLS configuration of this client and all of its
+    /// clones, without recreating them.
+    ///
+    /// Connections made after this call use the new configuration,
+    /// including the ones made by requests that were created before
+    /// it. This allows eg. rotating client certificates or root
+    /// certificates in long-running processes with zero downtime.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvalidCertificate`](enum.Error.html#variant.InvalidCertificate)
+    /// if the TLS backend does not accept one of the certificates in
+    /// the configuration. In that case, the old configuration is kept.
+    #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+    pub fn set_tls_config(&self, config: TlsConfig) -> Result<(), Error> {
+        self.tls.set(config.build()?);
+        // The idle connections were made with the old configuration.
+        self.pool.clear();
+        Ok(())
+    }
+
+    /// Sets the rustls configuration used for HTTPS requests created
+    /// with this client, instead of one built from a
+    /// [TlsConfig](struct.TlsConfig.html). See
+    /// [`Request::with_rustls_config`](struct.Request.html#method.with_rustls_config).
+    #[cfg(feature = "rustls")]
+    pub fn with_rustls_config(mut self, config: std::sync::Arc<rustls::ClientConfig>) -> Client {
+        self.tls = SharedTlsConfig::new(TlsBackendConfig::from_rustls(config));
+        self
+    }
+
+    /// Replaces the rustls configuration of this client and all of
+    /// its clones, like
+    /// [`set_tls_config`](#method.set_tls_config).
+    #[cfg(feature = "rustls")]
+    pub fn set_rustls_config(&self, config: std::sync::Arc<rustls::ClientConfig>) {
+        self.tls.set(TlsBackendConfig::from_rustls(config));
+        self.pool.clear();
+    }
+
+    /// Creates a new [`Request`](struct.Request.html) with this
+    /// client's configuration.
+    pub fn request<T: Into<URL>>(&self, method: Method, url: T) -> Request {
+        let mut request = Request::new(method, url);
+        for (key, value) in &self.headers {
+            request = request.with_header(key.clone(), value.clone());
+        }
+        let mut request = request
+            .with_max_request_body_size(self.max_request_body_size)
+            .with_max_response_size(self.max_response_size);
+        request.read_timeout = self.read_timeout;
+        request.rate_limiter = self.rate_limiter.clone();
+        request.cookie_jar = self.cookie_jar.clone();
+        request.token_provider = self.token_provider.clone();
+        request.socket_hook = self.socket_hook.clone();
+        request.event_hook = self.event_hook.clone();
+        request.chaos = self.chaos.clone();
+        request.pool = Some(self.pool.clone());
+        #[cfg(feature = "har")]
+        {
+            request.har_recorder = self.har_recorder.clone();
+        }
+        #[cfg(feature = "metrics")]
+        {
+            request.metrics = self.metrics.clone();
+        }
+        if let Some(generator) = &self.request_ids {
+            request = request.with_request_id(generator.generate(&self.random));
+        }
+        if let Some(context) = &self.trace_context {
+            request = request.with_trace_context(context.child_from(&self.random));
+        }
+        #[cfg(any(feature = "sha2", feature = "md-5"))]
+        {
+            request.random = self.random.clone();
+        }
+        #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+        {
+            request.tls = Some(self.tls.clone());
+        }
+        request
+    }
+
+    /// Alias for [Client::request] with `method` set to
+    /// [Method::Get](enum.Method.html).
+    pub fn get<T: Into<URL>>(&self, url: T) -> Request {
+        self.request(Method::Get, url)
+    }
+
+    /// Alias for [Client::request] with `method` set to
+    /// [Method::Head](enum.Method.html).
+    pub fn head<T: Into<URL>>(&self, url: T) -> Request {
+        self.request(Method::Head, url)
+    }
+
+    /// Alias for [Client::request] with `method` set to
+    /// [Method::Post](enum.Method.html).
+    pub fn post<T: Into<URL>>(&self, url: T) -> Request {
+        self.request(Method::Post, url)
+    }
+
+    /// Alias for [Client::request] with `method` set to
+    /// [Method::Put](enum.Method.html).
+    pub fn put<T: Into<URL>>(&self, url: T) -> Request {
+        self.request(Method::Put, url)
+    }
+
+    /// Alias for [Client::request] with `method` set to
+    /// [Method::Delete](enum.Method.html).
+    pub fn delete<T: Into<URL>>(&self, url: T) -> Request {
+        self.request(Method::Delete, url)
+    }
+
+    /// Alias for [Client::request] with `method` set to
+    /// [Method::Connect](enum.Method.html).
+    pub fn connect<T: Into<URL>>(&self, url: T) -> Request {
+        self.request(Method::Connect, url)
+    }
+
+    /// Alias for [Client::request] with `method` set to
+    /// [Method::Options](enum.Method.html).
+    pub fn options<T: Into<URL>>(&self, url: T) -> Request {
+        self.request(Method::Options, url)
+    }
+
+    /// Alias for [Client::request] with `method` set to
+    /// [Method::Trace](enum.Method.html).
+    pub fn trace<T: Into<URL>>(&self, url: T) -> Request {
+        self.request(Method::Trace, url)
+    }
+
+    /// Alias for [Client::request] with `method` set to
+    /// [Method::Patch](enum.Method.html).
+    pub fn patch<T: Into<URL>>(&self, url: T) -> Request {
+        self.request(Method::Patch, url)
+    }
+}
@@ -0,0 +1,56 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// A source of the current time, used for the timeout and deadline
+/// calculations of requests.
+///
+/// By default, requests use [SystemClock]. A custom clock can be set
+/// with [`Request::with_clock`](struct.Request.html#method.with_clock),
+/// eg. for testing timeout handling deterministically, or for
+/// simulation environments that control time.
+pub trait Clock: Send + Sync {
+    /// Returns the current time.
+    fn now(&self) -> Instant;
+}
+
+/// The default [Clock], based on [Instant::now].
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A shared reference to a [Clock], comparable by identity so that it
+/// can live in [Request](crate::Request).
+#[derive(Clone)]
+pub(crate) struct ClockRef(pub(crate) Arc<dyn Clock>);
+
+impl ClockRef {
+    pub(crate) fn now(&self) -> Instant {
+        self.0.now()
+    }
+}
+
+impl Default for ClockRef {
+    fn default() -> ClockRef {
+        ClockRef(Arc::new(SystemClock))
+    }
+}
+
+impl PartialEq for ClockRef {
+    fn eq(&self, other: &ClockRef) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for ClockRef {}
+
+impl fmt::Debug for ClockRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ClockRef")
+    }
+}
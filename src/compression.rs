@@ -0,0 +1,164 @@
+use crate::decoder::run_decoders;
+use crate::{BodyDecoder, Error};
+use flate2::write::{GzDecoder, ZlibDecoder};
+use std::collections::HashMap;
+use std::io::Write;
+
+/// The `Accept-Encoding` sent with requests that don't set one.
+pub(crate) const ACCEPT_ENCODING: &str = "gzip, deflate";
+
+/// Decodes gzip-compressed bodies.
+pub(crate) struct GzipDecoder(GzDecoder<Vec<u8>>);
+
+impl BodyDecoder for GzipDecoder {
+    fn decode(&mut self, input: &[u8], output: &mut Vec<u8>) -> Result<(), Error> {
+        self.0.write_all(input)?;
+        output.append(self.0.get_mut());
+        Ok(())
+    }
+
+    fn finish(&mut self, output: &mut Vec<u8>) -> Result<(), Error> {
+        self.0.try_finish()?;
+        output.append(self.0.get_mut());
+        Ok(())
+    }
+}
+
+/// Decodes bodies compressed with the "deflate" coding, which is
+/// zlib-wrapped deflate (RFC 9110 section 8.4.1.2).
+pub(crate) struct DeflateDecoder(ZlibDecoder<Vec<u8>>);
+
+impl BodyDecoder for DeflateDecoder {
+    fn decode(&mut self, input: &[u8], output: &mut Vec<u8>) -> Result<(), Error> {
+        self.0.write_all(input)?;
+        output.append(self.0.get_mut());
+        Ok(())
+    }
+
+    fn finish(&mut self, output: &mut Vec<u8>) -> Result<(), Error> {
+        self.0.try_finish()?;
+        output.append(self.0.get_mut());
+        Ok(())
+    }
+}
+
+/// Returns the decoder for the compression `coding`, or None if it's
+/// not supported.
+pub(crate) fn decoder_for(coding: &str) -> Option<Box<dyn BodyDecoder>> {
+    match coding {
+        "gzip" | "x-gzip" => Some(Box::new(GzipDecoder(GzDecoder::new(Vec::new())))),
+        "deflate" => Some(Box::new(DeflateDecoder(ZlibDecoder::new(Vec::new())))),
+        _ => None,
+    }
+}
+
+/// Decodes bodies with several content codings, eg.
+/// `Content-Encoding: deflate, gzip`, undoing them in reverse order.
+struct ChainDecoder(Vec<Box<dyn BodyDecoder>>);
+
+impl BodyDecoder for ChainDecoder {
+    fn decode(&mut self, input: &[u8], output: &mut Vec<u8>) -> Result<(), Error> {
+        output.append(&mut run_decoders(&mut self.0, input.to_vec(), false)?);
+        Ok(())
+    }
+
+    fn finish(&mut self, output: &mut Vec<u8>) -> Result<(), Error> {
+        output.append(&mut run_decoders(&mut self.0, Vec::new(), true)?);
+        Ok(())
+    }
+}
+
+/// Returns the content codings listed in the `Content-Encoding`
+/// header, in the order they were applied, or None if some of them
+/// aren't supported.
+fn content_codings(headers: &HashMap<String, String>) -> Option<Vec<String>> {
+    let codings = headers
+        .get("content-encoding")?
+        .split(',')
+        .map(|coding| coding.trim().to_ascii_lowercase())
+        .filter(|coding| !coding.is_empty() && coding != "identity")
+        .collect::<Vec<_>>();
+    if !codings.is_empty() && codings.iter().all(|coding| decoder_for(coding).is_some()) {
+        Some(codings)
+    } else {
+        None
+    }
+}
+
+/// Returns the decoder for the `Content-Encoding` of a response, or
+/// None if the body isn't compressed, or is compressed with a coding
+/// that isn't supported, in which case it's left as-is.
+pub(crate) fn content_decoder(headers: &HashMap<String, String>) -> Option<Box<dyn BodyDecoder>> {
+    let mut decoders = content_codings(headers)?
+        .iter()
+        .rev()
+        .filter_map(|coding| decoder_for(coding))
+        .collect::<Vec<_>>();
+    if decoders.len() == 1 {
+        decoders.pop()
+    } else {
+        Some(Box::new(ChainDecoder(decoders)))
+    }
+}
+
+/// Removes the headers that describe the compressed body from the
+/// headers of a response whose body is decoded by [content_decoder].
+pub(crate) fn without_content_encoding(
+    mut headers: HashMap<String, String>,
+) -> HashMap<String, String> {
+    if content_codings(&headers).is_some() {
+        headers.remove("content-encoding");
+        headers.remove("content-length");
+    }
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{content_decoder, decoder_for};
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::collections::HashMap;
+    use std::io::Write;
+
+    #[test]
+    fn test_gzip_in_pieces() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"hello, hello, hello!").unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut decoder = decoder_for("gzip").unwrap();
+        let mut output = Vec::new();
+        for piece in compressed.chunks(3) {
+            decoder.decode(piece, &mut output).unwrap();
+        }
+        decoder.finish(&mut output).unwrap();
+        assert_eq!(output, b"hello, hello, hello!");
+    }
+
+    #[test]
+    fn test_content_decoder() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"hello").unwrap();
+        let once = encoder.finish().unwrap();
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&once).unwrap();
+        let twice = encoder.finish().unwrap();
+
+        let mut headers = HashMap::new();
+        assert!(content_decoder(&headers).is_none());
+        headers.insert("content-encoding".to_string(), "br".to_string());
+        assert!(content_decoder(&headers).is_none());
+        headers.insert(
+            "content-encoding".to_string(),
+            "GZIP, identity, gzip".to_string(),
+        );
+        let mut decoder = content_decoder(&headers).unwrap();
+        let mut output = Vec::new();
+        for piece in twice.chunks(5) {
+            decoder.decode(piece, &mut output).unwrap();
+        }
+        decoder.finish(&mut output).unwrap();
+        assert_eq!(output, b"hello");
+    }
+}
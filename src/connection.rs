@@ -1,82 +1,350 @@
+use crate::clock::ClockRef;
+use crate::deprecation::DeprecationNotice;
 #[cfg(all(
     not(feature = "rustls"),
     any(feature = "openssl", feature = "native-tls")
 ))]
-use crate::native_tls::{TlsConnector, TlsStream};
-use crate::request::ParsedRequest;
-use crate::{Error, Method, ResponseLazy};
-#[cfg(feature = "https-rustls")]
-use once_cell::sync::Lazy;
+use crate::native_tls::{HandshakeError, TlsConnector, TlsStream};
+use crate::pool::{ConnectionPermit, PoolKey, PoolSlot};
+#[cfg(feature = "proxy")]
+use crate::proxy::Proxy;
+use crate::request::{split_ipv6_literal, ParsedRequest};
+use crate::response::{is_connection_closed, BACKING_READ_BUFFER_LENGTH};
+use crate::retry::is_idempotent;
+#[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+use crate::tls::TlsInfo;
 #[cfg(feature = "rustls")]
-use rustls::{
-    self, ClientConfig, ClientConnection, OwnedTrustAnchor, RootCertStore, ServerName, StreamOwned,
-};
+use crate::tls::CONFIG;
+use crate::{Error, Event, Response, ResponseLazy};
 #[cfg(feature = "rustls")]
+use rustls::{ClientConnection, ServerName, StreamOwned};
 use std::convert::TryFrom;
 use std::env;
+use std::fmt;
 use std::io::{self, BufReader, BufWriter, Read, Write};
-use std::net::{TcpStream, ToSocketAddrs};
-#[cfg(feature = "rustls")]
-use std::sync::Arc;
+use std::net::{Ipv6Addr, SocketAddr, SocketAddrV6, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
-#[cfg(feature = "webpki")]
-use webpki::TrustAnchor;
-#[cfg(feature = "webpki")]
-use webpki_roots::TLS_SERVER_ROOTS;
 
+type UnsecuredStream = BufReader<Transport>;
+/// A TLS session over the stream `S`.
 #[cfg(feature = "rustls")]
-static CONFIG: Lazy<Arc<ClientConfig>> = Lazy::new(|| {
-    let mut root_certificates = RootCertStore::empty();
-
-    // Try to load native certs
-    #[cfg(feature = "https-rustls-probe")]
-    if let Ok(os_roots) = rustls_native_certs::load_native_certs() {
-        for root_cert in os_roots {
-            // Ignore erroneous OS certificates, there's nothing
-            // to do differently in that situation anyways.
-            let _ = root_certificates.add(&rustls::Certificate(root_cert.0));
-        }
-    }
-
-    let create_owned_trust_anchor = |ta: &TrustAnchor| {
-        OwnedTrustAnchor::from_subject_spki_name_constraints(
-            ta.subject,
-            ta.spki,
-            ta.name_constraints,
-        )
-    };
-    root_certificates
-        .add_server_trust_anchors(TLS_SERVER_ROOTS.0.iter().map(create_owned_trust_anchor));
-    let config = ClientConfig::builder()
-        .with_safe_defaults()
-        .with_root_certificates(root_certificates)
-        .with_no_client_auth();
-    Arc::new(config)
-});
-
-type UnsecuredStream = BufReader<TcpStream>;
-#[cfg(feature = "rustls")]
-type SecuredStream = StreamOwned<ClientConnection, TcpStream>;
+type Tls<S> = StreamOwned<ClientConnection, S>;
 #[cfg(all(
     not(feature = "rustls"),
     any(feature = "openssl", feature = "native-tls")
 ))]
-type SecuredStream = TlsStream<TcpStream>;
+type Tls<S> = TlsStream<S>;
+#[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+type SecuredStream = Tls<Transport>;
+
+/// Returns the TCP connection under a TLS session.
+#[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+fn secured_tcp(tls: &SecuredStream) -> &TcpStream {
+    tls.get_ref().tcp()
+}
+
+/// The connection HTTP messages (or a TLS session with the server)
+/// are sent over: a TCP connection to the server or to a proxy, or a
+/// TLS session with an `https://` proxy.
+pub(crate) enum Transport {
+    Tcp(TcpStream),
+    #[cfg(all(
+        feature = "proxy",
+        any(feature = "rustls", feature = "openssl", feature = "native-tls")
+    ))]
+    Tls(Box<Tls<TcpStream>>),
+}
+
+impl Transport {
+    /// Returns the underlying TCP socket.
+    fn tcp(&self) -> &TcpStream {
+        match self {
+            Transport::Tcp(tcp) => tcp,
+            #[cfg(all(
+                feature = "proxy",
+                any(feature = "rustls", feature = "openssl", feature = "native-tls")
+            ))]
+            Transport::Tls(tls) => tls.get_ref(),
+        }
+    }
+}
+
+impl Read for Transport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Transport::Tcp(tcp) => tcp.read(buf),
+            #[cfg(all(
+                feature = "proxy",
+                any(feature = "rustls", feature = "openssl", feature = "native-tls")
+            ))]
+            Transport::Tls(tls) => tls.read(buf),
+        }
+    }
+}
+
+impl Write for Transport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Transport::Tcp(tcp) => tcp.write(buf),
+            #[cfg(all(
+                feature = "proxy",
+                any(feature = "rustls", feature = "openssl", feature = "native-tls")
+            ))]
+            Transport::Tls(tls) => tls.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Transport::Tcp(tcp) => tcp.flush(),
+            #[cfg(all(
+                feature = "proxy",
+                any(feature = "rustls", feature = "openssl", feature = "native-tls")
+            ))]
+            Transport::Tls(tls) => tls.flush(),
+        }
+    }
+}
+
+type SocketHookFn = dyn Fn(&TcpStream) -> io::Result<()> + Send + Sync;
+
+/// A hook called with each TCP connection right after it's connected.
+#[derive(Clone)]
+pub(crate) struct SocketHook(Arc<SocketHookFn>);
+
+impl SocketHook {
+    pub(crate) fn new<F>(hook: F) -> Self
+    where
+        F: Fn(&TcpStream) -> io::Result<()> + Send + Sync + 'static,
+    {
+        SocketHook(Arc::new(hook))
+    }
+
+    pub(crate) fn call(&self, stream: &TcpStream) -> io::Result<()> {
+        (self.0)(stream)
+    }
+}
+
+impl PartialEq for SocketHook {
+    fn eq(&self, other: &SocketHook) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SocketHook {}
+
+impl fmt::Debug for SocketHook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SocketHook")
+    }
+}
+
+type BodyReaderFn = Mutex<Option<Box<dyn Read + Send>>>;
+
+/// A request body which is read from a reader as it's sent. It can
+/// only be sent once, as the reader is used up by then.
+#[derive(Clone)]
+pub(crate) struct BodyReader {
+    reader: Arc<BodyReaderFn>,
+    length: Option<u64>,
+}
+
+impl BodyReader {
+    pub(crate) fn new<R: Read + Send + 'static>(reader: R, length: Option<u64>) -> BodyReader {
+        BodyReader {
+            reader: Arc::new(Mutex::new(Some(Box::new(reader)))),
+            length,
+        }
+    }
+
+    pub(crate) fn length(&self) -> Option<u64> {
+        self.length
+    }
+
+    /// Takes the reader out to send it, which fails if it has already
+    /// been sent, eg. by an earlier attempt or before a redirect.
+    pub(crate) fn take(&self, max_size: Option<usize>) -> Result<StreamingBody, Error> {
+        let reader = match self.reader.lock() {
+            Ok(mut reader) => reader.take(),
+            Err(poisoned) => poisoned.into_inner().take(),
+        };
+        match reader {
+            Some(reader) => Ok(StreamingBody {
+                reader,
+                length: self.length,
+                max_size,
+                read: 0,
+            }),
+            None => Err(Error::BodyReaderUsedUp),
+        }
+    }
+}
+
+impl PartialEq for BodyReader {
+    fn eq(&self, other: &BodyReader) -> bool {
+        Arc::ptr_eq(&self.reader, &other.reader)
+    }
+}
+
+impl Eq for BodyReader {}
+
+impl fmt::Debug for BodyReader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BodyReader")
+            .field("length", &self.length)
+            .finish()
+    }
+}
+
+/// A [BodyReader] being sent. Bodies without a length are sent with
+/// the chunked transfer coding.
+pub(crate) struct StreamingBody {
+    reader: Box<dyn Read + Send>,
+    length: Option<u64>,
+    max_size: Option<usize>,
+    read: u64,
+}
+
+impl StreamingBody {
+    fn chunked(&self) -> bool {
+        self.length.is_none()
+    }
+
+    /// Reads the next part of the body into `buf`, returning 0 at the
+    /// end of the body. At most `length` bytes are read.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let size = match self.length {
+            Some(length) => (length - self.read).min(buf.len() as u64) as usize,
+            None => buf.len(),
+        };
+        let buf = &mut buf[..size];
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let n = loop {
+            match self.reader.read(buf) {
+                Ok(n) => break n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(Error::IoError(err)),
+            }
+        };
+        self.read += n as u64;
+        if let Some(max_size) = self.max_size {
+            if self.read > max_size as u64 {
+                return Err(Error::RequestBodyTooLarge);
+            }
+        }
+        if n == 0 && self.length.is_some() {
+            return Err(Error::IoError(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the request body reader ended before the body's length",
+            )));
+        }
+        Ok(n)
+    }
+}
+
+/// How many times a host lookup is retried by default when it fails
+/// temporarily.
+pub(crate) const DEFAULT_DNS_RETRIES: u32 = 2;
+
+/// The delay before retrying a failed host lookup for the first time,
+/// doubled for each of the next retries.
+const DNS_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// The shortest timeout set on sockets. Sockets don't accept a zero
+/// timeout, and setting one would leave the previous timeout (or
+/// none) in place, so shorter timeouts are raised to this.
+const MIN_SOCKET_TIMEOUT: Duration = Duration::from_millis(1);
+
+/// Returns `timeout`, raised to [MIN_SOCKET_TIMEOUT] if it's shorter,
+/// for setting it on a socket.
+fn socket_timeout(timeout: Option<Duration>) -> Option<Duration> {
+    timeout.map(|timeout| timeout.max(MIN_SOCKET_TIMEOUT))
+}
+
+/// How much of the request is written at a time, each within the
+/// write timeout.
+const WRITE_CHUNK_SIZE: usize = 64 * 1024;
 
 pub(crate) enum HttpStream {
-    Unsecured(UnsecuredStream, Option<Instant>),
+    Unsecured(UnsecuredStream, Deadline),
     #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
-    Secured(Box<SecuredStream>, Option<Instant>),
+    Secured(Box<SecuredStream>, Deadline),
 }
 
 impl HttpStream {
-    fn create_unsecured(reader: UnsecuredStream, timeout_at: Option<Instant>) -> HttpStream {
-        HttpStream::Unsecured(reader, timeout_at)
+    fn create_unsecured(reader: UnsecuredStream, deadline: Deadline) -> HttpStream {
+        HttpStream::Unsecured(reader, deadline)
+    }
+
+    #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+    fn create_secured(reader: SecuredStream, deadline: Deadline) -> HttpStream {
+        HttpStream::Secured(Box::new(reader), deadline)
     }
 
+    /// Returns the underlying TCP socket.
+    pub(crate) fn tcp(&self) -> &TcpStream {
+        match self {
+            HttpStream::Unsecured(inner, _) => inner.get_ref().tcp(),
+            #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+            HttpStream::Secured(inner, _) => secured_tcp(inner),
+        }
+    }
+
+    /// Returns the TLS session with the server, or None if the stream
+    /// isn't secured.
     #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
-    fn create_secured(reader: SecuredStream, timeout_at: Option<Instant>) -> HttpStream {
-        HttpStream::Secured(Box::new(reader), timeout_at)
+    pub(crate) fn tls_info(&self) -> Option<TlsInfo> {
+        match self {
+            HttpStream::Unsecured(..) => None,
+            #[cfg(feature = "rustls")]
+            HttpStream::Secured(inner, _) => Some(TlsInfo::from_rustls(&inner.conn)),
+            #[cfg(all(
+                not(feature = "rustls"),
+                any(feature = "openssl", feature = "native-tls")
+            ))]
+            HttpStream::Secured(inner, _) => Some(TlsInfo::from_native(inner)),
+        }
+    }
+
+    /// Returns true if data has been read from the socket, but not
+    /// from this stream yet.
+    pub(crate) fn has_buffered_data(&self) -> bool {
+        match self {
+            HttpStream::Unsecured(inner, _) => !inner.buffer().is_empty(),
+            #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+            HttpStream::Secured(..) => false,
+        }
+    }
+
+    /// Replaces the deadline of the stream, when it's reused for
+    /// another request, or when its response's headers have been read.
+    pub(crate) fn set_deadline(&mut self, deadline: Deadline) {
+        match self {
+            HttpStream::Unsecured(_, current) => *current = deadline,
+            #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+            HttpStream::Secured(_, current) => *current = deadline,
+        }
+    }
+}
+
+impl Write for HttpStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            HttpStream::Unsecured(inner, _) => inner.get_mut().write(buf),
+            #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+            HttpStream::Secured(inner, _) => inner.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            HttpStream::Unsecured(inner, _) => inner.get_mut().flush(),
+            #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+            HttpStream::Secured(inner, _) => inner.flush(),
+        }
     }
 }
 
@@ -87,34 +355,137 @@ fn timeout_err() -> io::Error {
     )
 }
 
-fn timeout_at_to_duration(timeout_at: Option<Instant>) -> Result<Option<Duration>, io::Error> {
-    if let Some(timeout_at) = timeout_at {
-        if let Some(duration) = timeout_at.checked_duration_since(Instant::now()) {
-            Ok(Some(duration))
+/// The point in time when a request times out, along with the clock
+/// used to tell how far away it is, and how long a single read may
+/// wait for data.
+#[derive(Clone, Debug)]
+pub(crate) struct Deadline {
+    at: Option<Instant>,
+    /// How long the deadline was from when it was set.
+    length: Option<Duration>,
+    clock: ClockRef,
+    read_timeout: Option<Duration>,
+}
+
+impl Deadline {
+    /// Creates a deadline `timeout` from now, or one that never
+    /// passes if `timeout` is None.
+    pub(crate) fn new(timeout: Option<Duration>, clock: ClockRef) -> Deadline {
+        let at = timeout.map(|timeout| clock.now() + timeout);
+        Deadline {
+            at,
+            length: timeout,
+            clock,
+            read_timeout: None,
+        }
+    }
+
+    /// Limits each read to `read_timeout`, in addition to the
+    /// deadline.
+    fn with_read_timeout(mut self, read_timeout: Option<Duration>) -> Deadline {
+        self.read_timeout = read_timeout;
+        self
+    }
+
+    /// Returns the time left until the deadline, or None if there is
+    /// no deadline.
+    ///
+    /// The Result will be Err if the deadline has already passed.
+    pub(crate) fn remaining(&self) -> Result<Option<Duration>, io::Error> {
+        if let Some(at) = self.at {
+            if let Some(duration) = at.checked_duration_since(self.clock.now()) {
+                Ok(Some(duration))
+            } else {
+                Err(timeout_err())
+            }
         } else {
-            Err(timeout_err())
+            Ok(None)
+        }
+    }
+
+    /// Returns a deadline `timeout` from now, or this one if it's
+    /// earlier or `timeout` is None.
+    pub(crate) fn limited_to(&self, timeout: Option<Duration>) -> Deadline {
+        let limit = timeout.map(|timeout| self.clock.now() + timeout);
+        let at = match (self.at, limit) {
+            (Some(at), Some(limit)) => Some(at.min(limit)),
+            (at, limit) => at.or(limit),
+        };
+        let length = match (self.length, timeout) {
+            (Some(length), Some(timeout)) => Some(length.min(timeout)),
+            (length, timeout) => length.or(timeout),
+        };
+        Deadline {
+            at,
+            length,
+            clock: self.clock.clone(),
+            read_timeout: self.read_timeout,
+        }
+    }
+
+    /// Returns how long a read may wait for data: until the deadline,
+    /// or for the read timeout if that's sooner.
+    ///
+    /// The Result will be Err if the deadline has already passed.
+    fn read_timeout(&self) -> Result<Option<Duration>, io::Error> {
+        Ok(socket_timeout(
+            match (self.remaining()?, self.read_timeout) {
+                (Some(remaining), Some(read_timeout)) => Some(remaining.min(read_timeout)),
+                (remaining, read_timeout) => remaining.or(read_timeout),
+            },
+        ))
+    }
+
+    /// Returns how long a single write may go without progress: as
+    /// long as the whole deadline, or the read timeout if that's
+    /// shorter. Unlike the read timeout, this doesn't shrink as the
+    /// deadline gets closer, so that uploads which keep making progress
+    /// aren't failed by a write timeout derived from the time left.
+    /// The deadline itself still bounds the whole request, see
+    /// [enforce_timeout].
+    fn write_timeout(&self) -> Option<Duration> {
+        socket_timeout(match (self.length, self.read_timeout) {
+            (Some(length), Some(read_timeout)) => Some(length.min(read_timeout)),
+            (length, read_timeout) => length.or(read_timeout),
+        })
+    }
+
+    /// Turns the error of a read that ran into the read timeout into
+    /// a TimedOut error. Reads timing out surface as WouldBlock on
+    /// some platforms, which is retried until the deadline passes, so
+    /// without this, the read timeout would never end the request.
+    fn read_error(&self, err: io::Error) -> io::Error {
+        let timed_out = matches!(
+            err.kind(),
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+        );
+        if timed_out && self.read_timeout.is_some() && self.remaining().is_ok() {
+            io::Error::new(
+                io::ErrorKind::TimedOut,
+                "no data was received within the read timeout",
+            )
+        } else {
+            err
         }
-    } else {
-        Ok(None)
     }
 }
 
 impl Read for HttpStream {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        let timeout = |tcp: &TcpStream, timeout_at: Option<Instant>| -> io::Result<()> {
-            let _ = tcp.set_read_timeout(timeout_at_to_duration(timeout_at)?);
+        let timeout = |tcp: &TcpStream, deadline: &Deadline| -> io::Result<()> {
+            let _ = tcp.set_read_timeout(deadline.read_timeout()?);
             Ok(())
         };
 
         match self {
-            HttpStream::Unsecured(inner, timeout_at) => {
-                timeout(inner.get_ref(), *timeout_at)?;
-                inner.read(buf)
+            HttpStream::Unsecured(inner, deadline) => {
+                timeout(inner.get_ref().tcp(), deadline)?;
+                inner.read(buf).map_err(|err| deadline.read_error(err))
             }
             #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
-            HttpStream::Secured(inner, timeout_at) => {
-                timeout(inner.get_ref(), *timeout_at)?;
-                inner.read(buf)
+            HttpStream::Secured(inner, deadline) => {
+                timeout(secured_tcp(inner), deadline)?;
+                inner.read(buf).map_err(|err| deadline.read_error(err))
             }
         }
     }
@@ -124,7 +495,13 @@ impl Read for HttpStream {
 /// [`Request`](struct.Request.html)s.
 pub struct Connection {
     request: ParsedRequest,
-    timeout_at: Option<Instant>,
+    /// When the whole exchange, including the response body, times out.
+    deadline: Deadline,
+    /// When the response headers have to be received by.
+    headers_deadline: Deadline,
+    /// Allows using a connection to the host, if the pool limits the
+    /// number of connections per host. Handed over to the response.
+    permit: Option<ConnectionPermit>,
 }
 
 impl Connection {
@@ -138,104 +515,170 @@ impl Connection {
                 Ok(t) => t.parse::<u64>().ok(),
                 Err(_) => None,
             });
-        let timeout_at = timeout.map(|t| Instant::now() + Duration::from_secs(t));
+        let clock = request.config.clock.clone().unwrap_or_default();
+        let deadline = Deadline::new(timeout.map(Duration::from_secs), clock)
+            .with_read_timeout(request.config.read_timeout);
+        let headers_deadline = deadline.limited_to(request.config.headers_timeout);
         Connection {
             request,
-            timeout_at,
+            deadline,
+            headers_deadline,
+            permit: None,
         }
     }
 
-    /// Returns the timeout duration for operations that should end at
-    /// timeout and are starting "now".
+    /// Returns how long connecting to the server may take from
+    /// "now": until the headers are due, or for the connect timeout if
+    /// that's sooner.
     ///
     /// The Result will be Err if the timeout has already passed.
-    fn timeout(&self) -> Result<Option<Duration>, io::Error> {
-        let timeout = timeout_at_to_duration(self.timeout_at);
-        log::trace!("Timeout requested, it is currently: {:?}", timeout);
+    fn connect_timeout(&self) -> Result<Option<Duration>, io::Error> {
+        let timeout = self
+            .headers_deadline
+            .limited_to(self.request.config.connect_timeout)
+            .remaining()
+            .map(socket_timeout);
+        log::trace!("Connect timeout requested, it is currently: {:?}", timeout);
         timeout
     }
 
-    /// Sends the [`Request`](struct.Request.html), consumes this
-    /// connection, and returns a [`Response`](struct.Response.html).
-    #[cfg(feature = "rustls")]
-    pub(crate) fn send_https(mut self) -> Result<ResponseLazy, Error> {
-        enforce_timeout(self.timeout_at, move || {
-            self.request.host = ensure_ascii_host(self.request.host)?;
-            let bytes = self.request.as_bytes();
-
-            // Rustls setup
-            log::trace!("Setting up TLS parameters for {}.", self.request.host);
-            let dns_name = match ServerName::try_from(&*self.request.host) {
-                Ok(result) => result,
-                Err(err) => return Err(Error::IoError(io::Error::new(io::ErrorKind::Other, err))),
-            };
-            let sess = ClientConnection::new(CONFIG.clone(), dns_name)
-                .map_err(Error::RustlsCreateConnection)?;
-
-            log::trace!("Establishing TCP connection to {}.", self.request.host);
-            let tcp = self.connect()?;
+    /// Sets up a TLS session with the server over a new connection.
+    /// With rustls, the handshake is done as the request is written.
+    #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+    fn connect_tls(&self) -> Result<SecuredStream, Error> {
+        log::trace!("Establishing TCP connection to {}.", self.request.host);
+        let transport = self.connect()?;
 
-            // Send request
-            log::trace!("Establishing TLS session to {}.", self.request.host);
-            let mut tls = StreamOwned::new(sess, tcp); // I don't think this actually does any communication.
-            log::trace!("Writing HTTPS request to {}.", self.request.host);
-            let _ = tls.get_ref().set_write_timeout(self.timeout()?);
-            tls.write_all(&bytes)?;
+        log::trace!("Establishing TLS session to {}.", self.request.host);
+        let server_name = self.request.config.tls_server_name.as_ref();
+        let tls = self.start_tls(server_name.unwrap_or(&self.request.host), transport)?;
+        #[cfg(not(feature = "rustls"))]
+        self.tls_negotiated();
+        Ok(tls)
+    }
 
-            // Receive request
-            log::trace!("Reading HTTPS response from {}.", self.request.host);
-            let response = ResponseLazy::from_stream(
-                HttpStream::create_secured(tls, self.timeout_at),
-                self.request.config.max_headers_size,
-                self.request.config.max_status_line_len,
-            )?;
-            handle_redirects(self, response)
-        })
+    /// Sets up a TLS session with `host` over `stream`, which is
+    /// either a connection to the server, or one to an `https://`
+    /// proxy.
+    #[cfg(feature = "rustls")]
+    fn start_tls<S: Read + Write>(&self, host: &str, stream: S) -> Result<Tls<S>, Error> {
+        // Rustls setup
+        log::trace!("Setting up TLS parameters for {}.", host);
+        let dns_name = match ServerName::try_from(tls_server_name(host)) {
+            Ok(result) => result,
+            Err(err) => return Err(Error::IoError(io::Error::new(io::ErrorKind::Other, err))),
+        };
+        let tls_config = self.request.config.tls.as_ref().and_then(|tls| tls.get());
+        let config = match &tls_config {
+            Some(tls) => tls.rustls.clone(),
+            None => CONFIG.clone(),
+        };
+        let sess =
+            ClientConnection::new(config, dns_name).map_err(Error::RustlsCreateConnection)?;
+        #[allow(unused_mut)]
+        let mut tls = StreamOwned::new(sess, stream); // I don't think this actually does any communication.
+        #[cfg(feature = "sha2")]
+        if let Some(pins) = tls_config.map(|tls| tls.pins) {
+            if pins.contains_host(tls_server_name(host)) {
+                // Unlike usual, the handshake is finished here, so that
+                // nothing is sent before the pinned keys are checked.
+                tls.conn.complete_io(&mut tls.sock)?;
+                let chain = tls.conn.peer_certificates().unwrap_or(&[]);
+                pins.verify(
+                    tls_server_name(host),
+                    chain.iter().map(|certificate| certificate.0.as_slice()),
+                )?;
+            }
+        }
+        Ok(tls)
     }
 
-    /// Sends the [`Request`](struct.Request.html), consumes this
-    /// connection, and returns a [`Response`](struct.Response.html).
+    /// Sets up a TLS session with `host` over `stream`, which is
+    /// either a connection to the server, or one to an `https://`
+    /// proxy.
     #[cfg(all(
         not(feature = "rustls"),
         any(feature = "openssl", feature = "native-tls")
     ))]
-    pub(crate) fn send_https(mut self) -> Result<ResponseLazy, Error> {
-        enforce_timeout(self.timeout_at, move || {
-            self.request.host = ensure_ascii_host(self.request.host)?;
-            let bytes = self.request.as_bytes();
-
-            log::trace!("Setting up TLS parameters for {}.", self.request.host);
-            let dns_name = &self.request.host;
-            /*
-            let mut builder = TlsConnector::builder();
-            ...
-            let sess = match builder.build() {
-            */
-            let sess = match TlsConnector::new() {
+    fn start_tls<S: Read + Write>(&self, host: &str, stream: S) -> Result<Tls<S>, Error> {
+        log::trace!("Setting up TLS parameters for {}.", host);
+        let tls_config = self.request.config.tls.as_ref().and_then(|tls| tls.get());
+        let sess = match &tls_config {
+            Some(tls) => tls.native.clone(),
+            None => match TlsConnector::new() {
                 Ok(sess) => sess,
                 Err(err) => return Err(Error::IoError(io::Error::new(io::ErrorKind::Other, err))),
-            };
+            },
+        };
+        match sess.connect(tls_server_name(host), stream) {
+            #[cfg(feature = "sha2")]
+            Ok(tls) => {
+                if let Some(pins) = tls_config.map(|tls| tls.pins) {
+                    // Only the server's own certificate is available.
+                    let certificate = tls.peer_certificate().ok().flatten();
+                    let der = certificate.and_then(|certificate| certificate.to_der().ok());
+                    pins.verify(tls_server_name(host), der.as_deref())?;
+                }
+                Ok(tls)
+            }
+            #[cfg(not(feature = "sha2"))]
+            Ok(tls) => Ok(tls),
+            Err(HandshakeError::Failure(err)) => {
+                Err(Error::IoError(io::Error::new(io::ErrorKind::Other, err)))
+            }
+            Err(HandshakeError::WouldBlock(_)) => {
+                Err(Error::IoError(io::ErrorKind::WouldBlock.into()))
+            }
+        }
+    }
 
-            log::trace!("Establishing TCP connection to {}.", self.request.host);
-            let tcp = self.connect()?;
+    /// Emits the [`TlsNegotiated`](Event::TlsNegotiated) event.
+    #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+    fn tls_negotiated(&self) {
+        self.request.config.emit(|| Event::TlsNegotiated {
+            host: self.request.host.clone(),
+        });
+    }
+
+    /// Sends the [`Request`](struct.Request.html), consumes this
+    /// connection, and returns a [`Response`](struct.Response.html).
+    #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+    pub(crate) fn send_https(mut self) -> Result<ResponseLazy, Error> {
+        enforce_timeout(self.headers_deadline.clone(), move || {
+            self.request.host = ensure_ascii_host(self.request.host)?;
+            self.wait_for_connection()?;
+            if let Some(chaos) = &self.request.config.chaos {
+                chaos.before_send()?;
+            }
+            let head = self.request.head();
+            let mut body = self.request.streaming_body()?;
+            if let Some(response) = self.send_reused(&head, &mut body) {
+                return handle_redirects(self, response?);
+            }
+
+            let mut tls = self.connect_tls()?;
 
             // Send request
-            log::trace!("Establishing TLS session to {}.", self.request.host);
-            let mut tls = match sess.connect(dns_name, tcp) {
-                Ok(tls) => tls,
-                Err(err) => return Err(Error::IoError(io::Error::new(io::ErrorKind::Other, err))),
-            };
             log::trace!("Writing HTTPS request to {}.", self.request.host);
-            let _ = tls.get_ref().set_write_timeout(self.timeout()?);
-            tls.write_all(&bytes)?;
+            write_request(
+                &mut tls,
+                &[&head, self.request.body()],
+                body.as_mut(),
+                self.request.config.buffer_size,
+                &self.headers_deadline,
+                secured_tcp,
+            )?;
+            #[cfg(feature = "rustls")]
+            self.tls_negotiated();
+            self.written();
 
             // Receive request
             log::trace!("Reading HTTPS response from {}.", self.request.host);
             let response = ResponseLazy::from_stream(
-                HttpStream::create_secured(tls, self.timeout_at),
-                self.request.config.max_headers_size,
-                self.request.config.max_status_line_len,
+                HttpStream::create_secured(tls, self.headers_deadline.clone()),
+                &self.request.config,
             )?;
+            let response = self.received(response, false);
             handle_redirects(self, response)
         })
     }
@@ -243,66 +686,308 @@ impl Connection {
     /// Sends the [`Request`](struct.Request.html), consumes this
     /// connection, and returns a [`Response`](struct.Response.html).
     pub(crate) fn send(mut self) -> Result<ResponseLazy, Error> {
-        enforce_timeout(self.timeout_at, move || {
+        enforce_timeout(self.headers_deadline.clone(), move || {
             self.request.host = ensure_ascii_host(self.request.host)?;
-            let bytes = self.request.as_bytes();
+            self.wait_for_connection()?;
+            if let Some(chaos) = &self.request.config.chaos {
+                chaos.before_send()?;
+            }
+            let head = self.request.head();
+            let mut body = self.request.streaming_body()?;
+            if let Some(response) = self.send_reused(&head, &mut body) {
+                return handle_redirects(self, response?);
+            }
 
             log::trace!("Establishing TCP connection to {}.", self.request.host);
-            let tcp = self.connect()?;
+            let transport = self.connect()?;
 
             // Send request
             log::trace!("Writing HTTP request.");
-            let mut stream = BufWriter::new(tcp);
-            let _ = stream.get_ref().set_write_timeout(self.timeout()?);
-            stream.write_all(&bytes)?;
+            let mut stream = match self.request.config.buffer_size {
+                Some(size) => BufWriter::with_capacity(size, transport),
+                None => BufWriter::new(transport),
+            };
+            write_request(
+                &mut stream,
+                &[&head, self.request.body()],
+                body.as_mut(),
+                self.request.config.buffer_size,
+                &self.headers_deadline,
+                |stream| stream.get_ref().tcp(),
+            )?;
+            self.written();
 
             // Receive response
             log::trace!("Reading HTTP response.");
-            let tcp = match stream.into_inner() {
-                Ok(tcp) => tcp,
+            let transport = match stream.into_inner() {
+                Ok(transport) => transport,
                 Err(_) => {
                     return Err(Error::Other(
                         "IntoInnerError after writing the request into the TcpStream.",
                     ));
                 }
             };
-            let stream = HttpStream::create_unsecured(BufReader::new(tcp), self.timeout_at);
-            let response = ResponseLazy::from_stream(
-                stream,
-                self.request.config.max_headers_size,
-                self.request.config.max_status_line_len,
-            )?;
+            let reader = match self.request.config.buffer_size {
+                Some(size) => BufReader::with_capacity(size, transport),
+                None => BufReader::new(transport),
+            };
+            let stream = HttpStream::create_unsecured(reader, self.headers_deadline.clone());
+            let response = ResponseLazy::from_stream(stream, &self.request.config)?;
+            let response = self.received(response, false);
             handle_redirects(self, response)
         })
     }
 
-    fn connect(&self) -> Result<TcpStream, Error> {
-        let tcp_connect = |host: &str, port: u32| -> Result<TcpStream, Error> {
-            let host = format!("{}:{}", host, port);
-            let mut addrs = host.to_socket_addrs().map_err(Error::IoError)?;
-            let sock_address = addrs.next().ok_or(Error::AddressNotFound)?;
-            let stream = if let Some(timeout) = self.timeout()? {
-                TcpStream::connect_timeout(&sock_address, timeout)
+    /// Sends `requests`, which go to the same origin as the request
+    /// of this connection, on one new connection right after it,
+    /// before reading the responses to all of them in order.
+    ///
+    /// Redirects aren't followed, and the connection goes back into
+    /// the pool once the last response has been read.
+    pub(crate) fn send_pipelined(
+        mut self,
+        mut requests: Vec<ParsedRequest>,
+    ) -> Result<Vec<Response>, Error> {
+        enforce_timeout(self.deadline.clone(), move || {
+            self.request.host = ensure_ascii_host(self.request.host)?;
+            for request in &mut requests {
+                request.host = ensure_ascii_host(std::mem::take(&mut request.host))?;
+            }
+            self.wait_for_connection()?;
+
+            let mut batch = self.request.head();
+            batch.extend_from_slice(self.request.body());
+            for request in &requests {
+                batch.extend(request.head());
+                batch.extend_from_slice(request.body());
+            }
+
+            let mut stream = if self.request.https {
+                #[cfg(not(any(
+                    feature = "rustls",
+                    feature = "openssl",
+                    feature = "native-tls"
+                )))]
+                return Err(Error::HttpsFeatureNotEnabled);
+                #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+                HttpStream::create_secured(self.connect_tls()?, self.headers_deadline.clone())
             } else {
-                TcpStream::connect(sock_address)
+                let transport = self.connect()?;
+                HttpStream::create_unsecured(
+                    BufReader::new(transport),
+                    self.headers_deadline.clone(),
+                )
             };
-            stream.map_err(Error::from)
+            log::trace!(
+                "Writing {} pipelined requests to {}.",
+                requests.len() + 1,
+                self.request.host
+            );
+            let buffer_size = self.request.config.buffer_size;
+            let deadline = &self.headers_deadline;
+            write_request(
+                &mut stream,
+                &[&batch],
+                None,
+                buffer_size,
+                deadline,
+                HttpStream::tcp,
+            )?;
+            #[cfg(feature = "rustls")]
+            if self.request.https {
+                self.tls_negotiated();
+            }
+            self.written();
+            for request in &requests {
+                request.config.emit(|| Event::RequestWritten {
+                    method: request.config.method.clone(),
+                    url: request.url(),
+                });
+            }
+
+            let capacity = buffer_size.unwrap_or(BACKING_READ_BUFFER_LENGTH);
+            let mut reader = Some(BufReader::with_capacity(capacity, stream));
+            let mut responses = Vec::with_capacity(requests.len() + 1);
+            for i in 0..=requests.len() {
+                let mut next = match reader.take() {
+                    Some(next) => next,
+                    // The server closed the connection early.
+                    None => return Err(Error::ConnectionClosed),
+                };
+                next.get_mut().set_deadline(self.headers_deadline.clone());
+                let config = match i {
+                    0 => &self.request.config,
+                    i => &requests[i - 1].config,
+                };
+                let mut response = ResponseLazy::from_reader(next, config)?;
+                if i == requests.len() {
+                    response = self.received(response, false);
+                } else {
+                    response.set_deadline(self.deadline.clone());
+                }
+                let (response, next) = Response::create_pipelined(response)?;
+                responses.push(response);
+                reader = next;
+            }
+            Ok(responses)
+        })
+    }
+
+    /// Waits until a connection to the host may be used, if the pool
+    /// of the request limits the number of connections per host.
+    fn wait_for_connection(&mut self) -> Result<(), Error> {
+        let key = self.pool_key();
+        if let Some(pool) = &self.request.config.pool {
+            self.permit =
+                pool.acquire(&key, self.request.config.priority, &self.headers_deadline)?;
+        }
+        Ok(())
+    }
+
+    /// Sends the request on an idle connection from the pool of the
+    /// request, if there is one.
+    ///
+    /// Returns None if there was no idle connection, or if the server
+    /// closed it just as the request was sent and the request is safe
+    /// to send again on a new connection, which a streamed `body`
+    /// never is.
+    fn send_reused(
+        &mut self,
+        head: &[u8],
+        body: &mut Option<StreamingBody>,
+    ) -> Option<Result<ResponseLazy, Error>> {
+        let pool = self.request.config.pool.as_ref()?;
+        let mut stream = pool.take(&self.pool_key())?;
+        log::trace!("Reusing a connection to {}.", self.request.host);
+        self.connected(stream.tcp(), true);
+        stream.set_deadline(self.headers_deadline.clone());
+        let written = write_request(
+            &mut stream,
+            &[head, self.request.body()],
+            body.as_mut(),
+            self.request.config.buffer_size,
+            &self.headers_deadline,
+            HttpStream::tcp,
+        );
+        let result = match written {
+            Ok(()) => {
+                self.written();
+                ResponseLazy::from_stream(stream, &self.request.config)
+            }
+            Err(err) => Err(err),
+        };
+        match result {
+            Err(Error::ConnectionClosed)
+                if body.is_none() && is_idempotent(&self.request.config.method) =>
+            {
+                None
+            }
+            result => Some(result.map(|response| self.received(response, true))),
+        }
+    }
+
+    /// Prepares `response`, whose headers have been read, for reading
+    /// its body: the body is read until the request's deadline instead
+    /// of the headers' one, and the connection goes back into the pool
+    /// of the request once the body has been read. The faults of the
+    /// request's [Chaos](crate::Chaos) are injected here.
+    fn received(&mut self, mut response: ResponseLazy, connection_reused: bool) -> ResponseLazy {
+        response.set_deadline(self.deadline.clone());
+        if let Some(chaos) = &self.request.config.chaos {
+            chaos.received(&mut response);
+        }
+        match &self.request.config.pool {
+            Some(pool) => {
+                let slot = PoolSlot::new(pool.clone(), self.pool_key());
+                response.with_pool(slot, self.permit.take(), connection_reused)
+            }
+            None => response,
+        }
+    }
+
+    fn connected(&self, tcp: &TcpStream, reused: bool) {
+        if let Ok(addr) = tcp.peer_addr() {
+            self.request
+                .config
+                .emit(|| Event::Connected { addr, reused });
+        }
+    }
+
+    fn written(&self) {
+        self.request.config.emit(|| Event::RequestWritten {
+            method: self.request.config.method.clone(),
+            url: self.request.url(),
+        });
+    }
+
+    fn pool_key(&self) -> PoolKey {
+        let key = PoolKey::new(
+            self.request.https,
+            &self.request.host,
+            self.request.port.port(),
+        )
+        .with_socket_addrs(&self.request.config.socket_addrs);
+        #[cfg(feature = "proxy")]
+        let key = key.with_proxy(self.request.config.proxy.as_ref());
+        // Connections made with another TLS server name or
+        // configuration can't be reused, as their certificates were
+        // checked against other names, roots or pins.
+        #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+        let key = key
+            .with_tls_server_name(self.request.config.tls_server_name.as_deref())
+            .with_tls_config(self.request.config.tls.as_ref());
+        key
+    }
+
+    fn connect(&self) -> Result<Transport, Error> {
+        let resolve = |host: &str, port: u32| -> Result<Vec<SocketAddr>, Error> {
+            match split_ipv6_literal(host) {
+                Some((addr, Some(zone))) => Ok(vec![scoped_ipv6_addr(addr, zone, port)?]),
+                _ => {
+                    let address = format!("{}:{}", host, port);
+                    let lookup = || address.to_socket_addrs().map(Iterator::collect);
+                    let addrs = lookup_with_retries(
+                        lookup,
+                        self.request.config.dns_retries,
+                        &self.headers_deadline,
+                    )?;
+                    self.request.config.emit(|| Event::DnsResolved {
+                        host: host.to_string(),
+                        addrs: addrs.clone(),
+                    });
+                    Ok(addrs)
+                }
+            }
+        };
+        let tcp_connect = |addrs: Vec<SocketAddr>| -> Result<TcpStream, Error> {
+            let tcp = connect_happy_eyeballs(addrs, self.connect_timeout()?)?;
+            if let Some(hook) = &self.request.config.socket_hook {
+                hook.call(&tcp).map_err(Error::IoError)?;
+            }
+            self.connected(&tcp, false);
+            Ok(tcp)
         };
 
         #[cfg(feature = "proxy")]
         match self.request.config.proxy {
+            // Plain HTTP requests, and FTP gateway ones, are sent to
+            // the proxy itself instead of through a CONNECT tunnel.
+            Some(ref proxy) if self.request.is_forwarded() => {
+                self.proxy_transport(proxy, tcp_connect(resolve(&proxy.server, proxy.port)?)?)
+            }
             Some(ref proxy) => {
                 // do proxy things
-                let mut tcp = tcp_connect(&proxy.server, proxy.port)?;
+                let tcp = tcp_connect(resolve(&proxy.server, proxy.port)?)?;
+                let mut transport = self.proxy_transport(proxy, tcp)?;
 
-                write!(tcp, "{}", proxy.connect(&self.request)).unwrap();
-                tcp.flush()?;
+                write!(transport, "{}", proxy.connect(&self.request))?;
+                transport.flush()?;
 
                 let mut proxy_response = Vec::new();
 
                 loop {
                     let mut buf = vec![0; 256];
-                    let total = tcp.read(&mut buf)?;
+                    let total = transport.read(&mut buf)?;
                     proxy_response.append(&mut buf);
                     if total < 256 {
                         break;
@@ -311,34 +996,380 @@ impl Connection {
 
                 crate::Proxy::verify_response(&proxy_response)?;
 
-                Ok(tcp)
+                Ok(transport)
             }
-            None => tcp_connect(&self.request.host, self.request.port.port()),
+            None => Ok(Transport::Tcp(tcp_connect(self.target_addrs(resolve)?)?)),
         }
 
         #[cfg(not(feature = "proxy"))]
-        tcp_connect(&self.request.host, self.request.port.port())
+        Ok(Transport::Tcp(tcp_connect(self.target_addrs(resolve)?)?))
     }
-}
 
-fn handle_redirects(connection: Connection, response: ResponseLazy) -> Result<ResponseLazy, Error> {
-    let status_code = response.status_code;
-    let url = response.headers.get("location");
-    if let Some(connection) = get_redirect(connection, status_code, url) {
-        let connection = connection?;
-        if connection.request.https {
-            #[cfg(not(any(feature = "rustls", feature = "openssl", feature = "native-tls")))]
-            return Err(Error::HttpsFeatureNotEnabled);
+    /// Returns the connection to `proxy` over `tcp`, which is a TLS
+    /// session with the proxy if it's an `https://` proxy.
+    #[cfg(feature = "proxy")]
+    fn proxy_transport(&self, proxy: &Proxy, tcp: TcpStream) -> Result<Transport, Error> {
+        if !proxy.https {
+            Ok(Transport::Tcp(tcp))
+        } else {
             #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
-            return connection.send_https();
+            {
+                log::trace!("Establishing TLS session to proxy {}.", proxy.server);
+                let tls = self.start_tls(&proxy.server, tcp)?;
+                Ok(Transport::Tls(Box::new(tls)))
+            }
+            #[cfg(not(any(feature = "rustls", feature = "openssl", feature = "native-tls")))]
+            {
+                Err(Error::HttpsFeatureNotEnabled)
+            }
+        }
+    }
+
+    /// Returns the addresses of the request's host: the ones set with
+    /// [`Request::with_socket_addrs`], or the ones found by `resolve`.
+    fn target_addrs<F>(&self, resolve: F) -> Result<Vec<SocketAddr>, Error>
+    where
+        F: Fn(&str, u32) -> Result<Vec<SocketAddr>, Error>,
+    {
+        if self.request.config.socket_addrs.is_empty() {
+            resolve(&self.request.host, self.request.port.port())
+        } else {
+            Ok(self.request.config.socket_addrs.clone())
+        }
+    }
+}
+
+/// Runs the host lookup `lookup`, retrying it up to `retries` times
+/// with a short, exponential backoff when it fails temporarily, eg.
+/// because the resolver is overloaded or briefly unreachable.
+fn lookup_with_retries<F>(
+    lookup: F,
+    retries: u32,
+    deadline: &Deadline,
+) -> Result<Vec<SocketAddr>, Error>
+where
+    F: Fn() -> io::Result<Vec<SocketAddr>>,
+{
+    let mut delay = DNS_RETRY_DELAY;
+    let mut attempt = 0;
+    loop {
+        match lookup() {
+            Err(err) if attempt < retries && is_temporary_lookup_failure(&err) => {
+                let delay_left = match deadline.remaining()? {
+                    Some(remaining) => delay.min(remaining),
+                    None => delay,
+                };
+                log::debug!("Host lookup failed temporarily ({}), retrying.", err);
+                std::thread::sleep(delay_left);
+                delay *= 2;
+                attempt += 1;
+            }
+            result => return result.map_err(Error::IoError),
+        }
+    }
+}
+
+/// Returns true if `err` is a host lookup failure that's worth
+/// retrying, `EAI_AGAIN` (or `WSATRY_AGAIN` on Windows). The standard
+/// library doesn't expose the error code of lookups on Unix, only its
+/// message.
+fn is_temporary_lookup_failure(err: &io::Error) -> bool {
+    if cfg!(windows) {
+        err.raw_os_error() == Some(11002)
+    } else {
+        let message = err.to_string().to_lowercase();
+        message.contains("temporary failure in name resolution") || message.ends_with("try again")
+    }
+}
+
+/// Returns the socket address of the IPv6 address `addr` on the
+/// network interface `zone`, which is either an interface index or,
+/// on Linux, an interface name.
+fn scoped_ipv6_addr(addr: &str, zone: &str, port: u32) -> Result<SocketAddr, Error> {
+    let invalid = |msg: &str| Error::IoError(io::Error::new(io::ErrorKind::InvalidInput, msg));
+    let ip = addr
+        .parse::<Ipv6Addr>()
+        .map_err(|_| invalid("invalid IPv6 address"))?;
+    let port = u16::try_from(port).map_err(|_| invalid("invalid port"))?;
+    let scope_id = match zone.parse::<u32>() {
+        Ok(index) => index,
+        Err(_) => interface_index(zone).ok_or_else(|| invalid("unknown network interface"))?,
+    };
+    Ok(SocketAddr::V6(SocketAddrV6::new(ip, port, 0, scope_id)))
+}
+
+/// Returns the index of the network interface called `name`.
+#[cfg(target_os = "linux")]
+fn interface_index(name: &str) -> Option<u32> {
+    if name.is_empty() || name.contains('/') || name.starts_with('.') {
+        return None;
+    }
+    let path = format!("/sys/class/net/{}/ifindex", name);
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Returns the index of the network interface called `name`.
+#[cfg(not(target_os = "linux"))]
+fn interface_index(_name: &str) -> Option<u32> {
+    None
+}
+
+/// Returns the name to verify the server's certificate against,
+/// which is the address without the brackets and zone ID for IPv6
+/// literals.
+#[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+fn tls_server_name(host: &str) -> &str {
+    split_ipv6_literal(host).map_or(host, |(addr, _)| addr)
+}
+
+/// The delay between starting connection attempts to different
+/// addresses, as recommended by RFC 8305 section 5.
+const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Connects to one of `addrs`, racing the connection attempts as
+/// described in RFC 8305 (Happy Eyeballs).
+///
+/// The addresses are tried in an order that alternates between IPv6
+/// and IPv4, starting with the family of the first address. A new
+/// attempt is started whenever the previous one fails, or hasn't
+/// succeeded in [CONNECTION_ATTEMPT_DELAY]. The first connection to
+/// succeed is returned, so broken IPv6 connectivity only costs a
+/// fraction of a second, while IPv6 is still used where it works.
+fn connect_happy_eyeballs(
+    addrs: Vec<SocketAddr>,
+    timeout: Option<Duration>,
+) -> Result<TcpStream, Error> {
+    use std::sync::mpsc::{channel, RecvTimeoutError};
+
+    let connect = move |addr: SocketAddr| {
+        log::trace!("Attempting to connect to {}.", addr);
+        match timeout {
+            Some(timeout) => TcpStream::connect_timeout(&addr, timeout),
+            None => TcpStream::connect(addr),
+        }
+    };
+
+    if addrs.len() <= 1 {
+        let addr = addrs.into_iter().next().ok_or(Error::AddressNotFound)?;
+        return connect(addr).map_err(Error::from);
+    }
+
+    // Interleave the address families, preferring the first one.
+    let prefer_ipv6 = addrs[0].is_ipv6();
+    let (mut preferred, mut other): (Vec<SocketAddr>, Vec<SocketAddr>) = addrs
+        .into_iter()
+        .partition(|addr| addr.is_ipv6() == prefer_ipv6);
+    let mut ordered = Vec::with_capacity(preferred.len() + other.len());
+    preferred.reverse();
+    other.reverse();
+    while !preferred.is_empty() || !other.is_empty() {
+        ordered.extend(preferred.pop());
+        ordered.extend(other.pop());
+    }
+
+    let (sender, receiver) = channel();
+    let mut addrs = ordered.into_iter().peekable();
+    let mut pending = 0;
+    let mut last_error = None;
+    loop {
+        if let Some(addr) = addrs.next() {
+            let sender = sender.clone();
+            pending += 1;
+            std::thread::spawn(move || {
+                // If another attempt has already won, the receiver is
+                // gone, and this connection is simply dropped.
+                let _ = sender.send(connect(addr));
+            });
+        }
+        if pending == 0 {
+            break;
+        }
+
+        let result = if addrs.peek().is_some() {
+            match receiver.recv_timeout(CONNECTION_ATTEMPT_DELAY) {
+                Ok(result) => result,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        } else {
+            match receiver.recv() {
+                Ok(result) => result,
+                Err(_) => break,
+            }
+        };
+        pending -= 1;
+        match result {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_error = Some(err),
+        }
+    }
+    Err(last_error.map_or(Error::AddressNotFound, Error::IoError))
+}
+
+/// Writes the `parts` of the request into the stream, followed by
+/// `body` if the body is streamed from a reader, classifying errors
+/// caused by the server having closed the connection as
+/// [Error::ConnectionClosed]. A streamed body is read in pieces of
+/// `buffer_size` bytes, 64 KiB by default.
+///
+/// The request is written in chunks, each of which has to be written
+/// within the write timeout of the deadline (set on the socket
+/// returned by `tcp`), so that a stalled upload fails while one that
+/// keeps making progress runs until the deadline itself passes.
+fn write_request<W, F>(
+    stream: &mut W,
+    parts: &[&[u8]],
+    body: Option<&mut StreamingBody>,
+    buffer_size: Option<usize>,
+    deadline: &Deadline,
+    tcp: F,
+) -> Result<(), Error>
+where
+    W: Write,
+    F: Fn(&W) -> &TcpStream,
+{
+    let write_error = |err: io::Error| {
+        if is_connection_closed(&err) {
+            Error::ConnectionClosed
+        } else if matches!(
+            err.kind(),
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+        ) {
+            Error::IoError(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "no data could be written within the write timeout",
+            ))
         } else {
-            connection.send()
+            Error::IoError(err)
+        }
+    };
+    let _ = tcp(stream).set_write_timeout(deadline.write_timeout());
+    let write = |stream: &mut W, bytes: &[u8]| -> Result<(), Error> {
+        for chunk in bytes.chunks(WRITE_CHUNK_SIZE) {
+            stream.write_all(chunk).map_err(write_error)?;
+        }
+        Ok(())
+    };
+    for part in parts {
+        write(stream, part)?;
+    }
+    if let Some(body) = body {
+        let mut buf = vec![0; buffer_size.unwrap_or(WRITE_CHUNK_SIZE)];
+        loop {
+            let n = body.read(&mut buf)?;
+            if body.chunked() {
+                write(stream, format!("{:x}\r\n", n).as_bytes())?;
+                write(stream, &buf[..n])?;
+                write(stream, b"\r\n")?;
+            } else {
+                write(stream, &buf[..n])?;
+            }
+            if n == 0 {
+                break;
+            }
+        }
+    }
+    stream.flush().map_err(write_error)
+}
+
+fn handle_redirects(connection: Connection, response: ResponseLazy) -> Result<ResponseLazy, Error> {
+    if let Some(id) = response.request_id() {
+        log::debug!(
+            "Response {} from {} has request id {}.",
+            response.status_code,
+            connection.request.host,
+            id
+        );
+    }
+    if let Some(rate_limiter) = &connection.request.config.rate_limiter {
+        rate_limiter.update(&response.headers);
+    }
+    if let Some(jar) = &connection.request.config.cookie_jar {
+        let url = connection.request.url();
+        for set_cookie in &response.set_cookies {
+            jar.set_cookie(&url, set_cookie);
+        }
+    }
+    if let Some(hook) = &connection.request.config.deprecation_hook {
+        let url = connection.request.url();
+        if let Some(notice) = DeprecationNotice::from_headers(url, &response.headers) {
+            hook.call(&notice);
         }
+    }
+
+    #[cfg(any(feature = "sha2", feature = "md-5"))]
+    let mut connection = connection;
+    #[cfg(any(feature = "sha2", feature = "md-5"))]
+    if let Some(authorization) = get_digest_authorization(&mut connection, &response) {
+        connection
+            .request
+            .config
+            .headers
+            .retain(|key, _| !key.eq_ignore_ascii_case("authorization"));
+        connection
+            .request
+            .config
+            .headers
+            .insert("Authorization".to_string(), authorization);
+        // The response holds on to the connection, which might be
+        // the only one allowed to the host.
+        drop(response);
+        return send_again(connection);
+    }
+
+    let status_code = response.status_code;
+    let url = response.headers.get("location").cloned();
+    if let Some(connection) = get_redirect(connection, status_code, url.as_ref()) {
+        drop(response);
+        send_again(connection?)
     } else {
         Ok(response)
     }
 }
 
+fn send_again(connection: Connection) -> Result<ResponseLazy, Error> {
+    if connection.request.https {
+        #[cfg(not(any(feature = "rustls", feature = "openssl", feature = "native-tls")))]
+        return Err(Error::HttpsFeatureNotEnabled);
+        #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+        return connection.send_https();
+    } else {
+        connection.send()
+    }
+}
+
+/// Returns the `Authorization` header answering the Digest challenge
+/// of a 401 response, if the request has Digest credentials that
+/// haven't been used yet. They're only used once, so that wrong
+/// credentials don't cause an endless loop. Requests with a streamed
+/// body aren't answered, as the body can't be sent again.
+#[cfg(any(feature = "sha2", feature = "md-5"))]
+fn get_digest_authorization(
+    connection: &mut Connection,
+    response: &ResponseLazy,
+) -> Option<String> {
+    if response.status_code != 401 {
+        return None;
+    }
+    if connection.request.config.body_reader.is_some() {
+        log::debug!("Not answering the Digest challenge, the body was streamed");
+        return None;
+    }
+    let auth = connection.request.config.digest_auth.take()?;
+    let challenge = response.headers.get("www-authenticate")?;
+    log::debug!(
+        "Answering the Digest challenge from {}",
+        connection.request.host
+    );
+    let config = &connection.request.config;
+    auth.authorization(
+        challenge,
+        &config.method,
+        &connection.request.request_target(),
+        &config.random,
+    )
+}
+
 fn get_redirect(
     mut connection: Connection,
     status_code: i32,
@@ -352,17 +1383,18 @@ fn get_redirect(
             };
             log::debug!("Redirecting ({}) to: {}", status_code, url);
 
-            match connection.request.redirect_to(url.clone()) {
+            let config = &connection.request.config;
+            let policy = config.redirect_method_policy;
+            let method = policy.redirected_method(status_code, &config.method);
+            if policy.drops_body(status_code, &config.method) {
+                connection.request.remove_body();
+            }
+            match connection.request.redirect_to(url.clone(), method) {
                 Ok(()) => {
-                    if status_code == 303 {
-                        match connection.request.config.method {
-                            Method::Post | Method::Put | Method::Delete => {
-                                connection.request.config.method = Method::Get;
-                            }
-                            _ => {}
-                        }
-                    }
-
+                    connection.request.config.emit(|| Event::Redirected {
+                        status_code,
+                        url: connection.request.url(),
+                    });
                     Some(Ok(connection))
                 }
                 Err(err) => Some(Err(err)),
@@ -408,35 +1440,321 @@ fn ensure_ascii_host(host: String) -> Result<String, Error> {
 /// While minreq does use timeouts (somewhat) properly, some
 /// interfaces such as [ToSocketAddrs] don't allow for specifying the
 /// timeout. Hence this.
-fn enforce_timeout<F, R>(timeout_at: Option<Instant>, f: F) -> Result<R, Error>
+fn enforce_timeout<F, R>(deadline: Deadline, f: F) -> Result<R, Error>
 where
     F: 'static + Send + FnOnce() -> Result<R, Error>,
     R: 'static + Send,
 {
     use std::sync::mpsc::{channel, RecvTimeoutError};
 
-    match timeout_at {
-        Some(deadline) => {
+    match deadline.remaining() {
+        Ok(Some(timeout_duration)) => {
             let (sender, receiver) = channel();
             let thread = std::thread::spawn(move || {
                 let result = f();
                 let _ = sender.send(());
                 result
             });
-            if let Some(timeout_duration) = deadline.checked_duration_since(Instant::now()) {
-                match receiver.recv_timeout(timeout_duration) {
-                    Ok(()) => thread.join().unwrap(),
-                    Err(err) => match err {
-                        RecvTimeoutError::Timeout => Err(Error::IoError(timeout_err())),
-                        RecvTimeoutError::Disconnected => {
-                            Err(Error::Other("request connection paniced"))
-                        }
-                    },
-                }
+            match receiver.recv_timeout(timeout_duration) {
+                Ok(()) => thread.join().unwrap(),
+                Err(err) => match err {
+                    RecvTimeoutError::Timeout => Err(Error::IoError(timeout_err())),
+                    RecvTimeoutError::Disconnected => {
+                        Err(Error::Other("request connection paniced"))
+                    }
+                },
+            }
+        }
+        Ok(None) => f(),
+        Err(err) => Err(Error::IoError(err)),
+    }
+}
+
+#[cfg(test)]
+mod happy_eyeballs_tests {
+    use super::connect_happy_eyeballs;
+    use std::net::TcpListener;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_falls_back_to_working_address() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let working = listener.local_addr().unwrap();
+        // Nothing is listening on this port anymore.
+        let broken = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let start = Instant::now();
+        let stream = connect_happy_eyeballs(vec![broken, working], None).unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), working);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_all_addresses_fail() {
+        let unused = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let result = connect_happy_eyeballs(vec![unused, unused], None);
+        assert!(result.is_err());
+    }
+}
+
+#[cfg(test)]
+mod scoped_ipv6_tests {
+    use super::scoped_ipv6_addr;
+
+    #[test]
+    fn test_scoped_ipv6_addr() {
+        let addr = scoped_ipv6_addr("fe80::1", "3", 8080).unwrap();
+        assert_eq!(addr.to_string(), "[fe80::1%3]:8080");
+        assert!(scoped_ipv6_addr("fe80::1", "not an interface", 80).is_err());
+        assert!(scoped_ipv6_addr("fe80::1", "../lo", 80).is_err());
+        assert!(scoped_ipv6_addr("192.168.0.1", "3", 80).is_err());
+        assert!(scoped_ipv6_addr("fe80::1", "3", 65536).is_err());
+    }
+}
+
+#[cfg(test)]
+mod write_request_tests {
+    use super::{write_request, Deadline};
+    use crate::clock::{Clock, ClockRef};
+    use crate::Error;
+    use std::io::{self, BufWriter};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::{mpsc, Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    /// A clock that moves forward by a tenth of a second every time
+    /// it's read.
+    struct TickingClock(Mutex<Instant>);
+
+    impl Clock for TickingClock {
+        fn now(&self) -> Instant {
+            let mut now = self.0.lock().unwrap();
+            *now += Duration::from_millis(100);
+            *now
+        }
+    }
+
+    #[test]
+    fn test_write_timeout_follows_progress() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            io::copy(&mut stream, &mut io::sink()).unwrap()
+        });
+
+        let clock = Arc::new(TickingClock(Mutex::new(Instant::now())));
+        let deadline = Deadline::new(Some(Duration::from_secs(1)), ClockRef(clock));
+        let mut stream = BufWriter::new(TcpStream::connect(address).unwrap());
+        // The time left runs out while the request is being written,
+        // but the server keeps reading it, so it isn't cut short.
+        let body = vec![0; 4 * 1024 * 1024];
+        write_request(
+            &mut stream,
+            &[&body],
+            None,
+            None,
+            &deadline,
+            BufWriter::get_ref,
+        )
+        .unwrap();
+        drop(stream);
+        assert_eq!(server.join().unwrap(), body.len() as u64);
+    }
+
+    #[test]
+    fn test_write_timeout_stalled() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let (sender, receiver) = mpsc::channel::<()>();
+        let server = std::thread::spawn(move || {
+            // Accept the connection, but never read from it.
+            let (_stream, _) = listener.accept().unwrap();
+            let _ = receiver.recv();
+        });
+
+        let deadline = Deadline::new(Some(Duration::from_millis(200)), Default::default());
+        let mut stream = BufWriter::new(TcpStream::connect(address).unwrap());
+        let body = vec![0; 64 * 1024 * 1024];
+        match write_request(
+            &mut stream,
+            &[&body],
+            None,
+            None,
+            &deadline,
+            BufWriter::get_ref,
+        ) {
+            Err(Error::IoError(err)) => assert_eq!(err.kind(), io::ErrorKind::TimedOut),
+            result => panic!("expected a timeout, got {:?}", result),
+        }
+        drop(sender);
+        server.join().unwrap();
+    }
+}
+
+#[cfg(test)]
+mod lookup_retry_tests {
+    use super::{is_temporary_lookup_failure, lookup_with_retries, Deadline};
+    use std::cell::Cell;
+    use std::io;
+    use std::net::SocketAddr;
+
+    fn temporary_failure() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Other,
+            "failed to lookup address information: Temporary failure in name resolution",
+        )
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn test_temporary_lookup_failure() {
+        assert!(is_temporary_lookup_failure(&temporary_failure()));
+        let not_found = io::Error::new(
+            io::ErrorKind::Other,
+            "failed to lookup address information: Name or service not known",
+        );
+        assert!(!is_temporary_lookup_failure(&not_found));
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn test_lookup_with_retries() {
+        let deadline = Deadline::new(None, Default::default());
+        let addr: SocketAddr = "127.0.0.1:80".parse().unwrap();
+        let calls = Cell::new(0);
+        let lookup = || {
+            calls.set(calls.get() + 1);
+            if calls.get() < 3 {
+                Err(temporary_failure())
             } else {
-                Err(Error::IoError(timeout_err()))
+                Ok(vec![addr])
             }
+        };
+        assert_eq!(
+            lookup_with_retries(lookup, 2, &deadline).unwrap(),
+            vec![addr]
+        );
+        assert_eq!(calls.get(), 3);
+
+        calls.set(0);
+        assert!(lookup_with_retries(lookup, 1, &deadline).is_err());
+        assert_eq!(calls.get(), 2);
+
+        calls.set(0);
+        let not_found = || {
+            calls.set(calls.get() + 1);
+            Err(io::Error::new(io::ErrorKind::Other, "not found"))
+        };
+        assert!(lookup_with_retries(not_found, 2, &deadline).is_err());
+        assert_eq!(calls.get(), 1);
+    }
+}
+
+#[cfg(test)]
+mod deadline_tests {
+    use super::Deadline;
+    use crate::clock::{Clock, ClockRef};
+    use std::io;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    struct ManualClock(Mutex<Instant>);
+
+    impl ManualClock {
+        fn advance(&self, duration: Duration) {
+            *self.0.lock().unwrap() += duration;
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> Instant {
+            *self.0.lock().unwrap()
         }
-        None => f(),
+    }
+
+    #[test]
+    fn test_deadline_follows_clock() {
+        let clock = Arc::new(ManualClock(Mutex::new(Instant::now())));
+        let deadline = Deadline::new(Some(Duration::from_secs(10)), ClockRef(clock.clone()));
+        assert_eq!(deadline.remaining().unwrap(), Some(Duration::from_secs(10)));
+
+        clock.advance(Duration::from_secs(4));
+        assert_eq!(deadline.remaining().unwrap(), Some(Duration::from_secs(6)));
+
+        clock.advance(Duration::from_secs(7));
+        let err = deadline.remaining().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn test_limited_deadline() {
+        let clock = Arc::new(ManualClock(Mutex::new(Instant::now())));
+        let deadline = Deadline::new(Some(Duration::from_secs(10)), ClockRef(clock.clone()));
+        let limited = deadline.limited_to(Some(Duration::from_secs(3)));
+        assert_eq!(limited.remaining().unwrap(), Some(Duration::from_secs(3)));
+        let limited = deadline.limited_to(Some(Duration::from_secs(30)));
+        assert_eq!(limited.remaining().unwrap(), Some(Duration::from_secs(10)));
+        let limited = deadline.limited_to(None);
+        assert_eq!(limited.remaining().unwrap(), Some(Duration::from_secs(10)));
+
+        let deadline = Deadline::new(None, ClockRef(clock));
+        let limited = deadline.limited_to(Some(Duration::from_secs(3)));
+        assert_eq!(limited.remaining().unwrap(), Some(Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn test_zero_read_timeout() {
+        let deadline =
+            Deadline::new(None, Default::default()).with_read_timeout(Some(Duration::from_secs(0)));
+        assert_eq!(
+            deadline.read_timeout().unwrap(),
+            Some(super::MIN_SOCKET_TIMEOUT)
+        );
+    }
+
+    #[test]
+    fn test_read_timeout() {
+        let clock = Arc::new(ManualClock(Mutex::new(Instant::now())));
+        let deadline = Deadline::new(Some(Duration::from_secs(10)), ClockRef(clock.clone()))
+            .with_read_timeout(Some(Duration::from_secs(3)));
+        assert_eq!(
+            deadline.read_timeout().unwrap(),
+            Some(Duration::from_secs(3))
+        );
+        // The read timeout is kept by limited deadlines.
+        let limited = deadline.limited_to(Some(Duration::from_secs(5)));
+        let err = limited.read_error(io::ErrorKind::WouldBlock.into());
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+        clock.advance(Duration::from_secs(8));
+        assert_eq!(
+            deadline.read_timeout().unwrap(),
+            Some(Duration::from_secs(2))
+        );
+        clock.advance(Duration::from_secs(3));
+        assert!(deadline.read_timeout().is_err());
+        // Once the deadline has passed, its own error is returned by
+        // the next read.
+        let err = deadline.read_error(io::ErrorKind::WouldBlock.into());
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+
+        let deadline = Deadline::new(None, ClockRef(clock));
+        let err = deadline.read_error(io::ErrorKind::WouldBlock.into());
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+    }
+
+    #[test]
+    fn test_no_deadline() {
+        let clock = Arc::new(ManualClock(Mutex::new(Instant::now())));
+        let deadline = Deadline::new(None, ClockRef(clock.clone()));
+        clock.advance(Duration::from_secs(1_000_000));
+        assert_eq!(deadline.remaining().unwrap(), None);
     }
 }
@@ -0,0 +1,465 @@
+use crate::date::parse_http_date;
+use crate::request::parse_url;
+use crate::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The prefix of the lines of `HttpOnly` cookies in cookie files.
+const HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+
+/// A cookie stored in a [CookieJar], as described in [RFC 6265
+/// section 5.3](https://www.rfc-editor.org/rfc/rfc6265#section-5.3).
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(crate) struct Cookie {
+    pub(crate) name: String,
+    pub(crate) value: String,
+    /// The lowercase domain, without a leading dot.
+    pub(crate) domain: String,
+    /// True if the cookie had no `Domain` attribute, so it's only sent
+    /// to the exact host that set it.
+    pub(crate) host_only: bool,
+    pub(crate) path: String,
+    pub(crate) secure: bool,
+    pub(crate) http_only: bool,
+    /// When the cookie expires, or None for session cookies.
+    pub(crate) expires: Option<SystemTime>,
+}
+
+impl Cookie {
+    /// Parses a `Set-Cookie` header received from `host`, for a
+    /// request to `path`. Returns None if the header is invalid, or if
+    /// its `Domain` does not cover `host`.
+    pub(crate) fn parse(set_cookie: &str, host: &str, path: &str) -> Option<Cookie> {
+        let mut attributes = set_cookie.split(';');
+        let mut pair = attributes.next()?.splitn(2, '=');
+        let name = pair.next()?.trim();
+        let value = pair.next()?.trim().trim_matches('"');
+        if name.is_empty() {
+            return None;
+        }
+
+        let host = host.to_lowercase();
+        let mut cookie = Cookie {
+            name: name.to_string(),
+            value: value.to_string(),
+            domain: host.clone(),
+            host_only: true,
+            path: default_path(path).to_string(),
+            secure: false,
+            http_only: false,
+            expires: None,
+        };
+        let mut max_age = None;
+        for attribute in attributes {
+            let mut parts = attribute.splitn(2, '=');
+            let key = parts.next().unwrap_or("").trim().to_lowercase();
+            let value = parts.next().unwrap_or("").trim();
+            match key.as_str() {
+                "expires" => {
+                    if let Some(expires) = parse_cookie_date(value) {
+                        cookie.expires = Some(expires);
+                    }
+                }
+                "max-age" => {
+                    if let Ok(seconds) = value.parse::<i64>() {
+                        max_age = Some(seconds);
+                    }
+                }
+                "domain" => {
+                    let domain = value.trim_start_matches('.').to_lowercase();
+                    if domain.is_empty() {
+                        continue;
+                    }
+                    // A rough stand-in for the public suffix list:
+                    // cookies can't be set for top-level domains.
+                    if !domain_matches(&host, &domain) || (!domain.contains('.') && domain != host)
+                    {
+                        return None;
+                    }
+                    cookie.domain = domain;
+                    cookie.host_only = false;
+                }
+                "path" if value.starts_with('/') => cookie.path = value.to_string(),
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                _ => {}
+            }
+        }
+        // Max-Age takes precedence over Expires.
+        match max_age {
+            Some(seconds) if seconds <= 0 => cookie.expires = Some(UNIX_EPOCH),
+            Some(seconds) => {
+                cookie.expires = SystemTime::now().checked_add(Duration::from_secs(seconds as u64))
+            }
+            None => {}
+        }
+        Some(cookie)
+    }
+
+    /// Parses a line of a Netscape cookie file, returning None for
+    /// comments and invalid lines.
+    fn from_netscape(line: &str) -> Option<Cookie> {
+        let (line, http_only) = if let Some(line) = line.strip_prefix(HTTP_ONLY_PREFIX) {
+            (line, true)
+        } else if line.starts_with('#') {
+            return None;
+        } else {
+            (line, false)
+        };
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 6 || fields.len() > 7 {
+            return None;
+        }
+        let domain = fields[0].trim_start_matches('.').to_lowercase();
+        let expires = match fields[4].parse::<u64>().ok()? {
+            0 => None,
+            seconds => Some(UNIX_EPOCH.checked_add(Duration::from_secs(seconds))?),
+        };
+        if domain.is_empty() || fields[5].is_empty() {
+            return None;
+        }
+        Some(Cookie {
+            name: fields[5].to_string(),
+            value: fields.get(6).unwrap_or(&"").to_string(),
+            domain,
+            host_only: !fields[1].eq_ignore_ascii_case("TRUE"),
+            path: fields[2].to_string(),
+            secure: fields[3].eq_ignore_ascii_case("TRUE"),
+            http_only,
+            expires,
+        })
+    }
+
+    /// Formats this cookie as a line of a Netscape cookie file.
+    /// Session cookies are written with an expiry of 0.
+    fn to_netscape(&self) -> String {
+        let flag = |value: bool| if value { "TRUE" } else { "FALSE" };
+        let expires = self
+            .expires
+            .and_then(|expires| expires.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |expires| expires.as_secs());
+        format!(
+            "{}{}{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            if self.http_only { HTTP_ONLY_PREFIX } else { "" },
+            if self.host_only { "" } else { "." },
+            self.domain,
+            flag(!self.host_only),
+            self.path,
+            flag(self.secure),
+            expires,
+            self.name,
+            self.value,
+        )
+    }
+
+    pub(crate) fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires.map_or(false, |expires| expires <= now)
+    }
+
+    /// Returns true if this cookie should be sent with a request to
+    /// `path` on `host`.
+    fn matches(&self, https: bool, host: &str, path: &str) -> bool {
+        let domain_ok = if self.host_only {
+            self.domain == host
+        } else {
+            domain_matches(host, &self.domain)
+        };
+        domain_ok && path_matches(path, &self.path) && (https || !self.secure)
+    }
+}
+
+/// Returns true if `host` is `domain` or one of its subdomains. IP
+/// addresses only match themselves.
+fn domain_matches(host: &str, domain: &str) -> bool {
+    if host == domain {
+        return true;
+    }
+    let is_ip = host.starts_with('[') || host.parse::<std::net::Ipv4Addr>().is_ok();
+    !is_ip && host.ends_with(domain) && host[..host.len() - domain.len()].ends_with('.')
+}
+
+/// Returns true if `path` is `cookie_path` or below it.
+fn path_matches(path: &str, cookie_path: &str) -> bool {
+    path == cookie_path
+        || (path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || path[cookie_path.len()..].starts_with('/')))
+}
+
+/// Returns the path cookies are set for when they have no `Path`
+/// attribute: the "directory" of the request's path.
+fn default_path(path: &str) -> &str {
+    match path.rfind('/') {
+        Some(0) | None => "/",
+        Some(i) => &path[..i],
+    }
+}
+
+/// Parses the date of an `Expires` attribute, which is usually an
+/// HTTP date, but often with dashes between the day, month and year.
+fn parse_cookie_date(date: &str) -> Option<SystemTime> {
+    parse_http_date(date).or_else(|| parse_http_date(&date.replace('-', " ")))
+}
+
+/// Returns the host and path of `url`, as compared to the domains and
+/// paths of cookies, and whether it's an https url.
+fn cookie_target(url: &str) -> Option<(bool, String, String)> {
+    let (https, host, _, resource) = parse_url(url).ok()?;
+    let path_end = resource
+        .find(|c| c == '?' || c == '#')
+        .unwrap_or(resource.len());
+    let path = match &resource[..path_end] {
+        "" => "/",
+        path => path,
+    };
+    Some((https, host.to_lowercase(), path.to_string()))
+}
+
+/// Stores the cookies set by servers, and sends them back with the
+/// requests they apply to.
+///
+/// Cookies are stored from the `Set-Cookie` headers of every response
+/// received by the requests using the jar, including the responses
+/// that redirect, and sent in the `Cookie` header of the requests
+/// (and redirected requests) whose url matches their domain, path and
+/// `Secure` attribute. Expired cookies are removed.
+///
+/// A jar can be added to a [`Client`](struct.Client.html) with
+/// [`Client::with_cookie_jar`](struct.Client.html#method.with_cookie_jar),
+/// or to a single request with
+/// [`Request::with_cookie_jar`](struct.Request.html#method.with_cookie_jar).
+/// Clones of a jar share its cookies.
+///
+/// Unlike browsers, the jar does not use the public suffix list, so
+/// it only refuses cookies for top-level domains like `com`, not for
+/// domains like `co.uk`.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), minreq::Error> {
+/// let jar = minreq::CookieJar::new();
+/// let client = minreq::Client::new().with_cookie_jar(jar.clone());
+/// client.post("http://example.com/login").send()?;
+/// // Sent with the session cookie set by the login response.
+/// client.get("http://example.com/account").send()?;
+/// println!("{:?}", jar.cookie_header("http://example.com/account"));
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Default)]
+pub struct CookieJar {
+    cookies: Arc<Mutex<Vec<Cookie>>>,
+}
+
+impl CookieJar {
+    /// Creates an empty cookie jar.
+    pub fn new() -> CookieJar {
+        CookieJar::default()
+    }
+
+    /// Loads the cookies saved in the Netscape cookie file at `path`,
+    /// the format used by curl (`-b`/`-c`), wget and browser
+    /// extensions, eg. one written by [CookieJar::save]. Comments and
+    /// invalid lines are skipped, as are expired cookies.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`IoError`](enum.Error.html#variant.IoError) if the
+    /// file can't be read, eg. because it doesn't exist yet.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), minreq::Error> {
+    /// let jar = minreq::CookieJar::load("cookies.txt").unwrap_or_default();
+    /// let client = minreq::Client::new().with_cookie_jar(jar.clone());
+    /// client.get("http://example.com").send()?;
+    /// jar.save("cookies.txt")?;
+    /// # Ok(()) }
+    /// ```
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<CookieJar, Error> {
+        let jar = CookieJar::new();
+        jar.load_netscape(&fs::read_to_string(path)?);
+        Ok(jar)
+    }
+
+    /// Saves the cookies into the Netscape cookie file at `path`,
+    /// replacing it, so that they can be loaded with
+    /// [CookieJar::load] later. Session cookies, which have no expiry,
+    /// are saved too, with an expiry of 0.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        fs::write(path, self.to_netscape())?;
+        Ok(())
+    }
+
+    fn load_netscape(&self, file: &str) {
+        for line in file.lines() {
+            if let Some(cookie) = Cookie::from_netscape(line.trim_end_matches('\r')) {
+                self.insert(cookie);
+            }
+        }
+    }
+
+    fn to_netscape(&self) -> String {
+        let now = SystemTime::now();
+        let mut file = String::from("# Netscape HTTP Cookie File\n");
+        for cookie in self.cookies().iter() {
+            if !cookie.is_expired(now) {
+                file += &cookie.to_netscape();
+                file.push('\n');
+            }
+        }
+        file
+    }
+
+    fn cookies(&self) -> MutexGuard<'_, Vec<Cookie>> {
+        match self.cookies.lock() {
+            Ok(cookies) => cookies,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// Stores the cookie of the `Set-Cookie` header `set_cookie`, as
+    /// if it was received in the response to a request to `url`.
+    /// Invalid headers, and cookies for other domains, are ignored.
+    pub fn set_cookie(&self, url: &str, set_cookie: &str) {
+        if let Some((_, host, path)) = cookie_target(url) {
+            if let Some(cookie) = Cookie::parse(set_cookie, &host, &path) {
+                self.insert(cookie);
+            }
+        }
+    }
+
+    /// Stores `cookie`, replacing the one with the same name, domain
+    /// and path. Expired cookies remove the stored one instead.
+    pub(crate) fn insert(&self, cookie: Cookie) {
+        let mut cookies = self.cookies();
+        cookies.retain(|existing| {
+            existing.name != cookie.name
+                || existing.domain != cookie.domain
+                || existing.path != cookie.path
+        });
+        if !cookie.is_expired(SystemTime::now()) {
+            cookies.push(cookie);
+        }
+    }
+
+    /// Returns the value of the `Cookie` header sent with requests to
+    /// `url`, or None if no cookies apply to it. Cookies with longer
+    /// paths are listed first.
+    pub fn cookie_header(&self, url: &str) -> Option<String> {
+        let (https, host, path) = cookie_target(url)?;
+        let now = SystemTime::now();
+        let mut cookies = self.cookies();
+        cookies.retain(|cookie| !cookie.is_expired(now));
+        let mut matching: Vec<&Cookie> = cookies
+            .iter()
+            .filter(|cookie| cookie.matches(https, &host, &path))
+            .collect();
+        if matching.is_empty() {
+            return None;
+        }
+        matching.sort_by_key(|cookie| std::cmp::Reverse(cookie.path.len()));
+        let pairs: Vec<String> = matching
+            .iter()
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect();
+        Some(pairs.join("; "))
+    }
+
+    /// Removes all cookies.
+    pub fn clear(&self) {
+        self.cookies().clear();
+    }
+}
+
+impl PartialEq for CookieJar {
+    fn eq(&self, other: &CookieJar) -> bool {
+        Arc::ptr_eq(&self.cookies, &other.cookies)
+    }
+}
+
+impl Eq for CookieJar {}
+
+impl fmt::Debug for CookieJar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CookieJar")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{default_path, domain_matches, path_matches, CookieJar};
+
+    #[test]
+    fn test_cookie_jar() {
+        let jar = CookieJar::new();
+        let url = "http://www.example.com/a/b?c=d";
+        jar.set_cookie(url, "sid=1; Path=/; HttpOnly");
+        jar.set_cookie(url, "dir=2");
+        jar.set_cookie(url, "wide=3; Domain=.Example.com; Secure");
+        jar.set_cookie(url, "tld=4; Domain=com");
+        jar.set_cookie(url, "other=5; Domain=example.org");
+        jar.set_cookie(url, "gone=6; Expires=Wed, 21-Oct-2015 07:28:00 GMT");
+        jar.set_cookie(url, "invalid");
+
+        assert_eq!(jar.cookie_header(url).as_deref(), Some("dir=2; sid=1"));
+        assert_eq!(
+            jar.cookie_header("https://api.example.com/a/x").as_deref(),
+            Some("wide=3")
+        );
+        assert_eq!(jar.cookie_header("http://example.org/"), None);
+
+        jar.set_cookie(url, "sid=7; Path=/");
+        jar.set_cookie(url, "dir=; Max-Age=0");
+        assert_eq!(
+            jar.cookie_header("http://www.example.com/").as_deref(),
+            Some("sid=7")
+        );
+        jar.clear();
+        assert_eq!(jar.cookie_header(url), None);
+    }
+
+    #[test]
+    fn test_matching() {
+        assert!(domain_matches("www.example.com", "example.com"));
+        assert!(!domain_matches("wwwexample.com", "example.com"));
+        assert!(!domain_matches("10.0.0.1", "0.0.1"));
+        assert!(path_matches("/a/b", "/a"));
+        assert!(path_matches("/a/b", "/a/"));
+        assert!(!path_matches("/ab", "/a"));
+        assert_eq!(default_path("/a/b"), "/a");
+        assert_eq!(default_path("/a"), "/");
+    }
+
+    #[test]
+    fn test_netscape_format() {
+        let file = "# Netscape HTTP Cookie File\r\n\
+            .example.com\tTRUE\t/\tTRUE\t4102444800\twide\t1\r\n\
+            #HttpOnly_www.example.com\tFALSE\t/a\tFALSE\t0\tsid\t\r\n\
+            example.com\tFALSE\t/\tFALSE\t1\texpired\t3\n\
+            # a comment\n\
+            invalid line\n\
+            example.com\tFALSE\t/\tFALSE\t18446744073709551615\thuge\t4\n";
+        let jar = CookieJar::new();
+        jar.load_netscape(file);
+        assert_eq!(
+            jar.cookie_header("https://www.example.com/a/b").as_deref(),
+            Some("sid=; wide=1")
+        );
+        assert_eq!(jar.cookie_header("http://example.com/a"), None);
+        assert_eq!(
+            jar.to_netscape(),
+            "# Netscape HTTP Cookie File\n\
+             .example.com\tTRUE\t/\tTRUE\t4102444800\twide\t1\n\
+             #HttpOnly_www.example.com\tFALSE\t/a\tFALSE\t0\tsid\t\n"
+        );
+
+        jar.set_cookie("http://example.org/", "x=y; HttpOnly; Max-Age=60");
+        let saved = CookieJar::new();
+        saved.load_netscape(&jar.to_netscape());
+        assert_eq!(saved.to_netscape(), jar.to_netscape());
+    }
+}
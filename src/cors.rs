@@ -0,0 +1,154 @@
+use crate::{Method, Request, URL};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Creates a CORS preflight request: an `OPTIONS` request asking
+/// whether a request with `method` from `origin` would be allowed. Add
+/// an `Access-Control-Request-Headers` header for asking about
+/// non-simple headers as well.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), minreq::Error> {
+/// use minreq::Method;
+///
+/// let response = minreq::preflight("http://example.com/api", "http://example.org", Method::Put)
+///     .with_header("Access-Control-Request-Headers", "content-type")
+///     .send()?;
+/// let cors = response.cors();
+/// println!("Allowed: {}", cors.allows("http://example.org", &Method::Put, &["Content-Type"]));
+/// # Ok(()) }
+/// ```
+pub fn preflight<T: Into<URL>, U: Into<String>>(url: T, origin: U, method: Method) -> Request {
+    Request::new(Method::Options, url)
+        .with_header("Origin", origin)
+        .with_header("Access-Control-Request-Method", method.to_string())
+}
+
+/// The CORS policy of a resource, from the `Access-Control-Allow-*`
+/// headers of a response, usually to a [preflight] request. See
+/// [`Response::cors`](struct.Response.html#method.cors).
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct CorsPolicy {
+    /// The origin allowed to read the response, or "*" for any
+    /// origin, from `Access-Control-Allow-Origin`.
+    pub allow_origin: Option<String>,
+    /// The allowed methods, from `Access-Control-Allow-Methods`.
+    pub allow_methods: Vec<Method>,
+    /// The allowed request headers, in lowercase, from
+    /// `Access-Control-Allow-Headers`. May contain "*".
+    pub allow_headers: Vec<String>,
+    /// The response headers exposed to scripts, in lowercase, from
+    /// `Access-Control-Expose-Headers`.
+    pub expose_headers: Vec<String>,
+    /// Whether credentials may be sent, from
+    /// `Access-Control-Allow-Credentials`.
+    pub allow_credentials: bool,
+    /// How long the preflight result may be cached, from
+    /// `Access-Control-Max-Age`.
+    pub max_age: Option<Duration>,
+}
+
+impl CorsPolicy {
+    pub(crate) fn from_headers(headers: &HashMap<String, String>) -> CorsPolicy {
+        let list = |name: &str| {
+            headers
+                .get(name)
+                .map(|value| split_list(value).map(str::to_lowercase).collect())
+                .unwrap_or_default()
+        };
+        CorsPolicy {
+            allow_origin: headers
+                .get("access-control-allow-origin")
+                .map(|origin| origin.trim().to_string()),
+            allow_methods: parse_methods(headers.get("access-control-allow-methods")),
+            allow_headers: list("access-control-allow-headers"),
+            expose_headers: list("access-control-expose-headers"),
+            allow_credentials: headers
+                .get("access-control-allow-credentials")
+                .map_or(false, |value| value.trim() == "true"),
+            max_age: headers
+                .get("access-control-max-age")
+                .and_then(|value| value.trim().parse::<u64>().ok())
+                .map(Duration::from_secs),
+        }
+    }
+
+    /// Returns true if the policy allows a request from `origin` with
+    /// `method` and the request headers `headers`, following the
+    /// rules browsers use (without credentials, where "*" is a
+    /// wildcard). Simple methods (GET, HEAD and POST) are always
+    /// allowed.
+    pub fn allows(&self, origin: &str, method: &Method, headers: &[&str]) -> bool {
+        let origin_allowed = match &self.allow_origin {
+            Some(allowed) => allowed == "*" || allowed == origin,
+            None => false,
+        };
+        let method_allowed = matches!(method, Method::Get | Method::Head | Method::Post)
+            || self.allow_methods.contains(method)
+            || self
+                .allow_methods
+                .contains(&Method::Custom("*".to_string()));
+        let headers_allowed = self.allow_headers.iter().any(|header| header == "*")
+            || headers.iter().all(|header| {
+                let header = header.to_lowercase();
+                self.allow_headers.contains(&header)
+            });
+        origin_allowed && method_allowed && headers_allowed
+    }
+}
+
+/// Parses a list of methods, like the `Allow` header.
+pub(crate) fn parse_methods(header: Option<&String>) -> Vec<Method> {
+    let header = match header {
+        Some(header) => header,
+        None => return Vec::new(),
+    };
+    split_list(header).map(Method::from_name).collect()
+}
+
+fn split_list(header: &str) -> impl Iterator<Item = &str> {
+    header
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CorsPolicy;
+    use crate::Method;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    #[test]
+    fn test_cors_policy() {
+        let mut headers = HashMap::new();
+        headers.insert(
+            "access-control-allow-origin".to_string(),
+            "http://example.org".to_string(),
+        );
+        headers.insert(
+            "access-control-allow-methods".to_string(),
+            "PUT, DELETE,".to_string(),
+        );
+        headers.insert(
+            "access-control-allow-headers".to_string(),
+            "Content-Type, X-Api-Key".to_string(),
+        );
+        headers.insert("access-control-max-age".to_string(), "600".to_string());
+        let cors = CorsPolicy::from_headers(&headers);
+        assert_eq!(cors.allow_methods, vec![Method::Put, Method::Delete]);
+        assert_eq!(cors.allow_headers, vec!["content-type", "x-api-key"]);
+        assert_eq!(cors.max_age, Some(Duration::from_secs(600)));
+        assert!(!cors.allow_credentials);
+
+        let origin = "http://example.org";
+        assert!(cors.allows(origin, &Method::Put, &["X-API-Key"]));
+        assert!(cors.allows(origin, &Method::Get, &[]));
+        assert!(!cors.allows(origin, &Method::Patch, &[]));
+        assert!(!cors.allows(origin, &Method::Put, &["Authorization"]));
+        assert!(!cors.allows("http://example.net", &Method::Put, &[]));
+    }
+}
@@ -0,0 +1,576 @@
+//! Building requests from `curl` command lines, like the ones browsers'
+//! developer tools copy with "Copy as cURL".
+
+use crate::encoding::{base64_encode, percent_encode};
+#[cfg(feature = "proxy")]
+use crate::Proxy;
+use crate::{Error, Method, Request, URL};
+use std::fs;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// The options that take a value, by their long names.
+const VALUE_OPTIONS: [&str; 17] = [
+    "url",
+    "request",
+    "header",
+    "data",
+    "data-ascii",
+    "data-binary",
+    "data-raw",
+    "data-urlencode",
+    "json",
+    "user",
+    "user-agent",
+    "referer",
+    "cookie",
+    "max-time",
+    "max-redirs",
+    "proxy",
+    "output",
+];
+
+/// The options that don't change the request, eg. what curl prints.
+/// Redirects are followed regardless of `--location`.
+const IGNORED_OPTIONS: [&str; 12] = [
+    "silent",
+    "show-error",
+    "verbose",
+    "include",
+    "location",
+    "fail",
+    "globoff",
+    "no-buffer",
+    "compressed",
+    "http1.1",
+    "progress-bar",
+    "output",
+];
+
+impl Request {
+    /// Builds a request from a `curl` command line, eg. one copied
+    /// from the network tab of a browser's developer tools, for
+    /// replaying it with minreq.
+    ///
+    /// The command is split into words like a POSIX shell would,
+    /// including `'...'`, `"..."` and `$'...'` quoting and line
+    /// continuations, but nothing is expanded. The leading `curl` is
+    /// optional. The supported options are the url (or `--url`),
+    /// `-X`, `-H`, `-d`, `--data-ascii`, `--data-binary`,
+    /// `--data-raw`, `--data-urlencode`, `--json`, `-u`, `-A`, `-e`,
+    /// `-b`, `-I`, `-G`, `-m`, `--max-redirs` and, with the `proxy`
+    /// feature, `-x`. Options that only affect curl's output, like
+    /// `-s` or `-v`, are ignored, and any other option is an error,
+    /// so that the request isn't silently sent differently from how
+    /// curl would send it.
+    ///
+    /// Headers that minreq sets itself, like `Host` and
+    /// `Content-Length`, are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidCurlCommand`] if the command can't be
+    /// parsed or uses an unsupported option, and
+    /// [`Error::IoError`] if a file referred to with `-d @file`
+    /// can't be read.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), minreq::Error> {
+    /// let response = minreq::Request::from_curl(
+    ///     "curl 'http://example.com/api' -H 'Accept: application/json' --data-raw '{\"a\":1}'",
+    /// )?
+    /// .send()?;
+    /// # Ok(()) }
+    /// ```
+    pub fn from_curl(command: &str) -> Result<Request, Error> {
+        let mut words = split_words(command)?.into_iter().peekable();
+        if words.peek().map(String::as_str) == Some("curl") {
+            words.next();
+        }
+        let mut curl = CurlCommand::default();
+        while let Some(word) = words.next() {
+            if word.len() > 2 && word.starts_with("--") {
+                let value = option_value(&word[2..], None, &mut words)?;
+                curl.set(&word[2..], value)?;
+            } else if word.len() > 1 && word.starts_with('-') {
+                // Short options can be combined, eg. `-sSL`, and the
+                // last one can have its value attached, eg. `-XPOST`.
+                for (i, short) in word.char_indices().skip(1) {
+                    let name = long_name(short)
+                        .ok_or_else(|| invalid(format!("unsupported option -{}", short)))?;
+                    let attached = &word[i + short.len_utf8()..];
+                    let attached = if attached.is_empty() {
+                        None
+                    } else {
+                        Some(attached.to_string())
+                    };
+                    let takes_value = VALUE_OPTIONS.contains(&name);
+                    let value = option_value(name, attached, &mut words)?;
+                    curl.set(name, value)?;
+                    if takes_value {
+                        break;
+                    }
+                }
+            } else if curl.url.is_none() {
+                curl.url = Some(word);
+            } else {
+                return Err(invalid(format!("unexpected argument {}", word)));
+            }
+        }
+        curl.into_request()
+    }
+}
+
+/// The parts of a `curl` command that make up the request.
+#[derive(Default)]
+struct CurlCommand {
+    url: Option<URL>,
+    method: Option<String>,
+    headers: Vec<(String, String)>,
+    data: Option<Vec<u8>>,
+    json: bool,
+    head: bool,
+    get: bool,
+    user: Option<String>,
+    cookies: Vec<String>,
+    timeout: Option<u64>,
+    max_redirects: Option<usize>,
+    #[cfg(feature = "proxy")]
+    proxy: Option<String>,
+}
+
+impl CurlCommand {
+    fn set(&mut self, name: &str, value: Option<String>) -> Result<(), Error> {
+        let value = value.unwrap_or_default();
+        match name {
+            "url" => self.url = Some(value),
+            "request" => self.method = Some(value),
+            "header" => self.header(&value),
+            "data" | "data-ascii" => {
+                let data = read_data(value, true)?;
+                self.push_data(data, b"&");
+            }
+            "data-binary" => {
+                let data = read_data(value, false)?;
+                self.push_data(data, b"&");
+            }
+            "data-raw" => self.push_data(value.into_bytes(), b"&"),
+            "data-urlencode" => {
+                let data = url_encoded_data(&value)?;
+                self.push_data(data, b"&");
+            }
+            "json" => {
+                self.json = true;
+                let data = read_data(value, false)?;
+                self.push_data(data, b"");
+            }
+            "user" => self.user = Some(value),
+            "user-agent" => self.headers.push(("User-Agent".to_string(), value)),
+            "referer" => self.headers.push(("Referer".to_string(), value)),
+            // Without a '=', the value is the name of a cookie file.
+            "cookie" if value.contains('=') => self.cookies.push(value),
+            "max-time" => {
+                let seconds: f64 = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| invalid(format!("invalid --max-time {}", value)))?;
+                self.timeout = Some(seconds.ceil() as u64);
+            }
+            "max-redirs" => {
+                let max_redirects = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| invalid(format!("invalid --max-redirs {}", value)))?;
+                self.max_redirects = Some(max_redirects);
+            }
+            #[cfg(feature = "proxy")]
+            "proxy" => self.proxy = Some(value),
+            "head" => self.head = true,
+            "get" => self.get = true,
+            name if IGNORED_OPTIONS.contains(&name) => {}
+            name => return Err(invalid(format!("unsupported option --{}", name))),
+        }
+        Ok(())
+    }
+
+    /// Adds a `Name: value` header. Like curl, `Name;` adds the header
+    /// with an empty value, and `Name:` doesn't add anything.
+    fn header(&mut self, header: &str) {
+        let (name, value) = match header.find(':') {
+            Some(i) => (&header[..i], header[i + 1..].trim()),
+            None => match header.trim_end().strip_suffix(';') {
+                Some(name) => (name, ""),
+                None => return,
+            },
+        };
+        let name = name.trim();
+        if name.is_empty() || (value.is_empty() && header.contains(':')) {
+            return;
+        }
+        if !skip_imported_header(name) {
+            self.headers.push((name.to_string(), value.to_string()));
+        }
+    }
+
+    fn push_data(&mut self, data: Vec<u8>, separator: &[u8]) {
+        match &mut self.data {
+            Some(existing) => {
+                existing.extend_from_slice(separator);
+                existing.extend(data);
+            }
+            None => self.data = Some(data),
+        }
+    }
+
+    fn into_request(self) -> Result<Request, Error> {
+        let mut url = self.url.ok_or_else(|| invalid("no url".to_string()))?;
+        // Like curl, default to http for urls without a scheme.
+        if !url.contains("://") {
+            url.insert_str(0, "http://");
+        }
+        let mut data = self.data;
+        if self.get {
+            if let Some(data) = data.take() {
+                url.push(if url.contains('?') { '&' } else { '?' });
+                url.push_str(&String::from_utf8_lossy(&data));
+            }
+        }
+        let method = match self.method {
+            Some(method) => Method::from_name(&method),
+            None if self.head => Method::Head,
+            None if data.is_some() => Method::Post,
+            None => Method::Get,
+        };
+
+        let mut request = Request::new(method, url);
+        if let Some(data) = data {
+            request = if self.json {
+                request
+                    .with_header("Content-Type", "application/json")
+                    .with_header("Accept", "application/json")
+            } else {
+                request.with_header("Content-Type", "application/x-www-form-urlencoded")
+            };
+            request = request.with_body(data);
+        }
+        if let Some(mut user) = self.user {
+            // curl would prompt for the missing password.
+            if !user.contains(':') {
+                user.push(':');
+            }
+            let credentials = base64_encode(user.as_bytes());
+            request = request.with_header("Authorization", format!("Basic {}", credentials));
+        }
+        if !self.cookies.is_empty() {
+            request = request.with_header("Cookie", self.cookies.join("; "));
+        }
+        for (name, value) in self.headers {
+            request = request.with_header(name, value);
+        }
+        if let Some(timeout) = self.timeout {
+            request = request.with_timeout(timeout);
+        }
+        if let Some(max_redirects) = self.max_redirects {
+            request = request.with_max_redirects(max_redirects);
+        }
+        #[cfg(feature = "proxy")]
+        if let Some(proxy) = self.proxy {
+            request = request.with_proxy(Proxy::new(proxy)?);
+        }
+        Ok(request)
+    }
+}
+
+/// Returns true for the headers that aren't imported from other
+/// tools' requests, because minreq sets them itself or they describe
+/// the other tool's connection. This includes `Accept-Encoding`, as
+/// browsers accept encodings minreq can't decode, and the HTTP/2
+/// pseudo-headers, eg. `:authority`.
+pub(crate) fn skip_imported_header(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name.starts_with(':')
+        || [
+            "host",
+            "content-length",
+            "transfer-encoding",
+            "connection",
+            "keep-alive",
+            "accept-encoding",
+        ]
+        .contains(&name.as_str())
+}
+
+/// Returns the value of the option `name`, either the one attached to
+/// it or the next word, or None if the option doesn't take one.
+fn option_value<I: Iterator<Item = String>>(
+    name: &str,
+    attached: Option<String>,
+    words: &mut I,
+) -> Result<Option<String>, Error> {
+    if !VALUE_OPTIONS.contains(&name) {
+        return Ok(None);
+    }
+    match attached.or_else(|| words.next()) {
+        Some(value) => Ok(Some(value)),
+        None => Err(invalid(format!("missing the value of --{}", name))),
+    }
+}
+
+/// Returns the long name of a short option.
+fn long_name(short: char) -> Option<&'static str> {
+    Some(match short {
+        'X' => "request",
+        'H' => "header",
+        'd' => "data",
+        'u' => "user",
+        'A' => "user-agent",
+        'e' => "referer",
+        'b' => "cookie",
+        'm' => "max-time",
+        'x' => "proxy",
+        'o' => "output",
+        'I' => "head",
+        'G' => "get",
+        's' => "silent",
+        'S' => "show-error",
+        'v' => "verbose",
+        'i' => "include",
+        'L' => "location",
+        'f' => "fail",
+        'g' => "globoff",
+        'N' => "no-buffer",
+        'k' => "insecure",
+        'F' => "form",
+        _ => return None,
+    })
+}
+
+/// Returns the data of a `--data` option, read from a file if it
+/// starts with `@`. Like curl, `--data` strips the newlines from
+/// files, while `--data-binary` doesn't.
+fn read_data(value: String, strip_newlines: bool) -> Result<Vec<u8>, Error> {
+    let path = match value.strip_prefix('@') {
+        Some(path) => path,
+        None => return Ok(value.into_bytes()),
+    };
+    if path == "-" {
+        return Err(invalid(
+            "reading data from stdin is not supported".to_string(),
+        ));
+    }
+    let mut data = fs::read(path).map_err(Error::IoError)?;
+    if strip_newlines {
+        data.retain(|&byte| byte != b'\r' && byte != b'\n');
+    }
+    Ok(data)
+}
+
+/// Returns the data of a `--data-urlencode` option, which is one of
+/// `content`, `=content`, `name=content`, `@file` or `name@file`.
+fn url_encoded_data(value: &str) -> Result<Vec<u8>, Error> {
+    let (name, encoded) = match value.find(|c| c == '=' || c == '@') {
+        Some(i) if value[i..].starts_with('=') => {
+            (&value[..i], percent_encode(&value.as_bytes()[i + 1..]))
+        }
+        Some(i) => {
+            let data = fs::read(&value[i + 1..]).map_err(Error::IoError)?;
+            (&value[..i], percent_encode(&data))
+        }
+        None => ("", percent_encode(value.as_bytes())),
+    };
+    if name.is_empty() {
+        Ok(encoded.into_bytes())
+    } else {
+        Ok(format!("{}={}", name, encoded).into_bytes())
+    }
+}
+
+/// Splits a command line into words like a POSIX shell, without
+/// expanding anything.
+fn split_words(command: &str) -> Result<Vec<String>, Error> {
+    let unterminated = || invalid("unterminated quote or escape".to_string());
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\\' => match chars.next() {
+                // Line continuations, possibly with Windows line endings.
+                Some('\n') => {}
+                Some('\r') if chars.peek() == Some(&'\n') => {
+                    chars.next();
+                }
+                Some(c) => word.get_or_insert_with(String::new).push(c),
+                None => return Err(unterminated()),
+            },
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(unterminated()),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('\n') => {}
+                            Some(c) if "\"\\$`".contains(c) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err(unterminated()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err(unterminated()),
+                    }
+                }
+            }
+            '$' if chars.peek() == Some(&'\'') => {
+                chars.next();
+                ansi_c_quoted(&mut chars, word.get_or_insert_with(String::new))?;
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// Reads the rest of a `$'...'` string into `word`, unescaping the
+/// escape sequences browsers use in them.
+fn ansi_c_quoted(chars: &mut Peekable<Chars>, word: &mut String) -> Result<(), Error> {
+    let unterminated = || invalid("unterminated quote or escape".to_string());
+    loop {
+        let c =
+            match chars.next() {
+                Some('\'') => return Ok(()),
+                Some('\\') => match chars.next().ok_or_else(unterminated)? {
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    'a' => '\x07',
+                    'b' => '\x08',
+                    'e' | 'E' => '\x1b',
+                    'f' => '\x0c',
+                    'v' => '\x0b',
+                    'x' => hex_escape(chars, 2).filter(char::is_ascii).ok_or_else(|| {
+                        invalid("only ASCII \\x escapes are supported".to_string())
+                    })?,
+                    'u' => hex_escape(chars, 4)
+                        .ok_or_else(|| invalid("invalid \\u escape".to_string()))?,
+                    'U' => hex_escape(chars, 8)
+                        .ok_or_else(|| invalid("invalid \\U escape".to_string()))?,
+                    c => c,
+                },
+                Some(c) => c,
+                None => return Err(unterminated()),
+            };
+        word.push(c);
+    }
+}
+
+/// Reads up to `max_digits` hexadecimal digits as a character.
+fn hex_escape(chars: &mut Peekable<Chars>, max_digits: usize) -> Option<char> {
+    let mut code = 0;
+    let mut digits = 0;
+    while digits < max_digits {
+        match chars.peek().and_then(|c| c.to_digit(16)) {
+            Some(digit) => code = code * 16 + digit,
+            None => break,
+        }
+        chars.next();
+        digits += 1;
+    }
+    if digits == 0 {
+        return None;
+    }
+    std::char::from_u32(code)
+}
+
+fn invalid(message: String) -> Error {
+    Error::InvalidCurlCommand(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split_words;
+    use crate::{Error, Method, Request};
+
+    #[test]
+    fn test_split_words() {
+        let words =
+            split_words("curl 'a b' \"c \\\"d\\\"\" e\\ f \\\n  $'g\\nh\\'\\u00e4' i'j'k").unwrap();
+        assert_eq!(words, ["curl", "a b", "c \"d\"", "e f", "g\nh'ä", "ijk"]);
+        assert!(split_words("curl 'a").is_err());
+        assert!(split_words("curl \"a\\").is_err());
+    }
+
+    #[test]
+    fn test_from_curl() {
+        let request = Request::from_curl(
+            "curl 'https://example.com/api?x=1' \\\n  -X PUT -H 'Accept: text/plain' \
+             -H 'Host: example.org' -H 'X-Empty;' -H 'X-Removed:' \
+             -d a=1 --data-raw '@b' --data-urlencode 'c=d e' -sSL -m 2.5 -uuser:pass",
+        )
+        .unwrap();
+        assert_eq!(request.method, Method::Put);
+        assert_eq!(request.url, "https://example.com/api?x=1");
+        assert_eq!(request.body.as_deref(), Some(&b"a=1&@b&c=d%20e"[..]));
+        assert_eq!(request.timeout, Some(3));
+        let header = |name: &str| request.headers.get(name).map(String::as_str);
+        assert_eq!(header("Accept"), Some("text/plain"));
+        assert_eq!(
+            header("Content-Type"),
+            Some("application/x-www-form-urlencoded")
+        );
+        assert_eq!(header("Authorization"), Some("Basic dXNlcjpwYXNz"));
+        assert_eq!(header("X-Empty"), Some(""));
+        assert_eq!(header("X-Removed"), None);
+        assert_eq!(header("Host"), None);
+    }
+
+    #[test]
+    fn test_from_curl_methods() {
+        let request = Request::from_curl("example.com/a?b -G -d c=d").unwrap();
+        assert_eq!(request.method, Method::Get);
+        assert_eq!(request.url, "http://example.com/a?b&c=d");
+        assert_eq!(request.body, None);
+
+        let request = Request::from_curl("curl -I http://example.com").unwrap();
+        assert_eq!(request.method, Method::Head);
+
+        let request = Request::from_curl("curl http://example.com --json '{\"a\":1}'").unwrap();
+        assert_eq!(request.method, Method::Post);
+        assert_eq!(request.body.as_deref(), Some(&b"{\"a\":1}"[..]));
+        assert_eq!(
+            request.headers.get("Content-Type").map(String::as_str),
+            Some("application/json")
+        );
+    }
+
+    #[test]
+    fn test_from_curl_errors() {
+        for command in &[
+            "curl",
+            "curl -k https://example.com",
+            "curl --compressed http://example.com --unknown",
+            "curl http://example.com -H",
+            "curl http://example.com http://example.org",
+        ] {
+            match Request::from_curl(command) {
+                Err(Error::InvalidCurlCommand(_)) => {}
+                result => panic!("{}: {:?}", command, result),
+            }
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//! Parsing and formatting of the dates used in HTTP headers, like
+//! `Date`, `Expires`, `Last-Modified`, and `Retry-After`.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DAY_NAMES: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTH_NAMES: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Parses an HTTP date, as defined in [RFC 9110 section
+/// 5.6.7](https://www.rfc-editor.org/rfc/rfc9110#section-5.6.7).
+///
+/// The preferred IMF-fixdate format (`Sun, 06 Nov 1994 08:49:37 GMT`)
+/// is accepted, along with the obsolete RFC 850 (`Sunday, 06-Nov-94
+/// 08:49:37 GMT`) and asctime (`Sun Nov  6 08:49:37 1994`) formats,
+/// which recipients are required to accept as well. The day name is
+/// not validated.
+///
+/// Returns None if `date` is not in any of these formats, or if it's
+/// before 1970.
+///
+/// # Example
+///
+/// ```
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let date = minreq::parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT");
+/// assert_eq!(date, Some(UNIX_EPOCH + Duration::from_secs(784111777)));
+/// ```
+pub fn parse_http_date(date: &str) -> Option<SystemTime> {
+    let date = date.trim();
+    let (day, month, year, time) = if let Some(comma) = date.find(',') {
+        let rest: Vec<&str> = date[comma + 1..].split_whitespace().collect();
+        match rest.as_slice() {
+            // IMF-fixdate: Sun, 06 Nov 1994 08:49:37 GMT
+            [day, month, year, time, "GMT"] => {
+                if year.len() != 4 {
+                    return None;
+                }
+                (*day, *month, year.parse::<u64>().ok()?, *time)
+            }
+            // RFC 850: Sunday, 06-Nov-94 08:49:37 GMT
+            [date, time, "GMT"] => {
+                let mut parts = date.split('-');
+                let (day, month, year) = (parts.next()?, parts.next()?, parts.next()?);
+                if parts.next().is_some() || year.len() != 2 {
+                    return None;
+                }
+                let year = year.parse::<u64>().ok()?;
+                let year = if year < 70 { 2000 + year } else { 1900 + year };
+                (day, month, year, *time)
+            }
+            _ => return None,
+        }
+    } else {
+        // asctime: Sun Nov  6 08:49:37 1994
+        match date.split_whitespace().collect::<Vec<&str>>().as_slice() {
+            [_, month, day, time, year] if year.len() == 4 => {
+                (*day, *month, year.parse::<u64>().ok()?, *time)
+            }
+            _ => return None,
+        }
+    };
+
+    let day = day
+        .parse::<u64>()
+        .ok()
+        .filter(|day| (1..=31).contains(day))?;
+    let month = MONTH_NAMES.iter().position(|name| *name == month)? as u64 + 1;
+    let mut time = time.split(':').map(|part| {
+        if part.len() == 2 {
+            part.parse::<u64>().ok()
+        } else {
+            None
+        }
+    });
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+    if time.next().is_some() || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day)?;
+    let seconds = days
+        .checked_mul(86400)?
+        .checked_add(hour * 3600 + minute * 60 + second)?;
+    UNIX_EPOCH.checked_add(Duration::from_secs(seconds))
+}
+
+/// Formats `time` as an IMF-fixdate, the format used for dates in
+/// HTTP headers, eg. `Sun, 06 Nov 1994 08:49:37 GMT`. Times before
+/// 1970 are formatted as the start of 1970.
+///
+/// # Example
+///
+/// ```
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let time = UNIX_EPOCH + Duration::from_secs(784111777);
+/// assert_eq!(minreq::format_http_date(time), "Sun, 06 Nov 1994 08:49:37 GMT");
+/// ```
+pub fn format_http_date(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let days = seconds / 86400;
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        DAY_NAMES[(days % 7) as usize],
+        day,
+        MONTH_NAMES[month as usize - 1],
+        year,
+        seconds % 86400 / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+/// Formats `time` as an ISO 8601 date in UTC, with milliseconds, eg.
+/// `1994-11-06T08:49:37.000Z`.
+#[cfg(feature = "har")]
+pub(crate) fn format_iso_8601(time: SystemTime) -> String {
+    let duration = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = duration.as_secs();
+    let (year, month, day) = civil_from_days(seconds / 86400);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        seconds % 86400 / 3600,
+        seconds % 3600 / 60,
+        seconds % 60,
+        duration.subsec_millis()
+    )
+}
+
+/// Formats `time` in the basic ISO 8601 format of AWS Signature
+/// Version 4, eg. `19941106T084937Z`.
+#[cfg(feature = "s3-presign")]
+pub(crate) fn format_amz_date(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let (year, month, day) = civil_from_days(seconds / 86400);
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        seconds % 86400 / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+/// Returns the number of days between 1970-01-01 and the given date,
+/// or None if the date is before 1970 or doesn't exist.
+///
+/// Based on Howard Hinnant's `days_from_civil` algorithm.
+fn days_from_civil(year: u64, month: u64, day: u64) -> Option<u64> {
+    let days_in_month = match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+    if year < 1970 || day > days_in_month {
+        return None;
+    }
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let month_from_march = (month + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    Some(era * 146097 + day_of_era - 719468)
+}
+
+/// Returns the (year, month, day) `days` days after 1970-01-01.
+///
+/// Based on Howard Hinnant's `civil_from_days` algorithm.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_http_date, parse_http_date};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_parse_formats() {
+        let expected = Some(UNIX_EPOCH + Duration::from_secs(784111777));
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"), expected);
+        assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), expected);
+        assert_eq!(parse_http_date("Sun Nov  6 08:49:37 1994"), expected);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 UTC"), None);
+        assert_eq!(parse_http_date("Sun, 31 Feb 1994 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 8:49:37 GMT"), None);
+        assert_eq!(parse_http_date("Sun, 06 Foo 1994 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date("Wed, 31 Dec 1969 23:59:59 GMT"), None);
+        assert_eq!(parse_http_date("120"), None);
+        assert_eq!(parse_http_date("Sun Nov  6 08:49:37 300000000000"), None);
+        assert_eq!(
+            parse_http_date("Sun Nov  6 08:49:37 18446744073709551615"),
+            None
+        );
+        assert_eq!(
+            parse_http_date("Sun, 06 Nov 300000000000 08:49:37 GMT"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for &seconds in &[0, 68169600, 951782400, 1709164800, 4102444799] {
+            let time = UNIX_EPOCH + Duration::from_secs(seconds);
+            assert_eq!(parse_http_date(&format_http_date(time)), Some(time));
+        }
+        let time = UNIX_EPOCH + Duration::from_secs(1709164800);
+        assert_eq!(format_http_date(time), "Thu, 29 Feb 2024 00:00:00 GMT");
+    }
+
+    #[test]
+    #[cfg(feature = "har")]
+    fn test_format_iso_8601() {
+        let time = UNIX_EPOCH + Duration::from_millis(1709164800042);
+        assert_eq!(super::format_iso_8601(time), "2024-02-29T00:00:00.042Z");
+    }
+
+    #[test]
+    #[cfg(feature = "s3-presign")]
+    fn test_format_amz_date() {
+        let time = UNIX_EPOCH + Duration::from_secs(784111777);
+        assert_eq!(super::format_amz_date(time), "19941106T084937Z");
+    }
+}
@@ -0,0 +1,111 @@
+use crate::Error;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// A stage in the response body decoding pipeline.
+///
+/// The body of a response goes through a chain of decoders before
+/// it's handed out by [`ResponseLazy`](struct.ResponseLazy.html) (and
+/// by extension, [`Response`](struct.Response.html)). The transfer
+/// framing (`Transfer-Encoding: chunked`, `Content-Length`) is always
+/// removed first, and then the decoders added with
+/// [`Request::with_body_decoder`](struct.Request.html#method.with_body_decoder)
+/// are run in the order they were added, after the gzip and deflate
+/// content codings have been decoded if the `compression` feature is
+/// enabled. This allows inserting decoders for other content-encodings,
+/// charsets or proprietary framing without forking the response
+/// parser.
+///
+/// # Example
+///
+/// ```
+/// use minreq::{BodyDecoder, Error};
+///
+/// /// Decodes bodies encoded with ROT13.
+/// struct Rot13;
+///
+/// impl BodyDecoder for Rot13 {
+///     fn decode(&mut self, input: &[u8], output: &mut Vec<u8>) -> Result<(), Error> {
+///         output.extend(input.iter().map(|&byte| match byte {
+///             b'a'..=b'z' => (byte - b'a' + 13) % 26 + b'a',
+///             b'A'..=b'Z' => (byte - b'A' + 13) % 26 + b'A',
+///             _ => byte,
+///         }));
+///         Ok(())
+///     }
+/// }
+///
+/// let request = minreq::get("http://example.com").with_body_decoder(|headers| {
+///     match headers.get("content-encoding") {
+///         Some(encoding) if encoding == "rot13" => Some(Box::new(Rot13) as Box<dyn BodyDecoder>),
+///         _ => None,
+///     }
+/// });
+/// ```
+pub trait BodyDecoder: Send {
+    /// Decodes `input`, the output of the previous stage, and appends
+    /// the decoded bytes into `output`.
+    ///
+    /// Decoders don't need to produce output for every call, they can
+    /// buffer the input until they have enough to work with.
+    fn decode(&mut self, input: &[u8], output: &mut Vec<u8>) -> Result<(), Error>;
+
+    /// Called once after the previous stage has run out of bytes.
+    /// Any bytes still buffered by the decoder should be appended into
+    /// `output` here.
+    fn finish(&mut self, _output: &mut Vec<u8>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+type BodyDecoderFn = dyn Fn(&HashMap<String, String>) -> Option<Box<dyn BodyDecoder>> + Send + Sync;
+
+/// Creates the [BodyDecoder] for a response, based on its headers.
+#[derive(Clone)]
+pub(crate) struct BodyDecoderFactory(Arc<BodyDecoderFn>);
+
+impl BodyDecoderFactory {
+    pub(crate) fn new<F>(factory: F) -> BodyDecoderFactory
+    where
+        F: Fn(&HashMap<String, String>) -> Option<Box<dyn BodyDecoder>> + Send + Sync + 'static,
+    {
+        BodyDecoderFactory(Arc::new(factory))
+    }
+
+    pub(crate) fn create(&self, headers: &HashMap<String, String>) -> Option<Box<dyn BodyDecoder>> {
+        (self.0)(headers)
+    }
+}
+
+impl PartialEq for BodyDecoderFactory {
+    fn eq(&self, other: &BodyDecoderFactory) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for BodyDecoderFactory {}
+
+impl fmt::Debug for BodyDecoderFactory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BodyDecoderFactory")
+    }
+}
+
+/// Runs `data` through all of the `decoders`, finishing each decoder
+/// along the way if `finish` is true.
+pub(crate) fn run_decoders(
+    decoders: &mut [Box<dyn BodyDecoder>],
+    mut data: Vec<u8>,
+    finish: bool,
+) -> Result<Vec<u8>, Error> {
+    for decoder in decoders.iter_mut() {
+        let mut output = Vec::with_capacity(data.len());
+        decoder.decode(&data, &mut output)?;
+        if finish {
+            decoder.finish(&mut output)?;
+        }
+        data = output;
+    }
+    Ok(data)
+}
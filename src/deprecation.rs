@@ -0,0 +1,265 @@
+use crate::parse_http_date;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A warning from the `Warning` header ([RFC 7234 section
+/// 5.5](https://tools.ietf.org/html/rfc7234#section-5.5)).
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Warning {
+    /// The warning code, eg. 299 for "Miscellaneous Persistent
+    /// Warning".
+    pub code: u16,
+    /// The host or pseudonym of the server that added the warning, or
+    /// "-" if it's unknown.
+    pub agent: String,
+    /// The human-readable warning text.
+    pub text: String,
+    /// The date the warning was added, if given.
+    pub date: Option<SystemTime>,
+}
+
+/// The deprecation of the requested resource, from the `Deprecation`
+/// header ([RFC 9745](https://www.rfc-editor.org/rfc/rfc9745)).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Deprecation {
+    /// When the resource was or will be deprecated, if the server
+    /// said so. Older servers only send `Deprecation: true`, in which
+    /// case this is None.
+    pub date: Option<SystemTime>,
+}
+
+/// The deprecation-related headers of a response, passed to the hook
+/// set with
+/// [`Request::with_deprecation_hook`](struct.Request.html#method.with_deprecation_hook).
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DeprecationNotice {
+    /// The url of the request the response was for.
+    pub url: String,
+    /// The warnings in the `Warning` header.
+    pub warnings: Vec<Warning>,
+    /// The contents of the `Deprecation` header.
+    pub deprecation: Option<Deprecation>,
+    /// The date in the `Sunset` header ([RFC
+    /// 8594](https://tools.ietf.org/html/rfc8594)), after which the
+    /// resource is expected to become unavailable.
+    pub sunset: Option<SystemTime>,
+}
+
+impl DeprecationNotice {
+    /// Creates the notice for a response with `headers`, or None if
+    /// the response had none of the headers.
+    pub(crate) fn from_headers(url: String, headers: &HashMap<String, String>) -> Option<Self> {
+        let notice = DeprecationNotice {
+            url,
+            warnings: parse_warnings(headers),
+            deprecation: parse_deprecation(headers),
+            sunset: parse_sunset(headers),
+        };
+        if notice.warnings.is_empty() && notice.deprecation.is_none() && notice.sunset.is_none() {
+            None
+        } else {
+            Some(notice)
+        }
+    }
+}
+
+/// A deprecation hook that logs the notice with [log::warn].
+///
+/// # Example
+///
+/// ```
+/// let request = minreq::get("http://example.com/v1/users")
+///     .with_deprecation_hook(minreq::log_deprecation);
+/// ```
+pub fn log_deprecation(notice: &DeprecationNotice) {
+    if let Some(deprecation) = notice.deprecation {
+        match deprecation.date {
+            Some(date) => log::warn!("{} is deprecated as of {:?}.", notice.url, date),
+            None => log::warn!("{} is deprecated.", notice.url),
+        }
+    }
+    if let Some(sunset) = notice.sunset {
+        log::warn!("{} will be unavailable after {:?}.", notice.url, sunset);
+    }
+    for warning in &notice.warnings {
+        log::warn!(
+            "Warning {} from {} for {}: {}",
+            warning.code,
+            warning.agent,
+            notice.url,
+            warning.text
+        );
+    }
+}
+
+type DeprecationHookFn = dyn Fn(&DeprecationNotice) + Send + Sync;
+
+/// A hook called for responses with deprecation-related headers.
+#[derive(Clone)]
+pub(crate) struct DeprecationHook(Arc<DeprecationHookFn>);
+
+impl DeprecationHook {
+    pub(crate) fn new<F: Fn(&DeprecationNotice) + Send + Sync + 'static>(hook: F) -> Self {
+        DeprecationHook(Arc::new(hook))
+    }
+
+    pub(crate) fn call(&self, notice: &DeprecationNotice) {
+        (self.0)(notice)
+    }
+}
+
+impl PartialEq for DeprecationHook {
+    fn eq(&self, other: &DeprecationHook) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for DeprecationHook {}
+
+impl fmt::Debug for DeprecationHook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "DeprecationHook")
+    }
+}
+
+/// Parses the `Warning` header. Malformed warnings are skipped.
+pub(crate) fn parse_warnings(headers: &HashMap<String, String>) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    let header = match headers.get("warning") {
+        Some(header) => header,
+        None => return warnings,
+    };
+
+    let mut rest = header.as_str();
+    loop {
+        rest = rest.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+        if rest.is_empty() {
+            break;
+        }
+        match parse_warning(rest) {
+            Some((warning, after)) => {
+                warnings.push(warning);
+                rest = after;
+            }
+            None => {
+                // Skip to the next warning.
+                match rest.find(',') {
+                    Some(comma) => rest = &rest[comma + 1..],
+                    None => break,
+                }
+            }
+        }
+    }
+    warnings
+}
+
+/// Parses one `warn-code SP warn-agent SP warn-text [SP warn-date]`,
+/// returning the rest of the header after it.
+fn parse_warning(warning: &str) -> Option<(Warning, &str)> {
+    let mut parts = warning.splitn(3, ' ');
+    let code = parts.next()?;
+    if code.len() != 3 {
+        return None;
+    }
+    let code = code.parse::<u16>().ok()?;
+    let agent = parts.next()?.to_string();
+    let (text, rest) = parse_quoted_string(parts.next()?)?;
+
+    let after_text = rest.trim_start();
+    let (date, rest) = match parse_quoted_string(after_text) {
+        Some((date, rest)) => (parse_http_date(&date), rest),
+        None => (None, rest),
+    };
+    let warning = Warning {
+        code,
+        agent,
+        text,
+        date,
+    };
+    Some((warning, rest))
+}
+
+/// Parses the quoted string at the start of `s`, returning its
+/// unescaped contents and the rest of `s` after it.
+fn parse_quoted_string(s: &str) -> Option<(String, &str)> {
+    let mut chars = s.strip_prefix('"')?.char_indices();
+    let mut contents = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((contents, &s[i + 2..])),
+            '\\' => contents.push(chars.next()?.1),
+            c => contents.push(c),
+        }
+    }
+    None
+}
+
+/// Parses the `Deprecation` header, either in the RFC 9745 format
+/// (`@1688169599`), or the `true` or HTTP-date of earlier drafts.
+pub(crate) fn parse_deprecation(headers: &HashMap<String, String>) -> Option<Deprecation> {
+    let header = headers.get("deprecation")?.trim();
+    let date = if let Some(seconds) = header.strip_prefix('@') {
+        seconds
+            .parse::<u64>()
+            .ok()
+            .and_then(|seconds| UNIX_EPOCH.checked_add(Duration::from_secs(seconds)))
+    } else {
+        parse_http_date(header)
+    };
+    Some(Deprecation { date })
+}
+
+/// Parses the `Sunset` header.
+pub(crate) fn parse_sunset(headers: &HashMap<String, String>) -> Option<SystemTime> {
+    parse_http_date(headers.get("sunset")?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_deprecation, parse_warnings, Deprecation, Warning};
+    use std::collections::HashMap;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn headers(name: &str, value: &str) -> HashMap<String, String> {
+        let mut headers = HashMap::new();
+        headers.insert(name.to_string(), value.to_string());
+        headers
+    }
+
+    #[test]
+    fn test_parse_warnings() {
+        let header = r#"299 api.example.com "Deprecated, use \"v2\"", 110 - "Response is stale" "Sun, 06 Nov 1994 08:49:37 GMT", garbage"#;
+        let warnings = parse_warnings(&headers("warning", header));
+        assert_eq!(
+            warnings,
+            vec![
+                Warning {
+                    code: 299,
+                    agent: "api.example.com".to_string(),
+                    text: "Deprecated, use \"v2\"".to_string(),
+                    date: None,
+                },
+                Warning {
+                    code: 110,
+                    agent: "-".to_string(),
+                    text: "Response is stale".to_string(),
+                    date: Some(UNIX_EPOCH + Duration::from_secs(784111777)),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_deprecation() {
+        let date = Some(UNIX_EPOCH + Duration::from_secs(784111777));
+        let deprecation = parse_deprecation(&headers("deprecation", "@784111777"));
+        assert_eq!(deprecation, Some(Deprecation { date }));
+        let deprecation = parse_deprecation(&headers("deprecation", "true"));
+        assert_eq!(deprecation, Some(Deprecation { date: None }));
+        let deprecation = parse_deprecation(&headers("deprecation", "@18446744073709551615"));
+        assert_eq!(deprecation, Some(Deprecation { date: None }));
+        assert_eq!(parse_deprecation(&HashMap::new()), None);
+    }
+}
@@ -0,0 +1,259 @@
+use crate::hash::{Algorithm, Hasher};
+use crate::random::RandomRef;
+use crate::Method;
+use std::fmt;
+
+/// The credentials for HTTP Digest authentication
+/// ([RFC 7616](https://www.rfc-editor.org/rfc/rfc7616)), set with
+/// [`Request::with_digest_auth`](struct.Request.html#method.with_digest_auth).
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct DigestAuth {
+    user: String,
+    password: String,
+}
+
+impl DigestAuth {
+    pub(crate) fn new(user: String, password: String) -> DigestAuth {
+        DigestAuth { user, password }
+    }
+
+    /// Returns the value of the `Authorization` header answering the
+    /// Digest challenge in the `WWW-Authenticate` header value
+    /// `challenge`, or None if there's no Digest challenge, or its
+    /// algorithm or quality of protection isn't supported.
+    pub(crate) fn authorization(
+        &self,
+        challenge: &str,
+        method: &Method,
+        uri: &str,
+        random: &RandomRef,
+    ) -> Option<String> {
+        let cnonce = format!("{:016x}", random.next_u64());
+        self.authorization_with_cnonce(challenge, method, uri, &cnonce)
+    }
+
+    fn authorization_with_cnonce(
+        &self,
+        challenge: &str,
+        method: &Method,
+        uri: &str,
+        cnonce: &str,
+    ) -> Option<String> {
+        let params = parse_challenge(challenge)?;
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        };
+        let realm = param("realm")?;
+        let nonce = param("nonce")?;
+        let algorithm_name = param("algorithm").unwrap_or("MD5");
+        let (algorithm, session) = parse_algorithm(algorithm_name)?;
+        // Only qop=auth is supported, auth-int would need the body
+        // to be hashed.
+        let qop = match param("qop") {
+            Some(qop) if qop.split(',').any(|qop| qop.trim() == "auth") => Some("auth"),
+            Some(_) => return None,
+            None => None,
+        };
+
+        let mut ha1 = hex_digest(
+            algorithm,
+            &format!("{}:{}:{}", self.user, realm, self.password),
+        );
+        if session {
+            ha1 = hex_digest(algorithm, &format!("{}:{}:{}", ha1, nonce, cnonce));
+        }
+        let ha2 = hex_digest(algorithm, &format!("{}:{}", method, uri));
+        let response = match qop {
+            Some(qop) => hex_digest(
+                algorithm,
+                &format!("{}:{}:00000001:{}:{}:{}", ha1, nonce, cnonce, qop, ha2),
+            ),
+            None => hex_digest(algorithm, &format!("{}:{}:{}", ha1, nonce, ha2)),
+        };
+
+        let mut header = format!(
+            "Digest username=\"{}\", realm=\"{}\", uri=\"{}\", algorithm={}, nonce=\"{}\"",
+            quote(&self.user),
+            quote(realm),
+            quote(uri),
+            algorithm_name,
+            quote(nonce)
+        );
+        if let Some(qop) = qop {
+            header += &format!(", nc=00000001, cnonce=\"{}\", qop={}", cnonce, qop);
+        }
+        header += &format!(", response=\"{}\"", response);
+        if let Some(opaque) = param("opaque") {
+            header += &format!(", opaque=\"{}\"", quote(opaque));
+        }
+        Some(header)
+    }
+}
+
+impl fmt::Debug for DigestAuth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DigestAuth")
+            .field("user", &self.user)
+            .field("password", &"***")
+            .finish()
+    }
+}
+
+/// Returns the hash algorithm, and whether it's a `-sess` variant,
+/// named `name` in a challenge.
+fn parse_algorithm(name: &str) -> Option<(Algorithm, bool)> {
+    let upper = name.to_ascii_uppercase();
+    let (base, session) = match upper.strip_suffix("-SESS") {
+        Some(base) => (base, true),
+        None => (upper.as_str(), false),
+    };
+    match base {
+        #[cfg(feature = "md-5")]
+        "MD5" => Some((Algorithm::Md5, session)),
+        #[cfg(feature = "sha2")]
+        "SHA-256" => Some((Algorithm::Sha256, session)),
+        _ => None,
+    }
+}
+
+/// Returns the parameters of the Digest challenge in a
+/// `WWW-Authenticate` header value, which may also list challenges
+/// for other schemes.
+fn parse_challenge(header: &str) -> Option<Vec<(String, String)>> {
+    let mut rest = header.trim_start();
+    loop {
+        let scheme_end = rest.find(' ').unwrap_or(rest.len());
+        let (scheme, after) = rest.split_at(scheme_end);
+        let (params, after) = parse_params(after);
+        if scheme.eq_ignore_ascii_case("digest") {
+            return Some(params);
+        }
+        if after.is_empty() {
+            return None;
+        }
+        rest = after;
+    }
+}
+
+/// Parses comma-separated `key=value` parameters from the start of
+/// `input`, until the end or the next challenge's scheme, which is
+/// returned as the rest.
+fn parse_params(input: &str) -> (Vec<(String, String)>, &str) {
+    let mut params = Vec::new();
+    let mut rest = input.trim_start_matches(|c: char| c == ' ' || c == ',');
+    while let Some(eq) = rest.find('=') {
+        let key = rest[..eq].trim();
+        if key.is_empty() || key.contains(|c: char| c == ' ' || c == ',') {
+            break;
+        }
+        let after = rest[eq + 1..].trim_start();
+        let (value, after) = if let Some(quoted) = after.strip_prefix('"') {
+            let mut value = String::new();
+            let mut chars = quoted.char_indices();
+            let mut end = quoted.len();
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '\\' => {
+                        if let Some((_, c)) = chars.next() {
+                            value.push(c);
+                        }
+                    }
+                    '"' => {
+                        end = i + 1;
+                        break;
+                    }
+                    c => value.push(c),
+                }
+            }
+            (value, &quoted[end..])
+        } else {
+            let end = after.find(',').unwrap_or(after.len());
+            (after[..end].trim().to_string(), &after[end..])
+        };
+        params.push((key.to_string(), value));
+        rest = after.trim_start_matches(|c: char| c == ' ' || c == ',');
+    }
+    (params, rest)
+}
+
+fn hex_digest(algorithm: Algorithm, data: &str) -> String {
+    let mut hasher = Hasher::new(algorithm);
+    hasher.update(data.as_bytes());
+    hasher
+        .finish()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Escapes `value` for a quoted-string.
+fn quote(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_challenge, DigestAuth};
+    use crate::Method;
+
+    // The example from RFC 7616, section 3.9.1.
+    const CHALLENGE: &str = "Digest realm=\"http-auth@example.org\", qop=\"auth, auth-int\", \
+                             nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", \
+                             opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\"";
+    const CNONCE: &str = "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ";
+
+    fn response_of(header: &str) -> &str {
+        let start = header.find("response=\"").unwrap() + 10;
+        &header[start..start + header[start..].find('"').unwrap()]
+    }
+
+    #[test]
+    fn test_parse_challenge() {
+        let params =
+            parse_challenge("Basic realm=\"a, b\", Digest realm=\"x\", nonce=abc").unwrap();
+        assert_eq!(
+            params,
+            vec![
+                ("realm".to_string(), "x".to_string()),
+                ("nonce".to_string(), "abc".to_string())
+            ]
+        );
+        let params = parse_challenge("Digest realm=\"a\\\"b\", Basic realm=c").unwrap();
+        assert_eq!(params, vec![("realm".to_string(), "a\"b".to_string())]);
+        assert!(parse_challenge("Basic realm=\"x\"").is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "md-5")]
+    fn test_md5() {
+        let auth = DigestAuth::new("Mufasa".to_string(), "Circle of Life".to_string());
+        let header = auth
+            .authorization_with_cnonce(CHALLENGE, &Method::Get, "/dir/index.html", CNONCE)
+            .unwrap();
+        assert_eq!(response_of(&header), "8ca523f5e9506fed4657c9700eebdbec");
+        assert!(header.contains("qop=auth, "));
+        assert!(header.contains("opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\""));
+        assert!(!format!("{:?}", auth).contains("Circle"));
+    }
+
+    #[test]
+    #[cfg(feature = "sha2")]
+    fn test_sha256() {
+        let auth = DigestAuth::new("Mufasa".to_string(), "Circle of Life".to_string());
+        let challenge = format!("{}, algorithm=SHA-256", CHALLENGE);
+        let header = auth
+            .authorization_with_cnonce(&challenge, &Method::Get, "/dir/index.html", CNONCE)
+            .unwrap();
+        assert_eq!(
+            response_of(&header),
+            "753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1"
+        );
+        let challenge = format!("{}, algorithm=SHA-512-256", CHALLENGE);
+        assert!(auth
+            .authorization_with_cnonce(&challenge, &Method::Get, "/", CNONCE)
+            .is_none());
+    }
+}
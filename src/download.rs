@@ -0,0 +1,524 @@
+#[cfg(any(feature = "sha2", feature = "sha1", feature = "md-5"))]
+use crate::hash::{Algorithm, Hasher};
+use crate::remote_reader::parse_content_range;
+use crate::retry::{is_retryable_error, should_retry_status};
+use crate::{Error, Request, ResponseLazy, RetryPolicy};
+#[cfg(feature = "sha2")]
+use crate::{Method, URL};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+const DOWNLOAD_BUFFER_LENGTH: usize = 64 * 1024;
+/// How many bytes a segment downloads between saving the progress.
+const PROGRESS_SAVE_INTERVAL: u64 = 1024 * 1024;
+
+/// Downloads a large file into a local file in segments, each fetched
+/// with a range request on its own thread and connection.
+///
+/// A single connection's throughput is often limited by the latency
+/// to the server rather than the bandwidth, so downloading a file in
+/// several parts at once can be much faster.
+///
+/// The progress is saved next to the destination file, in a file with
+/// `.progress` appended to its name, so that an interrupted download
+/// can be resumed by calling [`download`](#method.download) again with
+/// the same destination. If the remote file has changed in the
+/// meantime (according to its `ETag` or `Last-Modified` header), the
+/// download starts over.
+///
+/// If the server doesn't support range requests, the file is
+/// downloaded over one connection, without resuming.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), minreq::Error> {
+/// let length = minreq::SegmentedDownload::new(minreq::get("http://example.com/large.iso"))
+///     .with_segments(8)
+///     .download("large.iso")?;
+/// println!("Downloaded {} bytes.", length);
+/// # Ok(()) }
+/// ```
+pub struct SegmentedDownload {
+    request: Request,
+    segments: usize,
+    min_segment_size: u64,
+    retry: RetryPolicy,
+    #[cfg(any(feature = "sha2", feature = "sha1", feature = "md-5"))]
+    checksum: Option<(Algorithm, Vec<u8>)>,
+}
+
+impl SegmentedDownload {
+    /// Creates a new download of the file `request` downloads. The
+    /// request is cloned for each segment, with a `Range` header added
+    /// to it.
+    ///
+    /// By default, the file is downloaded in 4 segments of at least 1
+    /// MiB, and each segment is retried 3 times, starting after half a
+    /// second.
+    pub fn new(request: Request) -> SegmentedDownload {
+        SegmentedDownload {
+            request,
+            segments: 4,
+            min_segment_size: 1024 * 1024,
+            retry: RetryPolicy::new(3).with_initial_delay(Duration::from_millis(500)),
+            #[cfg(any(feature = "sha2", feature = "sha1", feature = "md-5"))]
+            checksum: None,
+        }
+    }
+
+    /// Sets how many segments the file is split into, ie. how many
+    /// connections are used.
+    pub fn with_segments(mut self, segments: usize) -> SegmentedDownload {
+        self.segments = segments.max(1);
+        self
+    }
+
+    /// Sets the minimum size of a segment in bytes, so that small
+    /// files are split into fewer segments.
+    pub fn with_min_segment_size(mut self, min_segment_size: u64) -> SegmentedDownload {
+        self.min_segment_size = min_segment_size.max(1);
+        self
+    }
+
+    /// Sets how each segment is retried after failing, before giving
+    /// up on the download. Retries continue from where the segment was
+    /// interrupted.
+    pub fn with_retry(mut self, retry: RetryPolicy) -> SegmentedDownload {
+        self.retry = retry;
+        self
+    }
+
+    /// Checks that the digest of the downloaded file, calculated with
+    /// `algorithm`, is `digest`.
+    #[cfg(any(feature = "sha2", feature = "sha1", feature = "md-5"))]
+    pub fn with_checksum(mut self, algorithm: Algorithm, digest: Vec<u8>) -> SegmentedDownload {
+        self.checksum = Some((algorithm, digest));
+        self
+    }
+
+    /// Downloads the file to `path`, and returns its length.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RemoteFileChanged`](enum.Error.html#variant.RemoteFileChanged)
+    /// if the remote file changed during the download, in which case
+    /// the next attempt starts over, and
+    /// [`DigestMismatch`](enum.Error.html#variant.DigestMismatch) if a
+    /// checksum was given and the file doesn't match it. Otherwise,
+    /// returns the error of the segment that failed last, after its
+    /// retries. The progress is kept, so the download can be resumed.
+    pub fn download<P: AsRef<Path>>(&self, path: P) -> Result<u64, Error> {
+        let path = path.as_ref();
+        let probe = self
+            .request
+            .clone()
+            .with_header("Range", "bytes=0-0")
+            .send_lazy()?;
+        let length = match probe.status_code {
+            206 => probe
+                .headers
+                .get("content-range")
+                .and_then(|range| parse_content_range(range))
+                .map(|(_, _, length)| length)
+                .ok_or(Error::RangeRequestsNotSupported)?,
+            416 if probe.headers.get("content-range").map(|range| range.trim())
+                == Some("bytes */0") =>
+            {
+                File::create(path)?;
+                return Ok(0);
+            }
+            200 => {
+                log::debug!("Range requests are not supported, downloading in one segment.");
+                return self.download_whole(probe, path);
+            }
+            status => return Err(Error::RangeRequestFailed(status)),
+        };
+        // Weak entity tags can't be used in If-Range.
+        let validator = probe
+            .headers
+            .get("etag")
+            .filter(|etag| !etag.starts_with("W/"))
+            .or_else(|| probe.headers.get("last-modified"))
+            .cloned();
+        drop(probe);
+
+        let progress_path = progress_path(path);
+        let progress = match Progress::load(&progress_path, length, &validator) {
+            Some(progress) if path.exists() => progress,
+            _ => {
+                let file = File::create(path)?;
+                file.set_len(length)?;
+                let segments = self
+                    .segments
+                    .min((length / self.min_segment_size).max(1) as usize);
+                Progress::new(progress_path.clone(), length, validator.clone(), segments)
+            }
+        };
+        progress.save()?;
+
+        let progress = Arc::new(Mutex::new(progress));
+        let segment_count = lock(&progress).segments.len();
+        let workers: Vec<_> = (0..segment_count)
+            .map(|index| {
+                let worker = SegmentWorker {
+                    request: self.request.clone(),
+                    path: path.to_path_buf(),
+                    validator: validator.clone(),
+                    index,
+                    progress: progress.clone(),
+                    retry: self.retry.clone(),
+                };
+                thread::spawn(move || worker.run())
+            })
+            .collect();
+
+        let mut result = Ok(());
+        for worker in workers {
+            match worker.join() {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => result = Err(err),
+                Err(_) => result = Err(Error::Other("segment download thread panicked")),
+            }
+        }
+        if let Err(err) = result {
+            if let Error::RemoteFileChanged = err {
+                let _ = fs::remove_file(&progress_path);
+            } else {
+                lock(&progress).save()?;
+            }
+            return Err(err);
+        }
+        fs::remove_file(&progress_path)?;
+
+        self.verify(path)?;
+        Ok(length)
+    }
+
+    /// Downloads the rest of `response` to `path`, for servers that
+    /// don't support range requests.
+    fn download_whole(&self, mut response: ResponseLazy, path: &Path) -> Result<u64, Error> {
+        let mut file = File::create(path)?;
+        let length = io::copy(&mut response, &mut file)?;
+        self.verify(path)?;
+        Ok(length)
+    }
+
+    #[cfg(any(feature = "sha2", feature = "sha1", feature = "md-5"))]
+    fn verify(&self, path: &Path) -> Result<(), Error> {
+        if let Some((algorithm, expected)) = &self.checksum {
+            let mut file = File::open(path)?;
+            let mut hasher = Hasher::new(*algorithm);
+            let mut buffer = vec![0; DOWNLOAD_BUFFER_LENGTH];
+            loop {
+                match file.read(&mut buffer)? {
+                    0 => break,
+                    n => hasher.update(&buffer[..n]),
+                }
+            }
+            if hasher.finish() != *expected {
+                return Err(Error::DigestMismatch);
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(not(any(feature = "sha2", feature = "sha1", feature = "md-5")))]
+    fn verify(&self, _path: &Path) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Downloads `url` to `path` if the SHA-256 digest of the file is
+/// `expected_sha256`, given in hexadecimal like in the checksum files
+/// of releases, and returns its length.
+///
+/// The file is streamed into a temporary file next to `path` (with
+/// `.part` appended to its name) while it's hashed, and renamed to
+/// `path` only if the digest matches, so `path` never has a partial
+/// or unverified file. The temporary file is deleted if the download
+/// fails.
+///
+/// # Errors
+///
+/// Returns [`InvalidDigest`](enum.Error.html#variant.InvalidDigest)
+/// if `expected_sha256` isn't 64 hexadecimal digits, without
+/// downloading anything,
+/// [`DigestMismatch`](enum.Error.html#variant.DigestMismatch) if the
+/// digest doesn't match,
+/// [`StatusError`](enum.Error.html#variant.StatusError) if the server
+/// responded with an error status, and the error of the request or of
+/// writing the file otherwise.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), minreq::Error> {
+/// minreq::download_verified(
+///     "https://example.com/releases/tool-1.2.0.tar.gz",
+///     "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08",
+///     "tool-1.2.0.tar.gz",
+/// )?;
+/// # Ok(()) }
+/// ```
+#[cfg(feature = "sha2")]
+pub fn download_verified<T: Into<URL>, P: AsRef<Path>>(
+    url: T,
+    expected_sha256: &str,
+    path: P,
+) -> Result<u64, Error> {
+    let expected = parse_hex(expected_sha256.trim())
+        .filter(|digest| digest.len() == 32)
+        .ok_or(Error::InvalidDigest)?;
+    let path = path.as_ref();
+    let part_path = suffixed_path(path, ".part");
+    let result = download_hashed(url.into(), &part_path).and_then(|(length, digest)| {
+        if digest != expected {
+            return Err(Error::DigestMismatch);
+        }
+        fs::rename(&part_path, path)?;
+        Ok(length)
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&part_path);
+    }
+    result
+}
+
+/// Downloads `url` to `path`, and returns the length and SHA-256
+/// digest of the file.
+#[cfg(feature = "sha2")]
+fn download_hashed(url: URL, path: &Path) -> Result<(u64, Vec<u8>), Error> {
+    let mut response = Request::new(Method::Get, url)
+        .send_lazy()?
+        .error_for_status()?;
+    let mut file = File::create(path)?;
+    let mut hasher = Hasher::new(Algorithm::Sha256);
+    let mut buffer = vec![0; DOWNLOAD_BUFFER_LENGTH];
+    let mut length = 0;
+    loop {
+        let n = match response.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(Error::IoError(err)),
+        };
+        hasher.update(&buffer[..n]);
+        file.write_all(&buffer[..n])?;
+        length += n as u64;
+    }
+    // The data has to be on the disk before the rename makes the file
+    // visible, or a crash could leave a verified but empty file.
+    file.sync_all()?;
+    Ok((length, hasher.finish()))
+}
+
+#[cfg(feature = "sha2")]
+fn parse_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Downloads one segment of a [SegmentedDownload], on its own thread.
+struct SegmentWorker {
+    request: Request,
+    path: PathBuf,
+    validator: Option<String>,
+    index: usize,
+    progress: Arc<Mutex<Progress>>,
+    retry: RetryPolicy,
+}
+
+impl SegmentWorker {
+    fn run(self) -> Result<(), Error> {
+        self.retry.retry(
+            &self.request,
+            || self.download(),
+            |result| match result {
+                Err(Error::RangeRequestFailed(status)) if should_retry_status(*status) => {
+                    Some(None)
+                }
+                Err(err) if is_retryable_error(err) => Some(None),
+                _ => None,
+            },
+        )
+    }
+
+    fn download(&self) -> Result<(), Error> {
+        let (segment, length) = {
+            let progress = lock(&self.progress);
+            (progress.segments[self.index], progress.length)
+        };
+        let mut position = segment.start + segment.done;
+        if position > segment.end {
+            return Ok(());
+        }
+
+        let mut request = self
+            .request
+            .clone()
+            .with_header("Range", format!("bytes={}-{}", position, segment.end));
+        if let Some(validator) = &self.validator {
+            request = request.with_header("If-Range", validator.clone());
+        }
+        let mut response = request.send_lazy()?;
+        match response.status_code {
+            206 => {}
+            200 => return Err(Error::RemoteFileChanged),
+            status => return Err(Error::RangeRequestFailed(status)),
+        }
+        let range = response
+            .headers
+            .get("content-range")
+            .and_then(|range| parse_content_range(range));
+        match range {
+            Some((start, end, _)) if start == position && end == segment.end => {}
+            Some((_, _, other_length)) if other_length != length => {
+                return Err(Error::RemoteFileChanged)
+            }
+            _ => return Err(Error::RangeRequestsNotSupported),
+        }
+
+        let mut file = OpenOptions::new().write(true).open(&self.path)?;
+        file.seek(SeekFrom::Start(position))?;
+        let mut buffer = vec![0; DOWNLOAD_BUFFER_LENGTH];
+        let mut unsaved = 0;
+        while position <= segment.end {
+            let max_read = buffer.len().min((segment.end - position + 1) as usize);
+            let n = match response.read(&mut buffer[..max_read]) {
+                Ok(0) => return Err(Error::ConnectionClosed),
+                Ok(n) => n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(Error::IoError(err)),
+            };
+            file.write_all(&buffer[..n])?;
+            position += n as u64;
+            unsaved += n as u64;
+
+            let mut progress = lock(&self.progress);
+            progress.segments[self.index].done = position - segment.start;
+            if unsaved >= PROGRESS_SAVE_INTERVAL {
+                file.flush()?;
+                progress.save()?;
+                unsaved = 0;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The segments of a [SegmentedDownload], and how much of each has
+/// been downloaded.
+struct Progress {
+    path: PathBuf,
+    length: u64,
+    validator: Option<String>,
+    segments: Vec<Segment>,
+}
+
+#[derive(Clone, Copy)]
+struct Segment {
+    start: u64,
+    /// The last byte of the segment, inclusive like in `Range`.
+    end: u64,
+    done: u64,
+}
+
+impl Progress {
+    fn new(path: PathBuf, length: u64, validator: Option<String>, segments: usize) -> Progress {
+        let segment_size = length / segments as u64;
+        let segments = (0..segments as u64)
+            .map(|i| Segment {
+                start: i * segment_size,
+                end: if i + 1 == segments as u64 {
+                    length - 1
+                } else {
+                    (i + 1) * segment_size - 1
+                },
+                done: 0,
+            })
+            .collect();
+        Progress {
+            path,
+            length,
+            validator,
+            segments,
+        }
+    }
+
+    /// Loads the progress saved at `path`, if it's for a file of
+    /// `length` bytes with the same `validator`.
+    fn load(path: &Path, length: u64, validator: &Option<String>) -> Option<Progress> {
+        let contents = fs::read_to_string(path).ok()?;
+        let mut lines = contents.lines();
+        if lines.next()?.parse::<u64>().ok()? != length {
+            return None;
+        }
+        let saved_validator = lines.next()?;
+        if validator.as_deref().unwrap_or("") != saved_validator {
+            return None;
+        }
+        // The segments have to cover the file, one after the other, or
+        // parts of it could be left undownloaded.
+        let mut segments = Vec::new();
+        let mut next_start = 0;
+        for line in lines {
+            let mut numbers = line.split(' ').map(|number| number.parse::<u64>().ok());
+            let (start, end, done) = (numbers.next()??, numbers.next()??, numbers.next()??);
+            if start != next_start || end < start || end >= length || done > end - start + 1 {
+                return None;
+            }
+            next_start = end + 1;
+            segments.push(Segment { start, end, done });
+        }
+        if next_start != length {
+            return None;
+        }
+        log::debug!("Resuming the download saved in {:?}.", path);
+        Some(Progress {
+            path: path.to_path_buf(),
+            length,
+            validator: validator.clone(),
+            segments,
+        })
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let mut contents = format!(
+            "{}\n{}\n",
+            self.length,
+            self.validator.as_deref().unwrap_or("")
+        );
+        for segment in &self.segments {
+            contents += &format!("{} {} {}\n", segment.start, segment.end, segment.done);
+        }
+        fs::write(&self.path, contents)
+    }
+}
+
+fn progress_path(path: &Path) -> PathBuf {
+    suffixed_path(path, ".progress")
+}
+
+/// Returns `path` with `suffix` appended to its file name.
+fn suffixed_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut suffixed = path.as_os_str().to_os_string();
+    suffixed.push(suffix);
+    PathBuf::from(suffixed)
+}
+
+fn lock(progress: &Mutex<Progress>) -> MutexGuard<'_, Progress> {
+    match progress.lock() {
+        Ok(progress) => progress,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
@@ -0,0 +1,147 @@
+//! Small encoding helpers, to avoid pulling in dependencies for them.
+
+/// Encodes `data` as standard base64, with padding.
+pub(crate) fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity((data.len() + 2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &byte)| n | (byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Decodes standard base64, ignoring whitespace. Padding is optional.
+/// Returns None if `encoded` contains other characters.
+pub(crate) fn base64_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(encoded.len() / 4 * 3);
+    let mut n: u32 = 0;
+    let mut bits = 0;
+    for c in encoded.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            b' ' | b'\t' | b'\r' | b'\n' => continue,
+            _ => return None,
+        };
+        n = n << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((n >> bits) as u8);
+            n &= (1 << bits) - 1;
+        }
+    }
+    Some(decoded)
+}
+
+/// Percent-encodes everything in `data` except the unreserved
+/// characters of [RFC 3986](https://tools.ietf.org/html/rfc3986#section-2.3).
+pub(crate) fn percent_encode(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len());
+    for &byte in data {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Decodes the %XX escapes in `string`. Invalid escapes are left
+/// as-is.
+pub(crate) fn percent_decode(string: &str) -> String {
+    let bytes = string.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = if bytes[i] == b'%' && i + 2 < bytes.len() {
+            std::str::from_utf8(&bytes[i + 1..i + 3])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        } else {
+            None
+        };
+        if let Some(byte) = escaped {
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Returns the DER contents of the PEM blocks labeled `label` in
+/// `pem`, eg. all of the certificates for the label "CERTIFICATE".
+#[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+pub(crate) fn pem_blocks(pem: &str, label: &str) -> Option<Vec<Vec<u8>>> {
+    let begin = format!("-----BEGIN {}-----", label);
+    let end = format!("-----END {}-----", label);
+    let mut blocks = Vec::new();
+    let mut rest = pem;
+    while let Some(start) = rest.find(&begin) {
+        let after_begin = &rest[start + begin.len()..];
+        let stop = after_begin.find(&end)?;
+        blocks.push(base64_decode(&after_begin[..stop])?);
+        rest = &after_begin[stop + end.len()..];
+    }
+    Some(blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_base64_decode() {
+        use super::{base64_decode, base64_encode};
+        for (plain, encoded) in &[
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("user:password", "dXNlcjpwYXNzd29yZA=="),
+        ] {
+            assert_eq!(base64_decode(encoded).unwrap(), plain.as_bytes());
+            assert_eq!(&base64_encode(plain.as_bytes()), encoded);
+        }
+        assert!(base64_decode("Zm9v!").is_none());
+    }
+
+    #[test]
+    fn test_percent_encode() {
+        use super::percent_encode;
+        assert_eq!(percent_encode(b"a-b_c.d~e"), "a-b_c.d~e");
+        assert_eq!(
+            percent_encode("a b&c=d/ä".as_bytes()),
+            "a%20b%26c%3Dd%2F%C3%A4"
+        );
+    }
+
+    #[test]
+    #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+    fn test_pem_blocks() {
+        use super::pem_blocks;
+        let pem = "junk\n-----BEGIN CERTIFICATE-----\nZm9v\nYmFy\n-----END CERTIFICATE-----\n\
+                   -----BEGIN CERTIFICATE-----\nYmF6\n-----END CERTIFICATE-----\n";
+        let blocks = pem_blocks(pem, "CERTIFICATE").unwrap();
+        assert_eq!(blocks, vec![b"foobar".to_vec(), b"baz".to_vec()]);
+        assert!(pem_blocks(pem, "PRIVATE KEY").unwrap().is_empty());
+    }
+}
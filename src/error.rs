@@ -1,3 +1,4 @@
+use crate::StatusError;
 use std::{error, fmt, io, str};
 
 /// Represents an error while sending, receiving, or parsing an HTTP response.
@@ -37,7 +38,12 @@ pub enum Error {
     /// The response was a redirection, but the `Location` header is
     /// missing.
     RedirectLocationMissing,
-    /// The response redirections caused an infinite redirection loop.
+    /// The response redirections caused an infinite redirection loop,
+    /// ie. the same request would have been sent twice. The method and
+    /// url of each request in the loop are passed to the
+    /// [event hook](struct.Request.html#method.on_event) in an
+    /// [`Event::RedirectionLoop`](enum.Event.html#variant.RedirectionLoop).
+    // TODO: Attach the trace to this variant in 3.0
     InfiniteRedirectionLoop,
     /// Followed
     /// [`max_redirections`](struct.Request.html#method.with_max_redirections)
@@ -67,6 +73,148 @@ pub enum Error {
     ProxyConnect,
     /// The provided credentials were rejected by the proxy server.
     InvalidProxyCreds,
+    /// The server closed the connection before sending a response,
+    /// eg. because it was closing an idle keep-alive connection just
+    /// as the request was being sent.
+    ConnectionClosed,
+    /// The request body is larger than
+    /// [Request::with_max_request_body_size](crate::request::Request::with_max_request_body_size).
+    RequestBodyTooLarge,
+    /// The response body is larger than
+    /// [Request::with_max_response_size](crate::request::Request::with_max_response_size).
+    ResponseBodyTooLarge,
+    /// A certificate given in a [TlsConfig](crate::TlsConfig) could
+    /// not be parsed, or was rejected by the TLS backend.
+    InvalidCertificate,
+    /// The digest of the response body did not match the one sent by
+    /// the server, ie. the body was corrupted or tampered with.
+    DigestMismatch,
+    /// The server did not send a digest of the response body with an
+    /// algorithm that could be verified.
+    DigestMissing,
+    /// The server responded to a part of a
+    /// [ChunkedUpload](crate::ChunkedUpload) with the contained
+    /// non-2xx status code.
+    UploadPartRejected(i32),
+    /// The response body was sent with the contained transfer coding,
+    /// which isn't supported. Gzip and deflate are supported with the
+    /// `compression` feature.
+    UnsupportedTransferEncoding(String),
+    /// The response's `Content-Type` declares a charset that
+    /// [`Response::text`](struct.Response.html#method.text) can't
+    /// decode.
+    UnsupportedCharset(String),
+    /// The response body is not valid UTF-16, so the `text()`
+    /// conversion failed.
+    InvalidUtf16InBody,
+    /// The server responded to a range request with the whole file,
+    /// or with a different range than was requested.
+    RangeRequestsNotSupported,
+    /// The server responded to a range request with this status code,
+    /// instead of 206 Partial Content.
+    RangeRequestFailed(i32),
+    /// The remote file of a [SegmentedDownload](crate::SegmentedDownload)
+    /// changed during the download.
+    RemoteFileChanged,
+    /// The body of the response is not a valid WebDAV Multi-Status
+    /// XML document.
+    InvalidMultiStatus,
+    /// An `ftp://` url was requested without a proxy. FTP is only
+    /// supported through HTTP proxies that act as FTP gateways.
+    FtpRequiresProxy,
+    /// A header is not a valid structured field value of the expected
+    /// type ([RFC 8941](https://www.rfc-editor.org/rfc/rfc8941)).
+    InvalidStructuredField,
+    /// A chunk of a response with `Transfer-Encoding: chunked` is
+    /// larger than
+    /// [Request::with_max_chunk_size](crate::request::Request::with_max_chunk_size).
+    ChunkTooLarge,
+    /// The extensions after the length of a chunk are malformed.
+    MalformedChunkExtension,
+    /// The `curl` command line given to
+    /// [Request::from_curl](crate::request::Request::from_curl) can't
+    /// be parsed or uses an unsupported option.
+    InvalidCurlCommand(String),
+    #[cfg(feature = "har")]
+    /// The HTTP Archive given to
+    /// [Request::from_har](crate::request::Request::from_har) is not
+    /// valid JSON or is missing required fields.
+    InvalidHar(String),
+    /// The server responded with a client or server error status
+    /// code, see
+    /// [Response::error_for_status](crate::response::Response::error_for_status).
+    StatusError(Box<StatusError>),
+    /// The request body is streamed from a reader (see
+    /// [Request::with_body_reader](crate::request::Request::with_body_reader)),
+    /// which was already used up by an earlier send of the request, eg.
+    /// before a redirect or a retry.
+    BodyReaderUsedUp,
+    /// The response is not a multipart body, or the body is malformed,
+    /// see [MultipartReader](crate::MultipartReader).
+    InvalidMultipart,
+    /// The requests given to [send_pipelined](crate::send_pipelined)
+    /// can't be pipelined: they don't all go to the same origin, or
+    /// one of them isn't idempotent or has a streamed body.
+    InvalidPipeline,
+    /// The server closed the connection before sending the whole
+    /// body of the response, as given by its `Content-Length` header.
+    TruncatedBody {
+        /// The length of the body in the `Content-Length` header.
+        expected: usize,
+        /// How many bytes of the body were received.
+        got: usize,
+    },
+    /// None of the public keys pinned for the host with
+    /// `TlsConfig::with_pinned_key` are in the certificate chain the
+    /// server presented.
+    PinMismatch,
+    /// The response body is not a valid gRPC-Web response, see
+    /// [`Response::grpc_web_response`](crate::Response::grpc_web_response).
+    InvalidGrpcWeb,
+    #[cfg(feature = "json-rpc")]
+    /// The JSON-RPC server returned an error object instead of a
+    /// result.
+    JsonRpc(crate::JsonRpcError),
+    #[cfg(feature = "json-rpc")]
+    /// The response is not a JSON-RPC response to the call or batch
+    /// that was sent.
+    InvalidJsonRpc(String),
+    #[cfg(feature = "xml-rpc")]
+    /// The XML-RPC server returned a fault instead of a result.
+    XmlRpcFault {
+        /// The `faultCode` of the fault.
+        code: i64,
+        /// The `faultString` of the fault.
+        message: String,
+    },
+    #[cfg(feature = "xml-rpc")]
+    /// The response body is not a valid XML-RPC method response.
+    InvalidXmlRpc,
+    /// The response body is not a Prometheus or OpenMetrics text
+    /// exposition, see [`parse_metrics`](crate::parse_metrics).
+    InvalidMetrics {
+        /// The number of the first malformed line, starting from 1.
+        line: usize,
+    },
+    #[cfg(feature = "feeds")]
+    /// The response body is not a well-formed sitemap or feed, see
+    /// [`fetch_sitemap`](crate::fetch_sitemap) and
+    /// [`fetch_feed`](crate::fetch_feed).
+    InvalidFeed,
+    #[cfg(feature = "oauth")]
+    /// The response of an OAuth 2.0 token endpoint has no access
+    /// token, see [`ClientCredentials`](crate::ClientCredentials).
+    InvalidTokenResponse,
+    /// No connection to the host became available in time, because the
+    /// [per-host limit](crate::Client::with_max_connections_per_host)
+    /// was reached, see
+    /// [`Client::with_pool_timeout`](crate::Client::with_pool_timeout).
+    PoolTimeout,
+    #[cfg(feature = "sha2")]
+    /// The expected digest given to
+    /// [`download_verified`](crate::download_verified) isn't 64
+    /// hexadecimal digits.
+    InvalidDigest,
     // TODO: Uncomment these two for 3.0
     // /// The URL does not start with http:// or https://.
     // InvalidProtocol,
@@ -109,6 +257,50 @@ impl fmt::Display for Error {
             BadProxyCreds => write!(f, "the provided proxy credentials are malformed"),
             ProxyConnect => write!(f, "could not connect to the proxy server"),
             InvalidProxyCreds => write!(f, "the provided proxy credentials are invalid"),
+            ConnectionClosed => write!(f, "the server closed the connection before sending a response"),
+            RequestBodyTooLarge => write!(f, "the request body's size surpassed max_request_body_size"),
+            ResponseBodyTooLarge => write!(f, "the response body's size surpassed max_response_size"),
+            InvalidCertificate => write!(f, "the provided certificate is invalid"),
+            DigestMismatch => write!(f, "the response body does not match its digest"),
+            DigestMissing => write!(f, "the response does not contain a verifiable digest"),
+            UploadPartRejected(status) => write!(f, "the server rejected an upload part with status code {}", status),
+            UnsupportedTransferEncoding(coding) => write!(f, "the response uses an unsupported transfer coding: {}", coding),
+            UnsupportedCharset(charset) => write!(f, "the response uses an unsupported charset: {}", charset),
+            InvalidUtf16InBody => write!(f, "the response body is not valid UTF-16"),
+            RangeRequestsNotSupported => write!(f, "the server does not support range requests"),
+            RangeRequestFailed(status) => write!(f, "the range request failed with status code {}", status),
+            RemoteFileChanged => write!(f, "the remote file changed during the download"),
+            InvalidMultiStatus => write!(f, "the response body is not a valid multi-status document"),
+            FtpRequiresProxy => write!(f, "ftp urls can only be requested through a proxy"),
+            InvalidStructuredField => write!(f, "the header is not a valid structured field"),
+            ChunkTooLarge => write!(f, "a chunk of the response surpassed max_chunk_size"),
+            MalformedChunkExtension => write!(f, "the chunk extensions are malformed"),
+            InvalidCurlCommand(message) => write!(f, "invalid curl command: {}", message),
+            #[cfg(feature = "har")]
+            InvalidHar(message) => write!(f, "invalid HTTP archive: {}", message),
+            StatusError(err) => write!(f, "the server responded with {}", err),
+            BodyReaderUsedUp => write!(f, "the request body reader was already used up by an earlier send"),
+            InvalidMultipart => write!(f, "the response body is not a valid multipart body"),
+            InvalidPipeline => write!(f, "the requests can't be pipelined on the same connection"),
+            TruncatedBody { expected, got } => write!(f, "the connection was closed after {} of the {} bytes of the response body", got, expected),
+            PinMismatch => write!(f, "the server's certificate chain doesn't contain any of the keys pinned for it"),
+            InvalidGrpcWeb => write!(f, "the response body is not a valid gRPC-Web response"),
+            #[cfg(feature = "json-rpc")]
+            JsonRpc(err) => write!(f, "JSON-RPC error: {}", err),
+            #[cfg(feature = "json-rpc")]
+            InvalidJsonRpc(message) => write!(f, "invalid JSON-RPC response: {}", message),
+            #[cfg(feature = "xml-rpc")]
+            XmlRpcFault { code, message } => write!(f, "XML-RPC fault: {} (code {})", message, code),
+            #[cfg(feature = "xml-rpc")]
+            InvalidXmlRpc => write!(f, "the response body is not a valid XML-RPC method response"),
+            InvalidMetrics { line } => write!(f, "line {} of the metrics exposition is malformed", line),
+            #[cfg(feature = "feeds")]
+            InvalidFeed => write!(f, "the response body is not a well-formed sitemap or feed"),
+            #[cfg(feature = "oauth")]
+            InvalidTokenResponse => write!(f, "the token endpoint's response has no access token"),
+            PoolTimeout => write!(f, "timed out waiting for a connection from the pool"),
+            #[cfg(feature = "sha2")]
+            InvalidDigest => write!(f, "the expected digest is not a valid SHA-256 digest"),
             // TODO: Uncomment these two for 3.0
             // InvalidProtocol => write!(f, "the url does not start with http:// or https://"),
             // InvalidProtocolInRedirect => write!(f, "got redirected to an absolute url which does not start with http:// or https://"),
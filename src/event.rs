@@ -0,0 +1,125 @@
+use crate::{Method, URL};
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Something that happened while sending a request, passed to the
+/// hook set with [`Request::on_event`](struct.Request.html#method.on_event)
+/// or [`Client::on_event`](struct.Client.html#method.on_event), eg.
+/// for rendering the timeline of the request.
+///
+/// The events from [`DnsResolved`](#variant.DnsResolved) to
+/// [`StatusReceived`](#variant.StatusReceived) are emitted for each
+/// redirection and retry of the request. Events for things that
+/// don't happen are skipped, eg. there is no `DnsResolved` event when
+/// an idle connection is reused, and no
+/// [`Completed`](#variant.Completed) event if the body of a lazy
+/// response isn't read to the end.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Event {
+    /// The addresses of a host have been looked up. When the request
+    /// goes through a proxy, the host is the proxy's.
+    DnsResolved {
+        /// The host that was looked up.
+        host: String,
+        /// The addresses the host resolved to.
+        addrs: Vec<SocketAddr>,
+    },
+    /// A connection is ready to send the request on.
+    Connected {
+        /// The address of the server, or of the proxy.
+        addr: SocketAddr,
+        /// True if the connection is an idle one from the
+        /// [Client](crate::Client)'s pool, instead of a new one.
+        reused: bool,
+    },
+    /// The TLS handshake with the server has finished.
+    TlsNegotiated {
+        /// The host the TLS session is with.
+        host: String,
+    },
+    /// The request line, headers and body have been written.
+    RequestWritten {
+        /// The method of the request.
+        method: Method,
+        /// The url the request was sent to.
+        url: URL,
+    },
+    /// The status line and headers of the response have been
+    /// received.
+    StatusReceived {
+        /// The status code of the response, eg. 404.
+        status_code: i32,
+        /// The reason phrase of the response, eg. "Not Found".
+        reason_phrase: String,
+    },
+    /// The response is a redirection, which is followed.
+    Redirected {
+        /// The status code of the redirection, eg. 301.
+        status_code: i32,
+        /// The url the request is redirected to.
+        url: URL,
+    },
+    /// A redirection would send the same request twice, so the
+    /// request fails with an
+    /// [`InfiniteRedirectionLoop`](enum.Error.html#variant.InfiniteRedirectionLoop)
+    /// error.
+    RedirectionLoop {
+        /// The method and url of each request in the redirection
+        /// chain, the last one being the repeated request.
+        trace: Vec<(Method, URL)>,
+    },
+    /// The request failed and is sent again after `delay`, according
+    /// to its [RetryPolicy](crate::RetryPolicy).
+    Retried {
+        /// How many times the request has been retried, including
+        /// this time.
+        retry: usize,
+        /// How long the request waits before being sent again.
+        delay: Duration,
+    },
+    /// Bytes of the response body have been received from the
+    /// server, before they're decoded.
+    BodyChunk {
+        /// How many bytes were received.
+        length: usize,
+    },
+    /// The whole response body has been read.
+    Completed {
+        /// The status code of the response.
+        status_code: i32,
+        /// The length of the body, after it has been decoded.
+        body_length: usize,
+    },
+}
+
+type EventHookFn = dyn Fn(&Event) + Send + Sync;
+
+/// A hook called with the [Event]s of a request.
+#[derive(Clone)]
+pub(crate) struct EventHook(Arc<EventHookFn>);
+
+impl EventHook {
+    pub(crate) fn new<F: Fn(&Event) + Send + Sync + 'static>(hook: F) -> EventHook {
+        EventHook(Arc::new(hook))
+    }
+
+    pub(crate) fn call(&self, event: Event) {
+        (self.0)(&event)
+    }
+}
+
+impl PartialEq for EventHook {
+    fn eq(&self, other: &EventHook) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for EventHook {}
+
+impl fmt::Debug for EventHook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "EventHook")
+    }
+}
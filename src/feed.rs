@@ -0,0 +1,459 @@
+//! Streaming readers of [sitemaps](https://www.sitemaps.org/protocol.html)
+//! and RSS/Atom feeds, enabled by the `feeds` feature.
+
+use crate::xml::{Event, Reader};
+use crate::{Error, Method, Request, ResponseLazy, URL};
+use std::io::Read;
+
+/// A `<url>` of a sitemap, or a `<sitemap>` of a sitemap index,
+/// returned by [SitemapEntries].
+#[derive(Clone, PartialEq, Debug)]
+pub struct SitemapEntry {
+    /// The `<loc>` of the page or sitemap.
+    pub loc: String,
+    /// The `<lastmod>` date, as written, eg. `2005-01-01`.
+    pub lastmod: Option<String>,
+    /// The `<changefreq>`, eg. `monthly`.
+    pub changefreq: Option<String>,
+    /// The `<priority>`, from 0.0 to 1.0.
+    pub priority: Option<f64>,
+    /// True if the entry is a `<sitemap>` of a sitemap index, whose
+    /// entries can be fetched with [fetch_sitemap].
+    pub is_sitemap: bool,
+}
+
+/// An `<item>` of an RSS feed, or an `<entry>` of an Atom feed,
+/// returned by [FeedEntries].
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct FeedEntry {
+    /// The title of the entry.
+    pub title: Option<String>,
+    /// The url of the entry: the `<link>` of an RSS item, or the
+    /// `href` of the alternate `<link>` of an Atom entry.
+    pub link: Option<String>,
+    /// The `<guid>` of an RSS item, or the `<id>` of an Atom entry.
+    pub id: Option<String>,
+    /// The `<pubDate>` of an RSS item, or the `<published>` date of an
+    /// Atom entry, as written.
+    pub published: Option<String>,
+    /// The `<updated>` date of an Atom entry, as written.
+    pub updated: Option<String>,
+    /// The `<description>` of an RSS item, or the `<summary>` (or
+    /// else the `<content>`) of an Atom entry.
+    pub summary: Option<String>,
+}
+
+/// An iterator over the entries of a sitemap, read from `R` as they
+/// are needed, so that large sitemaps aren't kept in memory. Both
+/// sitemaps and sitemap indexes are read.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), minreq::Error> {
+/// for entry in minreq::fetch_sitemap("https://example.com/sitemap.xml")? {
+///     let entry = entry?;
+///     println!("{} (last modified {:?})", entry.loc, entry.lastmod);
+/// }
+/// # Ok(()) }
+/// ```
+pub struct SitemapEntries<R> {
+    records: Records<R>,
+}
+
+impl<R: Read> SitemapEntries<R> {
+    /// Reads the entries of the sitemap in `reader`, eg. a file.
+    pub fn new(reader: R) -> SitemapEntries<R> {
+        SitemapEntries {
+            records: Records::new(reader, &["url", "sitemap"]),
+        }
+    }
+}
+
+impl<R: Read> Iterator for SitemapEntries<R> {
+    type Item = Result<SitemapEntry, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let record = match self.records.next()? {
+                Ok(record) => record,
+                Err(err) => return Some(Err(err)),
+            };
+            // Entries without a location are useless, and skipped.
+            let loc = match record.field("loc") {
+                Some(loc) => loc,
+                None => continue,
+            };
+            return Some(Ok(SitemapEntry {
+                loc,
+                lastmod: record.field("lastmod"),
+                changefreq: record.field("changefreq"),
+                priority: record
+                    .field("priority")
+                    .and_then(|priority| priority.parse().ok()),
+                is_sitemap: record.name == "sitemap",
+            }));
+        }
+    }
+}
+
+/// An iterator over the entries of an RSS or Atom feed, read from `R`
+/// as they are needed.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), minreq::Error> {
+/// for entry in minreq::fetch_feed("https://example.com/feed.xml")? {
+///     let entry = entry?;
+///     println!("{:?}: {:?}", entry.title, entry.link);
+/// }
+/// # Ok(()) }
+/// ```
+pub struct FeedEntries<R> {
+    records: Records<R>,
+}
+
+impl<R: Read> FeedEntries<R> {
+    /// Reads the entries of the feed in `reader`, eg. a file.
+    pub fn new(reader: R) -> FeedEntries<R> {
+        FeedEntries {
+            records: Records::new(reader, &["item", "entry"]),
+        }
+    }
+}
+
+impl<R: Read> Iterator for FeedEntries<R> {
+    type Item = Result<FeedEntry, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = match self.records.next()? {
+            Ok(record) => record,
+            Err(err) => return Some(Err(err)),
+        };
+        let entry = if record.name == "item" {
+            FeedEntry {
+                title: record.field("title"),
+                link: record.field("link"),
+                id: record.field("guid"),
+                published: record.field("pubDate"),
+                updated: None,
+                summary: record.field("description"),
+            }
+        } else {
+            let link = record
+                .links
+                .iter()
+                .find(|(rel, _)| rel.as_deref().map_or(true, |rel| rel == "alternate"))
+                .or_else(|| record.links.first());
+            FeedEntry {
+                title: record.field("title"),
+                link: link.map(|(_, href)| href.clone()),
+                id: record.field("id"),
+                published: record.field("published"),
+                updated: record.field("updated"),
+                summary: record.field("summary").or_else(|| record.field("content")),
+            }
+        };
+        Some(Ok(entry))
+    }
+}
+
+/// An element of a sitemap or feed whose children are the fields of
+/// an entry.
+struct Record {
+    name: String,
+    /// The names and text of the element's children.
+    fields: Vec<(String, String)>,
+    /// The `rel` and `href` attributes of the `<link>` children.
+    links: Vec<(Option<String>, String)>,
+}
+
+impl Record {
+    /// Returns the text of the first child called `name`, if it isn't
+    /// empty.
+    fn field(&self, name: &str) -> Option<String> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, text)| text.clone())
+            .filter(|text| !text.is_empty())
+    }
+}
+
+/// Reads the elements called one of `names` from an XML stream.
+struct Records<R> {
+    xml: Reader<R>,
+    names: &'static [&'static str],
+    /// The names of the open elements.
+    open: Vec<String>,
+    done: bool,
+}
+
+impl<R: Read> Records<R> {
+    fn new(reader: R, names: &'static [&'static str]) -> Records<R> {
+        Records {
+            xml: Reader::new(reader),
+            names,
+            open: Vec::new(),
+            done: false,
+        }
+    }
+
+    fn read_record(&mut self) -> Result<Option<Record>, Error> {
+        let mut record: Option<(Record, usize)> = None;
+        let mut text = String::new();
+        loop {
+            let event = match self.xml.next_event().map_err(|_| Error::InvalidFeed)? {
+                Some(event) => event,
+                None if self.open.is_empty() && record.is_none() => return Ok(None),
+                None => return Err(Error::InvalidFeed),
+            };
+            match event {
+                Event::Start { name, attributes } => {
+                    match &mut record {
+                        Some((record, depth)) if self.open.len() == *depth + 1 => {
+                            text.clear();
+                            if name == "link" {
+                                let attribute = |wanted: &str| {
+                                    attributes
+                                        .iter()
+                                        .find(|(name, _)| name == wanted)
+                                        .map(|(_, value)| value.clone())
+                                };
+                                if let Some(href) = attribute("href") {
+                                    record.links.push((attribute("rel"), href));
+                                }
+                            }
+                        }
+                        Some(_) => {}
+                        None if self.names.contains(&name.as_str()) => {
+                            let new = Record {
+                                name: name.clone(),
+                                fields: Vec::new(),
+                                links: Vec::new(),
+                            };
+                            record = Some((new, self.open.len()));
+                        }
+                        None => {}
+                    }
+                    self.open.push(name);
+                }
+                Event::End(name) => {
+                    if self.open.pop().as_ref() != Some(&name) {
+                        return Err(Error::InvalidFeed);
+                    }
+                    let depth = record.as_ref().map(|(_, depth)| *depth);
+                    if depth == Some(self.open.len()) {
+                        return Ok(record.map(|(record, _)| record));
+                    }
+                    if let Some((record, depth)) = &mut record {
+                        if self.open.len() == *depth + 1 {
+                            record.fields.push((name, text.trim().to_string()));
+                        }
+                    }
+                }
+                Event::Text(part) => {
+                    if record.is_some() {
+                        text += &part;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for Records<R> {
+    type Item = Result<Record, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.read_record().transpose();
+        // Nothing more can be read after an error.
+        if !matches!(result, Some(Ok(_))) {
+            self.done = true;
+        }
+        result
+    }
+}
+
+/// Fetches the sitemap or sitemap index at `url`, and returns an
+/// iterator over its entries, which are parsed while the body is
+/// being downloaded. See [SitemapEntries].
+///
+/// # Errors
+///
+/// Returns [`StatusError`](enum.Error.html#variant.StatusError) if the
+/// server responded with an error status. The iterator returns
+/// [`InvalidFeed`](enum.Error.html#variant.InvalidFeed) if the body
+/// isn't well-formed XML.
+pub fn fetch_sitemap<T: Into<URL>>(url: T) -> Result<SitemapEntries<ResponseLazy>, Error> {
+    let response = Request::new(Method::Get, url)
+        .send_lazy()?
+        .error_for_status()?;
+    Ok(SitemapEntries::new(response))
+}
+
+/// Fetches the RSS or Atom feed at `url`, and returns an iterator over
+/// its entries, which are parsed while the body is being downloaded.
+/// See [FeedEntries].
+///
+/// # Errors
+///
+/// Returns [`StatusError`](enum.Error.html#variant.StatusError) if the
+/// server responded with an error status. The iterator returns
+/// [`InvalidFeed`](enum.Error.html#variant.InvalidFeed) if the body
+/// isn't well-formed XML.
+pub fn fetch_feed<T: Into<URL>>(url: T) -> Result<FeedEntries<ResponseLazy>, Error> {
+    let response = Request::new(Method::Get, url)
+        .with_header(
+            "Accept",
+            "application/rss+xml, application/atom+xml, application/xml;q=0.9, */*;q=0.8",
+        )
+        .send_lazy()?
+        .error_for_status()?;
+    Ok(FeedEntries::new(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FeedEntries, FeedEntry, SitemapEntries};
+    use crate::Error;
+    use std::io::{self, Read};
+
+    /// Returns one byte per read, so that every piece of markup is
+    /// split across reads.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.split_first() {
+                Some((byte, rest)) if !buf.is_empty() => {
+                    buf[0] = *byte;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn test_sitemap_entries() {
+        let sitemap = br#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <url>
+    <loc>http://www.example.com/?a=1&amp;b=2</loc>
+    <lastmod>2005-01-01</lastmod>
+    <changefreq>monthly</changefreq>
+    <priority>0.8</priority>
+  </url>
+  <url><loc/></url>
+  <!-- <url><loc>http://www.example.com/commented</loc></url> -->
+  <url><loc>http://www.example.com/catalog</loc></url>
+</urlset>"#;
+        let entries = SitemapEntries::new(Trickle(sitemap))
+            .collect::<Result<Vec<_>, Error>>()
+            .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].loc, "http://www.example.com/?a=1&b=2");
+        assert_eq!(entries[0].lastmod.as_deref(), Some("2005-01-01"));
+        assert_eq!(entries[0].changefreq.as_deref(), Some("monthly"));
+        assert_eq!(entries[0].priority, Some(0.8));
+        assert!(!entries[0].is_sitemap);
+        assert_eq!(entries[1].loc, "http://www.example.com/catalog");
+        assert_eq!(entries[1].lastmod, None);
+
+        let index = br#"<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <sitemap><loc>http://www.example.com/sitemap1.xml.gz</loc></sitemap>
+</sitemapindex>"#;
+        let entries = SitemapEntries::new(&index[..])
+            .collect::<Result<Vec<_>, Error>>()
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].is_sitemap);
+    }
+
+    #[test]
+    fn test_rss_entries() {
+        let rss = br#"<?xml version="1.0"?>
+<rss version="2.0" xmlns:dc="http://purl.org/dc/elements/1.1/">
+<channel>
+  <title>Channel</title>
+  <link>http://example.com/</link>
+  <item>
+    <title>First &lt;post&gt;</title>
+    <link>http://example.com/1</link>
+    <guid isPermaLink="false">post-1</guid>
+    <pubDate>Sat, 07 Sep 2002 00:00:01 GMT</pubDate>
+    <description><![CDATA[<p>Hello > world</p>]]></description>
+    <dc:creator>Someone</dc:creator>
+  </item>
+  <item><title>Second</title></item>
+</channel>
+</rss>"#;
+        let entries = FeedEntries::new(Trickle(rss))
+            .collect::<Result<Vec<_>, Error>>()
+            .unwrap();
+        assert_eq!(
+            entries,
+            [
+                FeedEntry {
+                    title: Some("First <post>".to_string()),
+                    link: Some("http://example.com/1".to_string()),
+                    id: Some("post-1".to_string()),
+                    published: Some("Sat, 07 Sep 2002 00:00:01 GMT".to_string()),
+                    updated: None,
+                    summary: Some("<p>Hello > world</p>".to_string()),
+                },
+                FeedEntry {
+                    title: Some("Second".to_string()),
+                    ..FeedEntry::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_atom_entries() {
+        let atom = br#"<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>Feed</title>
+  <link href="http://example.org/"/>
+  <entry>
+    <title type="text">Atom-Powered Robots Run Amok</title>
+    <link rel="edit" href="http://example.org/edit/1"/>
+    <link rel='alternate' href='http://example.org/2003/12/13/atom03'/>
+    <id>urn:uuid:1225c695-cfb8-4ebb-aaaa-80da344efa6a</id>
+    <updated>2003-12-13T18:30:02Z</updated>
+    <content type="xhtml"><div>Some <b>text</b>.</div></content>
+  </entry>
+</feed>"#;
+        let entries = FeedEntries::new(&atom[..])
+            .collect::<Result<Vec<_>, Error>>()
+            .unwrap();
+        assert_eq!(
+            entries,
+            [FeedEntry {
+                title: Some("Atom-Powered Robots Run Amok".to_string()),
+                link: Some("http://example.org/2003/12/13/atom03".to_string()),
+                id: Some("urn:uuid:1225c695-cfb8-4ebb-aaaa-80da344efa6a".to_string()),
+                published: None,
+                updated: Some("2003-12-13T18:30:02Z".to_string()),
+                summary: Some("Some text.".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_malformed_feed() {
+        let mut entries =
+            FeedEntries::new(&b"<rss><channel><item><title>A</title></item><item></rss>"[..]);
+        assert!(entries.next().unwrap().is_ok());
+        assert!(matches!(entries.next(), Some(Err(Error::InvalidFeed))));
+        assert!(entries.next().is_none());
+        let mut entries = FeedEntries::new(&b"<rss><item><title>A &bogus; B</title>"[..]);
+        assert!(matches!(entries.next(), Some(Err(Error::InvalidFeed))));
+    }
+}
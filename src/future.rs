@@ -0,0 +1,95 @@
+use crate::{Error, Request, Response};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+/// A [Response] being received in the background, returned by
+/// [`Request::send_async`](struct.Request.html#method.send_async).
+///
+/// The request is sent on a thread of its own, with the blocking
+/// I/O of [`send`](struct.Request.html#method.send), when the future
+/// is first polled. The thread wakes the task up once the response
+/// has been read, so the future works with any async runtime, but
+/// each request in flight takes up a thread. If the thread panics,
+/// the future resolves to an [`Other`](Error::Other) error.
+pub struct ResponseFuture {
+    request: Option<Request>,
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Default)]
+struct State {
+    result: Option<Result<Response, Error>>,
+    waker: Option<Waker>,
+}
+
+impl ResponseFuture {
+    pub(crate) fn new(request: Request) -> ResponseFuture {
+        ResponseFuture {
+            request: Some(request),
+            state: Arc::default(),
+        }
+    }
+}
+
+/// Hands the result of the request over to the future, and wakes
+/// up its task. If the thread panics before that, the future is
+/// woken up with an error when this is dropped during unwinding.
+struct Completion(Arc<Mutex<State>>);
+
+impl Completion {
+    fn finish(&self, result: Result<Response, Error>) {
+        let mut state = lock(&self.0);
+        state.result = Some(result);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Drop for Completion {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            self.finish(Err(Error::Other("the request thread panicked")));
+        }
+    }
+}
+
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    match state.lock() {
+        Ok(state) => state,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+impl Future for ResponseFuture {
+    type Output = Result<Response, Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = lock(&self.state);
+        if let Some(result) = state.result.take() {
+            return Poll::Ready(result);
+        }
+        state.waker = Some(cx.waker().clone());
+        drop(state);
+
+        if let Some(request) = self.request.take() {
+            let completion = Completion(self.state.clone());
+            std::thread::spawn(move || {
+                let result = request.send();
+                completion.finish(result);
+            });
+        }
+        Poll::Pending
+    }
+}
+
+impl fmt::Debug for ResponseFuture {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ResponseFuture")
+            .field("request", &self.request)
+            .finish()
+    }
+}
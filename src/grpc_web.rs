@@ -0,0 +1,237 @@
+//! Helpers for unary [gRPC-Web](https://github.com/grpc/grpc/blob/master/doc/PROTOCOL-WEB.md)
+//! calls, which frame protobuf messages in plain HTTP/1.1 requests.
+
+use crate::encoding::{base64_decode, base64_encode, percent_decode};
+use crate::{Error, Method, Request, URL};
+use std::collections::HashMap;
+
+/// The encoding of gRPC-Web messages on the wire.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GrpcWebMode {
+    /// The frames are sent as is (`application/grpc-web+proto`).
+    Binary,
+    /// The frames are base64-encoded (`application/grpc-web-text+proto`),
+    /// for proxies that only pass text through.
+    Text,
+}
+
+impl GrpcWebMode {
+    fn content_type(self) -> &'static str {
+        match self {
+            GrpcWebMode::Binary => "application/grpc-web+proto",
+            GrpcWebMode::Text => "application/grpc-web-text+proto",
+        }
+    }
+}
+
+/// Creates a unary gRPC-Web call of the method at `url`, eg.
+/// `https://example.com/helloworld.Greeter/SayHello`, with the
+/// serialized protobuf `message` as its argument. The response can be
+/// read with
+/// [`Response::grpc_web_response`](struct.Response.html#method.grpc_web_response).
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), minreq::Error> {
+/// use minreq::GrpcWebMode;
+///
+/// // A HelloRequest with the name "minreq", serialized by eg. prost.
+/// let message = b"\x0a\x06minreq";
+/// let response = minreq::grpc_web(
+///     "http://example.com/helloworld.Greeter/SayHello",
+///     GrpcWebMode::Binary,
+///     message,
+/// )
+/// .send()?
+/// .grpc_web_response()?;
+/// if response.status == 0 {
+///     println!("{:?}", response.message);
+/// } else {
+///     println!("the call failed: {}", response.status_message);
+/// }
+/// # Ok(()) }
+/// ```
+pub fn grpc_web<T: Into<URL>>(url: T, mode: GrpcWebMode, message: &[u8]) -> Request {
+    let mut body = Vec::with_capacity(message.len() + 5);
+    body.push(0);
+    body.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    body.extend_from_slice(message);
+    let body = match mode {
+        GrpcWebMode::Binary => body,
+        GrpcWebMode::Text => base64_encode(&body).into_bytes(),
+    };
+    Request::new(Method::Post, url)
+        .with_header("Content-Type", mode.content_type())
+        .with_header("Accept", mode.content_type())
+        .with_header("X-Grpc-Web", "1")
+        .with_body(body)
+}
+
+/// The result of a unary gRPC-Web call, returned by
+/// [`Response::grpc_web_response`](struct.Response.html#method.grpc_web_response).
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct GrpcWebResponse {
+    /// The serialized protobuf message returned by the method, or None
+    /// if there wasn't one, eg. because the call failed.
+    pub message: Option<Vec<u8>>,
+    /// The `grpc-status` of the call: 0 if it succeeded, or one of the
+    /// gRPC status codes otherwise, eg. 5 for NOT_FOUND.
+    pub status: u32,
+    /// The `grpc-message` describing the status, if any.
+    pub status_message: String,
+    /// The trailers of the call, including `grpc-status` and
+    /// `grpc-message`, with lowercase names.
+    pub trailers: HashMap<String, String>,
+}
+
+/// Parses a gRPC-Web response with the `headers` and the `body`. The
+/// trailers may also be in `headers`, if the server responded with
+/// only trailers.
+pub(crate) fn parse_grpc_web_response(
+    headers: &HashMap<String, String>,
+    body: &[u8],
+) -> Result<GrpcWebResponse, Error> {
+    let content_type = headers
+        .get("content-type")
+        .map_or("", |value| value.as_str());
+    let decoded;
+    let mut rest = if content_type.starts_with("application/grpc-web-text") {
+        decoded = decode_text(body).ok_or(Error::InvalidGrpcWeb)?;
+        &decoded[..]
+    } else {
+        body
+    };
+
+    let mut trailers: HashMap<String, String> = headers
+        .iter()
+        .filter(|(name, _)| name.starts_with("grpc-"))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    let mut message = None;
+    while !rest.is_empty() {
+        if rest.len() < 5 {
+            return Err(Error::InvalidGrpcWeb);
+        }
+        let flags = rest[0];
+        let length = u32::from_be_bytes([rest[1], rest[2], rest[3], rest[4]]) as usize;
+        let end = 5usize.checked_add(length).ok_or(Error::InvalidGrpcWeb)?;
+        let frame = rest.get(5..end).ok_or(Error::InvalidGrpcWeb)?;
+        rest = &rest[end..];
+        match flags {
+            // A message, which is the only one of a unary call.
+            0 if message.is_none() => message = Some(frame.to_vec()),
+            // The trailers, which end the response.
+            0x80 => {
+                for line in String::from_utf8_lossy(frame).split("\r\n") {
+                    if let Some(colon) = line.find(':') {
+                        trailers.insert(
+                            line[..colon].trim().to_ascii_lowercase(),
+                            line[colon + 1..].trim().to_string(),
+                        );
+                    }
+                }
+                break;
+            }
+            // Compressed messages aren't supported (and not asked
+            // for), and there's only one message.
+            _ => return Err(Error::InvalidGrpcWeb),
+        }
+    }
+
+    let status = trailers
+        .get("grpc-status")
+        .and_then(|status| status.parse().ok())
+        .ok_or(Error::InvalidGrpcWeb)?;
+    let status_message = trailers
+        .get("grpc-message")
+        .map_or_else(String::new, |message| percent_decode(message));
+    Ok(GrpcWebResponse {
+        message,
+        status,
+        status_message,
+        trailers,
+    })
+}
+
+/// Decodes a base64 `body`, which may be the concatenation of several
+/// padded chunks.
+fn decode_text(body: &[u8]) -> Option<Vec<u8>> {
+    let body = std::str::from_utf8(body).ok()?;
+    let mut decoded = Vec::new();
+    let mut rest = body;
+    while !rest.is_empty() {
+        let chunk_end = match rest.find('=') {
+            Some(padding) => {
+                padding
+                    + rest[padding..]
+                        .find(|c| c != '=')
+                        .unwrap_or(rest.len() - padding)
+            }
+            None => rest.len(),
+        };
+        decoded.extend(base64_decode(&rest[..chunk_end])?);
+        rest = &rest[chunk_end..];
+    }
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{grpc_web, parse_grpc_web_response, GrpcWebMode};
+    use crate::Error;
+    use std::collections::HashMap;
+
+    fn headers(content_type: &str) -> HashMap<String, String> {
+        let mut headers = HashMap::new();
+        headers.insert("content-type".to_string(), content_type.to_string());
+        headers
+    }
+
+    #[test]
+    fn test_request_framing() {
+        let request = grpc_web("http://example.com/a.B/C", GrpcWebMode::Binary, b"hi");
+        assert_eq!(request.body.unwrap(), b"\x00\x00\x00\x00\x02hi");
+        assert_eq!(
+            request.headers["Content-Type"],
+            "application/grpc-web+proto"
+        );
+        let request = grpc_web("http://example.com/a.B/C", GrpcWebMode::Text, b"hi");
+        assert_eq!(request.body.unwrap(), b"AAAAAAJoaQ==");
+    }
+
+    #[test]
+    fn test_binary_response() {
+        let body =
+            b"\x00\x00\x00\x00\x02hi\x80\x00\x00\x00\x20grpc-status: 0\r\ngrpc-message: \r\n";
+        let response =
+            parse_grpc_web_response(&headers("application/grpc-web+proto"), body).unwrap();
+        assert_eq!(response.message, Some(b"hi".to_vec()));
+        assert_eq!(response.status, 0);
+        assert_eq!(response.status_message, "");
+    }
+
+    #[test]
+    fn test_text_response() {
+        // The message and the trailers are encoded separately, as
+        // servers that stream the response do.
+        let body = b"AAAAAAJoaQ==gAAAACtncnBjLXN0YXR1czogNQ0KZ3JwYy1tZXNzYWdlOiBub3QlMjBmb3VuZA0K";
+        let response =
+            parse_grpc_web_response(&headers("application/grpc-web-text+proto"), body).unwrap();
+        assert_eq!(response.message, Some(b"hi".to_vec()));
+        assert_eq!(response.status, 5);
+        assert_eq!(response.status_message, "not found");
+    }
+
+    #[test]
+    fn test_trailers_only_response() {
+        let mut headers = headers("application/grpc-web+proto");
+        headers.insert("grpc-status".to_string(), "12".to_string());
+        let response = parse_grpc_web_response(&headers, b"").unwrap();
+        assert_eq!(response.message, None);
+        assert_eq!(response.status, 12);
+
+        let result = parse_grpc_web_response(&self::headers("text/html"), b"<html>");
+        assert!(matches!(result, Err(Error::InvalidGrpcWeb)));
+    }
+}
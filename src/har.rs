@@ -0,0 +1,569 @@
+use crate::curl::skip_imported_header;
+use crate::date::format_iso_8601;
+use crate::encoding::{base64_decode, base64_encode, percent_encode};
+use crate::{Error, Method, Request, Response, ResponseLazy};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Records the requests it's attached to, and their responses, into an
+/// [HTTP Archive](http://www.softwareishard.com/blog/har-12-spec/)
+/// (HAR), the format browsers' developer tools export. This is useful
+/// for sharing reproducible traces of API calls, eg. with the API's
+/// vendor.
+///
+/// Attach the recorder to requests with
+/// [`Request::with_har_recorder`](struct.Request.html#method.with_har_recorder)
+/// or to all requests of a [Client](crate::Client) with
+/// [`Client::with_har_recorder`](struct.Client.html#method.with_har_recorder).
+/// Clones of the recorder record into the same archive.
+///
+/// Each attempt of a request is recorded when its response has been
+/// received, after following any redirects. Bodies are recorded up to
+/// a size limit, and the bodies of responses received with
+/// [`send_lazy`](struct.Request.html#method.send_lazy) aren't recorded
+/// at all, as they're read after recording. Requests that fail without
+/// a response are not recorded.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let recorder = minreq::HarRecorder::new();
+/// minreq::get("http://example.com")
+///     .with_har_recorder(recorder.clone())
+///     .send()?;
+/// std::fs::write("example.har", recorder.to_json())?;
+/// # Ok(()) }
+/// ```
+#[derive(Clone)]
+pub struct HarRecorder {
+    entries: Arc<Mutex<Vec<Value>>>,
+    max_body_size: usize,
+}
+
+impl HarRecorder {
+    /// Creates a new recorder with an empty archive, which records
+    /// bodies up to 64 KiB.
+    pub fn new() -> HarRecorder {
+        HarRecorder {
+            entries: Arc::new(Mutex::new(Vec::new())),
+            max_body_size: 64 * 1024,
+        }
+    }
+
+    /// Sets how much of each request and response body is recorded.
+    /// Longer bodies are truncated, which is noted in the entry's
+    /// comment.
+    pub fn with_max_body_size(mut self, max_body_size: usize) -> HarRecorder {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    fn entries(&self) -> MutexGuard<'_, Vec<Value>> {
+        match self.entries.lock() {
+            Ok(entries) => entries,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// Returns the amount of recorded entries.
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    /// Returns true if nothing has been recorded yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all of the recorded entries.
+    pub fn clear(&self) {
+        self.entries().clear();
+    }
+
+    /// Returns the archive as a JSON [Value](serde_json::Value).
+    pub fn to_value(&self) -> Value {
+        json!({
+            "log": {
+                "version": "1.2",
+                "creator": {
+                    "name": "minreq",
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+                "entries": self.entries().clone(),
+            }
+        })
+    }
+
+    /// Returns the archive as pretty-printed JSON, ready to be saved
+    /// into a `.har` file.
+    pub fn to_json(&self) -> String {
+        // Serializing a Value can't fail.
+        serde_json::to_string_pretty(&self.to_value()).unwrap_or_default()
+    }
+
+    /// Writes the archive into `writer` as JSON.
+    pub fn write_to<W: Write>(&self, writer: W) -> io::Result<()> {
+        serde_json::to_writer_pretty(writer, &self.to_value()).map_err(io::Error::from)
+    }
+
+    /// Starts recording a request, which is finished with one of the
+    /// `finish_*` methods of the returned recording.
+    pub(crate) fn start(
+        &self,
+        method: &Method,
+        url: String,
+        headers: &HashMap<String, String>,
+        body: Option<&[u8]>,
+    ) -> HarRecording {
+        let mut comments = Vec::new();
+        let query = match url.find('?') {
+            Some(i) => url[i + 1..].split('#').next().unwrap_or("").to_string(),
+            None => String::new(),
+        };
+        let query: Vec<Value> = query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let mut parts = pair.splitn(2, '=');
+                let name = parts.next().unwrap_or("");
+                json!({"name": name, "value": parts.next().unwrap_or("")})
+            })
+            .collect();
+        let mut request = json!({
+            "method": method.to_string(),
+            "url": url,
+            "httpVersion": "HTTP/1.1",
+            "cookies": [],
+            "headers": har_headers(headers),
+            "queryString": query,
+            "headersSize": -1,
+            "bodySize": body.map_or(0, |body| body.len()),
+        });
+        if let Some(body) = body {
+            let mime_type = header(headers, "content-type").unwrap_or("");
+            let (text, encoding) = self.body_text(body, "request", &mut comments);
+            let mut post_data = json!({"mimeType": mime_type, "text": text});
+            if let Some(encoding) = encoding {
+                post_data["encoding"] = json!(encoding);
+            }
+            request["postData"] = post_data;
+        }
+        HarRecording {
+            recorder: self.clone(),
+            started: SystemTime::now(),
+            start: Instant::now(),
+            request,
+            comments,
+        }
+    }
+
+    /// Returns the text of a body for the archive, truncated to the
+    /// maximum body size, and base64 encoded if it's not UTF-8.
+    fn body_text(
+        &self,
+        body: &[u8],
+        name: &str,
+        comments: &mut Vec<String>,
+    ) -> (String, Option<&'static str>) {
+        let recorded = &body[..body.len().min(self.max_body_size)];
+        if recorded.len() < body.len() {
+            comments.push(format!(
+                "The {} body was truncated to {} of its {} bytes.",
+                name,
+                recorded.len(),
+                body.len()
+            ));
+        }
+        match std::str::from_utf8(recorded) {
+            Ok(text) => (text.to_string(), None),
+            Err(_) => (base64_encode(recorded), Some("base64")),
+        }
+    }
+}
+
+impl Default for HarRecorder {
+    fn default() -> HarRecorder {
+        HarRecorder::new()
+    }
+}
+
+impl PartialEq for HarRecorder {
+    fn eq(&self, other: &HarRecorder) -> bool {
+        Arc::ptr_eq(&self.entries, &other.entries)
+    }
+}
+
+impl Eq for HarRecorder {}
+
+impl fmt::Debug for HarRecorder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HarRecorder")
+            .field("max_body_size", &self.max_body_size)
+            .finish()
+    }
+}
+
+/// A request being recorded by a [HarRecorder].
+pub(crate) struct HarRecording {
+    recorder: HarRecorder,
+    started: SystemTime,
+    start: Instant,
+    request: Value,
+    comments: Vec<String>,
+}
+
+impl HarRecording {
+    /// Records `response`, whose headers were received at
+    /// `headers_received`.
+    pub(crate) fn finish_response(mut self, response: &Response, headers_received: Instant) {
+        let body = response.as_bytes();
+        let (text, encoding) = self
+            .recorder
+            .body_text(body, "response", &mut self.comments);
+        let mut content = json!({
+            "size": body.len(),
+            "mimeType": header(&response.headers, "content-type").unwrap_or(""),
+            "text": text,
+        });
+        if let Some(encoding) = encoding {
+            content["encoding"] = json!(encoding);
+        }
+        let response = self.response(
+            response.status_code,
+            &response.reason_phrase,
+            &response.headers,
+            content,
+            body.len() as i64,
+        );
+        self.finish(response, headers_received, Instant::now());
+    }
+
+    /// Records `response` without its body, which hasn't been read yet.
+    pub(crate) fn finish_lazy(mut self, response: &ResponseLazy) {
+        self.comments
+            .push("The response body was not recorded.".to_string());
+        let content = json!({
+            "size": 0,
+            "mimeType": header(&response.headers, "content-type").unwrap_or(""),
+        });
+        let response = self.response(
+            response.status_code,
+            &response.reason_phrase,
+            &response.headers,
+            content,
+            -1,
+        );
+        let now = Instant::now();
+        self.finish(response, now, now);
+    }
+
+    fn response(
+        &self,
+        status_code: i32,
+        reason_phrase: &str,
+        headers: &HashMap<String, String>,
+        content: Value,
+        body_size: i64,
+    ) -> Value {
+        json!({
+            "status": status_code,
+            "statusText": reason_phrase,
+            "httpVersion": "HTTP/1.1",
+            "cookies": [],
+            "headers": har_headers(headers),
+            "content": content,
+            "redirectURL": header(headers, "location").unwrap_or(""),
+            "headersSize": -1,
+            "bodySize": body_size,
+        })
+    }
+
+    fn finish(self, response: Value, headers_received: Instant, end: Instant) {
+        let wait = headers_received.duration_since(self.start);
+        let receive = end.duration_since(headers_received);
+        let mut entry = json!({
+            "startedDateTime": format_iso_8601(self.started),
+            "time": milliseconds(wait + receive),
+            "request": self.request,
+            "response": response,
+            "cache": {},
+            "timings": {
+                "send": 0,
+                "wait": milliseconds(wait),
+                "receive": milliseconds(receive),
+            },
+        });
+        if !self.comments.is_empty() {
+            entry["comment"] = json!(self.comments.join(" "));
+        }
+        self.recorder.entries().push(entry);
+    }
+}
+
+impl Request {
+    /// Builds the requests of an [HTTP Archive](http://www.softwareishard.com/blog/har-12-spec/)
+    /// (HAR), eg. one exported from a browser's developer tools or
+    /// recorded by a [HarRecorder], for replaying them with minreq.
+    /// See [`Request::from_har_entry`](struct.Request.html#method.from_har_entry)
+    /// for how the entries are converted.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidHar`] if `har` is not valid JSON, or
+    /// if it or one of its entries is missing required fields.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let har = std::fs::read_to_string("example.har")?;
+    /// for request in minreq::Request::from_har(&har)? {
+    ///     let response = request.send()?;
+    ///     println!("{}", response.status_code);
+    /// }
+    /// # Ok(()) }
+    /// ```
+    pub fn from_har(har: &str) -> Result<Vec<Request>, Error> {
+        let har: Value =
+            serde_json::from_str(har).map_err(|err| Error::InvalidHar(err.to_string()))?;
+        har["log"]["entries"]
+            .as_array()
+            .ok_or_else(|| invalid("missing log.entries"))?
+            .iter()
+            .map(Request::from_har_entry)
+            .collect()
+    }
+
+    /// Builds a request from an entry of an HTTP Archive, or from the
+    /// `request` object of one.
+    ///
+    /// The method, url, headers and body of the entry are used.
+    /// Headers that minreq sets itself or that describe the
+    /// recording browser's connection are skipped: `Host`,
+    /// `Content-Length`, `Transfer-Encoding`, `Connection`,
+    /// `Keep-Alive`, `Accept-Encoding` (as browsers accept encodings
+    /// minreq can't decode) and HTTP/2 pseudo-headers like
+    /// `:authority`. Headers recorded more than once are combined.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidHar`] if the entry is missing its
+    /// method or url, or its body is invalid.
+    pub fn from_har_entry(entry: &Value) -> Result<Request, Error> {
+        let entry = entry.get("request").unwrap_or(entry);
+        let method = entry["method"]
+            .as_str()
+            .ok_or_else(|| invalid("missing request.method"))?;
+        let url = entry["url"]
+            .as_str()
+            .ok_or_else(|| invalid("missing request.url"))?;
+        let mut request = Request::new(Method::from_name(method), url);
+
+        let mut headers: Vec<(&str, String)> = Vec::new();
+        for header in entry["headers"].as_array().into_iter().flatten() {
+            let (name, value) = match (header["name"].as_str(), header["value"].as_str()) {
+                (Some(name), Some(value)) => (name, value),
+                _ => return Err(invalid("malformed request header")),
+            };
+            if skip_imported_header(name) {
+                continue;
+            }
+            match headers
+                .iter_mut()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            {
+                // HTTP/2 requests often have a header per cookie.
+                Some((_, existing)) if name.eq_ignore_ascii_case("cookie") => {
+                    existing.push_str("; ");
+                    existing.push_str(value);
+                }
+                Some((_, existing)) => {
+                    existing.push_str(", ");
+                    existing.push_str(value);
+                }
+                None => headers.push((name, value.to_string())),
+            }
+        }
+        for (name, value) in headers {
+            request = request.with_header(name, value);
+        }
+
+        let post_data = &entry["postData"];
+        if post_data.is_object() {
+            if let Some(mime_type) = post_data["mimeType"].as_str() {
+                if !mime_type.is_empty() && !request.has_header("content-type") {
+                    request = request.with_header("Content-Type", mime_type);
+                }
+            }
+            let body = match post_data["text"].as_str() {
+                Some(text) if post_data["encoding"] == "base64" => {
+                    base64_decode(text).ok_or_else(|| invalid("malformed base64 body"))?
+                }
+                Some(text) => text.as_bytes().to_vec(),
+                None => har_params(&post_data["params"])?.into_bytes(),
+            };
+            request = request.with_body(body);
+        }
+        Ok(request)
+    }
+
+    fn has_header(&self, name: &str) -> bool {
+        self.headers
+            .keys()
+            .any(|key| key.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Returns the `postData.params` of an entry as a form-urlencoded
+/// body.
+fn har_params(params: &Value) -> Result<String, Error> {
+    let mut body = String::new();
+    for param in params.as_array().into_iter().flatten() {
+        let name = param["name"]
+            .as_str()
+            .ok_or_else(|| invalid("malformed postData param"))?;
+        if !body.is_empty() {
+            body.push('&');
+        }
+        body.push_str(&percent_encode(name.as_bytes()));
+        body.push('=');
+        let value = param["value"].as_str().unwrap_or("");
+        body.push_str(&percent_encode(value.as_bytes()));
+    }
+    Ok(body)
+}
+
+fn invalid(message: &str) -> Error {
+    Error::InvalidHar(message.to_string())
+}
+
+fn milliseconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1000.0 + duration.subsec_nanos() as f64 / 1_000_000.0
+}
+
+fn header<'a>(headers: &'a HashMap<String, String>, name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// Returns the headers as HAR name-value pairs, sorted by name so that
+/// archives of the same requests are the same.
+fn har_headers(headers: &HashMap<String, String>) -> Vec<Value> {
+    let mut headers: Vec<_> = headers.iter().collect();
+    headers.sort();
+    headers
+        .into_iter()
+        .map(|(name, value)| json!({"name": name, "value": value}))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HarRecorder;
+    use crate::{Error, Method, Request};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_har_request() {
+        let recorder = HarRecorder::new().with_max_body_size(4);
+        let mut headers = HashMap::new();
+        headers.insert("Content-Type".to_string(), "text/plain".to_string());
+        let recording = recorder.start(
+            &Method::Post,
+            "http://example.com/a?b=c&d#e".to_string(),
+            &headers,
+            Some(b"hello"),
+        );
+        let request = &recording.request;
+        assert_eq!(request["method"], "POST");
+        assert_eq!(request["queryString"][0]["name"], "b");
+        assert_eq!(request["queryString"][0]["value"], "c");
+        assert_eq!(request["queryString"][1]["name"], "d");
+        assert_eq!(request["bodySize"], 5);
+        assert_eq!(request["postData"]["mimeType"], "text/plain");
+        assert_eq!(request["postData"]["text"], "hell");
+        assert_eq!(recording.comments.len(), 1);
+    }
+
+    #[test]
+    fn test_from_har_entry() {
+        let entry = json!({
+            "startedDateTime": "2024-01-01T00:00:00.000Z",
+            "request": {
+                "method": "POST",
+                "url": "https://example.com/login?next=%2F",
+                "headers": [
+                    {"name": ":authority", "value": "example.com"},
+                    {"name": "accept-encoding", "value": "gzip, br"},
+                    {"name": "cookie", "value": "a=1"},
+                    {"name": "cookie", "value": "b=2"},
+                    {"name": "x-custom", "value": "c"},
+                ],
+                "postData": {
+                    "mimeType": "application/x-www-form-urlencoded",
+                    "params": [
+                        {"name": "user", "value": "me"},
+                        {"name": "pass", "value": "a&b c"},
+                    ],
+                },
+            },
+        });
+        let request = Request::from_har_entry(&entry).unwrap();
+        assert_eq!(request.method, Method::Post);
+        assert_eq!(request.url, "https://example.com/login?next=%2F");
+        assert_eq!(
+            request.body.as_deref(),
+            Some(&b"user=me&pass=a%26b%20c"[..])
+        );
+        let header = |name: &str| request.headers.get(name).map(String::as_str);
+        assert_eq!(header("cookie"), Some("a=1; b=2"));
+        assert_eq!(header("x-custom"), Some("c"));
+        assert_eq!(
+            header("Content-Type"),
+            Some("application/x-www-form-urlencoded")
+        );
+        assert_eq!(header(":authority"), None);
+        assert_eq!(header("accept-encoding"), None);
+    }
+
+    #[test]
+    fn test_from_har() {
+        let recorder = HarRecorder::new();
+        let mut headers = HashMap::new();
+        headers.insert(
+            "Content-Type".to_string(),
+            "application/octet-stream".to_string(),
+        );
+        recorder
+            .start(
+                &Method::Put,
+                "http://example.com/a".to_string(),
+                &headers,
+                Some(&[0xFF, 0x00]),
+            )
+            .finish(
+                json!({}),
+                std::time::Instant::now(),
+                std::time::Instant::now(),
+            );
+        let requests = Request::from_har(&recorder.to_json()).unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, Method::Put);
+        assert_eq!(requests[0].body.as_deref(), Some(&[0xFF, 0x00][..]));
+
+        for har in &["{", "{}", r#"{"log": {"entries": [{"request": {}}]}}"#] {
+            match Request::from_har(har) {
+                Err(Error::InvalidHar(_)) => {}
+                result => panic!("{}: {:?}", har, result),
+            }
+        }
+    }
+}
@@ -0,0 +1,336 @@
+use crate::decoder::BodyDecoderFactory;
+use crate::encoding::base64_decode;
+use crate::{BodyDecoder, Error};
+#[cfg(feature = "md-5")]
+use md5::Md5;
+#[cfg(feature = "sha1")]
+use sha1::Sha1;
+#[cfg(feature = "sha2")]
+use sha2::Sha256;
+use std::collections::HashMap;
+
+/// A hash algorithm for verifying the integrity of response bodies.
+/// Each variant is enabled by the feature of the same name, eg.
+/// `hash-sha256`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Algorithm {
+    /// SHA-256, enabled by the `hash-sha256` feature.
+    #[cfg(feature = "sha2")]
+    Sha256,
+    /// SHA-1, enabled by the `hash-sha1` feature.
+    #[cfg(feature = "sha1")]
+    Sha1,
+    /// MD5, enabled by the `hash-md5` feature.
+    #[cfg(feature = "md-5")]
+    Md5,
+}
+
+impl Algorithm {
+    /// Returns the names of this algorithm in the `Digest` header
+    /// (RFC 3230), in lowercase.
+    fn digest_names(self) -> &'static [&'static str] {
+        match self {
+            #[cfg(feature = "sha2")]
+            Algorithm::Sha256 => &["sha-256"],
+            #[cfg(feature = "sha1")]
+            Algorithm::Sha1 => &["sha", "sha-1"],
+            #[cfg(feature = "md-5")]
+            Algorithm::Md5 => &["md5"],
+        }
+    }
+
+    /// Returns the name of this algorithm in the HTTP Digest Algorithm
+    /// Values registry, used by `Repr-Digest` (RFC 9530).
+    pub(crate) fn structured_name(self) -> &'static str {
+        match self {
+            #[cfg(feature = "sha2")]
+            Algorithm::Sha256 => "sha-256",
+            #[cfg(feature = "sha1")]
+            Algorithm::Sha1 => "sha",
+            #[cfg(feature = "md-5")]
+            Algorithm::Md5 => "md5",
+        }
+    }
+}
+
+/// An in-progress hash computation.
+pub(crate) enum Hasher {
+    #[cfg(feature = "sha2")]
+    Sha256(Sha256),
+    #[cfg(feature = "sha1")]
+    Sha1(Sha1),
+    #[cfg(feature = "md-5")]
+    Md5(Md5),
+}
+
+impl Hasher {
+    pub(crate) fn new(algorithm: Algorithm) -> Hasher {
+        match algorithm {
+            #[cfg(feature = "sha2")]
+            Algorithm::Sha256 => Hasher::Sha256(sha2::Digest::new()),
+            #[cfg(feature = "sha1")]
+            Algorithm::Sha1 => Hasher::Sha1(sha1::Digest::new()),
+            #[cfg(feature = "md-5")]
+            Algorithm::Md5 => Hasher::Md5(md5::Digest::new()),
+        }
+    }
+
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        match self {
+            #[cfg(feature = "sha2")]
+            Hasher::Sha256(hasher) => sha2::Digest::update(hasher, data),
+            #[cfg(feature = "sha1")]
+            Hasher::Sha1(hasher) => sha1::Digest::update(hasher, data),
+            #[cfg(feature = "md-5")]
+            Hasher::Md5(hasher) => md5::Digest::update(hasher, data),
+        }
+    }
+
+    pub(crate) fn finish(self) -> Vec<u8> {
+        match self {
+            #[cfg(feature = "sha2")]
+            Hasher::Sha256(hasher) => sha2::Digest::finalize(hasher).to_vec(),
+            #[cfg(feature = "sha1")]
+            Hasher::Sha1(hasher) => sha1::Digest::finalize(hasher).to_vec(),
+            #[cfg(feature = "md-5")]
+            Hasher::Md5(hasher) => md5::Digest::finalize(hasher).to_vec(),
+        }
+    }
+}
+
+/// Returns the HMAC (RFC 2104) of `message` with `key`, made with
+/// `algorithm`, all of whose block sizes are 64 bytes.
+#[cfg(any(feature = "webhooks", feature = "s3-presign"))]
+pub(crate) fn hmac(algorithm: Algorithm, key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut block = if key.len() > 64 {
+        let mut hasher = Hasher::new(algorithm);
+        hasher.update(key);
+        hasher.finish()
+    } else {
+        key.to_vec()
+    };
+    block.resize(64, 0);
+
+    let mut inner = Hasher::new(algorithm);
+    inner.update(&block.iter().map(|byte| byte ^ 0x36).collect::<Vec<u8>>());
+    inner.update(message);
+    let mut outer = Hasher::new(algorithm);
+    outer.update(&block.iter().map(|byte| byte ^ 0x5C).collect::<Vec<u8>>());
+    outer.update(&inner.finish());
+    outer.finish()
+}
+
+/// A response body along with its digest, returned by
+/// [`ResponseLazy::into_hashed_vec`](struct.ResponseLazy.html#method.into_hashed_vec).
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct HashedBody {
+    /// The response body.
+    pub body: Vec<u8>,
+    /// The digest of the response body.
+    pub digest: Vec<u8>,
+    algorithm: Algorithm,
+    headers: HashMap<String, String>,
+}
+
+impl HashedBody {
+    pub(crate) fn new(
+        body: Vec<u8>,
+        digest: Vec<u8>,
+        algorithm: Algorithm,
+        headers: HashMap<String, String>,
+    ) -> HashedBody {
+        HashedBody {
+            body,
+            digest,
+            algorithm,
+            headers,
+        }
+    }
+
+    /// Returns the algorithm the digest was calculated with.
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// Checks the digest against the one sent by the server in the
+    /// `Digest` header (RFC 3230), or for MD5, the `Content-MD5`
+    /// header.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DigestMismatch`](enum.Error.html#variant.DigestMismatch)
+    /// if the digests differ, and
+    /// [`DigestMissing`](enum.Error.html#variant.DigestMissing) if the
+    /// server did not send a digest made with this body's algorithm.
+    pub fn verify(&self) -> Result<(), Error> {
+        let mut expected = Vec::new();
+        if let Some(digests) = self.headers.get("digest") {
+            for digest in digests.split(',') {
+                let mut parts = digest.trim().splitn(2, '=');
+                let name = parts.next().unwrap_or("").to_lowercase();
+                if self.algorithm.digest_names().contains(&name.as_str()) {
+                    expected.push(parts.next().unwrap_or(""));
+                }
+            }
+        }
+        #[cfg(feature = "md-5")]
+        if self.algorithm == Algorithm::Md5 {
+            if let Some(digest) = self.headers.get("content-md5") {
+                expected.push(digest);
+            }
+        }
+
+        if expected.is_empty() {
+            return Err(crate::Error::DigestMissing);
+        }
+        for digest in expected {
+            if base64_decode(digest.trim()).as_ref() != Some(&self.digest) {
+                return Err(crate::Error::DigestMismatch);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the digest made with `algorithm` in a `Repr-Digest` header
+/// value, eg. `sha-256=:AEGPTgUMw5e96wxZuDtpfm23RBU3nFwtgY5fw4NYORo=:`.
+///
+/// Returns Some(empty vec) if the digest is not valid base64, so that
+/// it can't match any actual digest.
+fn find_repr_digest(header: &str, algorithm: Algorithm) -> Option<Vec<u8>> {
+    header.split(',').find_map(|member| {
+        let mut parts = member.trim().splitn(2, '=');
+        let name = parts.next()?.trim().to_lowercase();
+        if name != algorithm.structured_name() {
+            return None;
+        }
+        let value = parts.next().unwrap_or("").trim();
+        let value = value.strip_prefix(':').and_then(|v| v.strip_suffix(':'));
+        Some(value.and_then(base64_decode).unwrap_or_default())
+    })
+}
+
+/// A [BodyDecoder] that passes the body through unchanged, and fails
+/// with [`DigestMismatch`](enum.Error.html#variant.DigestMismatch) at
+/// the end if the body did not match the expected digest.
+struct DigestValidator {
+    hasher: Option<Hasher>,
+    expected: Vec<u8>,
+}
+
+impl BodyDecoder for DigestValidator {
+    fn decode(&mut self, input: &[u8], output: &mut Vec<u8>) -> Result<(), Error> {
+        if let Some(hasher) = &mut self.hasher {
+            hasher.update(input);
+        }
+        output.extend_from_slice(input);
+        Ok(())
+    }
+
+    fn finish(&mut self, _output: &mut Vec<u8>) -> Result<(), Error> {
+        match self.hasher.take() {
+            Some(hasher) => {
+                if hasher.finish() == self.expected {
+                    Ok(())
+                } else {
+                    Err(Error::DigestMismatch)
+                }
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// Creates the decoder factory validating the `Repr-Digest` of
+/// responses made with `algorithm`. Partial responses are not
+/// validated, as the digest is of the whole representation.
+pub(crate) fn repr_digest_validator(algorithm: Algorithm) -> BodyDecoderFactory {
+    BodyDecoderFactory::new(move |headers| {
+        if headers.contains_key("content-range") {
+            return None;
+        }
+        let expected = find_repr_digest(headers.get("repr-digest")?, algorithm)?;
+        Some(Box::new(DigestValidator {
+            hasher: Some(Hasher::new(algorithm)),
+            expected,
+        }))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Algorithm, HashedBody, Hasher};
+    use std::collections::HashMap;
+
+    fn hashed(algorithm: Algorithm, headers: &[(&str, &str)]) -> HashedBody {
+        let mut hasher = Hasher::new(algorithm);
+        hasher.update(b"min");
+        hasher.update(b"req");
+        let headers = headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<HashMap<_, _>>();
+        HashedBody::new(b"minreq".to_vec(), hasher.finish(), algorithm, headers)
+    }
+
+    #[test]
+    #[cfg(feature = "sha2")]
+    fn test_verify_sha256() {
+        let digest = "SHA-256=IGqfd0YCFMdE8/LCPOR376Q7NQgVwA6Pze11aUMRfyI=";
+        let body = hashed(Algorithm::Sha256, &[("digest", digest)]);
+        assert_eq!(body.digest[..4], [0x20, 0x6a, 0x9f, 0x77]);
+        assert!(body.verify().is_ok());
+
+        let body = hashed(Algorithm::Sha256, &[("digest", "sha-256=AAAA")]);
+        assert!(matches!(body.verify(), Err(crate::Error::DigestMismatch)));
+
+        let body = hashed(Algorithm::Sha256, &[("digest", "md5=AAAA")]);
+        assert!(matches!(body.verify(), Err(crate::Error::DigestMissing)));
+    }
+
+    #[test]
+    #[cfg(feature = "md-5")]
+    fn test_verify_content_md5() {
+        let body = hashed(
+            Algorithm::Md5,
+            &[("content-md5", "6+ECi+pWriX4I70SBJqTWg==")],
+        );
+        assert!(body.verify().is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "sha1")]
+    fn test_sha1() {
+        let body = hashed(Algorithm::Sha1, &[]);
+        assert_eq!(body.digest[..4], [0xfa, 0x02, 0x8e, 0xbf]);
+    }
+
+    #[test]
+    #[cfg(feature = "sha2")]
+    fn test_find_repr_digest() {
+        use super::find_repr_digest;
+        let header = "sha-512=:AAAA:, SHA-256=:IGqfd0YCFMdE8/LCPOR376Q7NQgVwA6Pze11aUMRfyI=:";
+        let digest = find_repr_digest(header, Algorithm::Sha256).unwrap();
+        assert_eq!(digest[..4], [0x20, 0x6a, 0x9f, 0x77]);
+        assert_eq!(
+            find_repr_digest("sha-256=invalid", Algorithm::Sha256),
+            Some(Vec::new())
+        );
+        assert_eq!(find_repr_digest("sha-512=:AAAA:", Algorithm::Sha256), None);
+    }
+
+    #[test]
+    #[cfg(any(feature = "webhooks", feature = "s3-presign"))]
+    fn test_hmac_sha256() {
+        use super::hmac;
+        // Test cases 2 and 6 of RFC 4231.
+        let mac = hmac(Algorithm::Sha256, b"Jefe", b"what do ya want for nothing?");
+        assert_eq!(mac[..4], [0x5b, 0xdc, 0xc1, 0x46]);
+        assert_eq!(mac[28..], [0x64, 0xec, 0x38, 0x43]);
+        let key = [0xaa; 131];
+        let message = b"Test Using Larger Than Block-Size Key - Hash Key First";
+        let mac = hmac(Algorithm::Sha256, &key, message);
+        assert_eq!(mac[..4], [0x60, 0xe4, 0x31, 0x59]);
+        assert_eq!(mac[28..], [0x0e, 0xe3, 0x7f, 0x54]);
+    }
+}
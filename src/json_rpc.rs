@@ -0,0 +1,306 @@
+//! A [JSON-RPC 2.0](https://www.jsonrpc.org/specification) client over
+//! HTTP, enabled by the `json-rpc` feature.
+
+use crate::{Error, Method, Request, URL};
+use serde::de::DeserializeOwned;
+use serde::ser::Serialize;
+use serde_json::{json, Map, Value};
+use std::fmt;
+
+/// An error object returned by a JSON-RPC server, see
+/// [`Error::JsonRpc`](enum.Error.html#variant.JsonRpc).
+#[derive(Clone, PartialEq, Debug)]
+pub struct JsonRpcError {
+    /// The error code, eg. -32601 if the method doesn't exist.
+    pub code: i64,
+    /// A short description of the error.
+    pub message: String,
+    /// Additional information about the error, if the server sent
+    /// any.
+    pub data: Option<Value>,
+}
+
+impl JsonRpcError {
+    fn parse(value: &Value) -> Result<JsonRpcError, Error> {
+        let code = value["code"]
+            .as_i64()
+            .ok_or_else(|| invalid("the error object has no code"))?;
+        Ok(JsonRpcError {
+            code,
+            message: value["message"].as_str().unwrap_or_default().to_string(),
+            data: value.get("data").cloned(),
+        })
+    }
+}
+
+impl fmt::Display for JsonRpcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (code {})", self.message, self.code)
+    }
+}
+
+/// A batch of JSON-RPC calls and notifications, sent in one request
+/// with [`Request::send_json_rpc_batch`](struct.Request.html#method.send_json_rpc_batch).
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), minreq::Error> {
+/// use minreq::JsonRpcBatch;
+///
+/// let batch = JsonRpcBatch::new()
+///     .with_call("getblockcount", ())?
+///     .with_call("getblockhash", [0])?;
+/// let results = minreq::post("http://localhost:8332").send_json_rpc_batch(batch)?;
+/// for result in results {
+///     match result {
+///         Ok(value) => println!("{}", value),
+///         Err(err) => println!("error: {}", err),
+///     }
+/// }
+/// # Ok(()) }
+/// ```
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct JsonRpcBatch {
+    messages: Vec<Value>,
+    calls: usize,
+}
+
+impl JsonRpcBatch {
+    /// Creates an empty batch.
+    pub fn new() -> JsonRpcBatch {
+        JsonRpcBatch::default()
+    }
+
+    /// Adds a call of `method` with `params`, which should serialize
+    /// to an array or an object, or to null for no params. Its result
+    /// is returned in the order the calls were added.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SerdeJsonError`](enum.Error.html#variant.SerdeJsonError)
+    /// if `params` can't be serialized.
+    pub fn with_call<P: Serialize>(
+        mut self,
+        method: &str,
+        params: P,
+    ) -> Result<JsonRpcBatch, Error> {
+        self.calls += 1;
+        let message = message(method, params, Some(self.calls as u64))?;
+        self.messages.push(message);
+        Ok(self)
+    }
+
+    /// Adds a notification of `method` with `params`, which is a call
+    /// that the server doesn't respond to.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SerdeJsonError`](enum.Error.html#variant.SerdeJsonError)
+    /// if `params` can't be serialized.
+    pub fn with_notification<P: Serialize>(
+        mut self,
+        method: &str,
+        params: P,
+    ) -> Result<JsonRpcBatch, Error> {
+        self.messages.push(message(method, params, None)?);
+        Ok(self)
+    }
+}
+
+/// Returns a JSON-RPC request object, which is a notification if `id`
+/// is None.
+fn message<P: Serialize>(method: &str, params: P, id: Option<u64>) -> Result<Value, Error> {
+    let params = serde_json::to_value(params).map_err(Error::SerdeJsonError)?;
+    let mut message = Map::new();
+    message.insert("jsonrpc".to_string(), json!("2.0"));
+    message.insert("method".to_string(), json!(method));
+    if !params.is_null() {
+        message.insert("params".to_string(), params);
+    }
+    if let Some(id) = id {
+        message.insert("id".to_string(), json!(id));
+    }
+    Ok(Value::Object(message))
+}
+
+fn invalid(message: &str) -> Error {
+    Error::InvalidJsonRpc(message.to_string())
+}
+
+/// Returns the result of the call with the id `id` in the JSON-RPC
+/// response object `response`.
+fn call_result(response: &Value, id: u64) -> Result<Result<Value, JsonRpcError>, Error> {
+    if response["id"] != json!(id) {
+        return Err(invalid("the response's id doesn't match the request's"));
+    }
+    if let Some(error) = response.get("error") {
+        return Ok(Err(JsonRpcError::parse(error)?));
+    }
+    match response.get("result") {
+        Some(result) => Ok(Ok(result.clone())),
+        None => Err(invalid("the response has neither a result nor an error")),
+    }
+}
+
+/// Parses `body` as JSON, failing with the serde error if it isn't.
+fn parse_body(body: &[u8]) -> Result<Value, Error> {
+    serde_json::from_slice(body).map_err(|err| invalid(&err.to_string()))
+}
+
+/// Returns the error object of a response which isn't to any call in
+/// particular, eg. because the request couldn't be parsed.
+fn request_error(response: &Value) -> Result<Error, Error> {
+    match response.get("error") {
+        Some(error) if response["id"].is_null() => Ok(Error::JsonRpc(JsonRpcError::parse(error)?)),
+        _ => Err(invalid("expected an array of responses")),
+    }
+}
+
+impl Request {
+    /// Sends a JSON-RPC call of `method` with `params`, which should
+    /// serialize to an array or an object, or to null (eg. `()`) for
+    /// no params, and deserializes its result. The request is sent as
+    /// is, apart from its body and `Content-Type`, so it should be a
+    /// POST.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`JsonRpc`](enum.Error.html#variant.JsonRpc) if the
+    /// server returned an error object,
+    /// [`InvalidJsonRpc`](enum.Error.html#variant.InvalidJsonRpc) if
+    /// the response isn't a JSON-RPC response to the call, eg.
+    /// because its id doesn't match, and
+    /// [`SerdeJsonError`](enum.Error.html#variant.SerdeJsonError) if
+    /// the params can't be serialized or the result can't be
+    /// deserialized into `R`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), minreq::Error> {
+    /// let balance: String = minreq::post("https://rpc.example.com")
+    ///     .with_header("Authorization", "Bearer token")
+    ///     .send_json_rpc("eth_getBalance", ("0x407d73d8a49eeb85d32cf465507dd71d507100c1", "latest"))?;
+    /// # Ok(()) }
+    /// ```
+    pub fn send_json_rpc<P: Serialize, R: DeserializeOwned>(
+        self,
+        method: &str,
+        params: P,
+    ) -> Result<R, Error> {
+        let response = self.with_json(&message(method, params, Some(1))?)?.send()?;
+        let response = parse_body(response.as_bytes())?;
+        if response["id"].is_null() && response.get("error").is_some() {
+            return Err(request_error(&response)?);
+        }
+        match call_result(&response, 1)? {
+            Ok(result) => serde_json::from_value(result).map_err(Error::SerdeJsonError),
+            Err(error) => Err(Error::JsonRpc(error)),
+        }
+    }
+
+    /// Sends a [JsonRpcBatch] of calls and notifications, and returns
+    /// the results of the calls in the order they were added to the
+    /// batch, matched to them by their ids.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`JsonRpc`](enum.Error.html#variant.JsonRpc) if the
+    /// server rejected the whole batch with an error object,
+    /// [`InvalidJsonRpc`](enum.Error.html#variant.InvalidJsonRpc) if
+    /// the response isn't a JSON-RPC response to the batch, eg.
+    /// because a call is missing from it, and
+    /// [`SerdeJsonError`](enum.Error.html#variant.SerdeJsonError) if
+    /// the batch can't be serialized.
+    pub fn send_json_rpc_batch(
+        self,
+        batch: JsonRpcBatch,
+    ) -> Result<Vec<Result<Value, JsonRpcError>>, Error> {
+        let response = self.with_json(&batch.messages)?.send()?;
+        // Nothing is returned for a batch of notifications.
+        if batch.calls == 0 && response.as_bytes().iter().all(u8::is_ascii_whitespace) {
+            return Ok(Vec::new());
+        }
+        let response = parse_body(response.as_bytes())?;
+        let responses = match response.as_array() {
+            Some(responses) => responses,
+            None => return Err(request_error(&response)?),
+        };
+        (1..=batch.calls as u64)
+            .map(|id| {
+                let response = responses
+                    .iter()
+                    .find(|response| response["id"] == json!(id))
+                    .ok_or_else(|| invalid("a call is missing from the response"))?;
+                call_result(response, id)
+            })
+            .collect()
+    }
+}
+
+/// Sends a JSON-RPC call of `method` with `params` to `url`, and
+/// deserializes its result. See
+/// [`Request::send_json_rpc`](struct.Request.html#method.send_json_rpc)
+/// for sending it with other headers, eg. for authentication.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), minreq::Error> {
+/// let count: u64 = minreq::json_rpc_call("http://localhost:8332", "getblockcount", ())?;
+/// # Ok(()) }
+/// ```
+pub fn json_rpc_call<T, P, R>(url: T, method: &str, params: P) -> Result<R, Error>
+where
+    T: Into<URL>,
+    P: Serialize,
+    R: DeserializeOwned,
+{
+    Request::new(Method::Post, url).send_json_rpc(method, params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{call_result, message, JsonRpcBatch, JsonRpcError};
+    use serde_json::json;
+
+    #[test]
+    fn test_messages() {
+        assert_eq!(
+            message("add", [1, 2], Some(1)).unwrap(),
+            json!({"jsonrpc": "2.0", "method": "add", "params": [1, 2], "id": 1})
+        );
+        assert_eq!(
+            message("ping", (), None).unwrap(),
+            json!({"jsonrpc": "2.0", "method": "ping"})
+        );
+        let batch = JsonRpcBatch::new()
+            .with_call("a", ())
+            .unwrap()
+            .with_notification("b", ())
+            .unwrap()
+            .with_call("c", ())
+            .unwrap();
+        assert_eq!(batch.calls, 2);
+        assert_eq!(batch.messages[2]["id"], json!(2));
+    }
+
+    #[test]
+    fn test_call_result() {
+        let response = json!({"jsonrpc": "2.0", "result": 3, "id": 1});
+        assert_eq!(call_result(&response, 1).unwrap(), Ok(json!(3)));
+        assert!(call_result(&response, 2).is_err());
+        let response = json!({
+            "jsonrpc": "2.0",
+            "error": {"code": -32601, "message": "Method not found"},
+            "id": 1
+        });
+        let error = JsonRpcError {
+            code: -32601,
+            message: "Method not found".to_string(),
+            data: None,
+        };
+        assert_eq!(call_result(&response, 1).unwrap(), Err(error));
+    }
+}
@@ -42,8 +42,12 @@
 //!
 //! Like `https-rustls`, but also includes the
 //! [`rustls-native-certs`](https://crates.io/crates/rustls-native-certs)
-//! crate to auto-detect root certificates installed in common
-//! locations.
+//! crate to load the root certificates in the OS's certificate
+//! store, eg. a corporate proxy's CA, in addition to the ones from
+//! [`webpki-roots`](https://crates.io/crates/webpki-roots). Enabling
+//! the `rustls-native-certs` feature along with `https-rustls` does
+//! the same. The bundled roots can be left out with
+//! [`TlsConfig::with_webpki_roots`](struct.TlsConfig.html#method.with_webpki_roots).
 //!
 //! ## `https-native`
 //!
@@ -78,6 +82,8 @@
 //! [`with_json()`](struct.Request.html#method.with_json) and
 //! [`json()`](struct.Response.html#method.json) for constructing the
 //! struct from JSON and extracting the JSON body out, respectively.
+//! [`Response::problem_details`] parses RFC 9457 Problem Details
+//! error bodies.
 //!
 //! ## `punycode`
 //!
@@ -92,13 +98,92 @@
 //!
 //! ## `proxy`
 //!
-//! This feature enables HTTP proxy support. See [Proxy].
+//! This feature enables HTTP proxy support. See [Proxy]. It also
+//! enables `ftp://` urls, which are requested through HTTP proxies
+//! that act as FTP gateways.
 //!
 //! ## `urlencoding`
 //!
 //! This feature enables percent-encoding for the URL resource when
 //! creating a request and any subsequently added parameters from
-//! [`Request::with_param`].
+//! [`Request::with_param`]. Parameters added with
+//! [`Request::with_query_param`] are always encoded.
+//!
+//! ## `compression`
+//!
+//! This feature enables decoding gzip and deflate compressed response
+//! bodies. Requests are sent with an `Accept-Encoding: gzip, deflate`
+//! header (unless they set one themselves), and bodies with a
+//! `Content-Encoding` of gzip or deflate are decompressed while
+//! they're read, with the `Content-Encoding` and `Content-Length`
+//! headers removed. Bodies sent with `Transfer-Encoding: gzip` (or
+//! `deflate`), which some older servers do, are decoded too. See
+//! [`Request::with_raw_body`](struct.Request.html#method.with_raw_body)
+//! for getting the compressed bytes instead.
+//!
+//! ## `har`
+//!
+//! This feature enables [HarRecorder], for recording requests and
+//! their responses into HTTP Archive (HAR) files, and
+//! [`Request::from_har`] for replaying them, using the
+//! [`serde_json`](https://crates.io/crates/serde_json) crate.
+//!
+//! ## `feeds`
+//!
+//! This feature enables [fetch_sitemap] and [fetch_feed], which
+//! stream the entries of sitemaps and RSS or Atom feeds as they're
+//! downloaded.
+//!
+//! ## `json-rpc`
+//!
+//! This feature enables [`Request::send_json_rpc`] and
+//! [`Request::send_json_rpc_batch`] for calling JSON-RPC 2.0 servers,
+//! eg. the APIs of many blockchain nodes and daemons, using the
+//! [`serde_json`](https://crates.io/crates/serde_json) crate.
+//!
+//! ## `metrics`
+//!
+//! This feature enables [MetricsRecorder], for exporting request
+//! counts and durations into a metrics system, and
+//! [PrometheusMetrics], which encodes them for a Prometheus
+//! `/metrics` endpoint.
+//!
+//! ## `oauth`
+//!
+//! This feature enables [ClientCredentials], for getting OAuth 2.0
+//! access tokens with the client credentials grant, and
+//! authenticating requests with them as a [TokenProvider]. It enables
+//! `json-using-serde` too.
+//!
+//! ## `robots`
+//!
+//! This feature enables [RobotsTxt], for fetching the robots.txt
+//! file of a site and checking which of its paths a crawler may
+//! fetch.
+//!
+//! ## `s3-presign`
+//!
+//! This feature enables [S3Presigner], for creating presigned URLs
+//! that let others get or put objects in S3-compatible storage for a
+//! limited time. It enables `hash-sha256` too.
+//!
+//! ## `webhooks`
+//!
+//! This feature enables [WebhookSender], for delivering JSON payloads
+//! signed with HMAC-SHA256, with retries and a dead-letter hook. It
+//! enables `hash-sha256` and `json-using-serde` too.
+//!
+//! ## `xml-rpc`
+//!
+//! This feature enables [`Request::send_xml_rpc`] and
+//! [xml_rpc_call] for calling XML-RPC servers, eg. WordPress and
+//! supervisord, with [XmlRpcValue]s as the params and results.
+//!
+//! ## `hash-sha256`, `hash-sha1`, and `hash-md5`
+//!
+//! These features enable the corresponding variants of [Algorithm],
+//! for hashing response bodies while they're being downloaded with
+//! [`ResponseLazy::into_hashed_vec`].
 //!
 //! # Examples
 //!
@@ -248,15 +333,125 @@ extern crate serde;
 #[cfg(feature = "json-using-serde")]
 extern crate serde_json;
 
+mod chaos;
+mod charset;
+mod client;
+mod clock;
+#[cfg(feature = "flate2")]
+mod compression;
 mod connection;
+mod cookie;
+mod cors;
+mod curl;
+mod date;
+mod decoder;
+mod deprecation;
+#[cfg(any(feature = "sha2", feature = "md-5"))]
+mod digest;
+mod download;
+mod encoding;
 mod error;
+mod event;
+#[cfg(feature = "feeds")]
+mod feed;
+mod future;
+mod grpc_web;
+#[cfg(feature = "har")]
+mod har;
+#[cfg(any(feature = "sha2", feature = "sha1", feature = "md-5"))]
+mod hash;
+#[cfg(feature = "json-rpc")]
+mod json_rpc;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod multipart;
+#[cfg(feature = "oauth")]
+mod oauth;
+mod openmetrics;
+mod paginate;
+mod pool;
+#[cfg(feature = "json-using-serde")]
+mod problem;
 #[cfg(feature = "proxy")]
 mod proxy;
+mod random;
+mod rate_limit;
+mod redact;
+mod remote_reader;
 mod request;
 mod response;
+mod retry;
+#[cfg(feature = "robots")]
+mod robots;
+#[cfg(feature = "s3-presign")]
+mod s3;
+mod status;
+mod structured;
+#[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+mod tls;
+mod token;
+mod trace;
+mod upload;
+mod webdav;
+#[cfg(feature = "webhooks")]
+mod webhook;
+mod xml;
+#[cfg(feature = "xml-rpc")]
+mod xml_rpc;
 
+pub use chaos::*;
+pub use charset::*;
+pub use client::*;
+pub use clock::*;
+pub use cookie::*;
+pub use cors::*;
+pub use date::*;
+pub use decoder::*;
+pub use deprecation::*;
+pub use download::*;
 pub use error::*;
+pub use event::*;
+#[cfg(feature = "feeds")]
+pub use feed::*;
+pub use future::*;
+pub use grpc_web::*;
+#[cfg(feature = "har")]
+pub use har::*;
+#[cfg(any(feature = "sha2", feature = "sha1", feature = "md-5"))]
+pub use hash::*;
+#[cfg(feature = "json-rpc")]
+pub use json_rpc::*;
+#[cfg(feature = "metrics")]
+pub use metrics::*;
+pub use multipart::*;
+#[cfg(feature = "oauth")]
+pub use oauth::*;
+pub use openmetrics::*;
+pub use paginate::*;
+pub use pool::*;
+#[cfg(feature = "json-using-serde")]
+pub use problem::*;
 #[cfg(feature = "proxy")]
 pub use proxy::*;
+pub use random::*;
+pub use rate_limit::*;
+pub use remote_reader::*;
 pub use request::*;
 pub use response::*;
+pub use retry::*;
+#[cfg(feature = "robots")]
+pub use robots::*;
+#[cfg(feature = "s3-presign")]
+pub use s3::*;
+pub use status::*;
+pub use structured::*;
+#[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+pub use tls::*;
+pub use token::*;
+pub use trace::*;
+pub use upload::*;
+pub use webdav::*;
+#[cfg(feature = "webhooks")]
+pub use webhook::*;
+#[cfg(feature = "xml-rpc")]
+pub use xml_rpc::*;
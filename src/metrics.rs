@@ -0,0 +1,305 @@
+use crate::request::parse_url;
+use crate::{Error, Method, ResponseLazy};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// The upper bounds of the request duration histogram buckets, in
+/// seconds. These are the default buckets of the Prometheus clients.
+const DURATION_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// A measurement of a single request, passed to
+/// [`MetricsRecorder::record`].
+#[derive(Clone, Copy, Debug)]
+pub struct RequestMetrics<'a> {
+    /// The method of the request.
+    pub method: &'a Method,
+    /// The host the request was sent to, before following any
+    /// redirects.
+    pub host: &'a str,
+    /// The status code of the response, or None if the request
+    /// failed without a response.
+    pub status_code: Option<i32>,
+    /// How long it took to receive the response headers, including
+    /// following redirects.
+    pub duration: Duration,
+}
+
+/// Receives a measurement of each request sent, for exporting them
+/// into a metrics system. Attach a recorder to requests with
+/// [`Request::with_metrics`](struct.Request.html#method.with_metrics)
+/// or to all requests of a [Client](crate::Client) with
+/// [`Client::with_metrics`](struct.Client.html#method.with_metrics).
+///
+/// Each attempt of a request is recorded separately, so requests
+/// retried with a [RetryPolicy](crate::RetryPolicy) are recorded
+/// once per attempt. [PrometheusMetrics] is a ready-made
+/// implementation.
+pub trait MetricsRecorder: Send + Sync {
+    /// Records a request, right after its response headers were
+    /// received or it failed.
+    fn record(&self, request: &RequestMetrics);
+}
+
+/// A [MetricsRecorder] shared between requests.
+#[derive(Clone)]
+pub(crate) struct SharedMetrics(Arc<dyn MetricsRecorder>);
+
+impl SharedMetrics {
+    pub(crate) fn new<M: MetricsRecorder + 'static>(recorder: M) -> SharedMetrics {
+        SharedMetrics(Arc::new(recorder))
+    }
+
+    /// Starts measuring a request to `url`.
+    pub(crate) fn start(&self, method: &Method, url: &str) -> Measurement {
+        Measurement {
+            metrics: self.clone(),
+            method: method.clone(),
+            host: parse_url(url).map(|(_, host, ..)| host).unwrap_or_default(),
+            start: Instant::now(),
+        }
+    }
+}
+
+impl PartialEq for SharedMetrics {
+    fn eq(&self, other: &SharedMetrics) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SharedMetrics {}
+
+impl fmt::Debug for SharedMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SharedMetrics")
+    }
+}
+
+/// A request being measured.
+pub(crate) struct Measurement {
+    metrics: SharedMetrics,
+    method: Method,
+    host: String,
+    start: Instant,
+}
+
+impl Measurement {
+    /// Records the request, which resulted in `result`.
+    pub(crate) fn finish(self, result: &Result<ResponseLazy, Error>) {
+        self.metrics.0.record(&RequestMetrics {
+            method: &self.method,
+            host: &self.host,
+            status_code: result.as_ref().ok().map(|response| response.status_code),
+            duration: self.start.elapsed(),
+        });
+    }
+}
+
+/// A [MetricsRecorder] that keeps request counters and duration
+/// histograms, and encodes them in the [Prometheus text format] for
+/// serving them from a `/metrics` endpoint. Clones of it share the
+/// same metrics.
+///
+/// The metrics are labeled by method and host:
+///
+/// - `minreq_requests_total` counts the requests by response
+///   status code, with the status `error` for failed requests.
+/// - `minreq_request_duration_seconds` is a histogram of how long it
+///   took to receive the response headers.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), minreq::Error> {
+/// let metrics = minreq::PrometheusMetrics::new();
+/// let client = minreq::Client::new().with_metrics(metrics.clone());
+/// client.get("http://example.com").send()?;
+/// // In the /metrics handler:
+/// let body = metrics.encode();
+/// # Ok(()) }
+/// ```
+///
+/// [Prometheus text format]: https://prometheus.io/docs/instrumenting/exposition_formats/
+#[derive(Clone, Default)]
+pub struct PrometheusMetrics {
+    series: Arc<Mutex<Series>>,
+}
+
+#[derive(Default)]
+struct Series {
+    /// The request counts by method, host and status.
+    requests: BTreeMap<(String, String, String), u64>,
+    /// The duration histograms by method and host.
+    durations: BTreeMap<(String, String), Histogram>,
+}
+
+#[derive(Default)]
+struct Histogram {
+    buckets: [u64; DURATION_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl PrometheusMetrics {
+    /// The `Content-Type` to serve [`encode`](PrometheusMetrics::encode)d
+    /// metrics with.
+    pub const CONTENT_TYPE: &'static str = "text/plain; version=0.0.4";
+
+    /// Creates a new recorder with no requests recorded.
+    pub fn new() -> PrometheusMetrics {
+        PrometheusMetrics::default()
+    }
+
+    fn series(&self) -> MutexGuard<'_, Series> {
+        match self.series.lock() {
+            Ok(series) => series,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// Returns the metrics in the Prometheus text format.
+    pub fn encode(&self) -> String {
+        let series = self.series();
+        let mut text = String::new();
+        text += "# HELP minreq_requests_total The amount of requests sent.\n";
+        text += "# TYPE minreq_requests_total counter\n";
+        for ((method, host, status), count) in &series.requests {
+            writeln!(
+                text,
+                "minreq_requests_total{{method=\"{}\",host=\"{}\",status=\"{}\"}} {}",
+                escape(method),
+                escape(host),
+                status,
+                count
+            )
+            .unwrap();
+        }
+        text += "# HELP minreq_request_duration_seconds The time until the response headers were received.\n";
+        text += "# TYPE minreq_request_duration_seconds histogram\n";
+        for ((method, host), histogram) in &series.durations {
+            let labels = format!("method=\"{}\",host=\"{}\"", escape(method), escape(host));
+            let name = "minreq_request_duration_seconds";
+            for (bound, count) in DURATION_BUCKETS.iter().zip(&histogram.buckets) {
+                writeln!(
+                    text,
+                    "{}_bucket{{{},le=\"{}\"}} {}",
+                    name, labels, bound, count
+                )
+                .unwrap();
+            }
+            writeln!(
+                text,
+                "{}_bucket{{{},le=\"+Inf\"}} {}",
+                name, labels, histogram.count
+            )
+            .unwrap();
+            writeln!(text, "{}_sum{{{}}} {}", name, labels, histogram.sum).unwrap();
+            writeln!(text, "{}_count{{{}}} {}", name, labels, histogram.count).unwrap();
+        }
+        text
+    }
+}
+
+impl MetricsRecorder for PrometheusMetrics {
+    fn record(&self, request: &RequestMetrics) {
+        let method = request.method.to_string();
+        let host = request.host.to_string();
+        let status = match request.status_code {
+            Some(status_code) => status_code.to_string(),
+            None => "error".to_string(),
+        };
+        let mut series = self.series();
+        *series
+            .requests
+            .entry((method.clone(), host.clone(), status))
+            .or_insert(0) += 1;
+
+        let seconds = request.duration.as_secs_f64();
+        let histogram = series.durations.entry((method, host)).or_default();
+        // The buckets are cumulative.
+        for (bound, count) in DURATION_BUCKETS.iter().zip(&mut histogram.buckets) {
+            if seconds <= *bound {
+                *count += 1;
+            }
+        }
+        histogram.sum += seconds;
+        histogram.count += 1;
+    }
+}
+
+impl fmt::Debug for PrometheusMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PrometheusMetrics")
+    }
+}
+
+/// Escapes a label value.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MetricsRecorder, PrometheusMetrics, RequestMetrics};
+    use crate::Method;
+    use std::time::Duration;
+
+    #[test]
+    fn test_prometheus_metrics() {
+        let metrics = PrometheusMetrics::new();
+        for (status_code, millis) in &[(Some(200), 250), (Some(200), 500), (None, 20_000)] {
+            metrics.record(&RequestMetrics {
+                method: &Method::Get,
+                host: "example.com",
+                status_code: *status_code,
+                duration: Duration::from_millis(*millis),
+            });
+        }
+        metrics.record(&RequestMetrics {
+            method: &Method::Custom("A\"B".to_string()),
+            host: "example.com",
+            status_code: Some(404),
+            duration: Duration::from_millis(1),
+        });
+        let text = metrics.encode();
+        let labels = "method=\"GET\",host=\"example.com\"";
+        for line in &[
+            format!("minreq_requests_total{{{},status=\"200\"}} 2", labels),
+            format!("minreq_requests_total{{{},status=\"error\"}} 1", labels),
+            "minreq_requests_total{method=\"A\\\"B\",host=\"example.com\",status=\"404\"} 1"
+                .to_string(),
+            format!(
+                "minreq_request_duration_seconds_bucket{{{},le=\"0.1\"}} 0",
+                labels
+            ),
+            format!(
+                "minreq_request_duration_seconds_bucket{{{},le=\"0.25\"}} 1",
+                labels
+            ),
+            format!(
+                "minreq_request_duration_seconds_bucket{{{},le=\"10\"}} 2",
+                labels
+            ),
+            format!(
+                "minreq_request_duration_seconds_bucket{{{},le=\"+Inf\"}} 3",
+                labels
+            ),
+            format!("minreq_request_duration_seconds_sum{{{}}} 20.75", labels),
+            format!("minreq_request_duration_seconds_count{{{}}} 3", labels),
+        ] {
+            assert!(
+                text.lines().any(|l| l == line),
+                "{} not in:\n{}",
+                line,
+                text
+            );
+        }
+    }
+}
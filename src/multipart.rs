@@ -0,0 +1,290 @@
+use crate::Error;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read};
+
+/// A part of a multipart body, see [MultipartReader].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MultipartPart {
+    /// The headers of the part, with lowercase names.
+    pub headers: HashMap<String, String>,
+    /// The body of the part.
+    pub body: Vec<u8>,
+}
+
+impl MultipartPart {
+    /// Returns the `Content-Type` of the part. Parts without one are
+    /// `text/plain` (RFC 2046 section 5.1), unless the whole body is
+    /// `multipart/digest`.
+    pub fn content_type(&self) -> Option<&str> {
+        self.headers.get("content-type").map(String::as_str)
+    }
+
+    /// Returns the `Content-ID` of the part without the angle
+    /// brackets, which is how MTOM and other `multipart/related`
+    /// payloads refer to their parts.
+    pub fn content_id(&self) -> Option<&str> {
+        let id = self.headers.get("content-id")?.trim();
+        Some(id.trim_start_matches('<').trim_end_matches('>'))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum State {
+    Preamble,
+    Parts,
+    Done,
+}
+
+/// Iterates over the parts of a `multipart/*` body
+/// ([RFC 2046](https://www.rfc-editor.org/rfc/rfc2046#section-5.1)),
+/// eg. `multipart/mixed` batch responses or `multipart/related` MTOM
+/// payloads, reading the body one part at a time.
+///
+/// Returned by
+/// [`ResponseLazy::multipart`](struct.ResponseLazy.html#method.multipart),
+/// which only keeps the part being read in memory, and
+/// [`Response::multipart`](struct.Response.html#method.multipart).
+/// Parts which are multipart bodies themselves can be read by
+/// creating another reader over their body.
+///
+/// The iterator ends after the last part, or after the first error,
+/// which is [`InvalidMultipart`](enum.Error.html#variant.InvalidMultipart)
+/// if the body ends before the closing boundary.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), minreq::Error> {
+/// let response = minreq::post("http://example.com/batch").send_lazy()?;
+/// for part in response.multipart()? {
+///     let part = part?;
+///     println!("{:?}: {} bytes", part.content_type(), part.body.len());
+/// }
+/// # Ok(()) }
+/// ```
+pub struct MultipartReader<R: Read> {
+    reader: BufReader<R>,
+    delimiter: Vec<u8>,
+    state: State,
+}
+
+impl<R: Read> MultipartReader<R> {
+    /// Creates a reader for the multipart body in `reader`, whose
+    /// parts are separated by `boundary`, the `boundary` parameter of
+    /// its `Content-Type`.
+    pub fn new(reader: R, boundary: &str) -> MultipartReader<R> {
+        MultipartReader {
+            reader: BufReader::new(reader),
+            delimiter: format!("--{}", boundary).into_bytes(),
+            state: State::Preamble,
+        }
+    }
+
+    /// Reads a line, including the line ending. Returns an empty line
+    /// at the end of the body.
+    fn read_line(&mut self) -> Result<Vec<u8>, Error> {
+        let mut line = Vec::new();
+        self.reader
+            .read_until(b'\n', &mut line)
+            .map_err(read_error)?;
+        Ok(line)
+    }
+
+    /// Returns the kind of delimiter `line` is, if it's one.
+    fn delimiter(&self, line: &[u8]) -> Option<State> {
+        if !line.starts_with(&self.delimiter) {
+            return None;
+        }
+        let rest = &line[self.delimiter.len()..];
+        let (rest, state) = if rest.starts_with(b"--") {
+            (&rest[2..], State::Done)
+        } else {
+            (rest, State::Parts)
+        };
+        // Delimiters may be followed by whitespace.
+        if rest.iter().all(u8::is_ascii_whitespace) {
+            Some(state)
+        } else {
+            None
+        }
+    }
+
+    fn read_part(&mut self) -> Result<MultipartPart, Error> {
+        let mut headers = HashMap::new();
+        loop {
+            let line = self.read_line()?;
+            if line.is_empty() {
+                return Err(Error::InvalidMultipart);
+            }
+            let line = std::str::from_utf8(&line).map_err(|_| Error::InvalidMultipart)?;
+            let line = line.trim_end_matches(|c| c == '\r' || c == '\n');
+            if line.is_empty() {
+                break;
+            }
+            let mut parts = line.splitn(2, ':');
+            let name = parts.next().unwrap_or("").trim().to_lowercase();
+            let value = parts.next().ok_or(Error::InvalidMultipart)?.trim();
+            headers.insert(name, value.to_string());
+        }
+
+        let mut body = Vec::new();
+        loop {
+            let line = self.read_line()?;
+            if line.is_empty() {
+                return Err(Error::InvalidMultipart);
+            }
+            if let Some(state) = self.delimiter(&line) {
+                // The line break before the delimiter is part of it.
+                if body.ends_with(b"\n") {
+                    body.pop();
+                    if body.ends_with(b"\r") {
+                        body.pop();
+                    }
+                }
+                self.state = state;
+                return Ok(MultipartPart { headers, body });
+            }
+            body.extend(line);
+        }
+    }
+}
+
+/// Converts an error from reading the body, unwrapping the minreq
+/// errors returned by [ResponseLazy](crate::ResponseLazy)'s `Read`
+/// implementation.
+fn read_error(err: io::Error) -> Error {
+    if err.get_ref().map_or(true, |inner| !inner.is::<Error>()) {
+        return Error::IoError(err);
+    }
+    match err.into_inner().map(|inner| inner.downcast::<Error>()) {
+        Some(Ok(err)) => *err,
+        _ => Error::Other("a minreq error could not be unwrapped from an io::Error"),
+    }
+}
+
+impl<R: Read> Iterator for MultipartReader<R> {
+    type Item = Result<MultipartPart, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.state == State::Preamble {
+            match self.read_line() {
+                Ok(line) if line.is_empty() => {
+                    self.state = State::Done;
+                    return Some(Err(Error::InvalidMultipart));
+                }
+                Ok(line) => {
+                    if let Some(state) = self.delimiter(&line) {
+                        self.state = state;
+                    }
+                }
+                Err(err) => {
+                    self.state = State::Done;
+                    return Some(Err(err));
+                }
+            }
+        }
+        if self.state == State::Done {
+            return None;
+        }
+        let part = self.read_part();
+        if part.is_err() {
+            self.state = State::Done;
+        }
+        Some(part)
+    }
+}
+
+impl<R: Read> fmt::Debug for MultipartReader<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MultipartReader")
+            .field("delimiter", &String::from_utf8_lossy(&self.delimiter))
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+/// Returns the boundary of a multipart body, given its `Content-Type`.
+pub(crate) fn multipart_boundary(headers: &HashMap<String, String>) -> Result<&str, Error> {
+    let content_type = headers.get("content-type").ok_or(Error::InvalidMultipart)?;
+    let media_type = content_type.split(';').next().unwrap_or("").trim();
+    if !media_type
+        .get(..10)
+        .map_or(false, |prefix| prefix.eq_ignore_ascii_case("multipart/"))
+    {
+        return Err(Error::InvalidMultipart);
+    }
+    content_type
+        .split(';')
+        .skip(1)
+        .find_map(|param| {
+            let mut parts = param.splitn(2, '=');
+            let name = parts.next()?.trim();
+            if name.eq_ignore_ascii_case("boundary") {
+                Some(parts.next()?.trim().trim_matches('"'))
+            } else {
+                None
+            }
+        })
+        .filter(|boundary| !boundary.is_empty())
+        .ok_or(Error::InvalidMultipart)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{multipart_boundary, MultipartReader};
+    use crate::Error;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_multipart_reader() {
+        let body = b"This is the preamble.\r\n\
+            --simple boundary\r\n\
+            \r\n\
+            This is implicitly typed plain US-ASCII text.\r\n\
+            It does NOT end with a linebreak.\r\n\
+            --simple boundary\r\n\
+            Content-type: text/plain; charset=us-ascii\r\n\
+            Content-ID: <part2@example.com>\r\n\
+            \r\n\
+            This is explicitly typed plain US-ASCII text.\r\n\
+            It DOES end with a linebreak.\r\n\
+            \r\n\
+            --simple boundary-- \r\n\
+            This is the epilogue.\r\n";
+        let parts: Vec<_> = MultipartReader::new(&body[..], "simple boundary")
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(parts.len(), 2);
+        assert!(parts[0].headers.is_empty());
+        assert_eq!(
+            parts[0].body,
+            b"This is implicitly typed plain US-ASCII text.\r\nIt does NOT end with a linebreak."
+        );
+        assert_eq!(
+            parts[1].content_type(),
+            Some("text/plain; charset=us-ascii")
+        );
+        assert_eq!(parts[1].content_id(), Some("part2@example.com"));
+        assert!(parts[1].body.ends_with(b"linebreak.\r\n"));
+
+        let truncated = b"--b\r\n\r\nno closing boundary\r\n";
+        let mut reader = MultipartReader::new(&truncated[..], "b");
+        assert!(matches!(reader.next(), Some(Err(Error::InvalidMultipart))));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_multipart_boundary() {
+        let mut headers = HashMap::new();
+        assert!(multipart_boundary(&headers).is_err());
+        headers.insert(
+            "content-type".to_string(),
+            "Multipart/Related; type=\"application/xop+xml\"; boundary=\"uuid:0ca0e16e\""
+                .to_string(),
+        );
+        assert_eq!(multipart_boundary(&headers).unwrap(), "uuid:0ca0e16e");
+        headers.insert("content-type".to_string(), "text/plain".to_string());
+        assert!(multipart_boundary(&headers).is_err());
+    }
+}
@@ -50,7 +50,6 @@ impl From<imp::Error> for Error {
 #[derive(Clone)]
 pub struct Identity(imp::Identity);
 
-/*
 impl Identity {
     /// Parses a DER-formatted PKCS #12 archive, using the specified password to decrypt the key.
     ///
@@ -68,14 +67,24 @@ impl Identity {
         let identity = imp::Identity::from_pkcs12(der, password)?;
         Ok(Identity(identity))
     }
+
+    /// Parses a chain of PEM encoded X509 certificates, with the leaf certificate first.
+    /// `key` is a PEM encoded PKCS #8 formatted private key for the leaf certificate.
+    ///
+    /// The certificate chain should contain any intermediate cerficates that should be sent to
+    /// clients to allow them to build a chain to a trusted root.
+    ///
+    /// A certificate chain here means a series of PEM encoded certificates concatenated together.
+    pub fn from_pkcs8(pem: &[u8], key: &[u8]) -> Result<Identity> {
+        let identity = imp::Identity::from_pkcs8(pem, key)?;
+        Ok(Identity(identity))
+    }
 }
-*/
 
 /// An X509 certificate.
 #[derive(Clone)]
 pub struct Certificate(imp::Certificate);
 
-/*
 impl Certificate {
     /// Parses a DER-formatted X509 certificate.
     pub fn from_der(der: &[u8]) -> Result<Certificate> {
@@ -83,18 +92,22 @@ impl Certificate {
         Ok(Certificate(cert))
     }
 
-    /// Parses a PEM-formatted X509 certificate.
-    pub fn from_pem(pem: &[u8]) -> Result<Certificate> {
-        let cert = imp::Certificate::from_pem(pem)?;
-        Ok(Certificate(cert))
-    }
-
     /// Returns the DER-encoded representation of this certificate.
+    #[allow(dead_code)]
     pub fn to_der(&self) -> Result<Vec<u8>> {
         let der = self.0.to_der()?;
         Ok(der)
     }
 }
+
+/*
+impl Certificate {
+    /// Parses a PEM-formatted X509 certificate.
+    pub fn from_pem(pem: &[u8]) -> Result<Certificate> {
+        let cert = imp::Certificate::from_pem(pem)?;
+        Ok(Certificate(cert))
+    }
+}
 */
 
 /// A TLS stream which has been interrupted midway through the handshake process.
@@ -209,6 +222,8 @@ pub enum Protocol {
     Tlsv11,
     /// The TLS 1.2 protocol.
     Tlsv12,
+    /// The TLS 1.3 protocol.
+    Tlsv13,
     #[doc(hidden)]
     __NonExhaustive,
 }
@@ -226,7 +241,25 @@ pub struct TlsConnectorBuilder {
 }
 
 impl TlsConnectorBuilder {
-    /*
+    /// Adds a certificate to the set of roots that the connector will trust.
+    ///
+    /// The connector will use the system's trust root by default. This method can be used to add
+    /// to that set when communicating with servers not trusted by the system.
+    ///
+    /// Defaults to an empty set.
+    pub fn add_root_certificate(&mut self, cert: Certificate) -> &mut TlsConnectorBuilder {
+        self.root_certificates.push(cert);
+        self
+    }
+
+    /// Controls the use of built-in system certificates during certificate validation.
+    ///
+    /// Defaults to `false` -- built-in system certs will be used.
+    pub fn disable_built_in_roots(&mut self, disable: bool) -> &mut TlsConnectorBuilder {
+        self.disable_built_in_roots = disable;
+        self
+    }
+
     /// Sets the identity to be used for client certificate authentication.
     pub fn identity(&mut self, identity: Identity) -> &mut TlsConnectorBuilder {
         self.identity = Some(identity);
@@ -253,25 +286,6 @@ impl TlsConnectorBuilder {
         self
     }
 
-    /// Adds a certificate to the set of roots that the connector will trust.
-    ///
-    /// The connector will use the system's trust root by default. This method can be used to add
-    /// to that set when communicating with servers not trusted by the system.
-    ///
-    /// Defaults to an empty set.
-    pub fn add_root_certificate(&mut self, cert: Certificate) -> &mut TlsConnectorBuilder {
-        self.root_certificates.push(cert);
-        self
-    }
-
-    /// Controls the use of built-in system certificates during certificate validation.
-    ///
-    /// Defaults to `false` -- built-in system certs will be used.
-    pub fn disable_built_in_roots(&mut self, disable: bool) -> &mut TlsConnectorBuilder {
-        self.disable_built_in_roots = disable;
-        self
-    }
-
     /// Controls the use of certificate validation.
     ///
     /// Defaults to `false`.
@@ -313,7 +327,6 @@ impl TlsConnectorBuilder {
         self.accept_invalid_hostnames = accept_invalid_hostnames;
         self
     }
-    */
 
     /// Creates a new `TlsConnector`.
     pub fn build(&self) -> Result<TlsConnector> {
@@ -525,6 +538,31 @@ impl<S> TlsStream<S> {
     pub fn get_mut(&mut self) -> &mut S {
         self.0.get_mut()
     }
+    /// Returns the peer's leaf certificate, if available.
+    #[allow(dead_code)]
+    pub fn peer_certificate(&self) -> Result<Option<Certificate>> {
+        Ok(self.0.peer_certificate()?.map(Certificate))
+    }
+
+    /// Returns the peer's certificate chain, starting with its own
+    /// certificate.
+    pub fn peer_certificate_chain(&self) -> Vec<Certificate> {
+        self.0
+            .peer_certificate_chain()
+            .into_iter()
+            .map(Certificate)
+            .collect()
+    }
+
+    /// Returns the negotiated protocol version, eg. "TLSv1.3".
+    pub fn protocol_version(&self) -> &'static str {
+        self.0.protocol_version()
+    }
+
+    /// Returns the name of the negotiated cipher suite, if any.
+    pub fn cipher_name(&self) -> Option<&'static str> {
+        self.0.cipher_name()
+    }
 }
 
 /*
@@ -535,11 +573,6 @@ impl<S: io::Read + io::Write> TlsStream<S> {
         Ok(self.0.buffered_read_size()?)
     }
 
-    /// Returns the peer's leaf certificate, if available.
-    pub fn peer_certificate(&self) -> Result<Option<Certificate>> {
-        Ok(self.0.peer_certificate()?.map(Certificate))
-    }
-
     /// Returns the tls-server-end-point channel binding data as defined in [RFC 5929].
     ///
     /// [RFC 5929]: https://tools.ietf.org/html/rfc5929
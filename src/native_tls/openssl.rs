@@ -5,8 +5,8 @@ use openssl::error::ErrorStack;
 /*
 use ::openssl::hash::MessageDigest;
 use ::openssl::nid::Nid;
-use ::openssl::pkcs12::Pkcs12;
 */
+use openssl::pkcs12::Pkcs12;
 use openssl::pkey::PKey;
 use openssl::ssl::{
     self, MidHandshakeSslStream, SslAcceptor, SslConnector, SslContextBuilder, SslMethod,
@@ -37,6 +37,7 @@ fn supported_protocols(
             Protocol::Tlsv10 => SslVersion::TLS1,
             Protocol::Tlsv11 => SslVersion::TLS1_1,
             Protocol::Tlsv12 => SslVersion::TLS1_2,
+            Protocol::Tlsv13 => SslVersion::TLS1_3,
             Protocol::__NonExhaustive => unreachable!(),
         }
     }
@@ -76,10 +77,18 @@ fn supported_protocols(
                 | SslOptions::NO_TLSV1
                 | SslOptions::NO_TLSV1_1
         }
+        Some(Protocol::Tlsv13) => {
+            SslOptions::NO_SSLV2
+                | SslOptions::NO_SSLV3
+                | SslOptions::NO_TLSV1
+                | SslOptions::NO_TLSV1_1
+                | SslOptions::NO_TLSV1_2
+        }
         Some(Protocol::__NonExhaustive) => unreachable!(),
     };
     options |= match max {
-        None | Some(Protocol::Tlsv12) => SslOptions::empty(),
+        // NO_TLSV1_3 is not available in older versions of OpenSSL
+        None | Some(Protocol::Tlsv12) | Some(Protocol::Tlsv13) => SslOptions::empty(),
         Some(Protocol::Tlsv11) => SslOptions::NO_TLSV1_2,
         Some(Protocol::Tlsv10) => SslOptions::NO_TLSV1_1 | SslOptions::NO_TLSV1_2,
         Some(Protocol::Sslv3) => {
@@ -156,8 +165,10 @@ pub struct Identity {
     chain: Vec<X509>,
 }
 
-/*
 impl Identity {
+    // parse2 replaced parse in openssl 0.10.46, newer than the
+    // version required by minreq.
+    #[allow(deprecated)]
     pub fn from_pkcs12(buf: &[u8], pass: &str) -> Result<Identity, Error> {
         let pkcs12 = Pkcs12::from_der(buf)?;
         let parsed = pkcs12.parse(pass)?;
@@ -167,29 +178,37 @@ impl Identity {
             chain: parsed.chain.into_iter().flatten().collect(),
         })
     }
+
+    pub fn from_pkcs8(buf: &[u8], key: &[u8]) -> Result<Identity, Error> {
+        let pkey = PKey::private_key_from_pem(key)?;
+        let cert = X509::from_pem(buf)?;
+        let chain = X509::stack_from_pem(buf)?.into_iter().skip(1).collect();
+        Ok(Identity { pkey, cert, chain })
+    }
 }
-*/
 
 #[derive(Clone)]
 pub struct Certificate(X509);
 
-/*
 impl Certificate {
     pub fn from_der(buf: &[u8]) -> Result<Certificate, Error> {
         let cert = X509::from_der(buf)?;
         Ok(Certificate(cert))
     }
 
-    pub fn from_pem(buf: &[u8]) -> Result<Certificate, Error> {
-        let cert = X509::from_pem(buf)?;
-        Ok(Certificate(cert))
-    }
-
     pub fn to_der(&self) -> Result<Vec<u8>, Error> {
         let der = self.0.to_der()?;
         Ok(der)
     }
 }
+
+/*
+impl Certificate {
+    pub fn from_pem(buf: &[u8]) -> Result<Certificate, Error> {
+        let cert = X509::from_pem(buf)?;
+        Ok(Certificate(cert))
+    }
+}
 */
 
 pub struct MidHandshakeTlsStream<S>(MidHandshakeSslStream<S>);
@@ -369,6 +388,28 @@ impl<S> TlsStream<S> {
     pub fn get_mut(&mut self) -> &mut S {
         self.0.get_mut()
     }
+
+    pub fn peer_certificate(&self) -> Result<Option<Certificate>, Error> {
+        Ok(self.0.ssl().peer_certificate().map(Certificate))
+    }
+
+    pub fn peer_certificate_chain(&self) -> Vec<Certificate> {
+        match self.0.ssl().peer_cert_chain() {
+            Some(chain) => chain
+                .iter()
+                .map(|cert| Certificate(cert.to_owned()))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn protocol_version(&self) -> &'static str {
+        self.0.ssl().version_str()
+    }
+
+    pub fn cipher_name(&self) -> Option<&'static str> {
+        self.0.ssl().current_cipher().map(|cipher| cipher.name())
+    }
 }
 
 /*
@@ -377,10 +418,6 @@ impl<S: io::Read + io::Write> TlsStream<S> {
         Ok(self.0.ssl().pending())
     }
 
-    pub fn peer_certificate(&self) -> Result<Option<Certificate>, Error> {
-        Ok(self.0.ssl().peer_certificate().map(Certificate))
-    }
-
     pub fn tls_server_end_point(&self) -> Result<Option<Vec<u8>>, Error> {
         let cert = if self.0.ssl().is_server() {
             self.0.ssl().certificate().map(|x| x.to_owned())
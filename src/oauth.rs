@@ -0,0 +1,263 @@
+//! The OAuth 2.0 client credentials grant, enabled by the `oauth`
+//! feature.
+
+use crate::encoding::{base64_encode, percent_encode};
+use crate::{Error, Method, Request, TokenProvider, URL};
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// How long before their expiry tokens are refreshed, so that they
+/// don't expire while a request is on its way.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
+/// Gets access tokens from an OAuth 2.0 token endpoint with the client
+/// credentials grant
+/// ([RFC 6749, section 4.4](https://www.rfc-editor.org/rfc/rfc6749#section-4.4)),
+/// used for authenticating services rather than users.
+///
+/// The token is cached until 30 seconds before it expires, and shared
+/// between the clones of the `ClientCredentials`. As a
+/// [TokenProvider], it can authenticate requests and clients with
+/// [`Request::with_token_provider`](struct.Request.html#method.with_token_provider)
+/// and [`Client::with_token_provider`](struct.Client.html#method.with_token_provider).
+///
+/// The client id and secret are sent in a Basic `Authorization`
+/// header (`client_secret_basic`), unless
+/// [`with_credentials_in_body`](#method.with_credentials_in_body) is
+/// used.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), minreq::Error> {
+/// use minreq::{Client, ClientCredentials};
+///
+/// let credentials = ClientCredentials::new("https://auth.example.com/oauth/token", "my-service", "s3cr3t")
+///     .with_scope("orders:read");
+/// let client = Client::new().with_token_provider(credentials);
+/// let orders = client.get("https://api.example.com/orders").send()?;
+/// # Ok(()) }
+/// ```
+#[derive(Clone)]
+pub struct ClientCredentials {
+    token_url: URL,
+    client_id: String,
+    client_secret: String,
+    scopes: Vec<String>,
+    params: Vec<(String, String)>,
+    credentials_in_body: bool,
+    timeout: Option<u64>,
+    cache: Arc<Mutex<Option<CachedToken>>>,
+}
+
+struct CachedToken {
+    access_token: String,
+    expires_at: Option<Instant>,
+}
+
+impl ClientCredentials {
+    /// Creates a grant for getting tokens from `token_url` as the
+    /// client `client_id`, authenticated with `client_secret`.
+    pub fn new<U: Into<URL>, I: Into<String>, S: Into<String>>(
+        token_url: U,
+        client_id: I,
+        client_secret: S,
+    ) -> ClientCredentials {
+        ClientCredentials {
+            token_url: token_url.into(),
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            scopes: Vec::new(),
+            params: Vec::new(),
+            credentials_in_body: false,
+            timeout: None,
+            cache: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Adds a scope to ask for. The scopes are sent space-separated in
+    /// the `scope` parameter.
+    pub fn with_scope<T: Into<String>>(mut self, scope: T) -> ClientCredentials {
+        self.scopes.push(scope.into());
+        self
+    }
+
+    /// Adds a parameter to the token request, eg. the `audience` or
+    /// `resource` that some token endpoints require.
+    pub fn with_param<K: Into<String>, V: Into<String>>(
+        mut self,
+        name: K,
+        value: V,
+    ) -> ClientCredentials {
+        self.params.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sends the client id and secret as the `client_id` and
+    /// `client_secret` parameters of the token request
+    /// (`client_secret_post`) instead of in an `Authorization` header,
+    /// for token endpoints that only support that.
+    pub fn with_credentials_in_body(mut self) -> ClientCredentials {
+        self.credentials_in_body = true;
+        self
+    }
+
+    /// Sets the timeout of token requests in seconds.
+    pub fn with_timeout(mut self, timeout: u64) -> ClientCredentials {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Returns the cached access token, or requests a new one if there
+    /// is none or it is about to expire. Concurrent callers wait for
+    /// the same token request instead of making their own.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`StatusError`](enum.Error.html#variant.StatusError) if
+    /// the token endpoint responded with an error status, whose body
+    /// has the OAuth error, and
+    /// [`InvalidTokenResponse`](enum.Error.html#variant.InvalidTokenResponse)
+    /// if its response has no access token.
+    pub fn access_token(&self) -> Result<String, Error> {
+        let mut cache = self.lock_cache();
+        if let Some(token) = &*cache {
+            let fresh = token.expires_at.map_or(true, |expires_at| {
+                Instant::now() + EXPIRY_MARGIN < expires_at
+            });
+            if fresh {
+                return Ok(token.access_token.clone());
+            }
+        }
+        let token = self.request_token()?;
+        let access_token = token.access_token.clone();
+        *cache = Some(token);
+        Ok(access_token)
+    }
+
+    /// Forgets the cached token, so that the next request gets a new
+    /// one, eg. after a response with status 401 shows that the token
+    /// was revoked.
+    pub fn invalidate(&self) {
+        *self.lock_cache() = None;
+    }
+
+    fn lock_cache(&self) -> MutexGuard<'_, Option<CachedToken>> {
+        match self.cache.lock() {
+            Ok(cache) => cache,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    fn request_token(&self) -> Result<CachedToken, Error> {
+        let mut fields = vec![("grant_type", "client_credentials".to_string())];
+        if !self.scopes.is_empty() {
+            fields.push(("scope", self.scopes.join(" ")));
+        }
+        for (name, value) in &self.params {
+            fields.push((name.as_str(), value.clone()));
+        }
+        let mut request = Request::new(Method::Post, self.token_url.clone())
+            .with_header("Accept", "application/json");
+        if self.credentials_in_body {
+            fields.push(("client_id", self.client_id.clone()));
+            fields.push(("client_secret", self.client_secret.clone()));
+        } else {
+            // The credentials are form-urlencoded before being
+            // encoded into the header, as RFC 6749 requires.
+            let credentials = format!(
+                "{}:{}",
+                percent_encode(self.client_id.as_bytes()),
+                percent_encode(self.client_secret.as_bytes())
+            );
+            request = request.with_header(
+                "Authorization",
+                format!("Basic {}", base64_encode(credentials.as_bytes())),
+            );
+        }
+        if let Some(timeout) = self.timeout {
+            request = request.with_timeout(timeout);
+        }
+
+        let requested_at = Instant::now();
+        let response = request.with_form(&fields).send()?.error_for_status()?;
+        parse_token_response(&response.json()?, requested_at)
+    }
+}
+
+impl TokenProvider for ClientCredentials {
+    fn token(&self) -> Result<String, Error> {
+        self.access_token()
+    }
+}
+
+impl fmt::Debug for ClientCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ClientCredentials")
+            .field("token_url", &self.token_url)
+            .field("client_id", &self.client_id)
+            .field("scopes", &self.scopes)
+            .finish()
+    }
+}
+
+/// Reads the access token and its lifetime from a token response. The
+/// lifetime counts from `requested_at`, to err on the side of
+/// refreshing early.
+fn parse_token_response(
+    response: &serde_json::Value,
+    requested_at: Instant,
+) -> Result<CachedToken, Error> {
+    let access_token = response
+        .get("access_token")
+        .and_then(|token| token.as_str())
+        .filter(|token| !token.is_empty())
+        .ok_or(Error::InvalidTokenResponse)?;
+    // Some servers send the lifetime as a string.
+    let expires_in = response.get("expires_in").and_then(|expires_in| {
+        expires_in
+            .as_u64()
+            .or_else(|| expires_in.as_str()?.parse().ok())
+    });
+    Ok(CachedToken {
+        access_token: access_token.to_string(),
+        expires_at: expires_in.map(|seconds| requested_at + Duration::from_secs(seconds)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_token_response;
+    use crate::Error;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_parse_token_response() {
+        let now = Instant::now();
+        let response = serde_json::json!({
+            "access_token": "2YotnFZFEjr1zCsicMWpAA",
+            "token_type": "Bearer",
+            "expires_in": 3600,
+        });
+        let token = parse_token_response(&response, now).unwrap();
+        assert_eq!(token.access_token, "2YotnFZFEjr1zCsicMWpAA");
+        assert_eq!(token.expires_at, Some(now + Duration::from_secs(3600)));
+
+        let response = serde_json::json!({ "access_token": "abc", "expires_in": "60" });
+        let token = parse_token_response(&response, now).unwrap();
+        assert_eq!(token.expires_at, Some(now + Duration::from_secs(60)));
+
+        let response = serde_json::json!({ "access_token": "abc" });
+        assert_eq!(
+            parse_token_response(&response, now).unwrap().expires_at,
+            None
+        );
+
+        let response = serde_json::json!({ "error": "invalid_client" });
+        assert!(matches!(
+            parse_token_response(&response, now),
+            Err(Error::InvalidTokenResponse)
+        ));
+    }
+}
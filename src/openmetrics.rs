@@ -0,0 +1,320 @@
+//! Scraping of [Prometheus](https://prometheus.io/docs/instrumenting/exposition_formats/)
+//! and [OpenMetrics](https://github.com/OpenObservability/OpenMetrics/blob/main/specification/OpenMetrics.md)
+//! text exposition endpoints.
+
+use crate::{Error, Method, Request, URL};
+use std::collections::HashMap;
+
+/// The `Accept` header of scrapes, which prefers OpenMetrics like
+/// Prometheus itself does.
+const ACCEPT: &str = "application/openmetrics-text;version=1.0.0,text/plain;version=0.0.4;q=0.5";
+
+/// The type of a metric, declared by a `# TYPE` line.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MetricType {
+    /// A `counter`.
+    Counter,
+    /// A `gauge`.
+    Gauge,
+    /// A `histogram`.
+    Histogram,
+    /// A `gaugehistogram` (OpenMetrics only).
+    GaugeHistogram,
+    /// A `summary`.
+    Summary,
+    /// An `info` (OpenMetrics only).
+    Info,
+    /// A `stateset` (OpenMetrics only).
+    StateSet,
+    /// An `unknown` or `untyped` metric, or one without a `# TYPE`
+    /// line.
+    Unknown,
+}
+
+impl MetricType {
+    fn parse(name: &str) -> MetricType {
+        match name {
+            "counter" => MetricType::Counter,
+            "gauge" => MetricType::Gauge,
+            "histogram" => MetricType::Histogram,
+            "gaugehistogram" => MetricType::GaugeHistogram,
+            "summary" => MetricType::Summary,
+            "info" => MetricType::Info,
+            "stateset" => MetricType::StateSet,
+            _ => MetricType::Unknown,
+        }
+    }
+}
+
+/// A sample of a metric, eg. one line of
+/// `http_requests_total{method="get",code="200"} 1027`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct MetricSample {
+    /// The name of the sample, including suffixes like `_bucket` or
+    /// `_total`.
+    pub name: String,
+    /// The type of the metric the sample belongs to.
+    pub metric_type: MetricType,
+    /// The labels of the sample, eg. `le` for histogram buckets.
+    pub labels: HashMap<String, String>,
+    /// The value of the sample, which may be infinite or NaN.
+    pub value: f64,
+    /// The timestamp of the sample as written, if it has one: in
+    /// milliseconds in the Prometheus format, and in seconds in
+    /// OpenMetrics.
+    pub timestamp: Option<f64>,
+}
+
+impl MetricSample {
+    /// Returns the value of the label `name`, if the sample has it.
+    pub fn label(&self, name: &str) -> Option<&str> {
+        self.labels.get(name).map(|value| value.as_str())
+    }
+}
+
+/// Parses a Prometheus or OpenMetrics text exposition into its
+/// samples, in order. `# HELP` and `# UNIT` lines, and exemplars, are
+/// skipped.
+///
+/// # Errors
+///
+/// Returns [`InvalidMetrics`](enum.Error.html#variant.InvalidMetrics)
+/// with the number of the first malformed line.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> Result<(), minreq::Error> {
+/// let text = "# TYPE jobs_queued gauge\njobs_queued{queue=\"mail\"} 4\n";
+/// let samples = minreq::parse_metrics(text)?;
+/// assert_eq!(samples[0].label("queue"), Some("mail"));
+/// assert_eq!(samples[0].value, 4.0);
+/// # Ok(()) }
+/// ```
+pub fn parse_metrics(text: &str) -> Result<Vec<MetricSample>, Error> {
+    let mut types = HashMap::new();
+    let mut samples = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let invalid = || Error::InvalidMetrics { line: index + 1 };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(comment) = line.strip_prefix('#') {
+            let mut words = comment.split_whitespace();
+            match words.next() {
+                Some("EOF") => break,
+                Some("TYPE") => {
+                    let name = words.next().ok_or_else(invalid)?;
+                    let metric_type = words.next().ok_or_else(invalid)?;
+                    types.insert(name.to_string(), MetricType::parse(metric_type));
+                }
+                _ => {}
+            }
+            continue;
+        }
+        let mut sample = parse_sample(line).ok_or_else(invalid)?;
+        sample.metric_type = metric_type(&types, &sample.name);
+        samples.push(sample);
+    }
+    Ok(samples)
+}
+
+/// Returns the type of the metric family that the sample `name`
+/// belongs to, either by its name or by its name without a suffix.
+fn metric_type(types: &HashMap<String, MetricType>, name: &str) -> MetricType {
+    if let Some(metric_type) = types.get(name) {
+        return *metric_type;
+    }
+    let suffixes = [
+        "_total", "_bucket", "_count", "_sum", "_created", "_gcount", "_gsum", "_info",
+    ];
+    suffixes
+        .iter()
+        .filter_map(|suffix| name.strip_suffix(suffix))
+        .find_map(|family| types.get(family).copied())
+        .unwrap_or(MetricType::Unknown)
+}
+
+/// Parses a sample line, without its type.
+fn parse_sample(line: &str) -> Option<MetricSample> {
+    let name_end = line
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == ':'))
+        .unwrap_or(line.len());
+    let name = &line[..name_end];
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let mut rest = &line[name_end..];
+    let mut labels = HashMap::new();
+    if let Some(after) = rest.strip_prefix('{') {
+        rest = parse_labels(after, &mut labels)?;
+    }
+    // Exemplars follow a " # ".
+    let rest = match rest.find(" # ") {
+        Some(exemplar) => &rest[..exemplar],
+        None => rest,
+    };
+    if !rest.starts_with(|c: char| c.is_ascii_whitespace()) {
+        return None;
+    }
+    let mut fields = rest.split_whitespace();
+    let value = parse_value(fields.next()?)?;
+    let timestamp = match fields.next() {
+        Some(timestamp) => Some(timestamp.parse().ok()?),
+        None => None,
+    };
+    if fields.next().is_some() {
+        return None;
+    }
+    Some(MetricSample {
+        name: name.to_string(),
+        metric_type: MetricType::Unknown,
+        labels,
+        value,
+        timestamp,
+    })
+}
+
+/// Parses the labels after the `{` into `labels`, and returns the
+/// rest of the line after the `}`.
+fn parse_labels<'a>(mut rest: &'a str, labels: &mut HashMap<String, String>) -> Option<&'a str> {
+    loop {
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix('}') {
+            return Some(after);
+        }
+        let equals = rest.find('=')?;
+        let name = rest[..equals].trim();
+        rest = rest[equals + 1..].trim_start().strip_prefix('"')?;
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        let end = loop {
+            match chars.next()? {
+                (i, '"') => break i,
+                (_, '\\') => match chars.next()?.1 {
+                    'n' => value.push('\n'),
+                    c => value.push(c),
+                },
+                (_, c) => value.push(c),
+            }
+        };
+        labels.insert(name.to_string(), value);
+        rest = rest[end + 1..].trim_start();
+        if let Some(after) = rest.strip_prefix(',') {
+            rest = after;
+        } else if !rest.starts_with('}') {
+            return None;
+        }
+    }
+}
+
+fn parse_value(value: &str) -> Option<f64> {
+    match value {
+        "+Inf" | "Inf" => Some(f64::INFINITY),
+        "-Inf" => Some(f64::NEG_INFINITY),
+        "NaN" => Some(f64::NAN),
+        _ => value.parse().ok(),
+    }
+}
+
+/// Fetches the metrics exposed at `url`, eg.
+/// `http://localhost:9100/metrics`, and parses them with
+/// [parse_metrics]. OpenMetrics is asked for, and the Prometheus text
+/// format is accepted too.
+///
+/// # Errors
+///
+/// Returns [`StatusError`](enum.Error.html#variant.StatusError) if the
+/// endpoint responded with an error status, and
+/// [`InvalidMetrics`](enum.Error.html#variant.InvalidMetrics) if the
+/// response isn't a text exposition.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), minreq::Error> {
+/// let samples = minreq::scrape_metrics("http://localhost:9100/metrics")?;
+/// for sample in samples.iter().filter(|sample| sample.name == "node_load1") {
+///     println!("load: {}", sample.value);
+/// }
+/// # Ok(()) }
+/// ```
+pub fn scrape_metrics<T: Into<URL>>(url: T) -> Result<Vec<MetricSample>, Error> {
+    let response = Request::new(Method::Get, url)
+        .with_header("Accept", ACCEPT)
+        .send()?
+        .error_for_status()?;
+    parse_metrics(response.as_str()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_metrics, MetricType};
+    use crate::Error;
+
+    #[test]
+    fn test_parse_prometheus() {
+        let text = "# HELP http_requests_total The total number of HTTP requests.
+# TYPE http_requests_total counter
+http_requests_total{method=\"post\",code=\"200\"} 1027 1395066363000
+http_requests_total{method=\"post\",code=\"400\",} 3
+
+# A comment.
+msdos_file_access_time_seconds{path=\"C:\\\\DIR\\\\FILE.TXT\",error=\"Cannot find file:\\n\\\"FILE.TXT\\\"\"} 1.458255915e9
+# TYPE rpc_duration_seconds histogram
+rpc_duration_seconds_bucket{le=\"+Inf\"} 144320
+rpc_duration_seconds_sum 53423
+";
+        let samples = parse_metrics(text).unwrap();
+        assert_eq!(samples.len(), 5);
+        assert_eq!(samples[0].metric_type, MetricType::Counter);
+        assert_eq!(samples[0].label("code"), Some("200"));
+        assert_eq!(samples[0].timestamp, Some(1395066363000.0));
+        assert_eq!(samples[1].value, 3.0);
+        assert_eq!(samples[2].metric_type, MetricType::Unknown);
+        assert_eq!(samples[2].label("path"), Some("C:\\DIR\\FILE.TXT"));
+        assert_eq!(
+            samples[2].label("error"),
+            Some("Cannot find file:\n\"FILE.TXT\"")
+        );
+        assert_eq!(samples[3].metric_type, MetricType::Histogram);
+        assert_eq!(samples[3].label("le"), Some("+Inf"));
+        assert_eq!(samples[4].name, "rpc_duration_seconds_sum");
+        assert_eq!(samples[4].metric_type, MetricType::Histogram);
+    }
+
+    #[test]
+    fn test_parse_openmetrics() {
+        let text = "# TYPE foo counter
+# UNIT foo seconds
+foo_total 17.0 1520879607.789 # {trace_id=\"KOO5S4vxi0o\"} 0.67
+foo_created 1520430000.123
+# TYPE temperature gauge
+temperature -Inf
+# EOF
+";
+        let samples = parse_metrics(text).unwrap();
+        assert_eq!(samples.len(), 3);
+        assert_eq!(samples[0].metric_type, MetricType::Counter);
+        assert_eq!(samples[0].timestamp, Some(1520879607.789));
+        assert_eq!(samples[1].metric_type, MetricType::Counter);
+        assert_eq!(samples[2].value, f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_parse_invalid_metrics() {
+        for (text, line) in &[
+            ("ok 1\nnot_a_number x\n", 2),
+            ("unclosed{a=\"b\" 1\n", 1),
+            ("1starts_with_digit 1\n", 1),
+            ("missing_value\n", 1),
+            ("# TYPE\n", 1),
+        ] {
+            match parse_metrics(text) {
+                Err(Error::InvalidMetrics { line: got }) => assert_eq!(got, *line),
+                result => panic!("unexpected result for {:?}: {:?}", text, result),
+            }
+        }
+    }
+}
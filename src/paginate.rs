@@ -0,0 +1,207 @@
+use crate::{Error, Request, Response, URL};
+use std::collections::HashMap;
+use std::fmt;
+
+type NextPageFn = dyn FnMut(&Request, &Response) -> Option<Request>;
+
+/// Iterates over the pages of a paginated API, sending the request
+/// for each page only when the iterator gets to it.
+///
+/// After each page, a callback gets the page's request and response,
+/// and returns the request for the next page, or None if it was the
+/// last page. Most APIs either put a cursor in the body or in a
+/// header, or link to the next page in the `Link` header
+/// ([RFC 8288](https://www.rfc-editor.org/rfc/rfc8288)), which
+/// [`Paginator::links`] follows.
+///
+/// The iterator ends after the last page, or after the first error,
+/// which it yields. Responses with error status codes are yielded
+/// like any other page.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), minreq::Error> {
+/// let first = minreq::get("http://example.com/items");
+/// let base = first.clone();
+/// let pages = minreq::Paginator::new(first, move |_, response| {
+///     let cursor = response.headers.get("x-next-cursor")?;
+///     Some(base.clone().with_param("cursor", cursor.clone()))
+/// });
+/// for page in pages.with_max_pages(100) {
+///     println!("{}", page?.as_str()?);
+/// }
+/// # Ok(()) }
+/// ```
+pub struct Paginator {
+    next: Option<Request>,
+    next_page: Box<NextPageFn>,
+    max_pages: Option<usize>,
+    pages: usize,
+}
+
+impl Paginator {
+    /// Creates a paginator starting with `first`, which calls
+    /// `next_page` with each page's request and response to get the
+    /// request for the next page.
+    pub fn new<F>(first: Request, next_page: F) -> Paginator
+    where
+        F: FnMut(&Request, &Response) -> Option<Request> + 'static,
+    {
+        Paginator {
+            next: Some(first),
+            next_page: Box::new(next_page),
+            max_pages: None,
+            pages: 0,
+        }
+    }
+
+    /// Creates a paginator starting with `first`, which follows the
+    /// `rel="next"` links of the `Link` headers. The requests for the
+    /// next pages are copies of `first` with the linked url.
+    pub fn links(first: Request) -> Paginator {
+        Paginator::new(first, |request, response| {
+            let link = next_link(&response.headers)?;
+            let mut next = request.clone();
+            next.url = resolve_url(&request.url, &link);
+            next.params.clear();
+            Some(next)
+        })
+    }
+
+    /// Stops after `max_pages` pages, as a safeguard against APIs
+    /// that never stop returning a next page.
+    pub fn with_max_pages(mut self, max_pages: usize) -> Paginator {
+        self.max_pages = Some(max_pages);
+        self
+    }
+}
+
+impl Iterator for Paginator {
+    type Item = Result<Response, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.max_pages.map_or(false, |max| self.pages >= max) {
+            return None;
+        }
+        let request = self.next.take()?;
+        self.pages += 1;
+        match request.clone().send() {
+            Ok(response) => {
+                self.next = (self.next_page)(&request, &response);
+                Some(Ok(response))
+            }
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+impl fmt::Debug for Paginator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Paginator")
+            .field("next", &self.next)
+            .field("max_pages", &self.max_pages)
+            .field("pages", &self.pages)
+            .finish()
+    }
+}
+
+/// Returns the target of the `rel="next"` link in the `Link` header.
+fn next_link(headers: &HashMap<String, String>) -> Option<String> {
+    let mut rest = headers.get("link")?.as_str();
+    loop {
+        let start = rest.find('<')?;
+        let end = start + rest[start..].find('>')?;
+        let target = &rest[start + 1..end];
+        rest = &rest[end + 1..];
+        let params = &rest[..rest.find('<').unwrap_or(rest.len())];
+        let is_next = params.split(';').any(|param| {
+            let mut parts = param.splitn(2, '=');
+            let name = parts.next().unwrap_or("").trim();
+            let value = parts.next().unwrap_or("").trim().trim_matches('"');
+            name.eq_ignore_ascii_case("rel")
+                && value
+                    .split_whitespace()
+                    .any(|rel| rel.eq_ignore_ascii_case("next"))
+        });
+        if is_next {
+            return Some(target.trim().to_string());
+        }
+    }
+}
+
+/// Resolves a link target relative to the url it was linked from.
+fn resolve_url(base: &str, target: &str) -> URL {
+    if target.contains("://") {
+        return target.to_string();
+    }
+    let scheme_end = base.find("://").map_or(0, |i| i + 3);
+    let origin_end = base[scheme_end..]
+        .find(|c| c == '/' || c == '?' || c == '#')
+        .map_or(base.len(), |i| scheme_end + i);
+    let path_end = base[origin_end..]
+        .find(|c| c == '?' || c == '#')
+        .map_or(base.len(), |i| origin_end + i);
+    if target.starts_with("//") {
+        format!("{}{}", &base[..scheme_end.saturating_sub(2)], target)
+    } else if target.starts_with('/') {
+        format!("{}{}", &base[..origin_end], target)
+    } else if target.starts_with('?') {
+        format!("{}{}", &base[..path_end], target)
+    } else {
+        let directory_end = base[origin_end..path_end]
+            .rfind('/')
+            .map_or(origin_end, |i| origin_end + i);
+        format!("{}/{}", &base[..directory_end], target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{next_link, resolve_url};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_next_link() {
+        let mut headers = HashMap::new();
+        assert_eq!(next_link(&headers), None);
+        headers.insert(
+            "link".to_string(),
+            "<https://example.com/a?page=1>; rel=\"prev first\", \
+             <https://example.com/a?page=3,4>; title=\"x\"; REL=\"Next\""
+                .to_string(),
+        );
+        assert_eq!(
+            next_link(&headers).as_deref(),
+            Some("https://example.com/a?page=3,4")
+        );
+        headers.insert("link".to_string(), "</a?page=1>; rel=last".to_string());
+        assert_eq!(next_link(&headers), None);
+    }
+
+    #[test]
+    fn test_resolve_url() {
+        let base = "http://example.com/a/b?page=1";
+        assert_eq!(
+            resolve_url(base, "https://example.org/"),
+            "https://example.org/"
+        );
+        assert_eq!(resolve_url(base, "//example.org/c"), "http://example.org/c");
+        assert_eq!(
+            resolve_url(base, "/c?page=2"),
+            "http://example.com/c?page=2"
+        );
+        assert_eq!(
+            resolve_url(base, "?page=2"),
+            "http://example.com/a/b?page=2"
+        );
+        assert_eq!(
+            resolve_url(base, "c?page=2"),
+            "http://example.com/a/c?page=2"
+        );
+        assert_eq!(
+            resolve_url("http://example.com", "c"),
+            "http://example.com/c"
+        );
+    }
+}
@@ -0,0 +1,518 @@
+use crate::connection::{Deadline, HttpStream};
+#[cfg(feature = "proxy")]
+use crate::proxy::Proxy;
+#[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+use crate::tls::SharedTlsConfig;
+use crate::Error;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::net::{SocketAddr, TcpStream};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, Weak};
+use std::time::{Duration, Instant};
+
+/// How many idle connections are kept per host by default.
+pub(crate) const DEFAULT_MAX_IDLE_PER_HOST: usize = 8;
+
+/// How long idle connections are kept by default.
+pub(crate) const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// How many unread body bytes are drained by default from dropped
+/// responses, to keep their connections.
+pub(crate) const DEFAULT_MAX_DRAIN_BYTES: usize = 64 * 1024;
+
+/// The settings of a [ConnectionPool], set with the methods of
+/// [Client](crate::Client).
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct PoolConfig {
+    pub(crate) max_idle_per_host: usize,
+    pub(crate) idle_timeout: Duration,
+    pub(crate) max_per_host: Option<usize>,
+    /// How long a request may wait for a connection when
+    /// `max_per_host` is reached, in addition to its own timeout.
+    pub(crate) acquire_timeout: Option<Duration>,
+    /// How often idle connections are checked in the background, if
+    /// at all.
+    pub(crate) reap_interval: Option<Duration>,
+    /// How many unread body bytes are read from a dropped response
+    /// to put its connection back into the pool.
+    pub(crate) max_drain_bytes: usize,
+}
+
+impl Default for PoolConfig {
+    fn default() -> PoolConfig {
+        PoolConfig {
+            max_idle_per_host: DEFAULT_MAX_IDLE_PER_HOST,
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+            max_per_host: None,
+            acquire_timeout: None,
+            reap_interval: None,
+            max_drain_bytes: DEFAULT_MAX_DRAIN_BYTES,
+        }
+    }
+}
+
+/// What pooled connections are keyed by: connections are only reused
+/// for requests with the same scheme, host and port, which connect
+/// the same way (to the same addresses, through the same proxy and
+/// with the same TLS configuration).
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub(crate) struct PoolKey {
+    https: bool,
+    host: String,
+    port: u32,
+    socket_addrs: Vec<SocketAddr>,
+    #[cfg(feature = "proxy")]
+    proxy: Option<Proxy>,
+    #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+    tls_server_name: Option<String>,
+    /// The key holds on to the configuration, so that another one
+    /// can't be allocated at the same address while it's in use.
+    #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+    tls: Option<SharedTlsConfig>,
+}
+
+impl PoolKey {
+    pub(crate) fn new(https: bool, host: &str, port: u32) -> PoolKey {
+        PoolKey {
+            https,
+            host: host.to_string(),
+            port,
+            socket_addrs: Vec::new(),
+            #[cfg(feature = "proxy")]
+            proxy: None,
+            #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+            tls_server_name: None,
+            #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+            tls: None,
+        }
+    }
+
+    /// Sets the addresses connected to instead of the host's, if any.
+    pub(crate) fn with_socket_addrs(mut self, addrs: &[SocketAddr]) -> PoolKey {
+        self.socket_addrs = addrs.to_vec();
+        self
+    }
+
+    /// Sets the proxy the connection goes through.
+    #[cfg(feature = "proxy")]
+    pub(crate) fn with_proxy(mut self, proxy: Option<&Proxy>) -> PoolKey {
+        self.proxy = proxy.cloned();
+        self
+    }
+
+    /// Sets the name the TLS session was set up with, if it isn't the
+    /// host.
+    #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+    pub(crate) fn with_tls_server_name(mut self, name: Option<&str>) -> PoolKey {
+        self.tls_server_name = name.map(str::to_string);
+        self
+    }
+
+    /// Sets the TLS configuration the connection was made with, which
+    /// is compared by identity.
+    #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+    pub(crate) fn with_tls_config(mut self, tls: Option<&SharedTlsConfig>) -> PoolKey {
+        self.tls = tls.cloned();
+        self
+    }
+}
+
+struct IdleConnection {
+    stream: HttpStream,
+    since: Instant,
+}
+
+/// The order in which requests waiting for a connection to the same
+/// host are served, when the number of connections per host is
+/// limited with
+/// [`Client::with_max_connections_per_host`](struct.Client.html#method.with_max_connections_per_host).
+/// Requests with the same priority are served in the order they
+/// started waiting.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Priority {
+    /// Served after all the other requests, eg. for bulk downloads.
+    Low,
+    /// The default priority.
+    Normal,
+    /// Served before all the other requests, eg. for requests a user
+    /// is waiting on.
+    High,
+}
+
+impl Default for Priority {
+    fn default() -> Priority {
+        Priority::Normal
+    }
+}
+
+/// The connections in use for a host, and the requests waiting for
+/// one, as (priority, ticket) pairs. Tickets are handed out in
+/// increasing order, so the lowest one has waited the longest.
+#[derive(Default)]
+struct HostQueue {
+    active: usize,
+    waiting: Vec<(Priority, u64)>,
+    next_ticket: u64,
+}
+
+impl HostQueue {
+    /// Returns the ticket of the waiter to be served next.
+    fn next_served(&self) -> Option<u64> {
+        let (_, ticket) = self
+            .waiting
+            .iter()
+            .max_by_key(|(priority, ticket)| (*priority, std::cmp::Reverse(*ticket)))?;
+        Some(*ticket)
+    }
+}
+
+struct Pool {
+    config: PoolConfig,
+    idle: Mutex<HashMap<PoolKey, Vec<IdleConnection>>>,
+    hosts: Mutex<HashMap<PoolKey, HostQueue>>,
+    released: Condvar,
+}
+
+/// The keep-alive connections of a [Client](crate::Client), shared
+/// between its clones and the requests created with it.
+#[derive(Clone)]
+pub(crate) struct ConnectionPool(Arc<Pool>);
+
+impl ConnectionPool {
+    pub(crate) fn new(config: PoolConfig) -> ConnectionPool {
+        let pool = ConnectionPool(Arc::new(Pool {
+            config,
+            idle: Mutex::new(HashMap::new()),
+            hosts: Mutex::new(HashMap::new()),
+            released: Condvar::new(),
+        }));
+        if let Some(interval) = config.reap_interval {
+            let weak = Arc::downgrade(&pool.0);
+            let spawned = std::thread::Builder::new()
+                .name("minreq-pool-reaper".to_string())
+                .spawn(move || reap_until_dropped(weak, interval));
+            if let Err(err) = spawned {
+                log::warn!("Could not start the connection pool reaper: {}", err);
+            }
+        }
+        pool
+    }
+
+    pub(crate) fn config(&self) -> PoolConfig {
+        self.0.config
+    }
+
+    fn idle(&self) -> MutexGuard<'_, HashMap<PoolKey, Vec<IdleConnection>>> {
+        match self.0.idle.lock() {
+            Ok(idle) => idle,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    fn hosts(&self) -> MutexGuard<'_, HashMap<PoolKey, HostQueue>> {
+        match self.0.hosts.lock() {
+            Ok(hosts) => hosts,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// Waits until a connection to `key` may be used, if the number
+    /// of connections per host is limited. The connection may be used
+    /// until the returned permit is dropped. Waiters are served by
+    /// `priority`, and then in the order they started waiting.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PoolTimeout`](Error::PoolTimeout) if `deadline`, or
+    /// the acquire timeout of the pool, passes while waiting.
+    pub(crate) fn acquire(
+        &self,
+        key: &PoolKey,
+        priority: Priority,
+        deadline: &Deadline,
+    ) -> Result<Option<ConnectionPermit>, Error> {
+        let max = match self.0.config.max_per_host {
+            Some(max) => max.max(1),
+            None => return Ok(None),
+        };
+        let deadline = deadline.limited_to(self.0.config.acquire_timeout);
+        let mut hosts = self.hosts();
+        let queue = hosts.entry(key.clone()).or_default();
+        let ticket = queue.next_ticket;
+        queue.next_ticket += 1;
+        queue.waiting.push((priority, ticket));
+        loop {
+            let queue = hosts.get_mut(key).unwrap();
+            if queue.active < max && queue.next_served() == Some(ticket) {
+                queue.waiting.retain(|(_, waiter)| *waiter != ticket);
+                queue.active += 1;
+                // The next waiter may be able to go as well.
+                self.0.released.notify_all();
+                return Ok(Some(ConnectionPermit {
+                    pool: self.clone(),
+                    key: key.clone(),
+                }));
+            }
+            let remaining = match deadline.remaining() {
+                Ok(remaining) => remaining,
+                Err(_) => {
+                    queue.waiting.retain(|(_, waiter)| *waiter != ticket);
+                    self.0.released.notify_all();
+                    log::debug!("Timed out waiting for a connection to {}.", key.host);
+                    return Err(Error::PoolTimeout);
+                }
+            };
+            hosts = match remaining {
+                Some(remaining) => match self.0.released.wait_timeout(hosts, remaining) {
+                    Ok((hosts, _)) => hosts,
+                    Err(poisoned) => poisoned.into_inner().0,
+                },
+                None => match self.0.released.wait(hosts) {
+                    Ok(hosts) => hosts,
+                    Err(poisoned) => poisoned.into_inner(),
+                },
+            };
+        }
+    }
+
+    /// Takes the most recently used idle connection for `key`,
+    /// skipping (and closing) the ones that have expired or been
+    /// closed by the server in the meantime.
+    pub(crate) fn take(&self, key: &PoolKey) -> Option<HttpStream> {
+        let mut idle = self.idle();
+        let connections = idle.get_mut(key)?;
+        while let Some(connection) = connections.pop() {
+            if connection.since.elapsed() < self.0.config.idle_timeout
+                && is_open(connection.stream.tcp())
+            {
+                return Some(connection.stream);
+            }
+        }
+        None
+    }
+
+    /// Puts a connection, which has finished its exchange, back into
+    /// the pool. The oldest idle connection for `key` is closed if
+    /// there are too many.
+    pub(crate) fn put(&self, key: PoolKey, stream: HttpStream) {
+        let config = self.0.config;
+        if config.max_idle_per_host == 0 {
+            return;
+        }
+        let mut idle = self.idle();
+        let connections = idle.entry(key).or_default();
+        connections.retain(|connection| connection.since.elapsed() < config.idle_timeout);
+        if connections.len() >= config.max_idle_per_host {
+            connections.remove(0);
+        }
+        connections.push(IdleConnection {
+            stream,
+            since: Instant::now(),
+        });
+    }
+
+    /// Closes the idle connections which have expired or been closed
+    /// by the server.
+    pub(crate) fn reap(&self) {
+        let idle_timeout = self.0.config.idle_timeout;
+        let mut idle = self.idle();
+        for connections in idle.values_mut() {
+            connections.retain(|connection| {
+                connection.since.elapsed() < idle_timeout && is_open(connection.stream.tcp())
+            });
+        }
+        idle.retain(|_, connections| !connections.is_empty());
+    }
+
+    /// Closes all idle connections.
+    #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+    pub(crate) fn clear(&self) {
+        self.idle().clear();
+    }
+}
+
+impl Default for ConnectionPool {
+    fn default() -> ConnectionPool {
+        ConnectionPool::new(PoolConfig::default())
+    }
+}
+
+impl PartialEq for ConnectionPool {
+    fn eq(&self, other: &ConnectionPool) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for ConnectionPool {}
+
+impl fmt::Debug for ConnectionPool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ConnectionPool")
+    }
+}
+
+/// Where a response's connection goes back to once its body has been
+/// read.
+pub(crate) struct PoolSlot {
+    pool: ConnectionPool,
+    key: PoolKey,
+}
+
+impl PoolSlot {
+    pub(crate) fn new(pool: ConnectionPool, key: PoolKey) -> PoolSlot {
+        PoolSlot { pool, key }
+    }
+
+    pub(crate) fn put(self, stream: HttpStream) {
+        self.pool.put(self.key, stream);
+    }
+
+    pub(crate) fn max_drain_bytes(&self) -> usize {
+        self.pool.0.config.max_drain_bytes
+    }
+}
+
+/// Allows using a connection to a host while the number of
+/// connections per host is limited, see [ConnectionPool::acquire].
+/// The next waiting request is let through when this is dropped.
+pub(crate) struct ConnectionPermit {
+    pool: ConnectionPool,
+    key: PoolKey,
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        let mut hosts = self.pool.hosts();
+        if let Some(queue) = hosts.get_mut(&self.key) {
+            queue.active -= 1;
+            if queue.active == 0 && queue.waiting.is_empty() {
+                hosts.remove(&self.key);
+            }
+        }
+        self.pool.0.released.notify_all();
+    }
+}
+
+/// Reaps the idle connections of the pool every `interval`, until
+/// the pool has been dropped.
+fn reap_until_dropped(pool: Weak<Pool>, interval: Duration) {
+    loop {
+        std::thread::sleep(interval);
+        match pool.upgrade() {
+            Some(pool) => ConnectionPool(pool).reap(),
+            None => return,
+        }
+    }
+}
+
+/// Returns true if the server hasn't closed the idle connection
+/// `tcp`. An idle connection should have nothing to read, so any
+/// data (or the end of the stream) means it can't be reused.
+fn is_open(tcp: &TcpStream) -> bool {
+    if tcp.set_nonblocking(true).is_err() {
+        return false;
+    }
+    let open = match tcp.peek(&mut [0]) {
+        Err(err) => err.kind() == io::ErrorKind::WouldBlock,
+        Ok(_) => false,
+    };
+    tcp.set_nonblocking(false).is_ok() && open
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_open, ConnectionPool, PoolConfig, PoolKey, Priority};
+    use crate::connection::Deadline;
+    use crate::Error;
+    use std::io::Write;
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_is_open() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut server, _) = listener.accept().unwrap();
+        assert!(is_open(&client));
+
+        server.write_all(b"x").unwrap();
+        server.flush().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert!(!is_open(&client));
+
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        drop(server);
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert!(!is_open(&client));
+    }
+
+    #[test]
+    fn test_acquire_order() {
+        let pool = ConnectionPool::new(PoolConfig {
+            max_per_host: Some(1),
+            ..PoolConfig::default()
+        });
+        let key = PoolKey::new(false, "example.com", 80);
+        let deadline = Deadline::new(None, Default::default());
+        let permit = pool.acquire(&key, Priority::Normal, &deadline).unwrap();
+        assert!(permit.is_some());
+
+        let expired = Deadline::new(Some(Duration::from_millis(20)), Default::default());
+        assert!(matches!(
+            pool.acquire(&key, Priority::High, &expired),
+            Err(Error::PoolTimeout)
+        ));
+
+        let (sender, receiver) = mpsc::channel();
+        let mut waiters = Vec::new();
+        for (i, priority) in [
+            Priority::Low,
+            Priority::Normal,
+            Priority::Normal,
+            Priority::High,
+        ]
+        .iter()
+        .enumerate()
+        {
+            let (waiter_pool, waiter_key) = (pool.clone(), key.clone());
+            let (sender, deadline, priority) = (sender.clone(), deadline.clone(), *priority);
+            waiters.push(std::thread::spawn(move || {
+                let permit = waiter_pool
+                    .acquire(&waiter_key, priority, &deadline)
+                    .unwrap();
+                sender.send(i).unwrap();
+                drop(permit);
+            }));
+            // Make sure the waiters start waiting in order.
+            while pool.hosts()[&key].waiting.len() < i + 1 {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        }
+        drop(permit);
+        for waiter in waiters {
+            waiter.join().unwrap();
+        }
+        let order: Vec<usize> = receiver.try_iter().collect();
+        assert_eq!(order, vec![3, 1, 2, 0]);
+        assert!(pool.hosts().is_empty());
+    }
+
+    #[test]
+    fn test_acquire_timeout() {
+        let pool = ConnectionPool::new(PoolConfig {
+            max_per_host: Some(1),
+            acquire_timeout: Some(Duration::from_millis(20)),
+            ..PoolConfig::default()
+        });
+        let key = PoolKey::new(false, "example.com", 80);
+        let deadline = Deadline::new(None, Default::default());
+        let _permit = pool.acquire(&key, Priority::Normal, &deadline).unwrap();
+        assert!(matches!(
+            pool.acquire(&key, Priority::Normal, &deadline),
+            Err(Error::PoolTimeout)
+        ));
+    }
+}
@@ -0,0 +1,139 @@
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// The media type of Problem Details documents.
+const PROBLEM_JSON: &str = "application/problem+json";
+
+/// A Problem Details object ([RFC 9457](https://www.rfc-editor.org/rfc/rfc9457)),
+/// the JSON error format most HTTP APIs use. See
+/// [`Response::problem_details`](struct.Response.html#method.problem_details).
+///
+/// As the RFC requires, members with the wrong type are ignored, as
+/// if they were missing.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), minreq::Error> {
+/// let response = minreq::post("http://example.com/orders").send()?;
+/// if let Some(problem) = response.problem_details() {
+///     println!("{}: {}", problem.title.unwrap_or_default(), problem.detail.unwrap_or_default());
+///     if let Some(balance) = problem.extensions.get("balance") {
+///         println!("Balance: {}", balance);
+///     }
+/// }
+/// # Ok(()) }
+/// ```
+#[derive(Clone, PartialEq, Debug)]
+pub struct ProblemDetails {
+    /// A URI identifying the problem type, `about:blank` if the
+    /// problem has none.
+    pub problem_type: String,
+    /// A short, human-readable summary of the problem type.
+    pub title: Option<String>,
+    /// The status code the server generated for this occurrence of
+    /// the problem.
+    pub status: Option<i32>,
+    /// A human-readable explanation of this occurrence of the
+    /// problem.
+    pub detail: Option<String>,
+    /// A URI identifying this occurrence of the problem.
+    pub instance: Option<String>,
+    /// The other members of the object, which are specific to the
+    /// problem type.
+    pub extensions: Map<String, Value>,
+}
+
+impl ProblemDetails {
+    /// Parses a Problem Details object, returning None if `json` is
+    /// not a JSON object.
+    pub fn parse(json: &[u8]) -> Option<ProblemDetails> {
+        let mut object = match serde_json::from_slice(json) {
+            Ok(Value::Object(object)) => object,
+            _ => return None,
+        };
+        let mut string = |name: &str| match object.remove(name) {
+            Some(Value::String(value)) => Some(value),
+            _ => None,
+        };
+        let problem_type = string("type").unwrap_or_else(|| "about:blank".to_string());
+        let title = string("title");
+        let detail = string("detail");
+        let instance = string("instance");
+        let status = match object.remove("status") {
+            Some(Value::Number(status)) => status
+                .as_i64()
+                .filter(|status| (100..=599).contains(status))
+                .map(|status| status as i32),
+            _ => None,
+        };
+        Some(ProblemDetails {
+            problem_type,
+            title,
+            status,
+            detail,
+            instance,
+            extensions: object,
+        })
+    }
+}
+
+/// Returns the Problem Details in `body`, if the `Content-Type` says
+/// it's one.
+pub(crate) fn problem_details(
+    headers: &HashMap<String, String>,
+    body: &[u8],
+) -> Option<ProblemDetails> {
+    let content_type = headers.get("content-type")?;
+    let media_type = content_type.split(';').next().unwrap_or("").trim();
+    if media_type.eq_ignore_ascii_case(PROBLEM_JSON) {
+        ProblemDetails::parse(body)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{problem_details, ProblemDetails};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_problem_details() {
+        let body = br#"{
+            "type": "https://example.com/probs/out-of-credit",
+            "title": "You do not have enough credit.",
+            "status": 403,
+            "detail": "Your current balance is 30, but that costs 50.",
+            "instance": 7,
+            "balance": 30
+        }"#;
+        let problem = ProblemDetails::parse(body).unwrap();
+        assert_eq!(
+            problem.problem_type,
+            "https://example.com/probs/out-of-credit"
+        );
+        assert_eq!(
+            problem.title.as_deref(),
+            Some("You do not have enough credit.")
+        );
+        assert_eq!(problem.status, Some(403));
+        assert!(problem.detail.unwrap().starts_with("Your current balance"));
+        assert_eq!(problem.instance, None);
+        assert_eq!(problem.extensions.len(), 1);
+        assert_eq!(problem.extensions["balance"], 30);
+
+        let problem = ProblemDetails::parse(b"{\"status\": \"403\"}").unwrap();
+        assert_eq!(problem.problem_type, "about:blank");
+        assert_eq!(problem.status, None);
+        assert!(ProblemDetails::parse(b"[]").is_none());
+
+        let mut headers = HashMap::new();
+        assert!(problem_details(&headers, b"{}").is_none());
+        headers.insert(
+            "content-type".to_string(),
+            "Application/Problem+JSON; charset=utf-8".to_string(),
+        );
+        assert!(problem_details(&headers, b"{}").is_some());
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// A source of the random numbers used for generated values, like
+/// request IDs and trace span IDs.
+///
+/// By default, [SystemRandom] is used. A custom source can be set
+/// with [`Client::with_random_source`](struct.Client.html#method.with_random_source),
+/// eg. a seeded generator for deterministic tests, or one backed by
+/// the platform's generator in environments where the default isn't
+/// suitable.
+pub trait RandomSource: Send + Sync {
+    /// Returns a random number.
+    fn next_u64(&self) -> u64;
+}
+
+/// The default [RandomSource], good enough for IDs but not for
+/// cryptography. The standard library's randomly seeded hasher is
+/// used to avoid a dependency on a random number generator.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct SystemRandom;
+
+impl RandomSource for SystemRandom {
+    fn next_u64(&self) -> u64 {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let mut hasher = RandomState::new().build_hasher();
+        COUNTER.fetch_add(1, Ordering::Relaxed).hash(&mut hasher);
+        SystemTime::now().hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// A shared reference to a [RandomSource], comparable by identity so
+/// that it can live in [Client](crate::Client).
+#[derive(Clone)]
+pub(crate) struct RandomRef(pub(crate) Arc<dyn RandomSource>);
+
+impl RandomRef {
+    pub(crate) fn next_u64(&self) -> u64 {
+        self.0.next_u64()
+    }
+
+    /// Returns a random (version 4) UUID, eg.
+    /// `f81d4fae-7dec-41d0-a765-00a0c91e6bf6`.
+    pub(crate) fn uuid(&self) -> String {
+        let high = self.next_u64() & !0xF000 | 0x4000;
+        let low = self.next_u64() & !(0xC << 60) | 0x8 << 60;
+        format!(
+            "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+            high >> 32,
+            high >> 16 & 0xFFFF,
+            high & 0xFFFF,
+            low >> 48,
+            low & 0xFFFF_FFFF_FFFF
+        )
+    }
+}
+
+impl Default for RandomRef {
+    fn default() -> RandomRef {
+        RandomRef(Arc::new(SystemRandom))
+    }
+}
+
+impl PartialEq for RandomRef {
+    fn eq(&self, other: &RandomRef) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for RandomRef {}
+
+impl fmt::Debug for RandomRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RandomRef")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RandomRef, RandomSource};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    struct Counter(AtomicU64);
+
+    impl RandomSource for Counter {
+        fn next_u64(&self) -> u64 {
+            self.0.fetch_add(1, Ordering::Relaxed)
+        }
+    }
+
+    #[test]
+    fn test_uuid() {
+        let random = RandomRef::default();
+        let uuid = random.uuid();
+        assert_eq!(uuid.len(), 36);
+        assert_eq!(&uuid[14..15], "4");
+        assert!("89ab".contains(&uuid[19..20]));
+        assert_ne!(uuid, random.uuid());
+
+        let random = RandomRef(Arc::new(Counter(AtomicU64::new(0))));
+        assert_eq!(random.uuid(), "00000000-0000-4000-8000-000000000001");
+    }
+
+    #[test]
+    fn test_client_random_source() {
+        let client = crate::Client::new()
+            .with_request_ids()
+            .with_trace_context(crate::TraceContext::new())
+            .with_random_source(Counter(AtomicU64::new(7)));
+        let request = client.get("http://example.com");
+        assert_eq!(
+            request.headers["X-Request-Id"],
+            "00000000-0000-4007-8000-000000000008"
+        );
+        assert!(request.headers["traceparent"].ends_with("-0000000000000009-01"));
+    }
+}
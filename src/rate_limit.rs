@@ -0,0 +1,306 @@
+use crate::{BareItem, StructuredDictionary, StructuredList};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// `X-RateLimit-Reset` values at least this large are Unix timestamps
+/// (like GitHub's) rather than delays in seconds.
+const MIN_RESET_TIMESTAMP: u64 = 1_000_000_000;
+
+/// The rate limit quota of the server, from the `RateLimit` header
+/// ([draft-ietf-httpapi-ratelimit-headers]), the older
+/// `RateLimit-Limit`, `RateLimit-Remaining` and `RateLimit-Reset`
+/// headers, or the GitHub-style `X-RateLimit-*` headers. See
+/// [`Response::rate_limit`](struct.Response.html#method.rate_limit).
+///
+/// [draft-ietf-httpapi-ratelimit-headers]: https://datatracker.ietf.org/doc/draft-ietf-httpapi-ratelimit-headers/
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct RateLimit {
+    /// The amount of requests allowed in the time window.
+    pub limit: Option<u64>,
+    /// The amount of requests left in the current time window.
+    pub remaining: Option<u64>,
+    /// How long until the quota resets, as of when this was parsed.
+    pub reset: Option<Duration>,
+    parsed_at: Instant,
+}
+
+impl RateLimit {
+    /// Parses the rate limit headers, returning None if there aren't
+    /// any. The standard headers take precedence over the `X-` ones.
+    pub(crate) fn from_headers(headers: &HashMap<String, String>) -> Option<RateLimit> {
+        let mut rate_limit = RateLimit {
+            limit: None,
+            remaining: None,
+            reset: None,
+            parsed_at: Instant::now(),
+        };
+        if let Some(header) = headers.get("ratelimit") {
+            rate_limit.merge_structured(header, headers.get("ratelimit-policy"));
+        }
+        let integer = |name: &str| headers.get(name).and_then(|value| leading_integer(value));
+        rate_limit.limit = rate_limit
+            .limit
+            .or_else(|| integer("ratelimit-limit"))
+            .or_else(|| integer("x-ratelimit-limit"));
+        rate_limit.remaining = rate_limit
+            .remaining
+            .or_else(|| integer("ratelimit-remaining"))
+            .or_else(|| integer("x-ratelimit-remaining"));
+        rate_limit.reset = rate_limit
+            .reset
+            .or_else(|| integer("ratelimit-reset").map(Duration::from_secs))
+            .or_else(|| integer("x-ratelimit-reset").and_then(reset_delay));
+
+        if rate_limit.limit.is_none()
+            && rate_limit.remaining.is_none()
+            && rate_limit.reset.is_none()
+        {
+            None
+        } else {
+            Some(rate_limit)
+        }
+    }
+
+    /// Reads the `RateLimit` header, either in the dictionary form of
+    /// the earlier drafts (`limit=100, remaining=50, reset=30`) or the
+    /// list form of the later ones (`"default";r=50;t=30`, with the
+    /// limit in `RateLimit-Policy: "default";q=100;w=60`).
+    fn merge_structured(&mut self, header: &str, policy: Option<&String>) {
+        let integer = |item: Option<&BareItem>| {
+            item.and_then(BareItem::as_integer)
+                .filter(|&value| value >= 0)
+                .map(|value| value as u64)
+        };
+        if let Ok(dictionary) = StructuredDictionary::parse(header) {
+            let value = |key| {
+                integer(
+                    dictionary
+                        .get(key)
+                        .and_then(|member| member.as_item())
+                        .map(|item| &item.value),
+                )
+            };
+            self.limit = value("limit");
+            self.remaining = value("remaining");
+            self.reset = value("reset").map(Duration::from_secs);
+        } else if let Ok(list) = StructuredList::parse(header) {
+            if let Some(member) = list.members.first() {
+                self.remaining = integer(member.param("r"));
+                self.reset = integer(member.param("t")).map(Duration::from_secs);
+            }
+            let policy = policy.and_then(|policy| StructuredList::parse(policy).ok());
+            if let Some(member) = policy.as_ref().and_then(|policy| policy.members.first()) {
+                self.limit = integer(member.param("q"));
+            }
+        }
+    }
+
+    /// Returns true if there are no requests left in the current time
+    /// window.
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining == Some(0)
+    }
+
+    /// Returns when the quota resets, if the server said so (and the
+    /// time can be represented).
+    pub fn reset_at(&self) -> Option<Instant> {
+        self.reset
+            .and_then(|reset| self.parsed_at.checked_add(reset))
+    }
+
+    /// Sleeps until the quota resets if it's exhausted, and returns
+    /// how long it slept. Doesn't sleep if the server didn't say when
+    /// the quota resets.
+    pub fn wait_if_exhausted(&self) -> Duration {
+        if !self.is_exhausted() {
+            return Duration::from_secs(0);
+        }
+        let wait = self
+            .reset_at()
+            .and_then(|reset_at| reset_at.checked_duration_since(Instant::now()))
+            .unwrap_or_default();
+        if wait > Duration::from_secs(0) {
+            log::debug!("Rate limit exhausted, waiting {:?}.", wait);
+            thread::sleep(wait);
+        }
+        wait
+    }
+}
+
+/// A rate limiter that can be shared between requests (and
+/// [Client](crate::Client)s) by cloning it. It follows the rate limit
+/// headers of the responses: when the server says the quota is
+/// exhausted, later requests wait until it resets before they are
+/// sent.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), minreq::Error> {
+/// let client = minreq::Client::new().with_rate_limiter(minreq::RateLimiter::new());
+/// for page in 1..=10 {
+///     // Waits for the quota to reset if the previous response
+///     // exhausted it.
+///     client.get(format!("http://example.com/items?page={}", page)).send()?;
+/// }
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Default)]
+pub struct RateLimiter {
+    blocked_until: Arc<Mutex<Option<Instant>>>,
+}
+
+impl RateLimiter {
+    /// Creates a new rate limiter, which doesn't block requests until
+    /// it sees an exhausted quota.
+    pub fn new() -> RateLimiter {
+        RateLimiter::default()
+    }
+
+    fn blocked_until(&self) -> MutexGuard<'_, Option<Instant>> {
+        match self.blocked_until.lock() {
+            Ok(blocked_until) => blocked_until,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// Sleeps until requests are allowed again.
+    pub(crate) fn wait(&self) {
+        let blocked_until = *self.blocked_until();
+        let wait = blocked_until.and_then(|until| until.checked_duration_since(Instant::now()));
+        if let Some(wait) = wait {
+            log::debug!("Rate limit exhausted, waiting {:?}.", wait);
+            thread::sleep(wait);
+        }
+    }
+
+    /// Updates the limiter from the rate limit headers of a response.
+    pub(crate) fn update(&self, headers: &HashMap<String, String>) {
+        if let Some(rate_limit) = RateLimit::from_headers(headers) {
+            *self.blocked_until() = if rate_limit.is_exhausted() {
+                rate_limit.reset_at()
+            } else {
+                None
+            };
+        }
+    }
+}
+
+impl PartialEq for RateLimiter {
+    fn eq(&self, other: &RateLimiter) -> bool {
+        Arc::ptr_eq(&self.blocked_until, &other.blocked_until)
+    }
+}
+
+impl Eq for RateLimiter {}
+
+impl fmt::Debug for RateLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RateLimiter")
+    }
+}
+
+/// Parses the integer at the start of `value`, ignoring the quota
+/// policies some servers append, eg. `100, 100;w=60`.
+fn leading_integer(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let end = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    value[..end].parse().ok()
+}
+
+/// Returns the delay until an `X-RateLimit-Reset` time, which is
+/// either a Unix timestamp or a delay in seconds, or None if the
+/// timestamp is too far in the future to be represented.
+fn reset_delay(reset: u64) -> Option<Duration> {
+    if reset < MIN_RESET_TIMESTAMP {
+        return Some(Duration::from_secs(reset));
+    }
+    let reset = UNIX_EPOCH.checked_add(Duration::from_secs(reset))?;
+    Some(reset.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RateLimit;
+    use std::collections::HashMap;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    fn rate_limit(headers: &[(&str, &str)]) -> Option<RateLimit> {
+        let headers: HashMap<String, String> = headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        RateLimit::from_headers(&headers)
+    }
+
+    #[test]
+    fn test_rate_limit_headers() {
+        assert_eq!(rate_limit(&[("content-type", "text/plain")]), None);
+
+        let limit = rate_limit(&[("ratelimit", "limit=100, remaining=0, reset=30")]).unwrap();
+        assert_eq!(limit.limit, Some(100));
+        assert!(limit.is_exhausted());
+        assert_eq!(limit.reset, Some(Duration::from_secs(30)));
+
+        let limit = rate_limit(&[
+            ("ratelimit", "\"default\";r=50;t=30"),
+            ("ratelimit-policy", "\"default\";q=100;w=60"),
+        ])
+        .unwrap();
+        assert_eq!(limit.limit, Some(100));
+        assert_eq!(limit.remaining, Some(50));
+        assert_eq!(limit.reset, Some(Duration::from_secs(30)));
+
+        let limit = rate_limit(&[
+            ("ratelimit-limit", "100, 100;w=60"),
+            ("ratelimit-remaining", "99"),
+            ("ratelimit-reset", "5"),
+            ("x-ratelimit-remaining", "1"),
+        ])
+        .unwrap();
+        assert_eq!(limit.limit, Some(100));
+        assert_eq!(limit.remaining, Some(99));
+        assert_eq!(limit.reset, Some(Duration::from_secs(5)));
+
+        // Resets too far in the future to be represented are unknown.
+        let limit = rate_limit(&[
+            ("ratelimit-remaining", "0"),
+            ("ratelimit-reset", "18446744073709551615"),
+        ])
+        .unwrap();
+        assert_eq!(limit.reset_at(), None);
+        assert_eq!(limit.wait_if_exhausted(), Duration::from_secs(0));
+        let limit = rate_limit(&[
+            ("x-ratelimit-remaining", "0"),
+            ("x-ratelimit-reset", "18446744073709551615"),
+        ])
+        .unwrap();
+        assert_eq!(limit.reset, None);
+    }
+
+    #[test]
+    fn test_github_rate_limit_headers() {
+        let reset = SystemTime::now() + Duration::from_secs(120);
+        let reset = reset
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            .to_string();
+        let limit = rate_limit(&[
+            ("x-ratelimit-limit", "5000"),
+            ("x-ratelimit-remaining", "4999"),
+            ("x-ratelimit-reset", &reset),
+        ])
+        .unwrap();
+        assert_eq!(limit.limit, Some(5000));
+        assert_eq!(limit.remaining, Some(4999));
+        let delay = limit.reset.unwrap();
+        assert!(delay > Duration::from_secs(110) && delay <= Duration::from_secs(120));
+        assert_eq!(limit.wait_if_exhausted(), Duration::from_secs(0));
+    }
+}
@@ -0,0 +1,237 @@
+use crate::{Error, Request};
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// A reader over a remote file, which downloads only the parts that
+/// are read, with HTTP range requests.
+///
+/// The file is downloaded in blocks, by default of 64 KiB. When the
+/// reads continue from where the previous block ended, more blocks are
+/// downloaded at once (see [`with_read_ahead`](#method.with_read_ahead)),
+/// so that reading the file sequentially doesn't need a request per
+/// block.
+///
+/// This is useful for formats that can be read partially, like zip
+/// archives, which have their index at the end of the file.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::io::{Read, Seek, SeekFrom};
+///
+/// let mut reader = minreq::RemoteReader::new(minreq::get("http://example.com/archive.zip"));
+/// // Read the end of central directory record of the zip archive.
+/// reader.seek(SeekFrom::End(-22))?;
+/// let mut record = [0; 22];
+/// reader.read_exact(&mut record)?;
+/// # Ok(()) }
+/// ```
+pub struct RemoteReader {
+    request: Request,
+    block_size: usize,
+    read_ahead: usize,
+    position: u64,
+    length: Option<u64>,
+    buffer: Vec<u8>,
+    buffer_start: u64,
+}
+
+impl RemoteReader {
+    /// Creates a new reader over the file `request` downloads. The
+    /// request is cloned for each range request, with a `Range` header
+    /// added to it.
+    pub fn new(request: Request) -> RemoteReader {
+        RemoteReader {
+            request,
+            block_size: 64 * 1024,
+            read_ahead: 4,
+            position: 0,
+            length: None,
+            buffer: Vec::new(),
+            buffer_start: 0,
+        }
+    }
+
+    /// Sets the size of the blocks the file is downloaded in.
+    pub fn with_block_size(mut self, block_size: usize) -> RemoteReader {
+        self.block_size = block_size.max(1);
+        self
+    }
+
+    /// Sets how many extra blocks are downloaded when reading
+    /// sequentially. 0 disables reading ahead.
+    pub fn with_read_ahead(mut self, read_ahead: usize) -> RemoteReader {
+        self.read_ahead = read_ahead;
+        self
+    }
+
+    /// Returns the length of the remote file, sending a request for
+    /// its first byte if it's not known yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns
+    /// [`RangeRequestsNotSupported`](enum.Error.html#variant.RangeRequestsNotSupported)
+    /// if the server doesn't respond with a partial response, or
+    /// [`RangeRequestFailed`](enum.Error.html#variant.RangeRequestFailed)
+    /// if it responds with an error.
+    pub fn len(&mut self) -> Result<u64, Error> {
+        match self.length {
+            Some(length) => Ok(length),
+            None => {
+                self.fetch(0, 1)?;
+                self.length.ok_or(Error::RangeRequestsNotSupported)
+            }
+        }
+    }
+
+    /// Returns true if the remote file is empty. See
+    /// [`len`](#method.len).
+    pub fn is_empty(&mut self) -> Result<bool, Error> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Downloads `size` bytes starting from `start` into the buffer.
+    fn fetch(&mut self, start: u64, size: usize) -> Result<(), Error> {
+        let end = start + size as u64 - 1;
+        let response = self
+            .request
+            .clone()
+            .with_header("Range", format!("bytes={}-{}", start, end))
+            .send()?;
+
+        match response.status_code {
+            206 => {}
+            // The range starts past the end of the file.
+            416 => {
+                self.length = response
+                    .headers
+                    .get("content-range")
+                    .and_then(|range| parse_content_range(range))
+                    .map(|(_, _, length)| length)
+                    .or(self.length);
+                self.buffer.clear();
+                self.buffer_start = start;
+                return Ok(());
+            }
+            200 => return Err(Error::RangeRequestsNotSupported),
+            status => return Err(Error::RangeRequestFailed(status)),
+        }
+
+        let (range_start, range_end, length) = response
+            .headers
+            .get("content-range")
+            .and_then(|range| parse_content_range(range))
+            .ok_or(Error::RangeRequestsNotSupported)?;
+        let body = response.into_bytes();
+        if range_start != start || range_end - range_start + 1 != body.len() as u64 {
+            return Err(Error::RangeRequestsNotSupported);
+        }
+        log::trace!(
+            "Downloaded bytes {}-{} of {}.",
+            range_start,
+            range_end,
+            length
+        );
+        self.length = Some(length);
+        self.buffer = body;
+        self.buffer_start = start;
+        Ok(())
+    }
+}
+
+impl Read for RemoteReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.length.map_or(false, |length| self.position >= length) {
+            return Ok(0);
+        }
+
+        let buffer_end = self.buffer_start + self.buffer.len() as u64;
+        if self.position < self.buffer_start || self.position >= buffer_end {
+            let blocks = if self.position == buffer_end && !self.buffer.is_empty() {
+                1 + self.read_ahead
+            } else {
+                1
+            };
+            let size = self.block_size.max(buf.len()) * blocks;
+            self.fetch(self.position, size).map_err(into_io_error)?;
+        }
+
+        let offset = (self.position - self.buffer_start) as usize;
+        let available = &self.buffer[offset.min(self.buffer.len())..];
+        let count = available.len().min(buf.len());
+        buf[..count].copy_from_slice(&available[..count]);
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+impl Seek for RemoteReader {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        let position = match position {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(offset) => checked_offset(self.position, offset),
+            SeekFrom::End(offset) => checked_offset(self.len().map_err(into_io_error)?, offset),
+        };
+        match position {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+fn into_io_error(err: Error) -> io::Error {
+    match err {
+        Error::IoError(err) => err,
+        err => io::Error::new(io::ErrorKind::Other, err),
+    }
+}
+
+fn checked_offset(base: u64, offset: i64) -> Option<u64> {
+    if offset >= 0 {
+        base.checked_add(offset as u64)
+    } else {
+        base.checked_sub(offset.wrapping_neg() as u64)
+    }
+}
+
+/// Parses a `Content-Range` header, `bytes 0-499/1234`, into its
+/// start, end, and complete length. For unsatisfied ranges
+/// (`bytes */1234`), the start and end are 0.
+pub(crate) fn parse_content_range(content_range: &str) -> Option<(u64, u64, u64)> {
+    let range = content_range.trim().strip_prefix("bytes ")?;
+    let mut parts = range.splitn(2, '/');
+    let (range, length) = (parts.next()?, parts.next()?.parse::<u64>().ok()?);
+    if range == "*" {
+        return Some((0, 0, length));
+    }
+    let mut range = range.splitn(2, '-');
+    let start = range.next()?.parse::<u64>().ok()?;
+    let end = range.next()?.parse::<u64>().ok()?;
+    if start > end || end >= length {
+        return None;
+    }
+    Some((start, end, length))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_content_range;
+
+    #[test]
+    fn test_parse_content_range() {
+        assert_eq!(
+            parse_content_range("bytes 0-499/1234"),
+            Some((0, 499, 1234))
+        );
+        assert_eq!(parse_content_range("bytes */1234"), Some((0, 0, 1234)));
+        assert_eq!(parse_content_range("bytes 0-499/*"), None);
+        assert_eq!(parse_content_range("bytes 500-499/1234"), None);
+    }
+}
@@ -1,10 +1,36 @@
-use crate::{connection::HttpStream, Error};
+use crate::charset::{body_charset, decode, Charset};
+use crate::connection::{Deadline, HttpStream};
+use crate::cors::{parse_methods, CorsPolicy};
+use crate::decoder::run_decoders;
+use crate::deprecation::{parse_deprecation, parse_sunset, parse_warnings, Deprecation, Warning};
+use crate::event::EventHook;
+use crate::grpc_web::parse_grpc_web_response;
+#[cfg(any(feature = "sha2", feature = "sha1", feature = "md-5"))]
+use crate::hash::{Algorithm, HashedBody, Hasher};
+use crate::multipart::multipart_boundary;
+use crate::pool::{ConnectionPermit, PoolSlot};
+#[cfg(feature = "json-using-serde")]
+use crate::problem::{problem_details, ProblemDetails};
+use crate::status::is_error_status;
+use crate::trace::response_request_id;
+use crate::webdav::parse_multi_status;
+#[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+use crate::TlsInfo;
+use crate::{BodyDecoder, Error, Event, GrpcWebResponse, Method, MultiStatus, Request};
+use crate::{MultipartReader, RateLimit, StatusError};
+use crate::{StructuredDictionary, StructuredItem, StructuredList};
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::io::{self, BufReader, Bytes, ErrorKind, Read};
+use std::io::{self, BufReader, ErrorKind, Read};
+use std::net::SocketAddr;
 use std::str;
+use std::time::SystemTime;
 
-const BACKING_READ_BUFFER_LENGTH: usize = 16 * 1024;
+pub(crate) const BACKING_READ_BUFFER_LENGTH: usize = 16 * 1024;
 const MAX_CONTENT_LENGTH: usize = 16 * 1024;
+const DECODER_INPUT_LENGTH: usize = 4 * 1024;
+#[cfg(any(feature = "sha2", feature = "sha1", feature = "md-5"))]
+const HASHER_INPUT_LENGTH: usize = 8 * 1024;
 
 /// An HTTP response.
 ///
@@ -29,52 +55,242 @@ pub struct Response {
     pub headers: HashMap<String, String>,
 
     body: Vec<u8>,
+    remote_addr: Option<SocketAddr>,
+    local_addr: Option<SocketAddr>,
+    #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+    tls_info: Option<TlsInfo>,
+    connection_reused: bool,
+    content_sniffing: bool,
 }
 
 impl Response {
-    pub(crate) fn create(mut parent: ResponseLazy, is_head: bool) -> Result<Response, Error> {
+    pub(crate) fn create(mut parent: ResponseLazy) -> Result<Response, Error> {
+        let body = Response::read_body(&mut parent)?;
+        Ok(Response::from_parts(parent, body))
+    }
+
+    /// Reads the body of `parent` like [Response::create], and also
+    /// returns the reader of its connection, with the next pipelined
+    /// response, unless the server is closing the connection.
+    pub(crate) fn create_pipelined(
+        mut parent: ResponseLazy,
+    ) -> Result<(Response, Option<BufReader<HttpStream>>), Error> {
+        let body = Response::read_body(&mut parent)?;
+        let reader = match parent.keep_alive {
+            true => parent.stream.reader.take(),
+            false => None,
+        };
+        Ok((Response::from_parts(parent, body), reader))
+    }
+
+    fn read_body(parent: &mut ResponseLazy) -> Result<Vec<u8>, Error> {
         let mut body = Vec::new();
-        if !is_head && parent.status_code != 204 && parent.status_code != 304 {
-            for byte in &mut parent {
-                match byte {
-                    Ok((byte, length)) => {
-                        body.reserve(length);
-                        body.push(byte);
-                    }
-                    Err(Error::IoError(err)) if err.kind() == ErrorKind::WouldBlock => {
-                        // Busy waiting isn't ideal, but waiting for N milliseconds would be worse.
-                        std::thread::yield_now();
-                    }
-                    Err(err) => return Err(err),
+        // Responses without a body (eg. to HEAD requests) end right
+        // away, which also releases their connection.
+        for byte in parent {
+            match byte {
+                Ok((byte, length)) => {
+                    body.reserve(length);
+                    body.push(byte);
                 }
+                Err(Error::IoError(err)) if err.kind() == ErrorKind::WouldBlock => {
+                    // Busy waiting isn't ideal, but waiting for N milliseconds would be worse.
+                    std::thread::yield_now();
+                }
+                Err(err) => return Err(err),
             }
         }
+        Ok(body)
+    }
 
-        let ResponseLazy {
-            status_code,
-            reason_phrase,
-            headers,
-            ..
-        } = parent;
-
-        Ok(Response {
-            status_code,
-            reason_phrase,
-            headers,
+    fn from_parts(mut parent: ResponseLazy, body: Vec<u8>) -> Response {
+        Response {
+            status_code: parent.status_code,
+            reason_phrase: std::mem::take(&mut parent.reason_phrase),
+            headers: std::mem::take(&mut parent.headers),
             body,
-        })
+            remote_addr: parent.remote_addr,
+            local_addr: parent.local_addr,
+            #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+            tls_info: parent.tls_info.take(),
+            connection_reused: parent.connection_reused,
+            content_sniffing: parent.content_sniffing,
+        }
+    }
+
+    /// Returns the address of the server this response came from, if
+    /// it could be determined. When using a proxy, this is the
+    /// address of the proxy.
+    ///
+    /// Useful for logging which backend served the request when the
+    /// host resolves to multiple addresses.
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.remote_addr
+    }
+
+    /// Returns the local address of the socket the response was read
+    /// from, if it could be determined.
+    ///
+    /// Useful for debugging NAT and split-horizon DNS issues.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+
+    /// Returns the TLS session the response was received over, with
+    /// the certificates the server presented, or None if the request
+    /// wasn't sent over TLS. When using a proxy, this is the session
+    /// with the server, not with the proxy.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), minreq::Error> {
+    /// let response = minreq::head("https://example.com").send()?;
+    /// if let Some(tls) = response.tls_info() {
+    ///     println!("{:?} with {:?}", tls.protocol_version, tls.cipher_suite);
+    ///     println!("{} certificates", tls.peer_certificates.len());
+    /// }
+    /// # Ok(()) }
+    /// ```
+    #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+    pub fn tls_info(&self) -> Option<&TlsInfo> {
+        self.tls_info.as_ref()
+    }
+
+    /// Returns true if the response was received over a connection
+    /// that had already been used for an earlier request.
+    pub fn connection_reused(&self) -> bool {
+        self.connection_reused
+    }
+
+    /// Returns the warnings in the `Warning` header. Malformed
+    /// warnings are skipped.
+    pub fn warnings(&self) -> Vec<Warning> {
+        parse_warnings(&self.headers)
+    }
+
+    /// Returns the contents of the `Deprecation` header, or None if
+    /// the requested resource is not deprecated.
+    pub fn deprecation(&self) -> Option<Deprecation> {
+        parse_deprecation(&self.headers)
+    }
+
+    /// Returns the date in the `Sunset` header, after which the
+    /// requested resource is expected to become unavailable.
+    pub fn sunset(&self) -> Option<SystemTime> {
+        parse_sunset(&self.headers)
+    }
+
+    /// Returns the methods in the `Allow` header, ie. the methods the
+    /// resource supports, usually sent in response to an `OPTIONS`
+    /// request.
+    pub fn allow(&self) -> Vec<Method> {
+        parse_methods(self.headers.get("allow"))
+    }
+
+    /// Returns the CORS policy in the `Access-Control-Allow-*`
+    /// headers, usually sent in response to a
+    /// [preflight](fn.preflight.html) request.
+    pub fn cors(&self) -> CorsPolicy {
+        CorsPolicy::from_headers(&self.headers)
+    }
+
+    /// Returns the rate limit quota in the `RateLimit` or
+    /// `X-RateLimit-*` headers, or None if there are none. See
+    /// [RateLimit].
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        RateLimit::from_headers(&self.headers)
+    }
+
+    /// Returns the request ID the server sent back in the
+    /// `X-Request-Id`, `Request-Id` or `X-Correlation-Id` header, for
+    /// finding the request in the server's logs.
+    pub fn request_id(&self) -> Option<&str> {
+        response_request_id(&self.headers)
+    }
+
+    /// Parses the header `name` as a structured field item ([RFC
+    /// 8941](https://www.rfc-editor.org/rfc/rfc8941)). Returns None if
+    /// the header is missing or is not a valid item, in which case
+    /// the RFC says it should be ignored.
+    pub fn structured_item(&self, name: &str) -> Option<StructuredItem> {
+        structured_header(&self.headers, name, StructuredItem::parse)
+    }
+
+    /// Parses the header `name` as a structured field list, see
+    /// [`structured_item`](#method.structured_item).
+    pub fn structured_list(&self, name: &str) -> Option<StructuredList> {
+        structured_header(&self.headers, name, StructuredList::parse)
+    }
+
+    /// Parses the header `name` as a structured field dictionary, eg.
+    /// the `Priority` header, see
+    /// [`structured_item`](#method.structured_item).
+    pub fn structured_dictionary(&self, name: &str) -> Option<StructuredDictionary> {
+        structured_header(&self.headers, name, StructuredDictionary::parse)
+    }
+
+    /// Parses the body of a WebDAV 207 Multi-Status response, eg. the
+    /// response to a [propfind](fn.propfind.html) request.
+    ///
+    /// # Errors
+    ///
+    /// Returns
+    /// [`InvalidMultiStatus`](enum.Error.html#variant.InvalidMultiStatus)
+    /// if the body is not a Multi-Status XML document, and
+    /// [`InvalidUtf8InBody`](enum.Error.html#variant.InvalidUtf8InBody)
+    /// if it's not UTF-8.
+    pub fn multi_status(&self) -> Result<MultiStatus, Error> {
+        parse_multi_status(self.as_str()?)
+    }
+
+    /// Parses the response to a unary gRPC-Web call, made with
+    /// [grpc_web](fn.grpc_web.html). The body is decoded according to
+    /// its `Content-Type`, and the trailers can also be in the
+    /// headers, as servers send them when there's no message.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvalidGrpcWeb`](enum.Error.html#variant.InvalidGrpcWeb)
+    /// if the body isn't made of gRPC-Web frames, eg. because the
+    /// request failed at the HTTP level, or there's no `grpc-status`.
+    pub fn grpc_web_response(&self) -> Result<GrpcWebResponse, Error> {
+        parse_grpc_web_response(&self.headers, self.as_bytes())
+    }
+
+    /// Returns an iterator over the parts of the body, if it's a
+    /// `multipart/*` body, eg. the response of a batch API. See
+    /// [MultipartReader].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvalidMultipart`](enum.Error.html#variant.InvalidMultipart)
+    /// if the `Content-Type` is not a multipart media type with a
+    /// boundary.
+    pub fn multipart(&self) -> Result<MultipartReader<&[u8]>, Error> {
+        let boundary = multipart_boundary(&self.headers)?;
+        Ok(MultipartReader::new(&self.body, boundary))
+    }
+
+    /// Returns the Problem Details ([RFC 9457](https://www.rfc-editor.org/rfc/rfc9457))
+    /// in the body, if the `Content-Type` is
+    /// `application/problem+json` and the body is a JSON object. See
+    /// [ProblemDetails].
+    #[cfg(feature = "json-using-serde")]
+    pub fn problem_details(&self) -> Option<ProblemDetails> {
+        problem_details(&self.headers, &self.body)
     }
 
     /// Return true if the request's response code is in range 200-299 (HTTP OK)
-    /// 
+    ///
     /// Source: https://developer.mozilla.org/en-US/docs/Web/API/Response/ok
-    /// 
+    ///
     /// # Example
     ///
     /// ```no_run
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
     /// let response = minreq::get("http://example.com").send()?;
-    /// 
+    ///
     /// if response.is_ok() {
     ///     println!("Response body: {}", response.as_str().unwrap());
     /// }
@@ -85,6 +301,34 @@ impl Response {
         (200..299).contains(&self.status_code)
     }
 
+    /// Returns an [`Error::StatusError`] if the status code is a
+    /// client or server error (400-599), and the response otherwise.
+    /// The error keeps the start of the body, which often explains
+    /// the error. See [StatusError].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), minreq::Error> {
+    /// match minreq::get("http://example.com/private").send()?.error_for_status() {
+    ///     Ok(response) => println!("{}", response.as_str()?),
+    ///     Err(minreq::Error::StatusError(err)) if err.is_auth_error() => {
+    ///         println!("Access denied: {}", err.text()?)
+    ///     }
+    ///     Err(err) => return Err(err),
+    /// }
+    /// # Ok(()) }
+    /// ```
+    pub fn error_for_status(self) -> Result<Response, Error> {
+        if is_error_status(self.status_code) {
+            Err(Error::StatusError(Box::new(StatusError::from_response(
+                &self,
+            ))))
+        } else {
+            Ok(self)
+        }
+    }
+
     /// Returns the body as an `&str`.
     ///
     /// # Errors
@@ -111,6 +355,52 @@ impl Response {
         }
     }
 
+    /// Returns the charset of the body: the one in the `Content-Type`
+    /// header, or if there's no `Content-Type` and content sniffing is
+    /// enabled (see
+    /// [`Request::with_content_sniffing`](struct.Request.html#method.with_content_sniffing)),
+    /// the one guessed from the body. Returns None if neither applies,
+    /// or the charset is not supported.
+    pub fn charset(&self) -> Option<Charset> {
+        body_charset(&self.headers, &self.body, self.content_sniffing)
+            .ok()
+            .flatten()
+    }
+
+    /// Returns the body decoded into a string, using the
+    /// [charset](#method.charset) of the body, or UTF-8 if it has
+    /// none. A byte order mark at the start of the body is skipped.
+    ///
+    /// Unlike [`as_str`](#method.as_str), this only copies the body
+    /// if it's not UTF-8 (or ASCII).
+    ///
+    /// # Errors
+    ///
+    /// Returns
+    /// [`UnsupportedCharset`](enum.Error.html#variant.UnsupportedCharset)
+    /// if the `Content-Type` has a charset other than UTF-8, UTF-16,
+    /// or ISO-8859-1, and
+    /// [`InvalidUtf8InBody`](enum.Error.html#variant.InvalidUtf8InBody)
+    /// or
+    /// [`InvalidUtf16InBody`](enum.Error.html#variant.InvalidUtf16InBody)
+    /// if the body can't be decoded with its charset.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let response = minreq::get("http://example.com")
+    ///     .with_content_sniffing(true)
+    ///     .send()?;
+    /// println!("{}", response.text()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn text(&self) -> Result<Cow<'_, str>, Error> {
+        let charset = body_charset(&self.headers, &self.body, self.content_sniffing)?;
+        decode(&self.body, charset.unwrap_or(Charset::Utf8))
+    }
+
     /// Returns a reference to the contained bytes of the body. If you
     /// want the `Vec<u8>` itself, use
     /// [`into_bytes()`](#method.into_bytes) instead.
@@ -185,9 +475,18 @@ impl Response {
     where
         T: serde::de::Deserialize<'a>,
     {
-        let str = match self.as_str() {
-            Ok(str) => str,
-            Err(_) => return Err(Error::InvalidUtf8InResponse),
+        // With content sniffing, a byte order mark is skipped, but the
+        // body must still be UTF-8 to be deserialized from in place.
+        let str = if self.content_sniffing && !self.headers.contains_key("content-type") {
+            match self.text() {
+                Ok(Cow::Borrowed(str)) => str,
+                _ => return Err(Error::InvalidUtf8InResponse),
+            }
+        } else {
+            match self.as_str() {
+                Ok(str) => str,
+                Err(_) => return Err(Error::InvalidUtf8InResponse),
+            }
         };
         match serde_json::from_str(str) {
             Ok(json) => Ok(json),
@@ -244,24 +543,180 @@ pub struct ResponseLazy {
     stream: HttpStreamBytes,
     state: HttpStreamState,
     max_trailing_headers_size: Option<usize>,
+    max_chunk_size: Option<usize>,
+    decoders: Vec<Box<dyn BodyDecoder>>,
+    decoded: DecodedBody,
+    remote_addr: Option<SocketAddr>,
+    local_addr: Option<SocketAddr>,
+    #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+    tls_info: Option<TlsInfo>,
+    connection_reused: bool,
+    content_sniffing: bool,
+    max_response_size: Option<usize>,
+    body_bytes_read: usize,
+    keep_alive: bool,
+    pool: Option<PoolSlot>,
+    /// Allows the connection to be used while the number of
+    /// connections per host is limited, until the body has been read.
+    permit: Option<ConnectionPermit>,
+    /// The values of the `Set-Cookie` headers, which can't be combined
+    /// into one header like the others.
+    pub(crate) set_cookies: Vec<String>,
+    /// An error that happened in [Read::read] after some bytes were
+    /// read, returned by the next call.
+    read_error: Option<io::Error>,
+}
+
+/// The bytes of the stream a response is read from. The stream can
+/// be taken back once the response has been read, to be reused.
+struct HttpStreamBytes {
+    reader: Option<BufReader<HttpStream>>,
+    /// Called with a [`BodyChunk`](Event::BodyChunk) event for each
+    /// read from the stream, once the headers have been read.
+    event_hook: Option<EventHook>,
+    /// How many more bytes are read before the stream ends early, if
+    /// it's been truncated by [Chaos](crate::Chaos).
+    remaining: Option<usize>,
 }
 
-type HttpStreamBytes = Bytes<BufReader<HttpStream>>;
+impl HttpStreamBytes {
+    /// Takes the stream, unless it has already been taken or there is
+    /// unread data buffered from it.
+    fn take_stream(&mut self) -> Option<HttpStream> {
+        let reader = self.reader.take()?;
+        if reader.buffer().is_empty() && !reader.get_ref().has_buffered_data() {
+            Some(reader.into_inner())
+        } else {
+            None
+        }
+    }
+}
+
+impl Iterator for HttpStreamBytes {
+    type Item = io::Result<u8>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let reader = self.reader.as_mut()?;
+        if let Some(remaining) = &mut self.remaining {
+            if *remaining == 0 {
+                return None;
+            }
+            *remaining -= 1;
+        }
+        let mut byte = 0;
+        loop {
+            let refilled = reader.buffer().is_empty();
+            return match reader.read(std::slice::from_mut(&mut byte)) {
+                Ok(0) => None,
+                Ok(_) => {
+                    if let (true, Some(hook)) = (refilled, &self.event_hook) {
+                        let length = reader.buffer().len() + 1;
+                        hook.call(Event::BodyChunk { length });
+                    }
+                    Some(Ok(byte))
+                }
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => Some(Err(err)),
+            };
+        }
+    }
+}
+
+/// The bytes that have passed through the decoders, but haven't been
+/// iterated over yet.
+#[derive(Default)]
+struct DecodedBody {
+    bytes: Vec<u8>,
+    position: usize,
+    framing_done: bool,
+    pending_error: Option<Error>,
+}
 
 impl ResponseLazy {
-    pub(crate) fn from_stream(
-        stream: HttpStream,
-        max_headers_size: Option<usize>,
-        max_status_line_len: Option<usize>,
+    pub(crate) fn from_stream(stream: HttpStream, config: &Request) -> Result<ResponseLazy, Error> {
+        let capacity = config.buffer_size.unwrap_or(BACKING_READ_BUFFER_LENGTH);
+        ResponseLazy::from_reader(BufReader::with_capacity(capacity, stream), config)
+    }
+
+    /// Reads the status line and headers of a response from `reader`,
+    /// which may have buffered data already, eg. the start of the
+    /// next response of a pipelined connection.
+    pub(crate) fn from_reader(
+        reader: BufReader<HttpStream>,
+        config: &Request,
     ) -> Result<ResponseLazy, Error> {
-        let mut stream = BufReader::with_capacity(BACKING_READ_BUFFER_LENGTH, stream).bytes();
+        let remote_addr = reader.get_ref().tcp().peer_addr().ok();
+        let local_addr = reader.get_ref().tcp().local_addr().ok();
+        #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+        let tls_info = reader.get_ref().tls_info();
+        let mut stream = HttpStreamBytes {
+            reader: Some(reader),
+            event_hook: None,
+            remaining: None,
+        };
         let ResponseMetadata {
             status_code,
             reason_phrase,
             headers,
-            state,
+            mut state,
             max_trailing_headers_size,
-        } = read_metadata(&mut stream, max_headers_size, max_status_line_len)?;
+            transfer_codings,
+            keep_alive,
+            set_cookies,
+        } = read_metadata(
+            &mut stream,
+            config.max_headers_size,
+            config.max_status_line_len,
+        )?;
+        config.emit(|| Event::StatusReceived {
+            status_code,
+            reason_phrase: reason_phrase.clone(),
+        });
+        if let Some(hook) = &config.event_hook {
+            // Part of the body may have been read along with the
+            // headers.
+            let length = stream
+                .reader
+                .as_ref()
+                .map_or(0, |reader| reader.buffer().len());
+            if length > 0 {
+                hook.call(Event::BodyChunk { length });
+            }
+            stream.event_hook = Some(hook.clone());
+        }
+        // These responses never have a body, whatever their headers
+        // say (RFC 9112 section 6.3).
+        if config.method == Method::Head || status_code == 204 || status_code == 304 {
+            state = HttpStreamState::ContentLength(0, 0);
+        }
+
+        if let (HttpStreamState::ContentLength(length, _), Some(max_size)) =
+            (&state, config.max_response_size)
+        {
+            if *length > max_size {
+                return Err(Error::ResponseBodyTooLarge);
+            }
+        }
+
+        // The transfer codings are removed first, even for raw bodies,
+        // as they're a property of the message, not the content.
+        let mut decoders = Vec::new();
+        for coding in transfer_codings.iter().rev() {
+            decoders.push(transfer_decoder(coding)?);
+        }
+        if !config.raw_body {
+            decoders.extend(
+                config
+                    .body_decoders
+                    .iter()
+                    .filter_map(|factory| factory.create(&headers)),
+            );
+        }
+        #[cfg(feature = "compression")]
+        let headers = match config.raw_body {
+            true => headers,
+            false => crate::compression::without_content_encoding(headers),
+        };
 
         Ok(ResponseLazy {
             status_code,
@@ -270,18 +725,243 @@ impl ResponseLazy {
             stream,
             state,
             max_trailing_headers_size,
+            max_chunk_size: config.max_chunk_size,
+            decoders,
+            decoded: DecodedBody::default(),
+            remote_addr,
+            local_addr,
+            #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+            tls_info,
+            connection_reused: false,
+            content_sniffing: config.content_sniffing,
+            max_response_size: config.max_response_size,
+            body_bytes_read: 0,
+            keep_alive: keep_alive && !config.connection_close,
+            pool: None,
+            permit: None,
+            set_cookies,
+            read_error: None,
         })
     }
-}
 
-impl Iterator for ResponseLazy {
-    type Item = Result<(u8, usize), Error>;
+    /// Makes the connection of this response go back into a pool once
+    /// the body has been read, if the server allows reusing it. The
+    /// `permit` to use the connection is held until then.
+    pub(crate) fn with_pool(
+        mut self,
+        slot: PoolSlot,
+        permit: Option<ConnectionPermit>,
+        connection_reused: bool,
+    ) -> ResponseLazy {
+        self.connection_reused = connection_reused;
+        if self.keep_alive {
+            self.pool = Some(slot);
+        }
+        self.permit = permit;
+        self
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
+    /// Replaces this response with an empty one with `status_code`,
+    /// for [Chaos](crate::Chaos). The connection is closed.
+    pub(crate) fn substitute(&mut self, status_code: i32, reason_phrase: &str) {
+        self.status_code = status_code;
+        self.reason_phrase = reason_phrase.to_string();
+        self.headers.clear();
+        self.headers
+            .insert("content-length".to_string(), "0".to_string());
+        self.set_cookies.clear();
+        self.state = HttpStreamState::ContentLength(0, 0);
+        self.decoders.clear();
+        self.keep_alive = false;
+    }
+
+    /// Makes the body end after `length` more bytes from the server,
+    /// as if it had closed the connection, for [Chaos](crate::Chaos).
+    pub(crate) fn truncate(&mut self, length: usize) {
+        self.stream.remaining = Some(length);
+        self.keep_alive = false;
+    }
+
+    /// Replaces the deadline the body is read until.
+    pub(crate) fn set_deadline(&mut self, deadline: Deadline) {
+        if let Some(reader) = self.stream.reader.as_mut() {
+            reader.get_mut().set_deadline(deadline);
+        }
+    }
+
+    /// Puts the connection back into the pool, now that the body has
+    /// been read.
+    fn release_connection(&mut self) {
+        if let Some(slot) = self.pool.take() {
+            if let Some(stream) = self.stream.take_stream() {
+                slot.put(stream);
+            }
+            self.permit = None;
+        }
+    }
+
+    /// Returns the address of the server this response came from, if
+    /// it could be determined. See
+    /// [`Response::remote_addr`](struct.Response.html#method.remote_addr).
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.remote_addr
+    }
+
+    /// Returns the local address of the socket the response is read
+    /// from, if it could be determined.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+
+    /// Returns the TLS session the response is received over, or None
+    /// if the request wasn't sent over TLS. See
+    /// [`Response::tls_info`](struct.Response.html#method.tls_info).
+    #[cfg(any(feature = "rustls", feature = "openssl", feature = "native-tls"))]
+    pub fn tls_info(&self) -> Option<&TlsInfo> {
+        self.tls_info.as_ref()
+    }
+
+    /// Returns true if the response was received over a connection
+    /// that had already been used for an earlier request.
+    pub fn connection_reused(&self) -> bool {
+        self.connection_reused
+    }
+
+    /// Returns the warnings in the `Warning` header. Malformed
+    /// warnings are skipped.
+    pub fn warnings(&self) -> Vec<Warning> {
+        parse_warnings(&self.headers)
+    }
+
+    /// Returns the contents of the `Deprecation` header, or None if
+    /// the requested resource is not deprecated.
+    pub fn deprecation(&self) -> Option<Deprecation> {
+        parse_deprecation(&self.headers)
+    }
+
+    /// Returns the date in the `Sunset` header, after which the
+    /// requested resource is expected to become unavailable.
+    pub fn sunset(&self) -> Option<SystemTime> {
+        parse_sunset(&self.headers)
+    }
+
+    /// Returns the methods in the `Allow` header, ie. the methods the
+    /// resource supports, usually sent in response to an `OPTIONS`
+    /// request.
+    pub fn allow(&self) -> Vec<Method> {
+        parse_methods(self.headers.get("allow"))
+    }
+
+    /// Returns the CORS policy in the `Access-Control-Allow-*`
+    /// headers, usually sent in response to a
+    /// [preflight](fn.preflight.html) request.
+    pub fn cors(&self) -> CorsPolicy {
+        CorsPolicy::from_headers(&self.headers)
+    }
+
+    /// Returns the rate limit quota in the `RateLimit` or
+    /// `X-RateLimit-*` headers, or None if there are none. See
+    /// [RateLimit].
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        RateLimit::from_headers(&self.headers)
+    }
+
+    /// Returns the request ID the server sent back in the
+    /// `X-Request-Id`, `Request-Id` or `X-Correlation-Id` header, for
+    /// finding the request in the server's logs.
+    pub fn request_id(&self) -> Option<&str> {
+        response_request_id(&self.headers)
+    }
+
+    /// Parses the header `name` as a structured field item ([RFC
+    /// 8941](https://www.rfc-editor.org/rfc/rfc8941)). Returns None if
+    /// the header is missing or is not a valid item, in which case
+    /// the RFC says it should be ignored.
+    pub fn structured_item(&self, name: &str) -> Option<StructuredItem> {
+        structured_header(&self.headers, name, StructuredItem::parse)
+    }
+
+    /// Parses the header `name` as a structured field list, see
+    /// [`structured_item`](#method.structured_item).
+    pub fn structured_list(&self, name: &str) -> Option<StructuredList> {
+        structured_header(&self.headers, name, StructuredList::parse)
+    }
+
+    /// Parses the header `name` as a structured field dictionary, eg.
+    /// the `Priority` header, see
+    /// [`structured_item`](#method.structured_item).
+    pub fn structured_dictionary(&self, name: &str) -> Option<StructuredDictionary> {
+        structured_header(&self.headers, name, StructuredDictionary::parse)
+    }
+
+    /// Returns an iterator over the parts of the body, if it's a
+    /// `multipart/*` body, which reads the body one part at a time.
+    /// See [`Response::multipart`](struct.Response.html#method.multipart).
+    pub fn multipart(self) -> Result<MultipartReader<ResponseLazy>, Error> {
+        let boundary = multipart_boundary(&self.headers)?.to_string();
+        Ok(MultipartReader::new(self, &boundary))
+    }
+
+    /// Returns an [`Error::StatusError`] if the status code is a
+    /// client or server error (400-599), and the response otherwise.
+    /// The start of the body is read into the error. See
+    /// [`Response::error_for_status`](struct.Response.html#method.error_for_status).
+    pub fn error_for_status(self) -> Result<ResponseLazy, Error> {
+        if is_error_status(self.status_code) {
+            Err(Error::StatusError(Box::new(StatusError::from_lazy(self))))
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Reads the rest of the body into a `Vec<u8>`, calculating its
+    /// digest with `algorithm` along the way. The digest can then be
+    /// compared against the one sent by the server with
+    /// [`HashedBody::verify`](struct.HashedBody.html#method.verify).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), minreq::Error> {
+    /// let response = minreq::get("http://example.com/artifact.tar").send_lazy()?;
+    /// let hashed = response.into_hashed_vec(minreq::Algorithm::Sha256)?;
+    /// hashed.verify()?;
+    /// std::fs::write("artifact.tar", &hashed.body)?;
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as reading the body with the
+    /// [Iterator] implementation.
+    #[cfg(any(feature = "sha2", feature = "sha1", feature = "md-5"))]
+    pub fn into_hashed_vec(mut self, algorithm: Algorithm) -> Result<HashedBody, Error> {
+        let mut hasher = Hasher::new(algorithm);
+        let mut body = Vec::new();
+        let mut hashed_length = 0;
+        for byte in &mut self {
+            let (byte, length) = byte?;
+            body.reserve(length);
+            body.push(byte);
+            if body.len() - hashed_length >= HASHER_INPUT_LENGTH {
+                hasher.update(&body[hashed_length..]);
+                hashed_length = body.len();
+            }
+        }
+        hasher.update(&body[hashed_length..]);
+        let headers = std::mem::take(&mut self.headers);
+        Ok(HashedBody::new(body, hasher.finish(), algorithm, headers))
+    }
+
+    /// Returns the next byte of the body with the transfer framing
+    /// removed, but before any of the decoders have been run.
+    fn next_framed(&mut self) -> Option<<ResponseLazy as Iterator>::Item> {
         use HttpStreamState::*;
-        match self.state {
+        let next = match self.state {
             EndOnClose => read_until_closed(&mut self.stream),
-            ContentLength(ref mut length) => read_with_content_length(&mut self.stream, length),
+            ContentLength(ref mut length, expected) => {
+                read_with_content_length(&mut self.stream, length, expected)
+            }
             Chunked(ref mut expecting_chunks, ref mut length, ref mut content_length) => {
                 read_chunked(
                     &mut self.stream,
@@ -290,22 +970,155 @@ impl Iterator for ResponseLazy {
                     length,
                     content_length,
                     self.max_trailing_headers_size,
+                    self.max_chunk_size,
                 )
             }
+        };
+        match (&next, &self.state) {
+            (None, ContentLength(..)) | (None, Chunked(..)) => self.release_connection(),
+            (Some(Err(_)), _) => self.pool = None,
+            _ => {}
+        }
+        next
+    }
+
+    /// Returns the next byte of the body after it has gone through
+    /// the decoders.
+    fn next_decoded(&mut self) -> Option<<ResponseLazy as Iterator>::Item> {
+        loop {
+            if self.decoded.position < self.decoded.bytes.len() {
+                let byte = self.decoded.bytes[self.decoded.position];
+                self.decoded.position += 1;
+                let remaining = self.decoded.bytes.len() - self.decoded.position;
+                return Some(Ok((byte, remaining.min(MAX_CONTENT_LENGTH) + 1)));
+            }
+            if let Some(err) = self.decoded.pending_error.take() {
+                return Some(Err(err));
+            }
+            if self.decoded.framing_done {
+                return None;
+            }
+
+            let mut input = Vec::with_capacity(DECODER_INPUT_LENGTH);
+            while input.len() < DECODER_INPUT_LENGTH {
+                match self.next_framed() {
+                    Some(Ok((byte, _))) => input.push(byte),
+                    Some(Err(err)) => {
+                        self.decoded.pending_error = Some(err);
+                        break;
+                    }
+                    None => {
+                        self.decoded.framing_done = true;
+                        break;
+                    }
+                }
+            }
+
+            match run_decoders(&mut self.decoders, input, self.decoded.framing_done) {
+                Ok(bytes) => {
+                    self.decoded.bytes = bytes;
+                    self.decoded.position = 0;
+                }
+                Err(err) => {
+                    self.decoded.framing_done = true;
+                    return Some(Err(err));
+                }
+            }
         }
     }
 }
 
+/// Drains the rest of the body of an abandoned response, up to the
+/// [drain limit](crate::Client::with_max_drain_bytes) of its pool, so
+/// that its connection can go back into the pool. The connection is
+/// closed if the rest of the body is longer than that, or its length
+/// isn't known.
+impl Drop for ResponseLazy {
+    fn drop(&mut self) {
+        let max_drain_bytes = match &self.pool {
+            Some(slot) => slot.max_drain_bytes(),
+            None => return,
+        };
+        if let HttpStreamState::EndOnClose = self.state {
+            return;
+        }
+        let mut drained = 0;
+        while let Some(Ok(_)) = self.next_framed() {
+            drained += 1;
+            if drained > max_drain_bytes {
+                log::trace!(
+                    "Closing a connection with over {} unread bytes.",
+                    max_drain_bytes
+                );
+                self.pool = None;
+                return;
+            }
+        }
+    }
+}
+
+impl Iterator for ResponseLazy {
+    type Item = Result<(u8, usize), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = if self.decoders.is_empty() {
+            self.next_framed()
+        } else {
+            self.next_decoded()
+        };
+
+        match next {
+            Some(Ok(_)) => {
+                self.body_bytes_read += 1;
+                if let Some(max_size) = self.max_response_size {
+                    if self.body_bytes_read > max_size {
+                        return Some(Err(Error::ResponseBodyTooLarge));
+                    }
+                }
+            }
+            // The hook is taken so that the event is only emitted once.
+            None => {
+                if let Some(hook) = self.stream.event_hook.take() {
+                    hook.call(Event::Completed {
+                        status_code: self.status_code,
+                        body_length: self.body_bytes_read,
+                    });
+                }
+            }
+            Some(Err(_)) => {}
+        }
+        next
+    }
+}
+
 impl Read for ResponseLazy {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(err) = self.read_error.take() {
+            return Err(err);
+        }
         let mut index = 0;
-        for res in self {
+        while let Some(res) = self.next() {
             // there is no use for the estimated length in the read implementation
             // so it is ignored.
-            let (byte, _) = res.map_err(|e| match e {
-                Error::IoError(e) => e,
-                _ => io::Error::new(io::ErrorKind::Other, e),
-            })?;
+            let (byte, _) = match res {
+                Ok(byte) => byte,
+                Err(e) => {
+                    let err = match e {
+                        Error::IoError(e) => e,
+                        e @ Error::TruncatedBody { .. } => {
+                            io::Error::new(io::ErrorKind::UnexpectedEof, e)
+                        }
+                        _ => io::Error::new(io::ErrorKind::Other, e),
+                    };
+                    // The bytes read before the error are returned
+                    // first, as they'd be lost otherwise.
+                    if index > 0 {
+                        self.read_error = Some(err);
+                        return Ok(index);
+                    }
+                    return Err(err);
+                }
+            };
 
             buf[index] = byte;
             index += 1;
@@ -321,6 +1134,15 @@ impl Read for ResponseLazy {
     }
 }
 
+/// Returns the decoder for the transfer coding `coding`.
+fn transfer_decoder(coding: &str) -> Result<Box<dyn BodyDecoder>, Error> {
+    #[cfg(feature = "flate2")]
+    if let Some(decoder) = crate::compression::decoder_for(coding) {
+        return Ok(decoder);
+    }
+    Err(Error::UnsupportedTransferEncoding(coding.to_string()))
+}
+
 fn read_until_closed(bytes: &mut HttpStreamBytes) -> Option<<ResponseLazy as Iterator>::Item> {
     if let Some(byte) = bytes.next() {
         match byte {
@@ -335,15 +1157,20 @@ fn read_until_closed(bytes: &mut HttpStreamBytes) -> Option<<ResponseLazy as Ite
 fn read_with_content_length(
     bytes: &mut HttpStreamBytes,
     content_length: &mut usize,
+    expected: usize,
 ) -> Option<<ResponseLazy as Iterator>::Item> {
     if *content_length > 0 {
-        *content_length -= 1;
-
-        if let Some(byte) = bytes.next() {
-            match byte {
+        match bytes.next() {
+            Some(Ok(byte)) => {
+                *content_length -= 1;
                 // Cap Content-Length to 16KiB, to avoid out-of-memory issues.
-                Ok(byte) => return Some(Ok((byte, (*content_length).min(MAX_CONTENT_LENGTH) + 1))),
-                Err(err) => return Some(Err(Error::IoError(err))),
+                return Some(Ok((byte, (*content_length).min(MAX_CONTENT_LENGTH) + 1)));
+            }
+            Some(Err(err)) => return Some(Err(Error::IoError(err))),
+            None => {
+                let got = expected - *content_length;
+                *content_length = 0;
+                return Some(Err(Error::TruncatedBody { expected, got }));
             }
         }
     }
@@ -376,6 +1203,7 @@ fn read_chunked(
     chunk_length: &mut usize,
     content_length: &mut usize,
     max_trailing_headers_size: Option<usize>,
+    max_chunk_size: Option<usize>,
 ) -> Option<<ResponseLazy as Iterator>::Item> {
     if !*expecting_more_chunks && *chunk_length == 0 {
         return None;
@@ -384,7 +1212,7 @@ fn read_chunked(
     if *chunk_length == 0 {
         // Max length of the chunk length line is 1KB: not too long to
         // take up much memory, long enough to tolerate some chunk
-        // extensions (which are ignored).
+        // extensions (which are validated, but otherwise ignored).
 
         // Get the size of the next chunk
         let length_line = match read_line(bytes, Some(1024), Error::MalformedChunkLength) {
@@ -398,16 +1226,27 @@ fn read_chunked(
         let incoming_length = if length_line.is_empty() {
             0
         } else {
-            let length = if let Some(i) = length_line.find(';') {
-                length_line[..i].trim()
-            } else {
-                length_line.trim()
+            let (length, extensions) = match length_line.find(';') {
+                Some(i) => (length_line[..i].trim(), &length_line[i..]),
+                None => (length_line.trim(), ""),
             };
-            match usize::from_str_radix(length, 16) {
+            // from_str_radix would accept a sign, which chunk sizes
+            // can't have.
+            if length.starts_with('+') {
+                return Some(Err(Error::MalformedChunkLength));
+            }
+            let length = match usize::from_str_radix(length, 16) {
                 Ok(length) => length,
                 Err(_) => return Some(Err(Error::MalformedChunkLength)),
+            };
+            if !valid_chunk_extensions(extensions) {
+                return Some(Err(Error::MalformedChunkExtension));
             }
+            length
         };
+        if max_chunk_size.map_or(false, |max_size| incoming_length > max_size) {
+            return Some(Err(Error::ChunkTooLarge));
+        }
 
         if incoming_length == 0 {
             if let Err(err) = read_trailers(bytes, headers, max_trailing_headers_size) {
@@ -420,7 +1259,10 @@ fn read_chunked(
             return None;
         }
         *chunk_length = incoming_length;
-        *content_length += incoming_length;
+        *content_length = match content_length.checked_add(incoming_length) {
+            Some(length) => length,
+            None => return Some(Err(Error::MalformedChunkLength)),
+        };
     }
 
     if *chunk_length > 0 {
@@ -451,13 +1293,82 @@ fn read_chunked(
     None
 }
 
+/// Returns true if `extensions` is a valid list of chunk extensions,
+/// ie. `;name` or `;name=value` pairs where the value is a token or a
+/// quoted string, with optional whitespace around the separators
+/// ([RFC 9112 section 7.1.1](https://www.rfc-editor.org/rfc/rfc9112#section-7.1.1)).
+fn valid_chunk_extensions(extensions: &str) -> bool {
+    fn is_tchar(c: u8) -> bool {
+        c.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&c)
+    }
+    fn skip_whitespace(bytes: &[u8], i: &mut usize) {
+        while *i < bytes.len() && (bytes[*i] == b' ' || bytes[*i] == b'\t') {
+            *i += 1;
+        }
+    }
+    fn token(bytes: &[u8], i: &mut usize) -> bool {
+        let start = *i;
+        while *i < bytes.len() && is_tchar(bytes[*i]) {
+            *i += 1;
+        }
+        *i > start
+    }
+    fn quoted_string(bytes: &[u8], i: &mut usize) -> bool {
+        *i += 1;
+        while *i < bytes.len() {
+            match bytes[*i] {
+                b'"' => {
+                    *i += 1;
+                    return true;
+                }
+                b'\\' if *i + 1 < bytes.len() => *i += 2,
+                b'\t' | b' '..=b'~' | 0x80..=0xFF => *i += 1,
+                _ => return false,
+            }
+        }
+        false
+    }
+
+    let bytes = extensions.as_bytes();
+    let mut i = 0;
+    loop {
+        skip_whitespace(bytes, &mut i);
+        if i == bytes.len() {
+            return true;
+        }
+        if bytes[i] != b';' {
+            return false;
+        }
+        i += 1;
+        skip_whitespace(bytes, &mut i);
+        if !token(bytes, &mut i) {
+            return false;
+        }
+        skip_whitespace(bytes, &mut i);
+        if i < bytes.len() && bytes[i] == b'=' {
+            i += 1;
+            skip_whitespace(bytes, &mut i);
+            let valid = if i < bytes.len() && bytes[i] == b'"' {
+                quoted_string(bytes, &mut i)
+            } else {
+                token(bytes, &mut i)
+            };
+            if !valid {
+                return false;
+            }
+        }
+    }
+}
+
 enum HttpStreamState {
     // No Content-Length, and Transfer-Encoding != chunked, so we just
     // read unti lthe server closes the connection (this should be the
     // fallback, if I read the rfc right).
     EndOnClose,
-    // Content-Length was specified, read that amount of bytes
-    ContentLength(usize),
+    // Content-Length was specified, read that amount of bytes. The
+    // second number is the Content-Length, the first is how much of
+    // it is left to read.
+    ContentLength(usize, usize),
     // Transfer-Encoding == chunked, so we need to save two pieces of
     // information: are we expecting more chunks, how much is there
     // left of the current chunk, and how much have we read? The last
@@ -476,6 +1387,13 @@ struct ResponseMetadata {
     headers: HashMap<String, String>,
     state: HttpStreamState,
     max_trailing_headers_size: Option<usize>,
+    /// The transfer codings applied to the body, other than chunked,
+    /// in the order they were applied.
+    transfer_codings: Vec<String>,
+    /// Whether the server allows sending more requests on the
+    /// connection after this response.
+    keep_alive: bool,
+    set_cookies: Vec<String>,
 }
 
 fn read_metadata(
@@ -483,10 +1401,22 @@ fn read_metadata(
     mut max_headers_size: Option<usize>,
     max_status_line_len: Option<usize>,
 ) -> Result<ResponseMetadata, Error> {
-    let line = read_line(stream, max_status_line_len, Error::StatusLineOverflow)?;
+    // If the server closes the connection before sending anything
+    // back, the request was likely sent on a connection the server
+    // was in the middle of closing.
+    let line = match read_line_or_eof(stream, max_status_line_len, Error::StatusLineOverflow) {
+        Ok(Some(line)) => line,
+        Ok(None) => return Err(Error::ConnectionClosed),
+        Err(Error::IoError(err)) if is_connection_closed(&err) => {
+            return Err(Error::ConnectionClosed)
+        }
+        Err(err) => return Err(err),
+    };
     let (status_code, reason_phrase) = parse_status_line(&line);
+    let http_1_0 = line.starts_with("HTTP/1.0");
 
     let mut headers = HashMap::new();
+    let mut set_cookies = Vec::new();
     loop {
         let line = read_line(stream, max_headers_size, Error::HeadersOverflow)?;
         if line.is_empty() {
@@ -497,18 +1427,30 @@ fn read_metadata(
             *max_headers_size -= line.len() + 2;
         }
         if let Some(header) = parse_header(line) {
+            if header.0 == "set-cookie" {
+                set_cookies.push(header.1.clone());
+            }
             headers.insert(header.0, header.1);
         }
     }
 
     let mut chunked = false;
     let mut content_length = None;
+    let mut transfer_codings = Vec::new();
     for (header, value) in &headers {
-        // Handle the Transfer-Encoding header
-        if header.to_lowercase().trim() == "transfer-encoding"
-            && value.to_lowercase().trim() == "chunked"
-        {
-            chunked = true;
+        // Handle the Transfer-Encoding header. The codings are listed
+        // in the order they were applied, and chunked can only be the
+        // last one, eg. "gzip, chunked".
+        if header.to_lowercase().trim() == "transfer-encoding" {
+            transfer_codings = value
+                .split(',')
+                .map(|coding| coding.trim().to_lowercase())
+                .filter(|coding| !coding.is_empty() && coding != "identity")
+                .collect();
+            if transfer_codings.last().map(String::as_str) == Some("chunked") {
+                chunked = true;
+                transfer_codings.pop();
+            }
         }
 
         // Handle the Content-Length header
@@ -520,10 +1462,23 @@ fn read_metadata(
         }
     }
 
+    // HTTP/1.1 connections are persistent unless either side says
+    // otherwise, HTTP/1.0 ones only if the server says so.
+    let connection = headers
+        .get("connection")
+        .map(|value| value.to_ascii_lowercase())
+        .unwrap_or_default();
+    let has_option = |option: &str| connection.split(',').any(|o| o.trim() == option);
+    let keep_alive = if http_1_0 {
+        has_option("keep-alive")
+    } else {
+        !has_option("close")
+    };
+
     let state = if chunked {
         HttpStreamState::Chunked(true, 0, 0)
     } else if let Some(length) = content_length {
-        HttpStreamState::ContentLength(length)
+        HttpStreamState::ContentLength(length, length)
     } else {
         HttpStreamState::EndOnClose
     };
@@ -534,6 +1489,9 @@ fn read_metadata(
         headers,
         state,
         max_trailing_headers_size: max_headers_size,
+        transfer_codings,
+        keep_alive,
+        set_cookies,
     })
 }
 
@@ -542,8 +1500,20 @@ fn read_line(
     max_len: Option<usize>,
     overflow_error: Error,
 ) -> Result<String, Error> {
+    read_line_or_eof(stream, max_len, overflow_error).map(Option::unwrap_or_default)
+}
+
+/// Like [read_line], but returns `None` if the stream ended before
+/// any bytes could be read.
+fn read_line_or_eof(
+    stream: &mut HttpStreamBytes,
+    max_len: Option<usize>,
+    overflow_error: Error,
+) -> Result<Option<String>, Error> {
     let mut bytes = Vec::with_capacity(32);
+    let mut eof = true;
     for byte in stream {
+        eof = false;
         match byte {
             Ok(byte) => {
                 if let Some(max_len) = max_len {
@@ -567,7 +1537,35 @@ fn read_line(
             Err(err) => return Err(Error::IoError(err)),
         }
     }
-    String::from_utf8(bytes).map_err(|_error| Error::InvalidUtf8InResponse)
+    if eof {
+        return Ok(None);
+    }
+    match String::from_utf8(bytes) {
+        Ok(line) => Ok(Some(line)),
+        Err(_) => Err(Error::InvalidUtf8InResponse),
+    }
+}
+
+/// Returns true if the error means that the other end closed the
+/// connection.
+pub(crate) fn is_connection_closed(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        ErrorKind::BrokenPipe
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::UnexpectedEof
+    )
+}
+
+/// Parses the header `name` with `parse`, ignoring malformed values.
+fn structured_header<T>(
+    headers: &HashMap<String, String>,
+    name: &str,
+    parse: fn(&str) -> Result<T, Error>,
+) -> Option<T> {
+    let value = headers.get(&name.to_lowercase())?;
+    parse(value).ok()
 }
 
 fn parse_status_line(line: &str) -> (i32, String) {
@@ -0,0 +1,361 @@
+use crate::{parse_http_date, Error, Event, Method, Request, Response, ResponseLazy};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Controls if and how a request is retried after failing.
+///
+/// Requests are retried when sending them fails with an IO error
+/// (including timeouts) or because the server closed the connection
+/// (also in the middle of the body), or when the server responds with
+/// 408, 429, 502, 503 or 504. The delay between attempts starts at the
+/// initial delay and doubles after each attempt, up to the maximum
+/// delay. A `Retry-After` header overrides this, but is still capped
+/// at the maximum delay.
+///
+/// Only requests with idempotent methods (GET, HEAD, PUT, DELETE,
+/// OPTIONS and TRACE) are retried, unless they have an idempotency key
+/// (see
+/// [`Request::with_idempotency_key`](struct.Request.html#method.with_idempotency_key)),
+/// as the server might have processed the failed attempt.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), minreq::Error> {
+/// use minreq::RetryPolicy;
+/// use std::time::Duration;
+///
+/// let response = minreq::get("http://example.com")
+///     .with_retry(RetryPolicy::new(3).with_initial_delay(Duration::from_millis(500)))
+///     .send()?;
+/// # Ok(()) }
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RetryPolicy {
+    max_retries: usize,
+    initial_delay: Duration,
+    max_delay: Duration,
+    max_retry_time: Option<Duration>,
+    budget: Option<RetryBudget>,
+}
+
+impl RetryPolicy {
+    /// Creates a policy that retries a request at most `max_retries`
+    /// times, with an initial delay of 250 milliseconds and a maximum
+    /// delay of 10 seconds.
+    pub fn new(max_retries: usize) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            initial_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(10),
+            max_retry_time: None,
+            budget: None,
+        }
+    }
+
+    /// Sets the delay before the first retry.
+    pub fn with_initial_delay(mut self, initial_delay: Duration) -> RetryPolicy {
+        self.initial_delay = initial_delay;
+        self
+    }
+
+    /// Sets the maximum delay between retries.
+    pub fn with_max_delay(mut self, max_delay: Duration) -> RetryPolicy {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Sets the maximum total time spent on a request, measured from
+    /// the start of the first attempt. A retry that would start after
+    /// this time is not made.
+    pub fn with_max_retry_time(mut self, max_retry_time: Duration) -> RetryPolicy {
+        self.max_retry_time = Some(max_retry_time);
+        self
+    }
+
+    /// Limits the retries with `budget`, which can be shared between
+    /// many requests (and policies) by cloning it. See [RetryBudget].
+    pub fn with_budget(mut self, budget: RetryBudget) -> RetryPolicy {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Sends `request` with `send` until it succeeds, fails in a way
+    /// that shouldn't be retried, or the retries run out.
+    pub(crate) fn run<T, F>(&self, request: Request, send: F) -> Result<T, Error>
+    where
+        T: Retryable,
+        F: Fn(Request) -> Result<T, Error>,
+    {
+        if !request.is_retry_safe() {
+            return send(request);
+        }
+        self.retry(
+            &request,
+            || send(request.clone()),
+            |result| match result {
+                Ok(response) if should_retry_status(response.status_code()) => {
+                    Some(retry_after(response.headers()))
+                }
+                Ok(_) => None,
+                Err(err) if is_retryable_error(err) => Some(None),
+                Err(_) => None,
+            },
+        )
+    }
+
+    /// Makes `attempt` until it succeeds, fails in a way that
+    /// shouldn't be retried, or the retries run out. `should_retry`
+    /// returns None for results that aren't retried, and otherwise
+    /// the delay the server asked for, if any. Retries are passed to
+    /// the event hook of `request`.
+    pub(crate) fn retry<T, A, S>(
+        &self,
+        request: &Request,
+        mut attempt: A,
+        should_retry: S,
+    ) -> Result<T, Error>
+    where
+        A: FnMut() -> Result<T, Error>,
+        S: Fn(&Result<T, Error>) -> Option<Option<Duration>>,
+    {
+        let start = Instant::now();
+        if let Some(budget) = &self.budget {
+            budget.record_request();
+        }
+        let mut delay = self.initial_delay;
+        let mut retries = 0;
+        loop {
+            let result = attempt();
+            let retry_after = match should_retry(&result) {
+                Some(retry_after) => retry_after,
+                None => return result,
+            };
+            let wait = retry_after.unwrap_or(delay).min(self.max_delay);
+
+            if retries >= self.max_retries {
+                return result;
+            }
+            if let Some(max_retry_time) = self.max_retry_time {
+                if start.elapsed() + wait > max_retry_time {
+                    log::debug!("Request failed, not retrying: out of time.");
+                    return result;
+                }
+            }
+            if let Some(budget) = &self.budget {
+                if !budget.withdraw() {
+                    log::debug!("Request failed, not retrying: the retry budget is spent.");
+                    return result;
+                }
+            }
+
+            retries += 1;
+            log::debug!("Request failed, retry {} in {:?}.", retries, wait);
+            request.emit(|| Event::Retried {
+                retry: retries,
+                delay: wait,
+            });
+            thread::sleep(wait);
+            delay = (delay * 2).min(self.max_delay);
+        }
+    }
+}
+
+/// A limit on the fraction of requests that can be retried, shared
+/// between all the requests whose [RetryPolicy] uses it.
+///
+/// When a service is down, retrying every request multiplies the load
+/// on it, which can keep it from recovering. With a budget, only a
+/// fraction of the requests are retried during an outage, while
+/// occasional failures are still retried normally.
+///
+/// The budget is counted in windows of 10 seconds: within a window, at
+/// most `retry_ratio` times the requests made in it can be retried,
+/// plus a minimum of [`with_min_retries`](#method.with_min_retries)
+/// retries.
+///
+/// # Example
+///
+/// ```
+/// use minreq::{RetryBudget, RetryPolicy};
+///
+/// // Retry at most 10% of the requests, plus 5 retries per window.
+/// let budget = RetryBudget::new(0.1).with_min_retries(5);
+/// let policy = RetryPolicy::new(3).with_budget(budget);
+/// let first = minreq::get("http://example.com/a").with_retry(policy.clone());
+/// let second = minreq::get("http://example.com/b").with_retry(policy);
+/// ```
+#[derive(Clone)]
+pub struct RetryBudget {
+    retry_ratio: f32,
+    min_retries: u32,
+    window: Duration,
+    state: Arc<Mutex<RetryBudgetState>>,
+}
+
+struct RetryBudgetState {
+    window_start: Instant,
+    requests: u32,
+    retries: u32,
+}
+
+impl RetryBudget {
+    /// Creates a new budget allowing `retry_ratio` retries per
+    /// request, eg. 0.2 for retrying at most every fifth request, and
+    /// 10 retries per window regardless of the ratio.
+    pub fn new(retry_ratio: f32) -> RetryBudget {
+        RetryBudget {
+            retry_ratio,
+            min_retries: 10,
+            window: Duration::from_secs(10),
+            state: Arc::new(Mutex::new(RetryBudgetState {
+                window_start: Instant::now(),
+                requests: 0,
+                retries: 0,
+            })),
+        }
+    }
+
+    /// Sets the amount of retries allowed per window regardless of
+    /// the retry ratio, so that clients making few requests can still
+    /// retry.
+    pub fn with_min_retries(mut self, min_retries: u32) -> RetryBudget {
+        self.min_retries = min_retries;
+        self
+    }
+
+    fn with_state<R, F: FnOnce(&mut RetryBudgetState) -> R>(&self, f: F) -> R {
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if state.window_start.elapsed() >= self.window {
+            state.window_start = Instant::now();
+            state.requests = 0;
+            state.retries = 0;
+        }
+        f(&mut state)
+    }
+
+    pub(crate) fn record_request(&self) {
+        self.with_state(|state| state.requests = state.requests.saturating_add(1));
+    }
+
+    /// Takes a retry from the budget, returning false if there are
+    /// none left.
+    pub(crate) fn withdraw(&self) -> bool {
+        let (ratio, min_retries) = (self.retry_ratio, self.min_retries);
+        self.with_state(|state| {
+            let allowed = min_retries as f32 + ratio * state.requests as f32;
+            if (state.retries as f32) < allowed {
+                state.retries += 1;
+                true
+            } else {
+                false
+            }
+        })
+    }
+}
+
+impl PartialEq for RetryBudget {
+    fn eq(&self, other: &RetryBudget) -> bool {
+        Arc::ptr_eq(&self.state, &other.state)
+    }
+}
+
+impl Eq for RetryBudget {}
+
+impl fmt::Debug for RetryBudget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RetryBudget")
+            .field("retry_ratio", &self.retry_ratio)
+            .field("min_retries", &self.min_retries)
+            .finish()
+    }
+}
+
+/// The parts of a response needed for deciding whether to retry.
+pub(crate) trait Retryable {
+    fn status_code(&self) -> i32;
+    fn headers(&self) -> &HashMap<String, String>;
+}
+
+impl Retryable for Response {
+    fn status_code(&self) -> i32 {
+        self.status_code
+    }
+
+    fn headers(&self) -> &HashMap<String, String> {
+        &self.headers
+    }
+}
+
+impl Retryable for ResponseLazy {
+    fn status_code(&self) -> i32 {
+        self.status_code
+    }
+
+    fn headers(&self) -> &HashMap<String, String> {
+        &self.headers
+    }
+}
+
+/// Returns true if requests failing with `err` may succeed when
+/// they're retried, ie. the connection failed or was closed.
+pub(crate) fn is_retryable_error(err: &Error) -> bool {
+    matches!(
+        err,
+        Error::IoError(_) | Error::ConnectionClosed | Error::TruncatedBody { .. }
+    )
+}
+
+pub(crate) fn should_retry_status(status_code: i32) -> bool {
+    matches!(status_code, 408 | 429 | 502 | 503 | 504)
+}
+
+/// Returns the delay in the `Retry-After` header, given either in
+/// seconds or as a date.
+fn retry_after(headers: &HashMap<String, String>) -> Option<Duration> {
+    let retry_after = headers.get("retry-after")?.trim();
+    match retry_after.parse::<u64>() {
+        Ok(seconds) => Some(Duration::from_secs(seconds)),
+        Err(_) => {
+            let date = parse_http_date(retry_after)?;
+            Some(date.duration_since(SystemTime::now()).unwrap_or_default())
+        }
+    }
+}
+
+/// Returns true if requests with `method` can be safely repeated.
+pub(crate) fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        method,
+        Method::Get | Method::Head | Method::Put | Method::Delete | Method::Options | Method::Trace
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RetryBudget;
+
+    #[test]
+    fn test_retry_budget() {
+        let budget = RetryBudget::new(0.1).with_min_retries(1);
+        let shared = budget.clone();
+        for _ in 0..10 {
+            budget.record_request();
+        }
+        assert!(budget.withdraw());
+        assert!(shared.withdraw());
+        assert!(!budget.withdraw());
+
+        for _ in 0..10 {
+            shared.record_request();
+        }
+        assert!(budget.withdraw());
+        assert!(!budget.withdraw());
+    }
+}
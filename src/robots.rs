@@ -0,0 +1,273 @@
+//! Fetching and evaluating [robots.txt](https://www.rfc-editor.org/rfc/rfc9309)
+//! files, enabled by the `robots` feature.
+
+use crate::{Error, Method, Request, URL};
+use std::time::Duration;
+
+/// The rules of a robots.txt file, for checking which paths of a site
+/// a crawler may fetch, as described in RFC 9309.
+///
+/// The rules of the groups for the crawler's user agent apply, or the
+/// ones for `*` if there are none. The rule with the longest pattern
+/// matching a path decides whether it's allowed, with `Allow` winning
+/// ties, and paths that no rule matches are allowed. Patterns may
+/// contain `*` wildcards and end with `$` to match the end of the
+/// path.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), minreq::Error> {
+/// use minreq::RobotsTxt;
+///
+/// let robots = RobotsTxt::fetch("https://example.com")?;
+/// if robots.is_allowed("MyCrawler/1.0", "/articles/1?page=2") {
+///     if let Some(delay) = robots.crawl_delay("MyCrawler/1.0") {
+///         std::thread::sleep(delay);
+///     }
+///     let page = minreq::get("https://example.com/articles/1?page=2").send()?;
+/// }
+/// # Ok(()) }
+/// ```
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct RobotsTxt {
+    groups: Vec<Group>,
+    sitemaps: Vec<String>,
+}
+
+/// The rules following one or more `User-agent` lines.
+#[derive(Clone, PartialEq, Debug, Default)]
+struct Group {
+    /// The user agents the group is for, in lowercase.
+    user_agents: Vec<String>,
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+impl RobotsTxt {
+    /// Parses the contents of a robots.txt file. Lines that can't be
+    /// parsed are skipped, as the RFC requires.
+    pub fn parse(text: &str) -> RobotsTxt {
+        let mut robots = RobotsTxt::default();
+        // True while the current group's User-agent lines are read.
+        let mut in_user_agents = false;
+        for line in text.lines() {
+            let line = match line.find('#') {
+                Some(comment) => &line[..comment],
+                None => line,
+            };
+            let colon = match line.find(':') {
+                Some(colon) => colon,
+                None => continue,
+            };
+            let key = line[..colon].trim().to_ascii_lowercase();
+            let value = line[colon + 1..].trim();
+            match key.as_str() {
+                "user-agent" => {
+                    if !in_user_agents {
+                        robots.groups.push(Group::default());
+                        in_user_agents = true;
+                    }
+                    let group = robots.groups.last_mut().unwrap();
+                    group.user_agents.push(value.to_ascii_lowercase());
+                }
+                "allow" | "disallow" | "crawl-delay" => {
+                    in_user_agents = false;
+                    // Rules before the first User-agent line belong to
+                    // no group.
+                    let group = match robots.groups.last_mut() {
+                        Some(group) => group,
+                        None => continue,
+                    };
+                    if key == "crawl-delay" {
+                        if let Ok(seconds) = value.parse::<f64>() {
+                            if seconds >= 0.0 && seconds < u64::MAX as f64 {
+                                group.crawl_delay = Some(Duration::from_secs_f64(seconds));
+                            }
+                        }
+                    } else if !value.is_empty() {
+                        group.rules.push(Rule {
+                            allow: key == "allow",
+                            pattern: value.to_string(),
+                        });
+                    }
+                }
+                "sitemap" => robots.sitemaps.push(value.to_string()),
+                _ => {}
+            }
+        }
+        robots
+    }
+
+    /// Fetches and parses the robots.txt file of `origin`, eg.
+    /// `https://example.com`.
+    ///
+    /// As RFC 9309 requires, everything is allowed if the file
+    /// doesn't exist or the server responds with another 4xx status,
+    /// and nothing is if it responds with a 5xx status. Redirects are
+    /// followed.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the request if it fails, in which case
+    /// crawlers should assume that nothing is allowed.
+    pub fn fetch<T: Into<URL>>(origin: T) -> Result<RobotsTxt, Error> {
+        let origin = origin.into();
+        let url = format!("{}/robots.txt", origin.trim_end_matches('/'));
+        let response = Request::new(Method::Get, url).send()?;
+        Ok(match response.status_code {
+            200..=299 => RobotsTxt::parse(&String::from_utf8_lossy(response.as_bytes())),
+            400..=499 => RobotsTxt::default(),
+            _ => RobotsTxt::parse("User-agent: *\nDisallow: /"),
+        })
+    }
+
+    /// Returns true if the crawler with `user_agent`, eg.
+    /// `MyCrawler/1.0`, may fetch `path`, which may include a query.
+    /// Only the product token of the user agent (`MyCrawler`) is
+    /// matched against the file's user agents.
+    pub fn is_allowed(&self, user_agent: &str, path: &str) -> bool {
+        let path = if path.is_empty() { "/" } else { path };
+        if path == "/robots.txt" {
+            return true;
+        }
+        let mut best: Option<&Rule> = None;
+        for rule in self.groups_for(user_agent).flat_map(|group| &group.rules) {
+            if !matches(&rule.pattern, path) {
+                continue;
+            }
+            best = match best {
+                Some(best)
+                    if best.pattern.len() > rule.pattern.len()
+                        || (best.pattern.len() == rule.pattern.len() && best.allow) =>
+                {
+                    Some(best)
+                }
+                _ => Some(rule),
+            };
+        }
+        best.map_or(true, |rule| rule.allow)
+    }
+
+    /// Returns how long the crawler with `user_agent` should wait
+    /// between requests, if the file says so with `Crawl-delay`.
+    pub fn crawl_delay(&self, user_agent: &str) -> Option<Duration> {
+        self.groups_for(user_agent)
+            .find_map(|group| group.crawl_delay)
+    }
+
+    /// Returns the URLs of the `Sitemap` lines.
+    pub fn sitemaps(&self) -> &[String] {
+        &self.sitemaps
+    }
+
+    /// Returns the groups for the product token of `user_agent`, or
+    /// the ones for `*` if there are none.
+    fn groups_for<'a>(&'a self, user_agent: &str) -> impl Iterator<Item = &'a Group> {
+        let token = user_agent
+            .split(|c: char| c == '/' || c.is_whitespace())
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        let has_own = self
+            .groups
+            .iter()
+            .any(|group| group.user_agents.contains(&token));
+        let token = if has_own { token } else { "*".to_string() };
+        self.groups
+            .iter()
+            .filter(move |group| group.user_agents.contains(&token))
+    }
+}
+
+/// Returns true if the robots.txt `pattern` matches `path`.
+fn matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    if !path.starts_with(first) {
+        return false;
+    }
+    let mut rest = &path[first.len()..];
+    let parts: Vec<&str> = parts.collect();
+    if parts.is_empty() {
+        return !anchored || rest.is_empty();
+    }
+    for (i, part) in parts.iter().enumerate() {
+        // The last part of an anchored pattern has to be at the end,
+        // which is the only place left to look for it after a `*`.
+        if anchored && i == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(start) => rest = &rest[start + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{matches, RobotsTxt};
+    use std::time::Duration;
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("/fish", "/fish.html"));
+        assert!(!matches("/fish", "/Fish.asp"));
+        assert!(matches("/*.php", "/folder/filename.php?parameters"));
+        assert!(!matches("/*.php$", "/filename.php?parameters"));
+        assert!(matches("/*.php$", "/folder/filename.php"));
+        assert!(matches("/fish*.php", "/fishheads/catfish.php?parameters"));
+        assert!(!matches("/fish*.php", "/Fish.PHP"));
+        assert!(matches("/$", "/"));
+        assert!(!matches("/$", "/page"));
+    }
+
+    #[test]
+    fn test_is_allowed() {
+        let robots = RobotsTxt::parse(
+            "# Example
+User-agent: *
+Disallow: /private/
+Allow: /private/public.html
+Crawl-delay: 2.5
+
+User-agent: ExampleBot
+User-agent: OtherBot # Shares the group
+Disallow: /
+Allow: /$
+
+Sitemap: https://example.com/sitemap.xml
+",
+        );
+        assert!(robots.is_allowed("minreq", "/"));
+        assert!(!robots.is_allowed("minreq", "/private/secret.html"));
+        assert!(robots.is_allowed("minreq", "/private/public.html"));
+        assert!(robots.is_allowed("ExampleBot/2.1", "/"));
+        assert!(!robots.is_allowed("examplebot", "/page"));
+        assert!(!robots.is_allowed("OtherBot", "/page"));
+        assert!(robots.is_allowed("OtherBot", "/robots.txt"));
+        assert_eq!(
+            robots.crawl_delay("minreq/2.8"),
+            Some(Duration::from_millis(2500))
+        );
+        assert_eq!(robots.crawl_delay("ExampleBot"), None);
+        assert_eq!(robots.sitemaps(), ["https://example.com/sitemap.xml"]);
+
+        // Allow wins ties, and an empty file allows everything.
+        let robots = RobotsTxt::parse("User-agent: *\nDisallow: /page\nAllow: /page\n");
+        assert!(robots.is_allowed("minreq", "/page"));
+        assert!(RobotsTxt::parse("").is_allowed("minreq", "/anything"));
+    }
+}
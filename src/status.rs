@@ -0,0 +1,137 @@
+use crate::charset::{body_charset, decode, Charset};
+#[cfg(feature = "json-using-serde")]
+use crate::problem::{problem_details, ProblemDetails};
+use crate::{Error, Response, ResponseLazy};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+/// How much of the body of an error response is kept in a
+/// [StatusError].
+const MAX_ERROR_BODY_SIZE: usize = 64 * 1024;
+
+/// A response with a client or server error status code (400-599),
+/// returned in an [`Error::StatusError`] by
+/// [`Response::error_for_status`](struct.Response.html#method.error_for_status).
+///
+/// Many APIs explain their errors in the body, eg. why a request was
+/// unauthorized, so the first 64 KiB of the body are kept. They're
+/// only decoded when asked for, with [`text`](#method.text) or
+/// [`json`](#method.json).
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct StatusError {
+    /// The status code of the response, eg. 401.
+    pub status_code: i32,
+    /// The reason phrase of the response, eg. "Unauthorized".
+    pub reason_phrase: String,
+    /// The headers of the response. The header field names (the
+    /// keys) are all lowercase.
+    pub headers: HashMap<String, String>,
+    body: Vec<u8>,
+    truncated: bool,
+}
+
+impl StatusError {
+    pub(crate) fn from_response(response: &Response) -> StatusError {
+        let body = response.as_bytes();
+        let kept = &body[..body.len().min(MAX_ERROR_BODY_SIZE)];
+        StatusError {
+            status_code: response.status_code,
+            reason_phrase: response.reason_phrase.clone(),
+            headers: response.headers.clone(),
+            body: kept.to_vec(),
+            truncated: kept.len() < body.len(),
+        }
+    }
+
+    /// Reads the start of the body of `response`. Errors while
+    /// reading the body just end it, as the status is the error.
+    pub(crate) fn from_lazy(mut response: ResponseLazy) -> StatusError {
+        let mut body = Vec::new();
+        let mut truncated = false;
+        for byte in &mut response {
+            match byte {
+                Ok(_) if body.len() == MAX_ERROR_BODY_SIZE => {
+                    truncated = true;
+                    break;
+                }
+                Ok((byte, _)) => body.push(byte),
+                Err(_) => break,
+            }
+        }
+        StatusError {
+            status_code: response.status_code,
+            reason_phrase: std::mem::take(&mut response.reason_phrase),
+            headers: std::mem::take(&mut response.headers),
+            body,
+            truncated,
+        }
+    }
+
+    /// Returns the kept part of the body.
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Returns true if the body was longer than what was kept.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Returns true for `401 Unauthorized` and `403 Forbidden`
+    /// responses.
+    pub fn is_auth_error(&self) -> bool {
+        self.status_code == 401 || self.status_code == 403
+    }
+
+    /// Returns the body decoded into a string, like
+    /// [`Response::text`](struct.Response.html#method.text). A
+    /// truncated body may end in the middle of a character, which is
+    /// replaced with U+FFFD.
+    ///
+    /// # Errors
+    ///
+    /// See [`Response::text`](struct.Response.html#method.text).
+    pub fn text(&self) -> Result<Cow<'_, str>, Error> {
+        let charset = body_charset(&self.headers, &self.body, false)?;
+        match decode(&self.body, charset.unwrap_or(Charset::Utf8)) {
+            Err(_) if self.truncated && charset.map_or(true, |c| c == Charset::Utf8) => {
+                Ok(String::from_utf8_lossy(&self.body))
+            }
+            result => result,
+        }
+    }
+
+    /// Returns the Problem Details in the body, see
+    /// [`Response::problem_details`](struct.Response.html#method.problem_details).
+    #[cfg(feature = "json-using-serde")]
+    pub fn problem_details(&self) -> Option<ProblemDetails> {
+        problem_details(&self.headers, &self.body)
+    }
+
+    /// Deserializes the body from JSON, eg. into an API's error type.
+    ///
+    /// # Errors
+    ///
+    /// Returns
+    /// [`SerdeJsonError`](enum.Error.html#variant.SerdeJsonError) if
+    /// the body is not valid JSON (or truncated), and
+    /// [`InvalidUtf8InResponse`](enum.Error.html#variant.InvalidUtf8InResponse)
+    /// if it's not UTF-8.
+    #[cfg(feature = "json-using-serde")]
+    pub fn json<T: serde::de::DeserializeOwned>(&self) -> Result<T, Error> {
+        let text = std::str::from_utf8(&self.body).map_err(|_| Error::InvalidUtf8InResponse)?;
+        serde_json::from_str(text).map_err(Error::SerdeJsonError)
+    }
+}
+
+impl fmt::Display for StatusError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.status_code, self.reason_phrase)
+    }
+}
+
+/// Returns true if `status_code` is a client or server error.
+pub(crate) fn is_error_status(status_code: i32) -> bool {
+    (400..600).contains(&status_code)
+}
@@ -0,0 +1,576 @@
+use crate::encoding::base64_decode;
+use crate::Error;
+
+/// A bare item of a structured header field ([RFC 8941 section
+/// 3.3](https://www.rfc-editor.org/rfc/rfc8941#section-3.3)), ie. an
+/// item without its parameters.
+#[derive(Clone, PartialEq, Debug)]
+pub enum BareItem {
+    /// An integer, eg. `42`.
+    Integer(i64),
+    /// A decimal number, eg. `4.5`.
+    Decimal(f64),
+    /// A quoted string, eg. `"hello"`, without the quotes and escapes.
+    String(String),
+    /// A token, eg. `gzip` or `*/*`.
+    Token(String),
+    /// Binary data, base64 encoded between colons, eg. `:AQID:`.
+    ByteSequence(Vec<u8>),
+    /// A boolean, `?1` or `?0`.
+    Boolean(bool),
+}
+
+impl BareItem {
+    /// Returns the integer, or None if this is not an integer.
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            BareItem::Integer(integer) => Some(*integer),
+            _ => None,
+        }
+    }
+
+    /// Returns the number, or None if this is not an integer or a
+    /// decimal.
+    pub fn as_decimal(&self) -> Option<f64> {
+        match self {
+            BareItem::Integer(integer) => Some(*integer as f64),
+            BareItem::Decimal(decimal) => Some(*decimal),
+            _ => None,
+        }
+    }
+
+    /// Returns the string or token, or None if this is neither.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            BareItem::String(string) | BareItem::Token(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    /// Returns the binary data, or None if this is not a byte
+    /// sequence.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            BareItem::ByteSequence(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// Returns the boolean, or None if this is not a boolean.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            BareItem::Boolean(boolean) => Some(*boolean),
+            _ => None,
+        }
+    }
+}
+
+/// A structured field item: a bare item with parameters, eg.
+/// `"foo"; a=1`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct StructuredItem {
+    /// The value of the item.
+    pub value: BareItem,
+    /// The parameters of the item, in order. Parameters without a
+    /// value are `BareItem::Boolean(true)`.
+    pub params: Vec<(String, BareItem)>,
+}
+
+impl StructuredItem {
+    /// Parses a field value that is a single item.
+    ///
+    /// # Errors
+    ///
+    /// Returns
+    /// [`InvalidStructuredField`](enum.Error.html#variant.InvalidStructuredField)
+    /// if `value` is not a valid item.
+    pub fn parse(value: &str) -> Result<StructuredItem, Error> {
+        Parser::new(value).parse_all(Parser::parse_item)
+    }
+
+    /// Returns the value of the parameter `key`.
+    pub fn param(&self, key: &str) -> Option<&BareItem> {
+        find_param(&self.params, key)
+    }
+}
+
+/// A parenthesized list of items in a list or dictionary, eg.
+/// `("foo" "bar");lvl=5`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct InnerList {
+    /// The items of the list.
+    pub items: Vec<StructuredItem>,
+    /// The parameters of the list itself, in order.
+    pub params: Vec<(String, BareItem)>,
+}
+
+impl InnerList {
+    /// Returns the value of the parameter `key`.
+    pub fn param(&self, key: &str) -> Option<&BareItem> {
+        find_param(&self.params, key)
+    }
+}
+
+/// A member of a [StructuredList] or a [StructuredDictionary]: either
+/// an item or an inner list.
+#[derive(Clone, PartialEq, Debug)]
+pub enum ListMember {
+    /// A single item.
+    Item(StructuredItem),
+    /// A parenthesized list of items.
+    InnerList(InnerList),
+}
+
+impl ListMember {
+    /// Returns the item, or None if this is an inner list.
+    pub fn as_item(&self) -> Option<&StructuredItem> {
+        match self {
+            ListMember::Item(item) => Some(item),
+            ListMember::InnerList(_) => None,
+        }
+    }
+
+    /// Returns the value of the parameter `key`, of the item or the
+    /// inner list.
+    pub fn param(&self, key: &str) -> Option<&BareItem> {
+        match self {
+            ListMember::Item(item) => item.param(key),
+            ListMember::InnerList(list) => list.param(key),
+        }
+    }
+}
+
+/// A structured field list, eg. `sugar, tea, rum`. This is the type
+/// of headers like `Cache-Status`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct StructuredList {
+    /// The members of the list, in order.
+    pub members: Vec<ListMember>,
+}
+
+impl StructuredList {
+    /// Parses a field value that is a list. An empty value is an
+    /// empty list.
+    ///
+    /// # Errors
+    ///
+    /// Returns
+    /// [`InvalidStructuredField`](enum.Error.html#variant.InvalidStructuredField)
+    /// if `value` is not a valid list.
+    pub fn parse(value: &str) -> Result<StructuredList, Error> {
+        Parser::new(value).parse_all(|parser| {
+            let members = parser.parse_members(Parser::parse_member)?;
+            Ok(StructuredList { members })
+        })
+    }
+}
+
+/// A structured field dictionary, eg. `a=1, b=?0, c;foo=bar`. This is
+/// the type of headers like `Priority` and `RateLimit`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct StructuredDictionary {
+    /// The keys and values of the dictionary, in order. Keys without a
+    /// value have the value `BareItem::Boolean(true)`.
+    pub members: Vec<(String, ListMember)>,
+}
+
+impl StructuredDictionary {
+    /// Parses a field value that is a dictionary. An empty value is an
+    /// empty dictionary.
+    ///
+    /// # Errors
+    ///
+    /// Returns
+    /// [`InvalidStructuredField`](enum.Error.html#variant.InvalidStructuredField)
+    /// if `value` is not a valid dictionary.
+    pub fn parse(value: &str) -> Result<StructuredDictionary, Error> {
+        Parser::new(value).parse_all(|parser| {
+            let entries = parser.parse_members(|parser| {
+                let key = parser.parse_key()?;
+                let member = if parser.peek() == Some(b'=') {
+                    parser.position += 1;
+                    parser.parse_member()?
+                } else {
+                    ListMember::Item(StructuredItem {
+                        value: BareItem::Boolean(true),
+                        params: parser.parse_params()?,
+                    })
+                };
+                Ok((key, member))
+            })?;
+            let mut members = Vec::with_capacity(entries.len());
+            for (key, member) in entries {
+                insert(&mut members, key, member);
+            }
+            Ok(StructuredDictionary { members })
+        })
+    }
+
+    /// Returns the member with the key `key`.
+    pub fn get(&self, key: &str) -> Option<&ListMember> {
+        self.members
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, member)| member)
+    }
+}
+
+fn find_param<'a>(params: &'a [(String, BareItem)], key: &str) -> Option<&'a BareItem> {
+    params
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, value)| value)
+}
+
+/// Inserts `value`, replacing the value of an earlier `key` in its
+/// place, as the RFC requires for duplicate keys.
+fn insert<T>(entries: &mut Vec<(String, T)>, key: String, value: T) {
+    match entries.iter_mut().find(|(k, _)| *k == key) {
+        Some(entry) => entry.1 = value,
+        None => entries.push((key, value)),
+    }
+}
+
+/// A parser following the algorithms of [RFC 8941 section
+/// 4.2](https://www.rfc-editor.org/rfc/rfc8941#section-4.2).
+struct Parser<'a> {
+    input: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Parser<'a> {
+        Parser {
+            input: input.as_bytes(),
+            position: 0,
+        }
+    }
+
+    /// Runs `parse` over the whole input, which may only be
+    /// surrounded by spaces.
+    fn parse_all<T, F>(mut self, parse: F) -> Result<T, Error>
+    where
+        F: FnOnce(&mut Parser<'a>) -> Result<T, Error>,
+    {
+        self.skip_spaces();
+        let value = parse(&mut self)?;
+        self.skip_spaces();
+        if self.position == self.input.len() {
+            Ok(value)
+        } else {
+            Err(Error::InvalidStructuredField)
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Result<u8, Error> {
+        let c = self.peek().ok_or(Error::InvalidStructuredField)?;
+        self.position += 1;
+        Ok(c)
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek() == Some(b' ') {
+            self.position += 1;
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ') | Some(b'\t') = self.peek() {
+            self.position += 1;
+        }
+    }
+
+    /// Parses the comma-separated members of a list or dictionary.
+    fn parse_members<T, F>(&mut self, mut parse_member: F) -> Result<Vec<T>, Error>
+    where
+        F: FnMut(&mut Parser<'a>) -> Result<T, Error>,
+    {
+        let mut members = Vec::new();
+        while self.peek().is_some() {
+            members.push(parse_member(self)?);
+            self.skip_whitespace();
+            if self.peek().is_none() {
+                break;
+            }
+            if self.next()? != b',' {
+                return Err(Error::InvalidStructuredField);
+            }
+            self.skip_whitespace();
+            if self.peek().is_none() {
+                // A trailing comma.
+                return Err(Error::InvalidStructuredField);
+            }
+        }
+        Ok(members)
+    }
+
+    fn parse_member(&mut self) -> Result<ListMember, Error> {
+        if self.peek() != Some(b'(') {
+            return self.parse_item().map(ListMember::Item);
+        }
+        self.position += 1;
+        let mut items = Vec::new();
+        loop {
+            self.skip_spaces();
+            if self.peek() == Some(b')') {
+                self.position += 1;
+                let params = self.parse_params()?;
+                return Ok(ListMember::InnerList(InnerList { items, params }));
+            }
+            items.push(self.parse_item()?);
+            match self.peek() {
+                Some(b' ') | Some(b')') => {}
+                _ => return Err(Error::InvalidStructuredField),
+            }
+        }
+    }
+
+    fn parse_item(&mut self) -> Result<StructuredItem, Error> {
+        let value = self.parse_bare_item()?;
+        let params = self.parse_params()?;
+        Ok(StructuredItem { value, params })
+    }
+
+    fn parse_params(&mut self) -> Result<Vec<(String, BareItem)>, Error> {
+        let mut params = Vec::new();
+        while self.peek() == Some(b';') {
+            self.position += 1;
+            self.skip_spaces();
+            let key = self.parse_key()?;
+            let value = if self.peek() == Some(b'=') {
+                self.position += 1;
+                self.parse_bare_item()?
+            } else {
+                BareItem::Boolean(true)
+            };
+            insert(&mut params, key, value);
+        }
+        Ok(params)
+    }
+
+    fn parse_key(&mut self) -> Result<String, Error> {
+        let start = self.position;
+        match self.peek() {
+            Some(b'a'..=b'z') | Some(b'*') => self.position += 1,
+            _ => return Err(Error::InvalidStructuredField),
+        }
+        while let Some(b'a'..=b'z') | Some(b'0'..=b'9') | Some(b'_') | Some(b'-') | Some(b'.')
+        | Some(b'*') = self.peek()
+        {
+            self.position += 1;
+        }
+        Ok(self.slice(start))
+    }
+
+    fn parse_bare_item(&mut self) -> Result<BareItem, Error> {
+        match self.peek() {
+            Some(b'-') | Some(b'0'..=b'9') => self.parse_number(),
+            Some(b'"') => self.parse_string(),
+            Some(b'*') | Some(b'A'..=b'Z') | Some(b'a'..=b'z') => Ok(self.parse_token()),
+            Some(b':') => self.parse_byte_sequence(),
+            Some(b'?') => self.parse_boolean(),
+            _ => Err(Error::InvalidStructuredField),
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<BareItem, Error> {
+        let start = self.position;
+        if self.peek() == Some(b'-') {
+            self.position += 1;
+        }
+        let digits_start = self.position;
+        let mut point = None;
+        loop {
+            match self.peek() {
+                Some(b'0'..=b'9') => self.position += 1,
+                Some(b'.') if point.is_none() => {
+                    if self.position - digits_start > 12 {
+                        return Err(Error::InvalidStructuredField);
+                    }
+                    point = Some(self.position);
+                    self.position += 1;
+                }
+                _ => break,
+            }
+            let max_length = if point.is_some() { 16 } else { 15 };
+            if self.position - digits_start > max_length {
+                return Err(Error::InvalidStructuredField);
+            }
+        }
+        let number = self.slice(start);
+        match point {
+            None if self.position > digits_start => number
+                .parse()
+                .map(BareItem::Integer)
+                .map_err(|_| Error::InvalidStructuredField),
+            Some(point) if (2..=4).contains(&(self.position - point)) => number
+                .parse()
+                .map(BareItem::Decimal)
+                .map_err(|_| Error::InvalidStructuredField),
+            _ => Err(Error::InvalidStructuredField),
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<BareItem, Error> {
+        self.position += 1;
+        let mut string = String::new();
+        loop {
+            match self.next()? {
+                b'\\' => match self.next()? {
+                    c @ b'"' | c @ b'\\' => string.push(c as char),
+                    _ => return Err(Error::InvalidStructuredField),
+                },
+                b'"' => return Ok(BareItem::String(string)),
+                c @ 0x20..=0x7E => string.push(c as char),
+                _ => return Err(Error::InvalidStructuredField),
+            }
+        }
+    }
+
+    fn parse_token(&mut self) -> BareItem {
+        let start = self.position;
+        self.position += 1;
+        while let Some(c) = self.peek() {
+            if !(c.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~:/".contains(&c)) {
+                break;
+            }
+            self.position += 1;
+        }
+        BareItem::Token(self.slice(start))
+    }
+
+    fn parse_byte_sequence(&mut self) -> Result<BareItem, Error> {
+        self.position += 1;
+        let start = self.position;
+        loop {
+            match self.next()? {
+                b':' => break,
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'+' | b'/' | b'=' => {}
+                _ => return Err(Error::InvalidStructuredField),
+            }
+        }
+        let encoded = &self.input[start..self.position - 1];
+        // Only the characters checked above can get here, so the
+        // slice is ASCII.
+        let encoded = std::str::from_utf8(encoded).map_err(|_| Error::InvalidStructuredField)?;
+        base64_decode(encoded)
+            .map(BareItem::ByteSequence)
+            .ok_or(Error::InvalidStructuredField)
+    }
+
+    fn parse_boolean(&mut self) -> Result<BareItem, Error> {
+        self.position += 1;
+        match self.next()? {
+            b'1' => Ok(BareItem::Boolean(true)),
+            b'0' => Ok(BareItem::Boolean(false)),
+            _ => Err(Error::InvalidStructuredField),
+        }
+    }
+
+    /// Returns the input from `start` to the current position, which
+    /// the callers have checked to be ASCII.
+    fn slice(&self, start: usize) -> String {
+        String::from_utf8_lossy(&self.input[start..self.position]).into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_item() {
+        let item = StructuredItem::parse(" \"a \\\"b\\\\\";q=0.5;x ").unwrap();
+        assert_eq!(item.value, BareItem::String("a \"b\\".to_string()));
+        assert_eq!(item.param("q"), Some(&BareItem::Decimal(0.5)));
+        assert_eq!(item.param("x"), Some(&BareItem::Boolean(true)));
+
+        assert_eq!(
+            StructuredItem::parse("-42").unwrap().value,
+            BareItem::Integer(-42)
+        );
+        assert_eq!(
+            StructuredItem::parse("text/html").unwrap().value,
+            BareItem::Token("text/html".to_string())
+        );
+        assert_eq!(
+            StructuredItem::parse(":cHJldGVuZCB0aGlzIGlzIGJpbmFyeSBjb250ZW50Lg==:")
+                .unwrap()
+                .value,
+            BareItem::ByteSequence(b"pretend this is binary content.".to_vec())
+        );
+        assert_eq!(
+            StructuredItem::parse("?0").unwrap().value,
+            BareItem::Boolean(false)
+        );
+
+        for invalid in &[
+            "",
+            "1234567890123456",
+            "1.2345",
+            "1.",
+            "1234567890123.0",
+            "\"unterminated",
+            ":AQ-D:",
+            "?2",
+            "1;A=2",
+            "1 2",
+            "u=1",
+        ] {
+            assert!(StructuredItem::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_parse_list() {
+        let list = StructuredList::parse("sugar, tea,\trum").unwrap();
+        let tokens: Vec<_> = list
+            .members
+            .iter()
+            .map(|member| member.as_item().unwrap().value.as_str().unwrap())
+            .collect();
+        assert_eq!(tokens, vec!["sugar", "tea", "rum"]);
+
+        let list = StructuredList::parse("(\"foo\" \"bar\");lvl=5, ( );a").unwrap();
+        match &list.members[0] {
+            ListMember::InnerList(inner) => {
+                assert_eq!(inner.items.len(), 2);
+                assert_eq!(inner.param("lvl"), Some(&BareItem::Integer(5)));
+            }
+            member => panic!("expected an inner list, got {:?}", member),
+        }
+        assert_eq!(list.members[1].param("a"), Some(&BareItem::Boolean(true)));
+
+        assert!(StructuredList::parse("").unwrap().members.is_empty());
+        assert!(StructuredList::parse("a, b,").is_err());
+        assert!(StructuredList::parse("(a b").is_err());
+    }
+
+    #[test]
+    fn test_parse_dictionary() {
+        let dictionary = StructuredDictionary::parse("a=?0, b, c; foo=bar, a=2").unwrap();
+        let keys: Vec<_> = dictionary.members.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, vec!["a", "b", "c"]);
+        let value = |key| {
+            dictionary
+                .get(key)
+                .unwrap()
+                .as_item()
+                .unwrap()
+                .value
+                .clone()
+        };
+        assert_eq!(value("a"), BareItem::Integer(2));
+        assert_eq!(value("b"), BareItem::Boolean(true));
+        assert_eq!(
+            dictionary.get("c").unwrap().param("foo"),
+            Some(&BareItem::Token("bar".to_string()))
+        );
+        assert!(dictionary.get("d").is_none());
+        assert!(StructuredDictionary::parse("A=1").is_err());
+    }
+}
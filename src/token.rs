@@ -0,0 +1,63 @@
+use crate::Error;
+use std::fmt;
+use std::sync::Arc;
+
+/// A source of bearer tokens, asked for a token every time a request
+/// using it is sent (including retries), so that it can refresh
+/// expiring tokens. Set with
+/// [`Request::with_token_provider`](struct.Request.html#method.with_token_provider)
+/// or [`Client::with_token_provider`](struct.Client.html#method.with_token_provider).
+///
+/// Providers should cache their tokens, as they're called for every
+/// request. Closures returning a `Result<String, Error>` are
+/// providers too.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), minreq::Error> {
+/// let response = minreq::get("https://api.example.com/items")
+///     .with_token_provider(|| Ok(std::env::var("API_TOKEN").unwrap_or_default()))
+///     .send()?;
+/// # Ok(()) }
+/// ```
+pub trait TokenProvider: Send + Sync {
+    /// Returns the token to send in the `Authorization: Bearer`
+    /// header. Errors fail the request.
+    fn token(&self) -> Result<String, Error>;
+}
+
+impl<F: Fn() -> Result<String, Error> + Send + Sync> TokenProvider for F {
+    fn token(&self) -> Result<String, Error> {
+        self()
+    }
+}
+
+/// A shared reference to a [TokenProvider], comparable by identity so
+/// that it can live in [Client](crate::Client).
+#[derive(Clone)]
+pub(crate) struct TokenProviderRef(Arc<dyn TokenProvider>);
+
+impl TokenProviderRef {
+    pub(crate) fn new<P: TokenProvider + 'static>(provider: P) -> TokenProviderRef {
+        TokenProviderRef(Arc::new(provider))
+    }
+
+    pub(crate) fn token(&self) -> Result<String, Error> {
+        self.0.token()
+    }
+}
+
+impl PartialEq for TokenProviderRef {
+    fn eq(&self, other: &TokenProviderRef) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for TokenProviderRef {}
+
+impl fmt::Debug for TokenProviderRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TokenProvider")
+    }
+}
@@ -0,0 +1,195 @@
+use crate::random::RandomRef;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// The headers servers commonly echo the request ID in, in order of
+/// preference.
+const REQUEST_ID_HEADERS: [&str; 3] = ["x-request-id", "request-id", "x-correlation-id"];
+
+/// A [W3C Trace Context](https://www.w3.org/TR/trace-context/), sent
+/// in the `traceparent` header for correlating the request with the
+/// rest of a distributed trace. See
+/// [`Request::with_trace_context`](struct.Request.html#method.with_trace_context).
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), minreq::Error> {
+/// use minreq::TraceContext;
+///
+/// // Continue the trace of an incoming request, or start a new one.
+/// let context = TraceContext::parse("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+///     .unwrap_or_else(TraceContext::new);
+/// let response = minreq::get("http://example.com")
+///     .with_trace_context(context.child())
+///     .send()?;
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct TraceContext {
+    /// The ID of the whole trace.
+    pub trace_id: u128,
+    /// The ID of the span the request is made in.
+    pub parent_id: u64,
+    /// Whether the trace is being recorded.
+    pub sampled: bool,
+}
+
+impl TraceContext {
+    /// Starts a new, sampled trace with random IDs.
+    pub fn new() -> TraceContext {
+        let random = RandomRef::default();
+        let trace_id = (random.next_u64() as u128) << 64 | random.next_u64() as u128;
+        TraceContext {
+            trace_id: trace_id.max(1),
+            parent_id: random.next_u64().max(1),
+            sampled: true,
+        }
+    }
+
+    /// Parses a `traceparent` header, returning None if it's
+    /// malformed.
+    pub fn parse(traceparent: &str) -> Option<TraceContext> {
+        let mut parts = traceparent.trim().split('-');
+        let version = parts.next()?;
+        let (trace_id, parent_id, flags) = (parts.next()?, parts.next()?, parts.next()?);
+        // Later versions may add fields, but version 00 has none.
+        if version.len() != 2 || version == "ff" || (version == "00" && parts.next().is_some()) {
+            return None;
+        }
+        if trace_id.len() != 32 || parent_id.len() != 16 || flags.len() != 2 {
+            return None;
+        }
+        let trace_id = u128::from_str_radix(trace_id, 16).ok()?;
+        let parent_id = u64::from_str_radix(parent_id, 16).ok()?;
+        let flags = u8::from_str_radix(flags, 16).ok()?;
+        u8::from_str_radix(version, 16).ok()?;
+        if trace_id == 0 || parent_id == 0 {
+            return None;
+        }
+        Some(TraceContext {
+            trace_id,
+            parent_id,
+            sampled: flags & 1 == 1,
+        })
+    }
+
+    /// Returns the context for a new span in the same trace, eg. for
+    /// each request made while handling an incoming request.
+    pub fn child(&self) -> TraceContext {
+        self.child_from(&RandomRef::default())
+    }
+
+    /// Returns the context for a new span, with an ID from `random`.
+    pub(crate) fn child_from(&self, random: &RandomRef) -> TraceContext {
+        TraceContext {
+            parent_id: random.next_u64().max(1),
+            ..*self
+        }
+    }
+}
+
+impl Default for TraceContext {
+    fn default() -> TraceContext {
+        TraceContext::new()
+    }
+}
+
+impl fmt::Display for TraceContext {
+    /// Formats the context as a `traceparent` header value.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "00-{:032x}-{:016x}-{:02x}",
+            self.trace_id, self.parent_id, self.sampled as u8
+        )
+    }
+}
+
+type RequestIdGeneratorFn = dyn Fn(&RandomRef) -> String + Send + Sync;
+
+/// A function generating the `X-Request-Id` of each request made by a
+/// [Client](crate::Client).
+#[derive(Clone)]
+pub(crate) struct RequestIdGenerator(Arc<RequestIdGeneratorFn>);
+
+impl RequestIdGenerator {
+    pub(crate) fn new<F: Fn() -> String + Send + Sync + 'static>(generator: F) -> Self {
+        RequestIdGenerator(Arc::new(move |_: &RandomRef| generator()))
+    }
+
+    /// Generates an ID, using `random` unless the generator is a
+    /// custom one.
+    pub(crate) fn generate(&self, random: &RandomRef) -> String {
+        (self.0)(random)
+    }
+}
+
+impl Default for RequestIdGenerator {
+    fn default() -> RequestIdGenerator {
+        RequestIdGenerator(Arc::new(RandomRef::uuid))
+    }
+}
+
+impl PartialEq for RequestIdGenerator {
+    fn eq(&self, other: &RequestIdGenerator) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for RequestIdGenerator {}
+
+impl fmt::Debug for RequestIdGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RequestIdGenerator")
+    }
+}
+
+/// Returns the request ID the server sent back, from the
+/// `X-Request-Id`, `Request-Id` or `X-Correlation-Id` header.
+pub(crate) fn response_request_id(headers: &HashMap<String, String>) -> Option<&str> {
+    REQUEST_ID_HEADERS
+        .iter()
+        .find_map(|name| headers.get(*name))
+        .map(|id| id.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TraceContext;
+
+    #[test]
+    fn test_trace_context() {
+        let traceparent = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let context = TraceContext::parse(traceparent).unwrap();
+        assert_eq!(context.trace_id, 0x4bf92f3577b34da6a3ce929d0e0e4736);
+        assert_eq!(context.parent_id, 0x00f067aa0ba902b7);
+        assert!(context.sampled);
+        assert_eq!(context.to_string(), traceparent);
+
+        let child = context.child();
+        assert_eq!(child.trace_id, context.trace_id);
+        assert_ne!(child.parent_id, context.parent_id);
+
+        let new = TraceContext::new();
+        assert_eq!(TraceContext::parse(&new.to_string()), Some(new));
+
+        assert!(
+            TraceContext::parse("00-00000000000000000000000000000000-00f067aa0ba902b7-01")
+                .is_none()
+        );
+        assert!(
+            TraceContext::parse("ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+                .is_none()
+        );
+        assert!(
+            TraceContext::parse("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-x")
+                .is_none()
+        );
+        assert!(
+            TraceContext::parse("01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00-x")
+                .is_some()
+        );
+    }
+}
@@ -0,0 +1,267 @@
+#[cfg(any(feature = "sha2", feature = "sha1", feature = "md-5"))]
+use crate::encoding::base64_encode;
+#[cfg(any(feature = "sha2", feature = "sha1", feature = "md-5"))]
+use crate::hash::{Algorithm, Hasher};
+use crate::{Error, Request, Response};
+use std::io::{self, Read};
+use std::thread;
+use std::time::Duration;
+
+/// Information about a part of a [ChunkedUpload], for building the
+/// request that uploads it.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct UploadPart {
+    /// The number of the part, starting from 1. Calculated from the
+    /// offset, so resumed uploads continue the numbering.
+    pub number: usize,
+    /// The offset of the part's first byte in the whole upload.
+    pub offset: u64,
+    /// The size of the part in bytes.
+    pub size: usize,
+    /// The digest of the part, if a checksum algorithm was set with
+    /// [`ChunkedUpload::with_checksum`].
+    pub digest: Option<Vec<u8>>,
+}
+
+/// A part of a [ChunkedUpload] that was uploaded successfully.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct UploadedPart {
+    /// The part that was uploaded.
+    pub part: UploadPart,
+    /// The server's response to the part, eg. for reading the `ETag`
+    /// of an S3 multipart upload part.
+    pub response: Response,
+}
+
+/// Uploads a large body in parts, one request per part, retrying the
+/// parts that fail.
+///
+/// The requests are created by a closure, which gets the
+/// [UploadPart] being uploaded, so that the same helper works for
+/// different protocols: eg. tus.io uploads `PATCH` the parts with an
+/// `Upload-Offset` header, while S3-style multipart uploads `PUT`
+/// them with a `partNumber` parameter. The body of the request is set
+/// to the part's bytes by the helper.
+///
+/// The progress is kept in the `ChunkedUpload`, so if
+/// [`upload`](#method.upload) fails, the upload can be resumed by
+/// seeking the reader to [`offset`](#method.offset) and calling
+/// `upload` again.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), minreq::Error> {
+/// let file = std::fs::File::open("video.mp4")?;
+/// let mut upload = minreq::ChunkedUpload::new(|part| {
+///     minreq::patch("http://example.com/files/24e533e02ec3bc40c387f1a0e460e216")
+///         .with_header("Tus-Resumable", "1.0.0")
+///         .with_header("Upload-Offset", part.offset.to_string())
+///         .with_header("Content-Type", "application/offset+octet-stream")
+/// })
+/// .with_part_size(8 * 1024 * 1024);
+/// let parts = upload.upload(file)?;
+/// println!("Uploaded {} parts, {} bytes.", parts.len(), upload.offset());
+/// # Ok(()) }
+/// ```
+pub struct ChunkedUpload<F: FnMut(&UploadPart) -> Request> {
+    create_request: F,
+    part_size: usize,
+    max_retries: usize,
+    retry_delay: Duration,
+    #[cfg(any(feature = "sha2", feature = "sha1", feature = "md-5"))]
+    checksum: Option<Algorithm>,
+    parts: Vec<UploadedPart>,
+    offset: u64,
+}
+
+impl<F: FnMut(&UploadPart) -> Request> ChunkedUpload<F> {
+    /// Creates a new `ChunkedUpload`, which creates the request for
+    /// each part with `create_request`.
+    ///
+    /// By default, parts are 5 MiB, and each part is retried 3 times
+    /// before giving up.
+    pub fn new(create_request: F) -> ChunkedUpload<F> {
+        ChunkedUpload {
+            create_request,
+            part_size: 5 * 1024 * 1024,
+            max_retries: 3,
+            retry_delay: Duration::from_millis(500),
+            #[cfg(any(feature = "sha2", feature = "sha1", feature = "md-5"))]
+            checksum: None,
+            parts: Vec::new(),
+            offset: 0,
+        }
+    }
+
+    /// Sets the size of the parts in bytes. The last part may be
+    /// smaller.
+    pub fn with_part_size(mut self, part_size: usize) -> ChunkedUpload<F> {
+        self.part_size = part_size.max(1);
+        self
+    }
+
+    /// Sets how many times a part is retried after failing, before
+    /// giving up on the upload.
+    pub fn with_max_retries(mut self, max_retries: usize) -> ChunkedUpload<F> {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets the delay before the first retry of a part. The delay is
+    /// doubled for each further retry of the same part.
+    pub fn with_retry_delay(mut self, retry_delay: Duration) -> ChunkedUpload<F> {
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// Calculates the digest of each part with `algorithm`, and sends
+    /// it in a `Content-Digest` header (RFC 9530). For MD5, it's also
+    /// sent in the `Content-MD5` header expected by S3.
+    #[cfg(any(feature = "sha2", feature = "sha1", feature = "md-5"))]
+    pub fn with_checksum(mut self, algorithm: Algorithm) -> ChunkedUpload<F> {
+        self.checksum = Some(algorithm);
+        self
+    }
+
+    /// Sets the offset the upload continues from, for resuming an
+    /// upload started by another `ChunkedUpload`, eg. with the offset
+    /// returned by a tus.io server's `HEAD` response. The reader given
+    /// to [`upload`](#method.upload) should start from this offset.
+    pub fn with_offset(mut self, offset: u64) -> ChunkedUpload<F> {
+        self.offset = offset;
+        self
+    }
+
+    /// Returns the number of bytes uploaded so far, ie. the offset the
+    /// next part starts at.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the parts uploaded so far.
+    pub fn parts(&self) -> &[UploadedPart] {
+        &self.parts
+    }
+
+    /// Uploads the rest of `reader`, part by part, and returns all of
+    /// the parts uploaded by this `ChunkedUpload`.
+    ///
+    /// Parts are retried if sending them fails with an IO error, or if
+    /// the server responds with 408, 429, or a 5xx status code.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`UploadPartRejected`](enum.Error.html#variant.UploadPartRejected)
+    /// if the server responds to a part with a non-2xx status code that
+    /// isn't retried, or the last retry's status code was not 2xx.
+    /// Otherwise, returns the error of the last retry, or the error
+    /// from reading `reader`.
+    pub fn upload<R: Read>(&mut self, mut reader: R) -> Result<&[UploadedPart], Error> {
+        let mut buffer = vec![0; self.part_size];
+        loop {
+            let size = read_part(&mut reader, &mut buffer)?;
+            if size == 0 {
+                break;
+            }
+            let data = &buffer[..size];
+
+            let part = UploadPart {
+                number: (self.offset / self.part_size as u64) as usize + 1,
+                offset: self.offset,
+                size,
+                digest: self.digest(data),
+            };
+            let response = self.upload_part(&part, data)?;
+            self.offset += size as u64;
+            self.parts.push(UploadedPart { part, response });
+
+            if size < buffer.len() {
+                break;
+            }
+        }
+        Ok(&self.parts)
+    }
+
+    fn upload_part(&mut self, part: &UploadPart, data: &[u8]) -> Result<Response, Error> {
+        let mut retry_delay = self.retry_delay;
+        let mut retries_left = self.max_retries;
+        loop {
+            let request = (self.create_request)(part);
+            let request = self.add_checksum(request, part);
+            let result = request.with_body(data).send();
+            let retryable = match &result {
+                Ok(response) => {
+                    let status = response.status_code;
+                    status == 408 || status == 429 || (500..600).contains(&status)
+                }
+                Err(Error::IoError(_)) | Err(Error::ConnectionClosed) => true,
+                Err(_) => false,
+            };
+
+            if !retryable || retries_left == 0 {
+                return match result {
+                    Ok(response) if (200..300).contains(&response.status_code) => Ok(response),
+                    Ok(response) => Err(Error::UploadPartRejected(response.status_code)),
+                    Err(err) => Err(err),
+                };
+            }
+            log::debug!(
+                "Uploading part {} failed, retrying in {:?}.",
+                part.number,
+                retry_delay
+            );
+            thread::sleep(retry_delay);
+            retry_delay *= 2;
+            retries_left -= 1;
+        }
+    }
+
+    #[cfg(any(feature = "sha2", feature = "sha1", feature = "md-5"))]
+    fn digest(&self, data: &[u8]) -> Option<Vec<u8>> {
+        let mut hasher = Hasher::new(self.checksum?);
+        hasher.update(data);
+        Some(hasher.finish())
+    }
+
+    #[cfg(not(any(feature = "sha2", feature = "sha1", feature = "md-5")))]
+    fn digest(&self, _data: &[u8]) -> Option<Vec<u8>> {
+        None
+    }
+
+    #[cfg(any(feature = "sha2", feature = "sha1", feature = "md-5"))]
+    fn add_checksum(&self, mut request: Request, part: &UploadPart) -> Request {
+        if let (Some(algorithm), Some(digest)) = (self.checksum, &part.digest) {
+            let digest = base64_encode(digest);
+            #[cfg(feature = "md-5")]
+            if algorithm == Algorithm::Md5 {
+                request = request.with_header("Content-MD5", digest.clone());
+            }
+            request = request.with_header(
+                "Content-Digest",
+                format!("{}=:{}:", algorithm.structured_name(), digest),
+            );
+        }
+        request
+    }
+
+    #[cfg(not(any(feature = "sha2", feature = "sha1", feature = "md-5")))]
+    fn add_checksum(&self, request: Request, _part: &UploadPart) -> Request {
+        request
+    }
+}
+
+/// Fills `buffer` from `reader`, unless the reader ends first.
+/// Returns the amount of bytes read.
+fn read_part<R: Read>(reader: &mut R, buffer: &mut [u8]) -> Result<usize, Error> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(Error::IoError(err)),
+        }
+    }
+    Ok(filled)
+}
@@ -0,0 +1,239 @@
+//! Helpers for WebDAV ([RFC 4918](https://tools.ietf.org/html/rfc4918))
+//! requests and 207 Multi-Status responses.
+
+use crate::xml;
+use crate::{Error, Method, Request, URL};
+use std::fmt;
+
+/// The value of the `Depth` header, which controls how far into a
+/// collection a WebDAV request applies.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Depth {
+    /// Only the resource itself.
+    Zero,
+    /// The resource and its immediate children.
+    One,
+    /// The resource and all of its descendants.
+    Infinity,
+}
+
+impl fmt::Display for Depth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Depth::Zero => write!(f, "0"),
+            Depth::One => write!(f, "1"),
+            Depth::Infinity => write!(f, "infinity"),
+        }
+    }
+}
+
+/// Alias for [Request::new](struct.Request.html#method.new) with `method`
+/// set to `PROPFIND`, for reading the properties of a WebDAV resource.
+/// Without a body, all properties are requested.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), minreq::Error> {
+/// use minreq::Depth;
+///
+/// let response = minreq::propfind("http://example.com/dav/")
+///     .with_depth(Depth::One)
+///     .send()?;
+/// for resource in response.multi_status()?.responses {
+///     println!("{} (collection: {})", resource.href, resource.is_collection());
+/// }
+/// # Ok(()) }
+/// ```
+pub fn propfind<T: Into<URL>>(url: T) -> Request {
+    Request::new(Method::Custom("PROPFIND".to_string()), url)
+}
+
+/// Alias for [Request::new](struct.Request.html#method.new) with `method`
+/// set to `MKCOL`, for creating a WebDAV collection.
+pub fn mkcol<T: Into<URL>>(url: T) -> Request {
+    Request::new(Method::Custom("MKCOL".to_string()), url)
+}
+
+/// Creates a WebDAV `COPY` request, which copies the resource at `url`
+/// to `destination`.
+pub fn copy_to<T: Into<URL>, U: Into<URL>>(url: T, destination: U) -> Request {
+    Request::new(Method::Custom("COPY".to_string()), url).with_destination(destination)
+}
+
+/// Creates a WebDAV `MOVE` request, which moves the resource at `url`
+/// to `destination`.
+pub fn move_to<T: Into<URL>, U: Into<URL>>(url: T, destination: U) -> Request {
+    Request::new(Method::Custom("MOVE".to_string()), url).with_destination(destination)
+}
+
+/// The body of a 207 Multi-Status response, returned by
+/// [`Response::multi_status`](struct.Response.html#method.multi_status).
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MultiStatus {
+    /// The statuses of the resources the request applied to.
+    pub responses: Vec<MultiStatusResponse>,
+}
+
+/// The status of one resource in a [MultiStatus].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MultiStatusResponse {
+    /// The url of the resource, as sent by the server. It's usually
+    /// percent-encoded and relative to the server's root.
+    pub href: String,
+    /// The status code of the whole resource, if the server sent one
+    /// instead of per-property statuses.
+    pub status: Option<i32>,
+    /// The properties of the resource, grouped by their status.
+    pub propstats: Vec<PropStat>,
+}
+
+impl MultiStatusResponse {
+    /// Returns the value of the property with the local name `name`
+    /// (eg. "getcontentlength"), if the server returned it with a 2xx
+    /// status.
+    pub fn property(&self, name: &str) -> Option<&str> {
+        self.propstats
+            .iter()
+            .filter(|propstat| (200..300).contains(&propstat.status))
+            .flat_map(|propstat| propstat.properties.iter())
+            .find(|property| property.name == name)
+            .map(|property| property.value.as_str())
+    }
+
+    /// Returns true if the resource is a collection, according to its
+    /// `resourcetype` property.
+    pub fn is_collection(&self) -> bool {
+        self.propstats
+            .iter()
+            .filter(|propstat| (200..300).contains(&propstat.status))
+            .flat_map(|propstat| propstat.properties.iter())
+            .any(|property| {
+                property.name == "resourcetype"
+                    && property.children.iter().any(|child| child == "collection")
+            })
+    }
+}
+
+/// A group of properties that share the same status.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PropStat {
+    /// The status code of the properties, eg. 404 for properties the
+    /// resource doesn't have.
+    pub status: i32,
+    /// The properties.
+    pub properties: Vec<Property>,
+}
+
+/// A property of a WebDAV resource.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Property {
+    /// The local name of the property, without its namespace, eg.
+    /// "getlastmodified".
+    pub name: String,
+    /// The text content of the property.
+    pub value: String,
+    /// The local names of the property's child elements, eg.
+    /// "collection" for the `resourcetype` of a collection.
+    pub children: Vec<String>,
+}
+
+/// Parses the XML body of a 207 Multi-Status response.
+pub(crate) fn parse_multi_status(body: &str) -> Result<MultiStatus, Error> {
+    let root = xml::parse(body).ok_or(Error::InvalidMultiStatus)?;
+    if root.name != "multistatus" {
+        return Err(Error::InvalidMultiStatus);
+    }
+    let responses = root
+        .elements("response")
+        .map(|response| MultiStatusResponse {
+            href: response.child_text("href").trim().to_string(),
+            status: response
+                .child("status")
+                .and_then(|status| parse_status(&status.text())),
+            propstats: response
+                .elements("propstat")
+                .map(|propstat| PropStat {
+                    status: propstat
+                        .child("status")
+                        .and_then(|status| parse_status(&status.text()))
+                        .unwrap_or(0),
+                    properties: propstat
+                        .child("prop")
+                        .map(|prop| {
+                            prop.elements_all()
+                                .map(|property| Property {
+                                    name: property.name.clone(),
+                                    value: property.text().trim().to_string(),
+                                    children: property
+                                        .elements_all()
+                                        .map(|child| child.name.clone())
+                                        .collect(),
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                })
+                .collect(),
+        })
+        .collect();
+    Ok(MultiStatus { responses })
+}
+
+/// Parses the status code out of a status line, `HTTP/1.1 200 OK`.
+fn parse_status(status_line: &str) -> Option<i32> {
+    status_line.split_whitespace().nth(1)?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_multi_status;
+
+    #[test]
+    fn test_parse_multi_status() {
+        let body = r#"<?xml version="1.0" encoding="utf-8" ?>
+<D:multistatus xmlns:D="DAV:">
+  <D:response>
+    <D:href>/dav/</D:href>
+    <D:propstat>
+      <D:prop><D:resourcetype><D:collection/></D:resourcetype></D:prop>
+      <D:status>HTTP/1.1 200 OK</D:status>
+    </D:propstat>
+  </D:response>
+  <D:response>
+    <D:href>/dav/a%20&amp;%20b.txt</D:href>
+    <D:propstat>
+      <D:prop>
+        <D:getcontentlength>1234</D:getcontentlength>
+        <D:resourcetype/>
+        <!-- A comment. -->
+        <D:displayname><![CDATA[a & b]]></D:displayname>
+      </D:prop>
+      <D:status>HTTP/1.1 200 OK</D:status>
+    </D:propstat>
+    <D:propstat>
+      <D:prop><D:getetag/></D:prop>
+      <D:status>HTTP/1.1 404 Not Found</D:status>
+    </D:propstat>
+  </D:response>
+</D:multistatus>"#;
+        let multi_status = parse_multi_status(body).unwrap();
+        let responses = &multi_status.responses;
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0].href, "/dav/");
+        assert!(responses[0].is_collection());
+        assert_eq!(responses[1].href, "/dav/a%20&%20b.txt");
+        assert!(!responses[1].is_collection());
+        assert_eq!(responses[1].property("getcontentlength"), Some("1234"));
+        assert_eq!(responses[1].property("displayname"), Some("a & b"));
+        assert_eq!(responses[1].property("getetag"), None);
+        assert_eq!(responses[1].propstats[1].status, 404);
+    }
+
+    #[test]
+    fn test_parse_invalid_multi_status() {
+        assert!(parse_multi_status("<multistatus><response></multistatus>").is_err());
+        assert!(parse_multi_status("<html></html>").is_err());
+        assert!(parse_multi_status("not xml").is_err());
+    }
+}
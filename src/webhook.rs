@@ -0,0 +1,199 @@
+//! Signed webhook deliveries, enabled by the `webhooks` feature.
+
+use crate::encoding::{base64_decode, base64_encode};
+use crate::hash::{hmac, Algorithm};
+use crate::random::RandomRef;
+use crate::{Error, Method, Request, Response, RetryPolicy, URL};
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A delivery that failed even after retrying, passed to the
+/// dead-letter hook of a [WebhookSender], eg. for storing it and
+/// redelivering it later.
+#[derive(Debug)]
+pub struct DeadLetter<'a> {
+    /// The url the webhook was sent to.
+    pub url: &'a str,
+    /// The `webhook-id` of the delivery.
+    pub id: &'a str,
+    /// The JSON payload.
+    pub payload: &'a str,
+    /// The error of the last attempt, eg. a
+    /// [`StatusError`](enum.Error.html#variant.StatusError).
+    pub error: &'a Error,
+}
+
+type DeadLetterFn = dyn Fn(&DeadLetter) + Send + Sync;
+
+/// Sends webhooks: POST requests with a JSON payload, signed as
+/// described by the [Standard Webhooks](https://www.standardwebhooks.com/)
+/// specification, which many webhook receivers can verify.
+///
+/// Every delivery gets a unique `webhook-id` and a `webhook-timestamp`
+/// (in seconds since the Unix epoch), and its `webhook-signature` is
+/// `v1,` followed by the base64 encoded HMAC-SHA256 of
+/// `{id}.{timestamp}.{payload}`.
+///
+/// Deliveries are retried according to a [RetryPolicy], by default 5
+/// times with delays starting at a second, and sent with the id as
+/// their idempotency key. Deliveries that still fail, including ones
+/// answered with an error status, are passed to the
+/// [dead-letter hook](#method.with_dead_letter_hook).
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), minreq::Error> {
+/// use minreq::WebhookSender;
+///
+/// let sender = WebhookSender::new("whsec_MfKQ9r8GKYqrTwjUPD8ILPZIo2LaLaSw")
+///     .with_dead_letter_hook(|letter| {
+///         eprintln!("Could not deliver {} to {}: {}", letter.id, letter.url, letter.error);
+///     });
+/// let payload = serde_json::json!({ "type": "invoice.paid", "invoice": 42 });
+/// sender.send("https://example.com/webhooks", &payload)?;
+/// # Ok(()) }
+/// ```
+#[derive(Clone)]
+pub struct WebhookSender {
+    key: Vec<u8>,
+    retry: RetryPolicy,
+    timeout: Option<u64>,
+    dead_letter_hook: Option<Arc<DeadLetterFn>>,
+    random: RandomRef,
+}
+
+impl WebhookSender {
+    /// Creates a sender signing with `secret`. Secrets in the
+    /// `whsec_` format of Standard Webhooks are base64 decoded, and
+    /// other secrets are used as is.
+    pub fn new<T: AsRef<[u8]>>(secret: T) -> WebhookSender {
+        let secret = secret.as_ref();
+        let prefix = b"whsec_";
+        let key = Some(secret)
+            .filter(|secret| secret.starts_with(prefix))
+            .and_then(|secret| base64_decode(&String::from_utf8_lossy(&secret[prefix.len()..])))
+            .unwrap_or_else(|| secret.to_vec());
+        WebhookSender {
+            key,
+            retry: RetryPolicy::new(5).with_initial_delay(Duration::from_secs(1)),
+            timeout: None,
+            dead_letter_hook: None,
+            random: RandomRef::default(),
+        }
+    }
+
+    /// Sets how failed deliveries are retried.
+    pub fn with_retry(mut self, retry: RetryPolicy) -> WebhookSender {
+        self.retry = retry;
+        self
+    }
+
+    /// Sets the timeout of each attempt in seconds.
+    pub fn with_timeout(mut self, timeout: u64) -> WebhookSender {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets a hook called with the deliveries that failed even after
+    /// retrying. See [DeadLetter].
+    pub fn with_dead_letter_hook<F>(mut self, hook: F) -> WebhookSender
+    where
+        F: Fn(&DeadLetter) + Send + Sync + 'static,
+    {
+        self.dead_letter_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Serializes `payload` into JSON, and delivers it to `url`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SerdeJsonError`](enum.Error.html#variant.SerdeJsonError)
+    /// if the payload can't be serialized, and otherwise the error of
+    /// the last attempt, after passing it to the dead-letter hook.
+    /// Error statuses are returned as a
+    /// [`StatusError`](enum.Error.html#variant.StatusError).
+    pub fn send<U: Into<URL>, T: serde::ser::Serialize>(
+        &self,
+        url: U,
+        payload: &T,
+    ) -> Result<Response, Error> {
+        let payload = serde_json::to_string(payload).map_err(Error::SerdeJsonError)?;
+        self.send_json(url, &payload)
+    }
+
+    /// Delivers the already serialized JSON `payload` to `url`. See
+    /// [`send`](#method.send).
+    pub fn send_json<U: Into<URL>>(&self, url: U, payload: &str) -> Result<Response, Error> {
+        let url = url.into();
+        let id = format!("msg_{}", self.random.uuid().replace('-', ""));
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let mut request = Request::new(Method::Post, url.clone())
+            .with_header("Content-Type", "application/json; charset=UTF-8")
+            .with_header("webhook-id", id.clone())
+            .with_header("webhook-timestamp", timestamp.to_string())
+            .with_header("webhook-signature", self.signature(&id, timestamp, payload))
+            .with_idempotency_key(id.clone())
+            .with_retry(self.retry.clone())
+            .with_body(payload);
+        if let Some(timeout) = self.timeout {
+            request = request.with_timeout(timeout);
+        }
+
+        let result = request.send().and_then(Response::error_for_status);
+        if let Err(error) = &result {
+            log::warn!("Webhook {} to {} failed: {}", id, url, error);
+            if let Some(hook) = &self.dead_letter_hook {
+                hook(&DeadLetter {
+                    url: &url,
+                    id: &id,
+                    payload,
+                    error,
+                });
+            }
+        }
+        result
+    }
+
+    /// Returns the `webhook-signature` header value of a delivery.
+    fn signature(&self, id: &str, timestamp: u64, payload: &str) -> String {
+        let content = format!("{}.{}.{}", id, timestamp, payload);
+        let mac = hmac(Algorithm::Sha256, &self.key, content.as_bytes());
+        format!("v1,{}", base64_encode(&mac))
+    }
+}
+
+impl fmt::Debug for WebhookSender {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WebhookSender")
+            .field("retry", &self.retry)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WebhookSender;
+
+    #[test]
+    fn test_signature() {
+        // The example of the Standard Webhooks reference libraries.
+        let sender = WebhookSender::new("whsec_MfKQ9r8GKYqrTwjUPD8ILPZIo2LaLaSw");
+        let payload = r#"{"test": 2432232314}"#;
+        assert_eq!(
+            sender.signature("msg_p5jXN8AQM9LWM0D4loKWxJek", 1614265330, payload),
+            "v1,g0hM9SsE+OTPJTGt/tmIKtSyZlE3uFJELVlNIOLJ1OE="
+        );
+        // Secrets without the prefix are used as is.
+        let sender = WebhookSender::new("MfKQ9r8GKYqrTwjUPD8ILPZIo2LaLaSw");
+        assert_ne!(
+            sender.signature("msg_p5jXN8AQM9LWM0D4loKWxJek", 1614265330, payload),
+            "v1,g0hM9SsE+OTPJTGt/tmIKtSyZlE3uFJELVlNIOLJ1OE="
+        );
+    }
+}
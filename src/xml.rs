@@ -0,0 +1,312 @@
+//! Just enough of an XML parser for reading the bodies of WebDAV and
+//! XML-RPC responses, and of a streaming one for sitemaps and feeds.
+//! Namespace prefixes are dropped from the element names, and
+//! attributes are ignored by the former.
+
+#[cfg(feature = "feeds")]
+use std::io::{self, BufRead, BufReader, Read};
+
+pub(crate) enum Node {
+    Element(Element),
+    Text(String),
+}
+
+pub(crate) struct Element {
+    pub(crate) name: String,
+    pub(crate) children: Vec<Node>,
+}
+
+impl Element {
+    pub(crate) fn elements_all(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|child| match child {
+            Node::Element(element) => Some(element),
+            Node::Text(_) => None,
+        })
+    }
+
+    pub(crate) fn elements<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.elements_all()
+            .filter(move |element| element.name == name)
+    }
+
+    pub(crate) fn child(&self, name: &str) -> Option<&Element> {
+        self.elements_all().find(|element| element.name == name)
+    }
+
+    pub(crate) fn child_text(&self, name: &str) -> String {
+        self.child(name).map(Element::text).unwrap_or_default()
+    }
+
+    /// Returns the text inside the element, including the text of
+    /// its descendants.
+    pub(crate) fn text(&self) -> String {
+        let mut text = String::new();
+        for child in &self.children {
+            match child {
+                Node::Element(element) => text += &element.text(),
+                Node::Text(part) => text += part,
+            }
+        }
+        text
+    }
+}
+
+/// Parses `document` into its root element, or returns None if
+/// it's malformed.
+pub(crate) fn parse(document: &str) -> Option<Element> {
+    // The stack of open elements, with a pseudo-element at the
+    // bottom for the document itself.
+    let mut stack = vec![Element {
+        name: String::new(),
+        children: Vec::new(),
+    }];
+    let mut rest = document;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("<!--") {
+            rest = &after[after.find("-->")? + 3..];
+        } else if let Some(after) = rest.strip_prefix("<![CDATA[") {
+            let end = after.find("]]>")?;
+            push_text(stack.last_mut()?, after[..end].to_string());
+            rest = &after[end + 3..];
+        } else if rest.starts_with("<?") || rest.starts_with("<!") {
+            rest = &rest[rest.find('>')? + 1..];
+        } else if let Some(after) = rest.strip_prefix("</") {
+            let end = after.find('>')?;
+            let element = stack.pop()?;
+            if element.name != local_name(after[..end].trim()) || stack.is_empty() {
+                return None;
+            }
+            stack.last_mut()?.children.push(Node::Element(element));
+            rest = &after[end + 1..];
+        } else if let Some(after) = rest.strip_prefix('<') {
+            let end = after.find('>')?;
+            let tag = &after[..end];
+            let self_closing = tag.ends_with('/');
+            let tag = tag.trim_end_matches('/');
+            let name = tag.split_whitespace().next()?;
+            let element = Element {
+                name: local_name(name).to_string(),
+                children: Vec::new(),
+            };
+            if self_closing {
+                stack.last_mut()?.children.push(Node::Element(element));
+            } else {
+                stack.push(element);
+            }
+            rest = &after[end + 1..];
+        } else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            push_text(stack.last_mut()?, unescape(&rest[..end])?);
+            rest = &rest[end..];
+        }
+    }
+
+    let document = stack.pop()?;
+    if !stack.is_empty() {
+        return None;
+    }
+    let mut elements = document
+        .children
+        .into_iter()
+        .filter_map(|child| match child {
+            Node::Element(element) => Some(element),
+            Node::Text(_) => None,
+        });
+    elements.next()
+}
+
+fn push_text(element: &mut Element, text: String) {
+    if !text.is_empty() {
+        element.children.push(Node::Text(text));
+    }
+}
+
+fn local_name(name: &str) -> &str {
+    match name.rfind(':') {
+        Some(colon) => &name[colon + 1..],
+        None => name,
+    }
+}
+
+/// Replaces the entity and character references in `text`.
+fn unescape(text: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped += &rest[..start];
+        let end = rest[start..].find(';')? + start;
+        let entity = &rest[start + 1..end];
+        let c = match entity {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = if let Some(hex) = entity.strip_prefix("#x") {
+                    u32::from_str_radix(hex, 16).ok()?
+                } else {
+                    entity.strip_prefix('#')?.parse().ok()?
+                };
+                std::char::from_u32(code)?
+            }
+        };
+        unescaped.push(c);
+        rest = &rest[end + 1..];
+    }
+    unescaped += rest;
+    Some(unescaped)
+}
+
+/// Escapes `text` for use as the text of an element.
+#[cfg(feature = "xml-rpc")]
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '<' => escaped += "&lt;",
+            '>' => escaped += "&gt;",
+            '&' => escaped += "&amp;",
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A piece of an XML document read by [Reader].
+#[cfg(feature = "feeds")]
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Event {
+    /// A start tag, with its attributes. Self-closing tags are
+    /// followed by an [End](Event::End) event.
+    Start {
+        name: String,
+        attributes: Vec<(String, String)>,
+    },
+    End(String),
+    Text(String),
+}
+
+/// Reads the [Event]s of an XML document from a stream, without
+/// keeping the whole document in memory.
+#[cfg(feature = "feeds")]
+pub(crate) struct Reader<R> {
+    input: BufReader<R>,
+    /// The end tag following a self-closing tag.
+    pending_end: Option<String>,
+}
+
+#[cfg(feature = "feeds")]
+impl<R: Read> Reader<R> {
+    pub(crate) fn new(input: R) -> Reader<R> {
+        Reader {
+            input: BufReader::new(input),
+            pending_end: None,
+        }
+    }
+
+    /// Returns the next event, or None at the end of the document.
+    /// Malformed markup is returned as an error of kind InvalidData.
+    pub(crate) fn next_event(&mut self) -> io::Result<Option<Event>> {
+        if let Some(name) = self.pending_end.take() {
+            return Ok(Some(Event::End(name)));
+        }
+        loop {
+            let next = match self.input.fill_buf()?.first() {
+                Some(next) => *next,
+                None => return Ok(None),
+            };
+            if next != b'<' {
+                let mut text = Vec::new();
+                while let Some(length) = self.text_length()? {
+                    text.extend_from_slice(&self.input.buffer()[..length]);
+                    self.input.consume(length);
+                }
+                let text = String::from_utf8(text).map_err(|_| invalid())?;
+                return Ok(Some(Event::Text(unescape(&text).ok_or_else(invalid)?)));
+            }
+
+            let tag = self.read_tag()?;
+            if let Some(cdata) = tag.strip_prefix("<![CDATA[") {
+                return Ok(Some(Event::Text(cdata.to_string())));
+            } else if tag.starts_with("<!") || tag.starts_with("<?") {
+                continue;
+            } else if let Some(name) = tag.strip_prefix("</") {
+                return Ok(Some(Event::End(local_name(name.trim()).to_string())));
+            }
+            let self_closing = tag.ends_with('/');
+            let tag = tag[1..].trim_end_matches('/');
+            let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+            let name = local_name(&tag[..name_end]).to_string();
+            if name.is_empty() {
+                return Err(invalid());
+            }
+            let attributes = parse_attributes(&tag[name_end..]).ok_or_else(invalid)?;
+            if self_closing {
+                self.pending_end = Some(name.clone());
+            }
+            return Ok(Some(Event::Start { name, attributes }));
+        }
+    }
+
+    /// Returns how many of the buffered bytes are text, or None if
+    /// the text has ended.
+    fn text_length(&mut self) -> io::Result<Option<usize>> {
+        let buffer = self.input.fill_buf()?;
+        match buffer.iter().position(|byte| *byte == b'<') {
+            Some(0) => Ok(None),
+            Some(length) => Ok(Some(length)),
+            None if buffer.is_empty() => Ok(None),
+            None => Ok(Some(buffer.len())),
+        }
+    }
+
+    /// Reads a tag, comment, CDATA section or other markup starting
+    /// with `<`, and returns it without its final `>` (or `-->` and
+    /// `]]>`).
+    fn read_tag(&mut self) -> io::Result<String> {
+        let mut tag = Vec::new();
+        loop {
+            if self.input.read_until(b'>', &mut tag)? == 0 || tag.last() != Some(&b'>') {
+                return Err(invalid());
+            }
+            // Comments and CDATA sections may contain `>`.
+            let terminator: &[u8] = if tag.starts_with(b"<!--") {
+                b"-->"
+            } else if tag.starts_with(b"<![CDATA[") {
+                b"]]>"
+            } else {
+                b">"
+            };
+            if tag.ends_with(terminator) {
+                tag.truncate(tag.len() - terminator.len());
+                return String::from_utf8(tag).map_err(|_| invalid());
+            }
+        }
+    }
+}
+
+#[cfg(feature = "feeds")]
+fn invalid() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "malformed XML")
+}
+
+/// Parses the attributes of a start tag, dropping their namespace
+/// prefixes.
+#[cfg(feature = "feeds")]
+fn parse_attributes(mut rest: &str) -> Option<Vec<(String, String)>> {
+    let mut attributes = Vec::new();
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return Some(attributes);
+        }
+        let equals = rest.find('=')?;
+        let name = local_name(rest[..equals].trim()).to_string();
+        rest = rest[equals + 1..].trim_start();
+        let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let end = rest[1..].find(quote)? + 1;
+        attributes.push((name, unescape(&rest[1..end])?));
+        rest = &rest[end + 1..];
+    }
+}
@@ -0,0 +1,413 @@
+//! An [XML-RPC](http://xmlrpc.com/spec.md) client, enabled by the
+//! `xml-rpc` feature, for services that don't offer anything newer.
+
+use crate::encoding::{base64_decode, base64_encode};
+use crate::xml::{self, Element};
+use crate::{Error, Method, Request, URL};
+use std::collections::BTreeMap;
+
+/// A value passed to or returned by an XML-RPC method.
+#[derive(Clone, PartialEq, Debug)]
+pub enum XmlRpcValue {
+    /// An `<int>`, `<i4>` or `<i8>`. Values that don't fit in 32 bits
+    /// are sent as `<i8>`, which not all servers understand.
+    Int(i64),
+    /// A `<boolean>`.
+    Boolean(bool),
+    /// A `<string>`, or a value without a type.
+    String(String),
+    /// A `<double>`.
+    Double(f64),
+    /// A `<dateTime.iso8601>`, as written, eg. `19980717T14:08:55`.
+    DateTime(String),
+    /// A `<base64>`, decoded.
+    Base64(Vec<u8>),
+    /// An `<array>`.
+    Array(Vec<XmlRpcValue>),
+    /// A `<struct>`, by member name.
+    Struct(BTreeMap<String, XmlRpcValue>),
+    /// A `<nil/>`, which is an extension to XML-RPC.
+    Nil,
+}
+
+impl XmlRpcValue {
+    /// Returns the value if it's an [Int](#variant.Int).
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            XmlRpcValue::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value if it's a [Boolean](#variant.Boolean).
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            XmlRpcValue::Boolean(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value if it's a [String](#variant.String).
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            XmlRpcValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value if it's a [Double](#variant.Double).
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            XmlRpcValue::Double(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the values if it's an [Array](#variant.Array).
+    pub fn as_array(&self) -> Option<&[XmlRpcValue]> {
+        match self {
+            XmlRpcValue::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Returns the member called `name`, if this is a
+    /// [Struct](#variant.Struct) with one.
+    pub fn get(&self, name: &str) -> Option<&XmlRpcValue> {
+        match self {
+            XmlRpcValue::Struct(members) => members.get(name),
+            _ => None,
+        }
+    }
+
+    /// Appends the value, wrapped in a `<value>`, to `xml`.
+    fn write(&self, xml: &mut String) {
+        *xml += "<value>";
+        match self {
+            XmlRpcValue::Int(value) if *value as i32 as i64 == *value => {
+                *xml += &format!("<int>{}</int>", value)
+            }
+            XmlRpcValue::Int(value) => *xml += &format!("<i8>{}</i8>", value),
+            XmlRpcValue::Boolean(value) => {
+                *xml += if *value {
+                    "<boolean>1</boolean>"
+                } else {
+                    "<boolean>0</boolean>"
+                }
+            }
+            XmlRpcValue::String(value) => {
+                *xml += &format!("<string>{}</string>", xml::escape(value))
+            }
+            XmlRpcValue::Double(value) => *xml += &format!("<double>{}</double>", value),
+            XmlRpcValue::DateTime(value) => {
+                *xml += &format!(
+                    "<dateTime.iso8601>{}</dateTime.iso8601>",
+                    xml::escape(value)
+                )
+            }
+            XmlRpcValue::Base64(value) => {
+                *xml += &format!("<base64>{}</base64>", base64_encode(value))
+            }
+            XmlRpcValue::Array(values) => {
+                *xml += "<array><data>";
+                for value in values {
+                    value.write(xml);
+                }
+                *xml += "</data></array>";
+            }
+            XmlRpcValue::Struct(members) => {
+                *xml += "<struct>";
+                for (name, value) in members {
+                    *xml += &format!("<member><name>{}</name>", xml::escape(name));
+                    value.write(xml);
+                    *xml += "</member>";
+                }
+                *xml += "</struct>";
+            }
+            XmlRpcValue::Nil => *xml += "<nil/>",
+        }
+        *xml += "</value>";
+    }
+
+    /// Parses a `<value>` element, or returns None if it's malformed.
+    fn parse(value: &Element) -> Option<XmlRpcValue> {
+        let typed = match value.elements_all().next() {
+            Some(typed) => typed,
+            // A value without a type is a string.
+            None => return Some(XmlRpcValue::String(value.text())),
+        };
+        let text = typed.text();
+        Some(match typed.name.as_str() {
+            "int" | "i4" | "i8" => XmlRpcValue::Int(text.trim().parse().ok()?),
+            "boolean" => match text.trim() {
+                "1" => XmlRpcValue::Boolean(true),
+                "0" => XmlRpcValue::Boolean(false),
+                _ => return None,
+            },
+            "string" => XmlRpcValue::String(text),
+            "double" => XmlRpcValue::Double(text.trim().parse().ok()?),
+            "dateTime.iso8601" => XmlRpcValue::DateTime(text.trim().to_string()),
+            "base64" => {
+                let encoded: String = text.split_whitespace().collect();
+                XmlRpcValue::Base64(base64_decode(&encoded)?)
+            }
+            "array" => XmlRpcValue::Array(
+                typed
+                    .child("data")?
+                    .elements("value")
+                    .map(XmlRpcValue::parse)
+                    .collect::<Option<_>>()?,
+            ),
+            "struct" => XmlRpcValue::Struct(
+                typed
+                    .elements("member")
+                    .map(|member| {
+                        let value = XmlRpcValue::parse(member.child("value")?)?;
+                        Some((member.child("name")?.text(), value))
+                    })
+                    .collect::<Option<_>>()?,
+            ),
+            "nil" => XmlRpcValue::Nil,
+            _ => return None,
+        })
+    }
+}
+
+impl From<i32> for XmlRpcValue {
+    fn from(value: i32) -> XmlRpcValue {
+        XmlRpcValue::Int(value as i64)
+    }
+}
+
+impl From<i64> for XmlRpcValue {
+    fn from(value: i64) -> XmlRpcValue {
+        XmlRpcValue::Int(value)
+    }
+}
+
+impl From<bool> for XmlRpcValue {
+    fn from(value: bool) -> XmlRpcValue {
+        XmlRpcValue::Boolean(value)
+    }
+}
+
+impl From<f64> for XmlRpcValue {
+    fn from(value: f64) -> XmlRpcValue {
+        XmlRpcValue::Double(value)
+    }
+}
+
+impl From<&str> for XmlRpcValue {
+    fn from(value: &str) -> XmlRpcValue {
+        XmlRpcValue::String(value.to_string())
+    }
+}
+
+impl From<String> for XmlRpcValue {
+    fn from(value: String) -> XmlRpcValue {
+        XmlRpcValue::String(value)
+    }
+}
+
+impl From<Vec<XmlRpcValue>> for XmlRpcValue {
+    fn from(values: Vec<XmlRpcValue>) -> XmlRpcValue {
+        XmlRpcValue::Array(values)
+    }
+}
+
+impl From<BTreeMap<String, XmlRpcValue>> for XmlRpcValue {
+    fn from(members: BTreeMap<String, XmlRpcValue>) -> XmlRpcValue {
+        XmlRpcValue::Struct(members)
+    }
+}
+
+/// Returns the `<methodCall>` document calling `method` with
+/// `params`.
+fn method_call(method: &str, params: &[XmlRpcValue]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\"?><methodCall>");
+    xml += &format!("<methodName>{}</methodName><params>", xml::escape(method));
+    for param in params {
+        xml += "<param>";
+        param.write(&mut xml);
+        xml += "</param>";
+    }
+    xml += "</params></methodCall>";
+    xml
+}
+
+/// Parses a `<methodResponse>` document into its result, which is
+/// [Nil](XmlRpcValue::Nil) if there isn't one.
+fn parse_method_response(body: &str) -> Result<XmlRpcValue, Error> {
+    let root = xml::parse(body).ok_or(Error::InvalidXmlRpc)?;
+    if root.name != "methodResponse" {
+        return Err(Error::InvalidXmlRpc);
+    }
+    if let Some(fault) = root.child("fault") {
+        let fault = fault
+            .child("value")
+            .and_then(XmlRpcValue::parse)
+            .ok_or(Error::InvalidXmlRpc)?;
+        let code = fault.get("faultCode").and_then(XmlRpcValue::as_i64);
+        let message = fault.get("faultString").and_then(XmlRpcValue::as_str);
+        return match (code, message) {
+            (Some(code), Some(message)) => Err(Error::XmlRpcFault {
+                code,
+                message: message.to_string(),
+            }),
+            _ => Err(Error::InvalidXmlRpc),
+        };
+    }
+    let params = root.child("params").ok_or(Error::InvalidXmlRpc)?;
+    match params.child("param") {
+        Some(param) => param
+            .child("value")
+            .and_then(XmlRpcValue::parse)
+            .ok_or(Error::InvalidXmlRpc),
+        None => Ok(XmlRpcValue::Nil),
+    }
+}
+
+impl Request {
+    /// Sends an XML-RPC call of `method` with `params`, and returns
+    /// its result. The request is sent as is, apart from its body and
+    /// `Content-Type`, so it should be a POST.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`XmlRpcFault`](enum.Error.html#variant.XmlRpcFault) if
+    /// the server returned a fault, and
+    /// [`InvalidXmlRpc`](enum.Error.html#variant.InvalidXmlRpc) if the
+    /// response isn't an XML-RPC method response.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), minreq::Error> {
+    /// let info = minreq::post("http://localhost:9001/RPC2")
+    ///     .with_header("Authorization", "Basic dXNlcjoxMjM=")
+    ///     .send_xml_rpc("supervisor.getProcessInfo", &["worker".into()])?;
+    /// println!("{:?}", info.get("statename"));
+    /// # Ok(()) }
+    /// ```
+    pub fn send_xml_rpc(self, method: &str, params: &[XmlRpcValue]) -> Result<XmlRpcValue, Error> {
+        let response = self
+            .with_header("Content-Type", "text/xml")
+            .with_body(method_call(method, params))
+            .send()?;
+        let body = std::str::from_utf8(response.as_bytes()).map_err(|_| Error::InvalidXmlRpc)?;
+        parse_method_response(body)
+    }
+}
+
+/// Sends an XML-RPC call of `method` with `params` to `url`, and
+/// returns its result. See
+/// [`Request::send_xml_rpc`](struct.Request.html#method.send_xml_rpc)
+/// for sending it with other headers, eg. for authentication.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> Result<(), minreq::Error> {
+/// let methods = minreq::xml_rpc_call(
+///     "https://example.com/xmlrpc.php",
+///     "system.listMethods",
+///     &[],
+/// )?;
+/// # Ok(()) }
+/// ```
+pub fn xml_rpc_call<T: Into<URL>>(
+    url: T,
+    method: &str,
+    params: &[XmlRpcValue],
+) -> Result<XmlRpcValue, Error> {
+    Request::new(Method::Post, url).send_xml_rpc(method, params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{method_call, parse_method_response, XmlRpcValue};
+    use crate::Error;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_method_call() {
+        let mut members = BTreeMap::new();
+        members.insert("a&b".to_string(), XmlRpcValue::Nil);
+        let params = [
+            XmlRpcValue::from(41),
+            XmlRpcValue::from(1i64 << 40),
+            XmlRpcValue::from("<hi>"),
+            XmlRpcValue::Array(vec![true.into(), 0.5.into()]),
+            XmlRpcValue::Base64(b"foo".to_vec()),
+            XmlRpcValue::Struct(members),
+        ];
+        assert_eq!(
+            method_call("examples.test", &params),
+            "<?xml version=\"1.0\"?><methodCall><methodName>examples.test</methodName><params>\
+             <param><value><int>41</int></value></param>\
+             <param><value><i8>1099511627776</i8></value></param>\
+             <param><value><string>&lt;hi&gt;</string></value></param>\
+             <param><value><array><data><value><boolean>1</boolean></value>\
+             <value><double>0.5</double></value></data></array></value></param>\
+             <param><value><base64>Zm9v</base64></value></param>\
+             <param><value><struct><member><name>a&amp;b</name><value><nil/></value>\
+             </member></struct></value></param>\
+             </params></methodCall>"
+        );
+    }
+
+    #[test]
+    fn test_parse_method_response() {
+        let body = "<?xml version=\"1.0\"?>
+<methodResponse>
+  <params>
+    <param>
+      <value><struct>
+        <member><name>name</name><value>South Dakota</value></member>
+        <member><name>pid</name><value><i4>-12</i4></value></member>
+        <member><name>data</name><value><base64>Zm9v
+YmFy</base64></value></member>
+        <member><name>tags</name><value><array><data>
+          <value><string> a </string></value>
+          <value><ex:nil/></value>
+        </data></array></value></member>
+      </struct></value>
+    </param>
+  </params>
+</methodResponse>";
+        let result = parse_method_response(body).unwrap();
+        assert_eq!(result.get("name").unwrap().as_str(), Some("South Dakota"));
+        assert_eq!(result.get("pid").unwrap().as_i64(), Some(-12));
+        assert_eq!(
+            result.get("data"),
+            Some(&XmlRpcValue::Base64(b"foobar".to_vec()))
+        );
+        assert_eq!(
+            result.get("tags").unwrap().as_array().unwrap(),
+            &[XmlRpcValue::from(" a "), XmlRpcValue::Nil]
+        );
+
+        let body = "<methodResponse><params/></methodResponse>";
+        assert_eq!(parse_method_response(body).unwrap(), XmlRpcValue::Nil);
+    }
+
+    #[test]
+    fn test_parse_fault() {
+        let body = "<methodResponse><fault><value><struct>
+<member><name>faultCode</name><value><int>4</int></value></member>
+<member><name>faultString</name><value><string>Too many parameters.</string></value></member>
+</struct></value></fault></methodResponse>";
+        match parse_method_response(body) {
+            Err(Error::XmlRpcFault { code, message }) => {
+                assert_eq!(code, 4);
+                assert_eq!(message, "Too many parameters.");
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+        let body = "<methodResponse><params><param><value><int>x</int></value></param></params></methodResponse>";
+        assert!(matches!(
+            parse_method_response(body),
+            Err(Error::InvalidXmlRpc)
+        ));
+    }
+}
@@ -30,14 +30,12 @@ pub fn setup() {
                 let url = String::from(request.url().split('#').next().unwrap());
                 match request.method() {
                     Method::Get if url == "/header_pong" => {
-                        for header in headers {
-                            if header.field.as_str() == "Ping" {
-                                let response = Response::from_string(format!("{}", header.value));
-                                request.respond(response).ok();
-                                return;
-                            }
-                        }
-                        request.respond(Response::from_string("No header!")).ok();
+                        let ping = headers
+                            .iter()
+                            .find(|header| header.field.as_str() == "Ping")
+                            .map(|header| header.value.to_string());
+                        let body = ping.unwrap_or_else(|| "No header!".to_string());
+                        request.respond(Response::from_string(body)).ok();
                     }
 
                     Method::Get if url == "/slow_a" => {
@@ -50,6 +48,82 @@ pub fn setup() {
                         let response = Response::from_string(format!("j: {}{}", content, fragment));
                         request.respond(response).ok();
                     }
+                    Method::Get if url == "/digest" => {
+                        let response = Response::from_string("minreq")
+                            .with_header(
+                                Header::from_str(
+                                    "Digest: sha-256=IGqfd0YCFMdE8/LCPOR376Q7NQgVwA6Pze11aUMRfyI=",
+                                )
+                                .unwrap(),
+                            )
+                            .with_header(
+                                Header::from_str("Content-MD5: 6+ECi+pWriX4I70SBJqTWg==").unwrap(),
+                            );
+                        request.respond(response).ok();
+                    }
+                    Method::Get if url == "/repr_digest" => {
+                        let mut want_digest = String::new();
+                        for header in headers.iter() {
+                            if header.field.as_str() == "Want-Repr-Digest" {
+                                want_digest = header.value.to_string();
+                            }
+                        }
+                        let digest = if want_digest.starts_with("sha-256=") {
+                            "sha-256=:IGqfd0YCFMdE8/LCPOR376Q7NQgVwA6Pze11aUMRfyI=:"
+                        } else {
+                            "sha-256=:AAAAd0YCFMdE8/LCPOR376Q7NQgVwA6Pze11aUMRfyI=:"
+                        };
+                        let response = Response::from_string("minreq").with_header(
+                            Header::from_str(&format!("Repr-Digest: {}", digest)).unwrap(),
+                        );
+                        request.respond(response).ok();
+                    }
+                    Method::Get if url == "/deprecated" => {
+                        let response = Response::from_string("old")
+                            .with_header(Header::from_str("Deprecation: @784111777").unwrap())
+                            .with_header(
+                                Header::from_str("Sunset: Sun, 06 Nov 1994 08:49:37 GMT").unwrap(),
+                            );
+                        request.respond(response).ok();
+                    }
+                    Method::Get if url == "/range" => {
+                        let file = "0123456789abcdefghijklmnopqrstuvwxyz";
+                        let range = headers
+                            .iter()
+                            .find(|header| header.field.as_str() == "Range")
+                            .map(|header| header.value.to_string());
+                        let range = range.as_ref().and_then(|range| {
+                            let mut range = range.trim_start_matches("bytes=").split('-');
+                            let start = range.next()?.parse::<usize>().ok()?;
+                            let end = range.next()?.parse::<usize>().ok()?;
+                            Some((start, end.min(file.len() - 1)))
+                        });
+                        let response = match range {
+                            Some((start, _)) if start >= file.len() => Response::empty(416)
+                                .with_header(
+                                    Header::from_str(&format!(
+                                        "Content-Range: bytes */{}",
+                                        file.len()
+                                    ))
+                                    .unwrap(),
+                                )
+                                .boxed(),
+                            Some((start, end)) => Response::from_string(&file[start..=end])
+                                .with_status_code(206)
+                                .with_header(
+                                    Header::from_str(&format!(
+                                        "Content-Range: bytes {}-{}/{}",
+                                        start,
+                                        end,
+                                        file.len()
+                                    ))
+                                    .unwrap(),
+                                )
+                                .boxed(),
+                            None => Response::from_string(file).boxed(),
+                        };
+                        request.respond(response).ok();
+                    }
                     Method::Post if url == "/a" => {
                         let response = Response::from_string("POST to /a is not valid.");
                         request.respond(response).ok();
@@ -91,6 +165,13 @@ pub fn setup() {
                         );
                         request.respond(response).ok();
                     }
+                    Method::Post if url == "/redirect-302" => {
+                        let response = Response::empty(302).with_header(
+                            Header::from_bytes(&b"Location"[..], &b"http://localhost:35562/a"[..])
+                                .unwrap(),
+                        );
+                        request.respond(response).ok();
+                    }
                     Method::Post if url == "/redirect" => {
                         let response = Response::empty(303).with_header(
                             Header::from_bytes(&b"Location"[..], &b"http://localhost:35562/a"[..])
@@ -152,6 +233,55 @@ pub fn setup() {
                         let response = Response::from_string(format!("p: {}", content));
                         request.respond(response).ok();
                     }
+                    Method::Get if url == "/request_id" => {
+                        let header = |name: &'static str| {
+                            headers
+                                .iter()
+                                .find(|header| header.field.equiv(name))
+                                .map(|header| header.value.to_string())
+                                .unwrap_or_default()
+                        };
+                        let request_id = format!("X-Request-Id: {}", header("X-Request-Id"));
+                        let response = Response::from_string(header("traceparent"))
+                            .with_header(Header::from_str(&request_id).unwrap());
+                        request.respond(response).ok();
+                    }
+                    Method::Get if url == "/rate_limit" => {
+                        let response = Response::from_string("limited")
+                            .with_header(Header::from_str("RateLimit-Limit: 1").unwrap())
+                            .with_header(Header::from_str("RateLimit-Remaining: 0").unwrap())
+                            .with_header(Header::from_str("RateLimit-Reset: 1").unwrap());
+                        request.respond(response).ok();
+                    }
+                    Method::Get if url.starts_with("/pages") => {
+                        let page: usize = url.rsplit('=').next().unwrap().parse().unwrap_or(1);
+                        let mut response = Response::from_string(page.to_string());
+                        if page < 3 {
+                            let link = format!("Link: <?page={}>; rel=\"next\"", page + 1);
+                            response = response.with_header(Header::from_str(&link).unwrap());
+                        }
+                        request.respond(response).ok();
+                    }
+                    Method::Options if url == "/cors" => {
+                        let mut response = Response::empty(204)
+                            .with_header(Header::from_str("Allow: GET, PUT, OPTIONS").unwrap());
+                        let origin = headers
+                            .iter()
+                            .find(|header| header.field.as_str() == "Origin");
+                        if let Some(origin) = origin {
+                            let allow_origin =
+                                format!("Access-Control-Allow-Origin: {}", origin.value);
+                            response = response
+                                .with_header(Header::from_str(&allow_origin).unwrap())
+                                .with_header(
+                                    Header::from_str("Access-Control-Allow-Methods: PUT").unwrap(),
+                                )
+                                .with_header(
+                                    Header::from_str("Access-Control-Max-Age: 600").unwrap(),
+                                );
+                        }
+                        request.respond(response).ok();
+                    }
                     Method::Connect if url == "/h" => {
                         let response = Response::from_string(format!("q: {}", content));
                         request.respond(response).ok();